@@ -0,0 +1,46 @@
+//! Micro-benchmarks of the pieces of a physics frame. There's no benchmark
+//! crate to lean on, so each one gets timed over a fixed number of runs and
+//! the fastest of a few samples is reported. Run with `cargo bench`
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use mqtest::bench::Fixture;
+
+/// Number of timed samples per benchmark, the fastest one gets reported
+const SAMPLES: usize = 10;
+
+/// Number of runs of the benchmarked code per sample
+const RUNS: u32 = 10_000;
+
+/// Time `func` and print how long a single run of it takes
+fn bench(name: &str, mut func: impl FnMut()) {
+    // Warm up the caches and branch predictors first
+    for _ in 0..RUNS {
+        func();
+    }
+
+    let best = (0..SAMPLES).map(|_| {
+        let start = Instant::now();
+        for _ in 0..RUNS {
+            func();
+        }
+        start.elapsed()
+    }).min().unwrap_or(Duration::ZERO);
+    println!("{:32} {:10.1} ns/iter", name,
+        best.as_nanos() as f64 / RUNS as f64);
+}
+
+fn main() {
+    let mut fixture = Fixture::new(1);
+    bench("physics step", || fixture.step());
+
+    let mut fixture = Fixture::new(2);
+    bench("generate column", || fixture.generate());
+
+    for count in [0, 4, 16, 64, 256] {
+        let mut fixture = Fixture::new(3);
+        fixture.set_obstacles(count);
+        bench(&format!("collision pass, {} obstacles", count),
+            || { black_box(fixture.collide()); });
+    }
+}
@@ -0,0 +1,36 @@
+#version 100
+// Example custom filter, which washes the field out towards red after a
+// death and slowly shifts the hue as the score goes up. Pick it with
+// `shader config/shader.glsl` in `settings.txt`, or `--shader`
+precision mediump float;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform sampler2D Texture;
+uniform float Time;
+uniform float Score;
+uniform float Flash;
+
+void main() {
+    vec3 rgb = texture2D(Texture, uv).rgb;
+
+    // Rotate the colors a little for every 10000 points
+    float angle = Score / 10000.0 * 0.2;
+    mat3 hue = mat3(
+        0.299 + 0.701 * cos(angle) + 0.168 * sin(angle),
+        0.587 - 0.587 * cos(angle) + 0.330 * sin(angle),
+        0.114 - 0.114 * cos(angle) - 0.497 * sin(angle),
+        0.299 - 0.299 * cos(angle) - 0.328 * sin(angle),
+        0.587 + 0.413 * cos(angle) + 0.035 * sin(angle),
+        0.114 - 0.114 * cos(angle) + 0.292 * sin(angle),
+        0.299 - 0.300 * cos(angle) + 1.250 * sin(angle),
+        0.587 - 0.588 * cos(angle) - 1.050 * sin(angle),
+        0.114 + 0.886 * cos(angle) - 0.203 * sin(angle));
+    rgb = hue * rgb;
+
+    // Pulse gently over time, and flash red
+    rgb *= 0.95 + 0.05 * sin(Time * 2.0);
+    rgb = mix(rgb, vec3(1.0, 0.2, 0.2), Flash * 0.6);
+    gl_FragColor = vec4(rgb, 1.0) * color;
+}
@@ -0,0 +1,62 @@
+/* C ABI for the headless helicopter simulation. Build the library with
+ * `cargo build --release` in this directory.
+ *
+ * All positions and speeds are raw fixed point with 5 fractional bits, the
+ * same values the simulation works with. */
+
+#ifndef HELI_H
+#define HELI_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define HELI_INPUT_THRUST 1
+#define HELI_INPUT_FIRE   2
+
+#define HELI_OBSERVED_COLUMNS   8
+#define HELI_OBSERVED_OBSTACLES 8
+
+typedef struct heli heli_t;
+
+typedef struct {
+    int16_t x, y, width, height;
+} heli_rect_t;
+
+typedef struct {
+    int16_t x, top, bottom;
+} heli_column_t;
+
+typedef struct {
+    uint64_t      frame;
+    int16_t       player_y;
+    int16_t       player_speed;
+    int16_t       player_size;
+    heli_column_t columns[HELI_OBSERVED_COLUMNS];
+    heli_rect_t   obstacles[HELI_OBSERVED_OBSTACLES];
+} heli_state_t;
+
+typedef struct {
+    uint64_t score;
+    uint64_t frames;
+    uint64_t state;
+} heli_verdict_t;
+
+/* Create a game with the default rules and `seed` */
+heli_t *heli_new(uint64_t seed);
+
+/* Simulate a physics frame with a bitmask of HELI_INPUT_* bits. Returns the
+ * score gained, or -1 once the game is over */
+int64_t heli_step(heli_t *handle, uint8_t input);
+
+/* Write the current state of the game to `out` */
+void heli_state(const heli_t *handle, heli_state_t *out);
+
+/* Play back the replay file of `len` bytes at `bytes` until the game is
+ * over, writing the outcome to `out`. Returns 0, or -1 if the replay is
+ * invalid or desynced */
+int32_t heli_verify(const uint8_t *bytes, size_t len, heli_verdict_t *out);
+
+/* Free a game created with heli_new() */
+void heli_free(heli_t *handle);
+
+#endif
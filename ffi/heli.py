@@ -0,0 +1,140 @@
+"""Python bindings for the headless helicopter simulation, on top of the C ABI
+in `heli.h`. Build the library with `cargo build --release` in this
+directory, or point `HELI_LIB` at a build of it elsewhere.
+
+All positions and speeds are raw fixed point with 5 fractional bits, the same
+values the simulation works with.
+
+    import heli
+
+    with heli.Game(seed=1) as game:
+        while game.step(heli.INPUT_THRUST) >= 0:
+            pass
+        print(game.state().frame)
+
+    print(heli.verify(open("run.heli", "rb").read()).score)
+"""
+
+import ctypes
+import os
+import sys
+
+INPUT_THRUST = 1
+INPUT_FIRE = 2
+
+OBSERVED_COLUMNS = 8
+OBSERVED_OBSTACLES = 8
+
+
+class Rect(ctypes.Structure):
+    """Bounding box of an obstacle"""
+    _fields_ = [
+        ("x", ctypes.c_int16),
+        ("y", ctypes.c_int16),
+        ("width", ctypes.c_int16),
+        ("height", ctypes.c_int16),
+    ]
+
+
+class Column(ctypes.Structure):
+    """Gap between the ceiling and the floor of a wall column"""
+    _fields_ = [
+        ("x", ctypes.c_int16),
+        ("top", ctypes.c_int16),
+        ("bottom", ctypes.c_int16),
+    ]
+
+
+class State(ctypes.Structure):
+    """What the player gets to see, entries which aren't there are all
+    zeros"""
+    _fields_ = [
+        ("frame", ctypes.c_uint64),
+        ("player_y", ctypes.c_int16),
+        ("player_speed", ctypes.c_int16),
+        ("player_size", ctypes.c_int16),
+        ("columns", Column * OBSERVED_COLUMNS),
+        ("obstacles", Rect * OBSERVED_OBSTACLES),
+    ]
+
+
+class Verdict(ctypes.Structure):
+    """Outcome of playing back a replay"""
+    _fields_ = [
+        ("score", ctypes.c_uint64),
+        ("frames", ctypes.c_uint64),
+        ("state", ctypes.c_uint64),
+    ]
+
+
+def _load():
+    """Load the library from `HELI_LIB`, or the release build next to this
+    file"""
+    path = os.environ.get("HELI_LIB")
+    if path is None:
+        name = {
+            "darwin": "libheli_ffi.dylib",
+            "win32": "heli_ffi.dll",
+        }.get(sys.platform, "libheli_ffi.so")
+        path = os.path.join(os.path.dirname(os.path.abspath(__file__)),
+                            "target", "release", name)
+
+    lib = ctypes.CDLL(path)
+    lib.heli_new.argtypes = [ctypes.c_uint64]
+    lib.heli_new.restype = ctypes.c_void_p
+    lib.heli_step.argtypes = [ctypes.c_void_p, ctypes.c_uint8]
+    lib.heli_step.restype = ctypes.c_int64
+    lib.heli_state.argtypes = [ctypes.c_void_p, ctypes.POINTER(State)]
+    lib.heli_state.restype = None
+    lib.heli_verify.argtypes = [ctypes.c_char_p, ctypes.c_size_t,
+                                ctypes.POINTER(Verdict)]
+    lib.heli_verify.restype = ctypes.c_int32
+    lib.heli_free.argtypes = [ctypes.c_void_p]
+    lib.heli_free.restype = None
+    return lib
+
+
+_lib = _load()
+
+
+class Game:
+    """A game with the default rules, stepped one physics frame at a time"""
+
+    def __init__(self, seed):
+        self._handle = _lib.heli_new(seed)
+
+    def step(self, input):
+        """Simulate a physics frame with a bitmask of `INPUT_*` bits. Returns
+        the score gained, or -1 once the game is over"""
+        return _lib.heli_step(self._handle, input)
+
+    def state(self):
+        """Current state of the game"""
+        state = State()
+        _lib.heli_state(self._handle, ctypes.byref(state))
+        return state
+
+    def close(self):
+        """Free the game, it can't be used afterwards"""
+        if self._handle is not None:
+            _lib.heli_free(self._handle)
+            self._handle = None
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *args):
+        self.close()
+
+    def __del__(self):
+        self.close()
+
+
+def verify(replay):
+    """Play back the replay file `replay` (as bytes) until the game is over,
+    returning its `Verdict`. Raises `ValueError` if the replay is invalid or
+    desynced"""
+    verdict = Verdict()
+    if _lib.heli_verify(replay, len(replay), ctypes.byref(verdict)) != 0:
+        raise ValueError("Replay is invalid or desynced")
+    return verdict
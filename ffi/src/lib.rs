@@ -0,0 +1,89 @@
+//! C ABI for the headless simulation, so it can be embedded in fuzzers, other
+//! engines, or non-Rust tooling. `heli.h` has the matching declarations
+
+use mqtest::env::{Env, Observation};
+
+/// Outcome of playing back a replay with [`heli_verify`]
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct Verdict {
+    /// Final score
+    pub score: u64,
+
+    /// Number of physics frames simulated
+    pub frames: u64,
+
+    /// Hash of the simulation state the game ended in
+    pub state: u64,
+}
+
+/// Create a game with the default rules and `seed`. Free it with [`heli_free`]
+#[no_mangle]
+pub extern "C" fn heli_new(seed: u64) -> *mut Env {
+    let mut env = Env::new();
+    env.reset(seed);
+    Box::into_raw(Box::new(env))
+}
+
+/// Simulate a physics frame with the `input` bitmask of `HELI_INPUT_*` bits.
+/// Returns the score gained, or -1 once the game is over
+///
+/// # Safety
+///
+/// `handle` must come from [`heli_new`] and not have been freed
+#[no_mangle]
+pub unsafe extern "C" fn heli_step(handle: *mut Env, input: u8) -> i64 {
+    let env = &mut *handle;
+    if env.done() {
+        return -1;
+    }
+    env.step(input).1
+}
+
+/// Write the current state of the game to `out`
+///
+/// # Safety
+///
+/// `handle` must come from [`heli_new`] and not have been freed, and `out`
+/// must point to a writable `heli_state_t`
+#[no_mangle]
+pub unsafe extern "C" fn heli_state(handle: *const Env,
+        out: *mut Observation) {
+    *out = (*handle).observe();
+}
+
+/// Play back the replay file of `len` bytes at `bytes` until the game is over,
+/// writing the outcome to `out`. Returns 0, or -1 if the replay is invalid
+/// or desynced
+///
+/// # Safety
+///
+/// `bytes` must point to `len` readable bytes, and `out` must point to a
+/// writable `heli_verdict_t`
+#[no_mangle]
+pub unsafe extern "C" fn heli_verify(bytes: *const u8, len: usize,
+        out: *mut Verdict) -> i32 {
+    match Env::verify(std::slice::from_raw_parts(bytes, len)) {
+        Ok(verdict) => {
+            *out = Verdict {
+                score:  verdict.score,
+                frames: verdict.frames,
+                state:  verdict.state,
+            };
+            0
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Free a game created with [`heli_new`]
+///
+/// # Safety
+///
+/// `handle` must come from [`heli_new`] and not have been freed already
+#[no_mangle]
+pub unsafe extern "C" fn heli_free(handle: *mut Env) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
@@ -0,0 +1,104 @@
+//! Checks that `heli.py` sees the same games as the simulation does, and that
+//! its structs have the same layouts as the ones in `heli.h`. Needs `python3`
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::mem::size_of;
+use mqtest::env::{Column, Env, Observation, Rect};
+
+/// Seed the game gets played on
+const SEED: u64 = 3;
+
+/// Number of physics frames to play, thrusting every other one
+const FRAMES: u64 = 40;
+
+/// Replay which gets verified
+const REPLAY: &str = "../tests/replays/normal.bin";
+
+/// Observation as a line of numbers, the same way the Python side prints it
+fn line(obs: &Observation) -> String {
+    let mut ret = vec![obs.frame as i64, obs.player_y as i64,
+        obs.player_speed as i64, obs.player_size as i64];
+    for column in obs.columns.iter() {
+        ret.extend([column.x, column.top, column.bottom].map(i64::from));
+    }
+    for rect in obs.obstacles.iter() {
+        ret.extend([rect.x, rect.y, rect.width, rect.height]
+            .map(i64::from));
+    }
+    ret.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+#[test]
+fn python_matches_the_simulation() {
+    let mut env = Env::new();
+    env.reset(SEED);
+    let mut score = 0;
+    for frame in 0..FRAMES {
+        let (_, reward, done) = env.step((frame % 2) as u8);
+        score += reward;
+        assert!(!done);
+    }
+    let verdict = Env::verify(&std::fs::read(REPLAY).unwrap()).unwrap();
+
+    // Struct sizes, the game, the replay, and then a bounce replay without
+    // an end frame, which plays up to the cap and gets rejected there
+    let expected = format!("{} {} {}\n{}\n{}\n{} {} {}\nrejected\n",
+        size_of::<Rect>(), size_of::<Column>(), size_of::<Observation>(),
+        line(&env.observe()), score,
+        verdict.score, verdict.frames, verdict.state);
+
+    // Tests don't get the library built, so build the release one which
+    // `heli.py` loads by default, in the target directory of the test
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let status = Command::new(env!("CARGO"))
+        .current_dir(&dir)
+        .args(["build", "--offline", "--release"])
+        .status()
+        .expect("Failed to run cargo");
+    assert!(status.success());
+    let mut lib = std::env::current_exe().unwrap();
+    lib.pop();
+    lib.pop();
+    lib.pop();
+    lib.push("release");
+    lib.push(if cfg!(windows) {
+        "heli_ffi.dll"
+    } else if cfg!(target_os = "macos") {
+        "libheli_ffi.dylib"
+    } else {
+        "libheli_ffi.so"
+    });
+
+    let script = format!(r#"
+import ctypes, heli
+print(ctypes.sizeof(heli.Rect), ctypes.sizeof(heli.Column),
+      ctypes.sizeof(heli.State))
+with heli.Game({seed}) as game:
+    score = sum(game.step(frame % 2) for frame in range({frames}))
+    state = game.state()
+    values = [state.frame, state.player_y, state.player_speed,
+              state.player_size]
+    for column in state.columns:
+        values += [column.x, column.top, column.bottom]
+    for rect in state.obstacles:
+        values += [rect.x, rect.y, rect.width, rect.height]
+    print(" ".join(map(str, values)))
+    print(score)
+verdict = heli.verify(open("{replay}", "rb").read())
+print(verdict.score, verdict.frames, verdict.state)
+try:
+    heli.verify(b"HELIREPLAY 2 seed=1 mutators=bounce\n")
+except ValueError:
+    print("rejected")
+"#, seed = SEED, frames = FRAMES, replay = REPLAY);
+
+    let out = Command::new("python3")
+        .current_dir(&dir)
+        .env("HELI_LIB", &lib)
+        .args(["-c", &script])
+        .output()
+        .expect("Failed to run python3");
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), expected);
+}
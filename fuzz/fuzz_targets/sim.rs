@@ -0,0 +1,71 @@
+//! Plays arbitrary input sequences on arbitrary seeds, presets, and mutators
+//! through the headless simulation, looking for panics and overflows, then
+//! plays them back as a replay with arbitrary difficulty header values, which
+//! have to either get rejected or play out. Run with `cargo fuzz run sim`
+//!
+//! The data is an 8 byte little-endian seed, a preset byte, a mutator bitmask
+//! byte, a little-endian `i16` for each of [`HEADER_KEYS`], and then input
+//! bitmasks which get repeated until the game is over
+
+#![no_main]
+
+use std::convert::TryInto;
+use libfuzzer_sys::fuzz_target;
+use mqtest::env::{self, Env};
+
+/// Most physics frames to simulate for each input
+const MAX_FRAMES: usize = 20_000;
+
+/// Replay header fields which get fuzzed values
+const HEADER_KEYS: [&str; 8] = [
+    "start_gap", "min_gap", "scroll", "max_scroll", "gravity", "impulse",
+    "friction", "adaptive",
+];
+
+/// Length of the data before the inputs
+const PREFIX: usize = 10 + HEADER_KEYS.len() * 2;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() <= PREFIX {
+        return;
+    }
+    let seed = u64::from_le_bytes(data[..8].try_into().unwrap());
+    let presets: Vec<&str> = env::presets().collect();
+    let preset = presets[data[8] as usize % presets.len()];
+    let mutators = data[9];
+    let values = &data[10..PREFIX];
+    let inputs = &data[PREFIX..];
+
+    let mut env = Env::with_preset(preset).unwrap();
+    let mut names = Vec::new();
+    for (idx, name) in env::mutators().enumerate() {
+        if mutators & (1 << idx) != 0 {
+            env.enable_mutator(name);
+            names.push(name);
+        }
+    }
+    env.reset(seed);
+
+    for &input in inputs.iter().cycle().take(MAX_FRAMES) {
+        if env.step(input).2 {
+            break;
+        }
+    }
+
+    // The same run as a replay, with the header values in place. Replays
+    // stop at the frame they say they ended on, ended or not
+    let mut header = format!("HELIREPLAY 2 seed={:x} preset={} frames={}",
+        seed, preset, MAX_FRAMES);
+    if !names.is_empty() {
+        header += &format!(" mutators={}", names.join(","));
+    }
+    for (key, value) in HEADER_KEYS.iter().zip(values.chunks(2)) {
+        header += &format!(" {}={}", key,
+            i16::from_le_bytes(value.try_into().unwrap()));
+    }
+    header.push('\n');
+
+    let mut bytes = header.into_bytes();
+    bytes.extend(inputs.iter().cycle().take(MAX_FRAMES));
+    let _ = Env::verify(&bytes);
+});
@@ -0,0 +1,93 @@
+//! Shapes collected into a single mesh, so a whole frame of render objects
+//! goes out in one draw call rather than one per shape
+
+use macroquad::prelude::*;
+use macroquad::models::Vertex;
+
+/// Most indices a single mesh can be drawn with
+const MAX_INDICES: usize = 5000;
+
+/// Shapes waiting to be drawn
+pub struct Batch {
+    /// Vertices and indices of everything queued up
+    mesh: Mesh,
+}
+
+impl Default for Batch {
+    fn default() -> Self {
+        Self {
+            mesh: Mesh {
+                vertices: Vec::new(),
+                indices:  Vec::new(),
+                texture:  None,
+            },
+        }
+    }
+}
+
+impl Batch {
+    /// Add a shape of `vertices` with `indices` relative to its first vertex,
+    /// drawing what's queued up first if it wouldn't fit
+    fn push(&mut self, vertices: &[Vec2], indices: &[u16], color: Color) {
+        if self.mesh.indices.len() + indices.len() > MAX_INDICES {
+            self.flush();
+        }
+
+        let base = self.mesh.vertices.len() as u16;
+        self.mesh.vertices.extend(vertices.iter().map(|x| Vertex {
+            position: vec3(x.x, x.y, 0.),
+            uv:       Vec2::ZERO,
+            color,
+        }));
+        self.mesh.indices.extend(indices.iter().map(|x| base + x));
+    }
+
+    /// Add a quad with its corners in order around it
+    pub fn quad(&mut self, corners: [Vec2; 4], color: Color) {
+        self.push(&corners, &[0, 1, 2, 0, 2, 3], color);
+    }
+
+    /// Add a rectangle, like [`draw_rectangle`]
+    pub fn rectangle(&mut self, x: f32, y: f32, width: f32, height: f32,
+            color: Color) {
+        self.quad([
+            vec2(x, y), vec2(x + width, y),
+            vec2(x + width, y + height), vec2(x, y + height),
+        ], color);
+    }
+
+    /// Add a line `thickness` wide, like [`draw_line`]
+    pub fn line(&mut self, from: Vec2, to: Vec2, thickness: f32,
+            color: Color) {
+        let dir = (to - from).normalize_or_zero();
+        let side = vec2(-dir.y, dir.x) * thickness / 2.;
+        self.quad([from + side, to + side, to - side, from - side], color);
+    }
+
+    /// Add a regular polygon rotated by `rotation` degrees, like
+    /// [`draw_poly`]
+    pub fn poly(&mut self, center: Vec2, sides: u8, radius: f32,
+            rotation: f32, color: Color) {
+        let mut vertices = [Vec2::ZERO; u8::MAX as usize + 1];
+        let mut indices = [0u16; u8::MAX as usize * 3];
+        vertices[0] = center;
+        for ii in 0..sides as usize {
+            let angle = ii as f32 / sides as f32 * std::f32::consts::PI * 2. +
+                rotation.to_radians();
+            vertices[ii + 1] = center + vec2(angle.cos(), angle.sin()) * radius;
+            indices[ii * 3..ii * 3 + 3].copy_from_slice(&[0, ii as u16 + 1,
+                (ii + 1) as u16 % sides as u16 + 1]);
+        }
+        self.push(&vertices[..sides as usize + 1],
+            &indices[..sides as usize * 3], color);
+    }
+
+    /// Draw everything queued up
+    pub fn flush(&mut self) {
+        if !self.mesh.indices.is_empty() {
+            draw_mesh(&self.mesh);
+        }
+        self.mesh.vertices.clear();
+        self.mesh.indices.clear();
+    }
+}
@@ -0,0 +1,226 @@
+//! `bench` subcommand, which runs the headless simulation as fast as it can
+//! and reports how fast it went and how much it allocated
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use crate::{Autopilot, Controller, GameField, Playback, Replay, Result, Rules};
+use crate::{Fxpt, Level, Obstacle, Preset, Rng, Script};
+use crate::{GAME_FIELD_HEIGHT, GAME_FIELD_WIDTH, INPUT_THRUST};
+use crate::{OBSTACLE_HEIGHT, OBSTACLE_WIDTH, PLAYER_X};
+
+/// Default number of physics frames to simulate
+const DEFAULT_FRAMES: u64 = 10_000_000;
+
+/// Number of allocations made through [`CountingAlloc`]
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of bytes allocated through [`CountingAlloc`]
+static ALLOCATED: AtomicU64 = AtomicU64::new(0);
+
+/// System allocator which counts allocations, so the benchmark can report
+/// them. It only counts when the binary installs it as the global allocator
+pub struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize)
+            -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(new_size as u64, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// Allocations and bytes allocated so far, these stay at zero unless
+/// [`CountingAlloc`] is the global allocator
+pub fn allocations() -> (u64, u64) {
+    (ALLOCATIONS.load(Ordering::Relaxed), ALLOCATED.load(Ordering::Relaxed))
+}
+
+/// Run the `bench` subcommand with the arguments following it
+pub fn run(args: &[String]) -> Result<()> {
+    let mut rules = Rules::default();
+    let mut frames = DEFAULT_FRAMES;
+    let mut replay = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |what: &str| {
+            args.next().ok_or_else(|| format!("{} requires {}", arg, what))
+        };
+        match arg.as_str() {
+            "--seed"   => {
+                rules.seed = u64::from_str_radix(
+                    value("a seed")?.trim_start_matches("0x"), 16)?;
+            }
+            "--preset" => {
+                let name = value("a preset name")?;
+                rules.preset = Preset::from_name(name)
+                    .ok_or_else(|| format!("Unknown preset {:?}", name))?;
+                rules.difficulty = rules.preset.difficulty();
+            }
+            "--frames" => {
+                frames = value("a frame count")?.replace('_', "").parse()?;
+            }
+            "--replay" => {
+                let path = value("a replay file")?;
+                replay = Some(Replay::load(path, Rules::default())?);
+            }
+            "--verbose" => crate::log::verbose(),
+            _ => return Err(format!("Unknown bench argument {:?}", arg).into()),
+        }
+    }
+
+    // Replays play on their own rules, level, and script
+    let level = replay.as_ref().and_then(|x| x.level.as_ref())
+        .map(|path| Level::load(path))
+        .transpose()?;
+    let script = replay.as_ref().and_then(|x| x.script.as_ref())
+        .map(|(path, hash)| Script::load_recorded(path, *hash))
+        .transpose()?;
+    if let Some(replay) = &replay {
+        if replay.inputs.is_empty() {
+            return Err("Replay has no inputs to benchmark with".into());
+        }
+        rules = replay.rules;
+    }
+    let limit = replay.as_ref().map_or(u64::MAX, |x| x.inputs.len() as u64);
+
+    // Play with the bot or the replay, starting over whenever a game or the
+    // replay ends, until enough frames have been simulated. The bot moves on
+    // to the next seed each time
+    let mut sim_time = Duration::ZERO;
+    let mut sim_allocs = (0, 0);
+    let mut games = 0u64;
+    let mut simulated = 0;
+    let start = Instant::now();
+    while simulated < frames {
+        let mut field = GameField::new(rules);
+        field.level = level.clone();
+        field.script = script.clone();
+        let mut controller: Box<dyn Controller> = match &replay {
+            Some(replay) => {
+                field.votes = replay.votes.clone();
+                Box::new(Playback(replay.inputs.clone()))
+            }
+            None => Box::new(Autopilot),
+        };
+        while !field.dead && !field.finished && simulated < frames &&
+                field.physics_frames < limit {
+            let input = controller.decide(&field.state());
+
+            let (allocs, bytes) = allocations();
+            let step_start = Instant::now();
+            field.step(input);
+            sim_time += step_start.elapsed();
+            let (new_allocs, new_bytes) = allocations();
+            sim_allocs.0 += new_allocs - allocs;
+            sim_allocs.1 += new_bytes - bytes;
+
+            simulated += 1;
+        }
+
+        games += 1;
+        if replay.is_none() {
+            rules.seed = rules.seed.wrapping_add(1);
+        }
+    }
+    let total_time = start.elapsed();
+
+    let per_sec = |time: Duration| simulated as f64 / time.as_secs_f64();
+    println!("Simulated {} frames over {} games", simulated, games);
+    println!("Simulation {:12.0} frames/s ({:.3} s)",
+        per_sec(sim_time), sim_time.as_secs_f64());
+    println!("Overall    {:12.0} frames/s ({:.3} s)",
+        per_sec(total_time), total_time.as_secs_f64());
+    println!("Allocated  {:12} times, {} bytes in the simulation \
+        ({:.3} per frame)", sim_allocs.0, sim_allocs.1,
+        sim_allocs.0 as f64 / simulated.max(1) as f64);
+    Ok(())
+}
+
+/// A game for micro-benchmarks to poke at the pieces of a physics frame in
+/// isolation
+pub struct Fixture {
+    /// Game being benchmarked
+    field: GameField,
+
+    /// The game right after the field filled up, to start over from
+    start: GameField,
+
+    /// Inputs the next physics step is fed with
+    rng: Rng,
+}
+
+impl Fixture {
+    /// Create a game on `seed` which has been played long enough for the
+    /// field to fill up
+    pub fn new(seed: u64) -> Self {
+        let field = GameField::new(Rules { seed, ..Default::default() });
+        let mut ret = Self {
+            start: field.clone(),
+            field,
+            rng:   Rng::new(seed),
+        };
+        while ret.field.walls.first().is_none_or(|x| x.x.0 > 0) {
+            ret.step();
+        }
+        ret.start = ret.field.clone();
+        ret
+    }
+
+    /// Replace the obstacles with `count` static obstacles spread out over
+    /// the field ahead of the player, and move the player into the gap, so
+    /// collision checks have to go through everything
+    pub fn set_obstacles(&mut self, count: usize) {
+        let field = &mut self.field;
+        let ahead = PLAYER_X.0 + field.player_size().0;
+        if let Some(ceiling) = field.walls.iter().find(|x| {
+            x.y.0 == 0 && x.x.0 <= PLAYER_X.0 && PLAYER_X.0 < x.x.0 + x.width.0
+        }) {
+            field.player_y = Fxpt(ceiling.height.0 + 1);
+        }
+
+        field.obstacles.clear();
+        for _ in 0..count {
+            let x = ahead + (self.rng.rand() %
+                ((GAME_FIELD_WIDTH.0 - ahead) as u64)) as i16;
+            let y = (self.rng.rand() %
+                ((GAME_FIELD_HEIGHT.0 - OBSTACLE_HEIGHT.0) as u64)) as i16;
+            field.obstacles.push(Obstacle::new(Fxpt(x), Fxpt(y),
+                OBSTACLE_WIDTH, OBSTACLE_HEIGHT));
+        }
+    }
+
+    /// Simulate a physics frame with random thrust, without ever dying
+    pub fn step(&mut self) {
+        self.field.invulnerable_frames = u16::MAX;
+        self.field.step((self.rng.rand() & 1) as u8 * INPUT_THRUST);
+    }
+
+    /// Generate a column of walls and whatever goes in it just off the
+    /// right of the field. Nothing scrolls, so this starts over from the
+    /// full field every so often before the coords run out
+    pub fn generate(&mut self) {
+        let last_x = self.field.walls.last().map_or(GAME_FIELD_WIDTH, |x| x.x);
+        if last_x.0 > i16::MAX - 2 * OBSTACLE_WIDTH.0 {
+            self.field = self.start.clone();
+            return;
+        }
+        self.field.generate_column(Fxpt(last_x.0 + OBSTACLE_WIDTH.0));
+    }
+
+    /// Check the player against everything, returning if anything was hit
+    pub fn collide(&self) -> bool {
+        self.field.collides(&self.field.player_bounds())
+    }
+}
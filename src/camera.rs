@@ -0,0 +1,73 @@
+//! Camera between the game field and the screen. Fixed cameras show the whole
+//! field, dynamic ones zoom in a little and smoothly follow the player up and
+//! down, zooming back out as the scrolling speeds up
+
+/// Zoom of a dynamic camera at the starting scroll speed
+const MAX_ZOOM: f32 = 1.15;
+
+/// Scroll speed, relative to the starting speed, at which a dynamic camera
+/// is all the way zoomed out
+const ZOOM_OUT_SPEED: f32 = 1.5;
+
+/// How quickly a dynamic camera catches up, the fraction of the way it's
+/// left behind after a second is `exp(-CATCH_UP_RATE)`
+const CATCH_UP_RATE: f32 = 4.;
+
+/// What part of the game field gets shown
+#[derive(Clone, Copy)]
+pub struct Camera {
+    /// Follow the player rather than showing the whole field
+    pub dynamic: bool,
+
+    /// Top left of the view in game field pixels
+    x: f32,
+    y: f32,
+
+    /// Screen pixels per game field pixel, on top of the scale of the field
+    zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self { dynamic: false, x: 0., y: 0., zoom: 1. }
+    }
+}
+
+impl Camera {
+    /// Move the camera `elapsed` seconds closer to framing the point
+    /// (`x`, `y`) of a `width` by `height` field, with the field scrolling at
+    /// `speed` times its starting speed. The point stays put horizontally
+    pub fn update(&mut self, x: f32, y: f32, width: f32, height: f32,
+            speed: f32, elapsed: f32) {
+        if !self.dynamic {
+            *self = Self::default();
+            return;
+        }
+
+        let zoomed_out = ((speed - 1.) / (ZOOM_OUT_SPEED - 1.)).clamp(0., 1.);
+        let zoom = MAX_ZOOM + (1. - MAX_ZOOM) * zoomed_out;
+        let view_h = height / zoom;
+        let target_y = (y - view_h / 2.).clamp(0., height - view_h);
+
+        let blend = 1. - (-CATCH_UP_RATE * elapsed).exp();
+        self.zoom += (zoom - self.zoom) * blend;
+        self.y += (target_y - self.y) * blend;
+        self.y = self.y.clamp(0., height - height / self.zoom);
+        self.x = x * (1. - 1. / self.zoom);
+        self.x = self.x.clamp(0., width - width / self.zoom);
+    }
+
+    /// Where to draw the game field with its top left at (`offset_x`,
+    /// `offset_y`) and `scale` screen pixels per game field pixel to show
+    /// the view, returning the same for the view
+    pub fn transform(&self, offset_x: f32, offset_y: f32, scale: f32)
+            -> (f32, f32, f32) {
+        let scale = scale * self.zoom;
+        (offset_x - self.x * scale, offset_y - self.y * scale, scale)
+    }
+
+    /// Check if parts of the field are out of view
+    pub fn cropped(&self) -> bool {
+        self.zoom > 1.
+    }
+}
@@ -0,0 +1,228 @@
+//! Screenshots and clips. F12 saves the whole window and Shift+F12 just the
+//! game field at its internal resolution, for crisp pixels. F9 saves the last
+//! few seconds of the run as an animated PNG. On WASM they get downloaded by
+//! the browser instead
+
+use std::collections::VecDeque;
+use macroquad::prelude::*;
+use crate::{error, GameField, Result};
+use crate::{GAME_FIELD_HEIGHT, GAME_FIELD_WIDTH};
+
+/// Seconds of play a clip covers
+const CLIP_SECONDS: u64 = 5;
+
+/// Physics frames between the frames of a clip
+const CLIP_FRAME_STEP: u64 = 2;
+
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    /// Hand `data` to the browser as a download named `name`, provided by the
+    /// plugin in `index.html`
+    fn mqtest_download(name: *const u8, name_len: usize, data: *const u8,
+        data_len: usize);
+}
+
+/// Take a screenshot if F12 was pressed this frame, which has to be after
+/// everything was drawn
+pub fn update(field: &GameField) {
+    if !is_key_pressed(KeyCode::F12) {
+        return;
+    }
+
+    let crisp = is_key_down(KeyCode::LeftShift) ||
+        is_key_down(KeyCode::RightShift);
+    let shot = if crisp { field_image(field) } else { get_screen_data() };
+    let path = format!("screenshot-{}.png", timestamp());
+    match save("screenshot", &path, &shot) {
+        Ok(())   => log_info!("Saved screenshot {}", path),
+        Err(err) => log_error!("{}", err),
+    }
+}
+
+/// Draw the game field of the last frame at 1x into an image
+pub fn field_image(field: &GameField) -> Image {
+    field_image_sized(field, f32::from(GAME_FIELD_WIDTH) as u32,
+        f32::from(GAME_FIELD_HEIGHT) as u32)
+}
+
+/// Draw the game field of the last frame into a `width` by `height` image,
+/// scaled to fit and centered
+pub fn field_image_sized(field: &GameField, width: u32, height: u32)
+        -> Image {
+    let (width, height) = (width as f32, height as f32);
+    let target = render_target(width as u32, height as u32);
+    target.texture.set_filter(FilterMode::Nearest);
+    set_camera(&Camera2D {
+        render_target: Some(target),
+        ..Camera2D::from_display_rect(Rect::new(0., 0., width, height))
+    });
+
+    let (field_w, field_h) =
+        (f32::from(GAME_FIELD_WIDTH), f32::from(GAME_FIELD_HEIGHT));
+    let scale = (width / field_w).min(height / field_h);
+    draw_rectangle(0., 0., width, height, BLACK);
+    field.draw_objects((width - field_w * scale) / 2.,
+        (height - field_h * scale) / 2., scale);
+
+    // Switching back to the screen flushes the drawing into the texture
+    set_default_camera();
+    let shot = target.texture.get_texture_data();
+    target.texture.delete();
+    shot
+}
+
+/// Rows of `shot` flipped the right way up, images read back from the GPU
+/// are upside down
+pub fn upright(shot: &Image) -> Vec<u8> {
+    shot.bytes.chunks(shot.width as usize * 4).rev().flatten().copied()
+        .collect()
+}
+
+/// Encode `shot` as a PNG and save it to the `what` file at `path`
+pub fn save(what: &'static str, path: &str, shot: &Image) -> Result<()> {
+    let mut png = Vec::new();
+    image::png::PngEncoder::new(&mut png).encode(&upright(shot),
+        shot.width as u32, shot.height as u32, image::ColorType::Rgba8)
+        .map_err(|err| format!("Couldn't encode {}: {}", path, err))?;
+    write(what, path, &png)
+}
+
+/// Save the `what` file `data` to `path`, or download it on WASM
+fn write(what: &'static str, path: &str, data: &[u8]) -> Result<()> {
+    #[cfg(not(target_arch = "wasm32"))]
+    error::write(what, path, data)?;
+    #[cfg(target_arch = "wasm32")]
+    unsafe {
+        let _ = what;
+        mqtest_download(path.as_ptr(), path.len(), data.as_ptr(), data.len());
+    }
+    Ok(())
+}
+
+/// The last few seconds of a run, kept as snapshots of the game which get
+/// played forward again with the recorded inputs when a clip is saved, rather
+/// than as rendered frames
+#[derive(Default)]
+pub struct Clip {
+    /// Snapshots of the game taken about every second, oldest first
+    snapshots: VecDeque<GameField>,
+}
+
+impl Clip {
+    /// Snapshot `field` if a second passed since the last snapshot, and save
+    /// a clip if F9 was pressed this frame
+    pub fn update(&mut self, field: &GameField) {
+        let due = self.snapshots.back().is_none_or(|x| {
+            field.physics_frames >=
+                x.physics_frames + field.rules.tick_rate as u64
+        });
+        if due {
+            self.snapshots.push_back(field.clone());
+            if self.snapshots.len() as u64 > CLIP_SECONDS + 1 {
+                self.snapshots.pop_front();
+            }
+        }
+
+        if is_key_pressed(KeyCode::F9) {
+            let path = format!("clip-{}.png", timestamp());
+            match self.save(&path, field) {
+                Ok(())   => log_info!("Saved clip {}", path),
+                Err(err) => log_error!("{}", err),
+            }
+        }
+    }
+
+    /// Play the last `CLIP_SECONDS` leading up to `field` again, saving every
+    /// `CLIP_FRAME_STEP`th frame of the game field to `path` as an APNG
+    pub fn save(&self, path: &str, field: &GameField) -> Result<()> {
+        let start = field.physics_frames
+            .saturating_sub(CLIP_SECONDS * field.rules.tick_rate as u64);
+        let Some(snapshot) = self.snapshots.iter().rev()
+            .find(|x| x.physics_frames <= start)
+            .or(self.snapshots.front()) else { return Ok(()) };
+        let mut replay = snapshot.clone();
+        let frames = (field.physics_frames - replay.physics_frames)
+            .div_ceil(CLIP_FRAME_STEP).max(1);
+
+        let err = |err: png::EncodingError| {
+            format!("Couldn't encode {}: {}", path, err)
+        };
+        let mut apng = Vec::new();
+        let mut encoder = png::Encoder::new(&mut apng,
+            f32::from(GAME_FIELD_WIDTH) as u32,
+            f32::from(GAME_FIELD_HEIGHT) as u32);
+        encoder.set_color(png::ColorType::RGBA);
+        let mut writer = encoder.write_header().map_err(err)?;
+        writer.write_chunk(*b"acTL", &[
+            (frames as u32).to_be_bytes(), 0u32.to_be_bytes(),
+        ].concat()).map_err(err)?;
+
+        // Every frame replaces the last one entirely, and is shown for the
+        // time it covers
+        let mut sequence = 0u32;
+        for frame in 0..frames {
+            let until = (replay.physics_frames + CLIP_FRAME_STEP)
+                .min(field.physics_frames);
+            while replay.inputs.len() < field.inputs.len() &&
+                    replay.physics_frames < until {
+                replay.step(field.inputs[replay.inputs.len()]);
+            }
+            replay.build_objects();
+            let shot = field_image(&replay);
+
+            writer.write_chunk(*b"fcTL", &[
+                &sequence.to_be_bytes()[..],
+                &(shot.width as u32).to_be_bytes(),
+                &(shot.height as u32).to_be_bytes(),
+                &0u32.to_be_bytes(), &0u32.to_be_bytes(),
+                &(CLIP_FRAME_STEP as u16).to_be_bytes(),
+                &field.rules.tick_rate.to_be_bytes(),
+                &[0, 0],
+            ].concat()).map_err(err)?;
+            sequence += 1;
+
+            // The first frame doubles as the still image, the rest get
+            // compressed the same way with no filtering
+            if frame == 0 {
+                writer.write_image_data(&upright(&shot)).map_err(err)?;
+                continue;
+            }
+            let mut rows = Vec::new();
+            for row in upright(&shot).chunks(shot.width as usize * 4) {
+                rows.push(0);
+                rows.extend_from_slice(row);
+            }
+            writer.write_chunk(*b"fdAT", &[
+                &sequence.to_be_bytes()[..],
+                &deflate::deflate_bytes_zlib(&rows),
+            ].concat()).map_err(err)?;
+            sequence += 1;
+        }
+        drop(writer);
+
+        write("clip", path, &apng)
+    }
+}
+
+/// Current UTC time as `YYYY-MM-DD-HHMMSS-mmm`, for file names
+pub fn timestamp() -> String {
+    let now = miniquad::date::now();
+    let secs = now as i64;
+    let (days, time) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // Convert days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!("{:04}-{:02}-{:02}-{:02}{:02}{:02}-{:03}", year, month, day,
+        time / 3600, time / 60 % 60, time % 60,
+        (now.fract() * 1000.) as u32)
+}
@@ -0,0 +1,152 @@
+//! Career mode profile, where the coins collected in career runs get banked
+//! and spent on [`Upgrade`]s which stay bought. The profile is a file of
+//! `key value` lines like the settings, the coins in the bank followed by
+//! the level of every upgrade bought:
+//!
+//! ```text
+//! coins  42
+//! shield 1
+//! gap    2
+//! ```
+
+use macroquad::prelude::*;
+use crate::{error, field_transform, Result, Upgrade};
+use crate::{GAME_FIELD_HEIGHT, GAME_FIELD_WIDTH};
+
+/// File the career profile is kept in
+const PROFILE_FILE: &str = "career.txt";
+
+/// Keys which buy the next level of each of [`Upgrade::ALL`]
+const UPGRADE_KEYS: [KeyCode; Upgrade::ALL.len()] =
+    [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3];
+
+/// Coins banked and upgrades bought over the career
+#[derive(Clone, Copy, Default)]
+pub struct Profile {
+    /// Coins left to spend
+    pub coins: u64,
+
+    /// Levels bought of each of [`Upgrade::ALL`]
+    pub upgrades: [u8; Upgrade::ALL.len()],
+}
+
+impl Profile {
+    /// Load the profile, a career which hasn't started yet has nothing
+    pub fn load() -> Self {
+        error::read_to_string("career", PROFILE_FILE)
+            .inspect_err(|err| log_debug!("No career loaded: {}", err))
+            .and_then(|text| {
+                Self::parse(&text)
+                    .map_err(|err| err.in_file("career", PROFILE_FILE))
+                    .inspect_err(|err| log_warn!("Discarding {}", err))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Parse a profile, anything which isn't in it is zero
+    fn parse(text: &str) -> Result<Self> {
+        let mut profile = Self::default();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line.split_once(char::is_whitespace)
+                .map(|(key, value)| (key, value.trim()))
+                .ok_or_else(|| format!("{:?} has no value", line))?;
+
+            if key == "coins" {
+                profile.coins = value.parse()?;
+                continue;
+            }
+            let upgrade = Upgrade::from_name(key)
+                .ok_or_else(|| format!("Unknown upgrade {:?}", key))?;
+            profile.upgrades[upgrade as usize] =
+                value.parse::<u8>()?.min(upgrade.max_level());
+        }
+        Ok(profile)
+    }
+
+    /// Write the profile back out
+    fn save(&self) {
+        let mut text = format!("coins  {}\n", self.coins);
+        for (upgrade, level) in Upgrade::ALL.iter().zip(self.upgrades) {
+            text += &format!("{:<6} {}\n", upgrade.name(), level);
+        }
+        if let Err(err) = error::write("career", PROFILE_FILE, text) {
+            log_error!("{}", err);
+        }
+    }
+
+    /// Bank the `coins` collected in a career run
+    pub fn bank(&mut self, coins: u64) {
+        self.coins += coins;
+        self.save();
+    }
+
+    /// Buy the next level of `upgrade`, returning if there was one the bank
+    /// could pay for
+    fn buy(&mut self, upgrade: Upgrade) -> bool {
+        let level = self.upgrades[upgrade as usize];
+        let cost = upgrade.cost(level);
+        if level >= upgrade.max_level() || self.coins < cost {
+            return false;
+        }
+
+        self.coins -= cost;
+        self.upgrades[upgrade as usize] += 1;
+        self.save();
+        true
+    }
+}
+
+/// Show the career shop until the player starts the run, where the number
+/// keys buy upgrades with the coins in `profile`
+pub async fn shop(profile: &mut Profile) {
+    loop {
+        for (&upgrade, key) in Upgrade::ALL.iter().zip(UPGRADE_KEYS) {
+            if is_key_pressed(key) && profile.buy(upgrade) {
+                log_info!("Bought {} level {}", upgrade.name(),
+                    profile.upgrades[upgrade as usize]);
+            }
+        }
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::Space) ||
+                is_mouse_button_pressed(MouseButton::Left) {
+            return;
+        }
+
+        let (offset_x, offset_y, scale) = field_transform();
+        let target_w = scale * f32::from(GAME_FIELD_WIDTH);
+        let target_h = scale * f32::from(GAME_FIELD_HEIGHT);
+
+        clear_background(BLACK);
+        draw_rectangle_lines(offset_x, offset_y, target_w, target_h, 2., GOLD);
+
+        let centered = |text: &str, y: f32, size: f32, color: Color| {
+            let width = measure_text(text, None, size as u16, 1.).width;
+            draw_text(text, offset_x + (target_w - width) / 2., y, size,
+                color);
+        };
+
+        centered(tr!("career"), offset_y + target_h / 6., 56. * scale, GOLD);
+        centered(&tr!("career_coins", profile.coins),
+            offset_y + target_h * 0.3, 24. * scale, WHITE);
+        for (idx, &upgrade) in Upgrade::ALL.iter().enumerate() {
+            let level = profile.upgrades[upgrade as usize];
+            let (text, color) = if level >= upgrade.max_level() {
+                (tr!("career_maxed", idx + 1, upgrade.title()), GREEN)
+            } else {
+                let cost = upgrade.cost(level);
+                (tr!("career_upgrade", idx + 1, upgrade.title(), level,
+                    upgrade.max_level(), cost),
+                 if profile.coins >= cost { YELLOW } else { DARKGRAY })
+            };
+            centered(&text, offset_y + target_h * 0.45 +
+                idx as f32 * 26. * scale, 20. * scale, color);
+        }
+        centered(tr!("career_start"), offset_y + target_h * 0.82,
+            20. * scale, GRAY);
+
+        next_frame().await;
+    }
+}
@@ -0,0 +1,53 @@
+//! `closest` subcommand, which plays a replay and lists its near-misses with
+//! the least clearance, along with where to start playback to watch each of
+//! them again, like:
+//!
+//! `mqtest closest inputs.bin --count 5`
+
+use crate::{env, error, Result, CLOSE_CALL_LEAD};
+
+/// Default number of near-misses listed
+const DEFAULT_COUNT: usize = 10;
+
+/// Run the `closest` subcommand with the arguments following it
+pub fn run(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let mut count = DEFAULT_COUNT;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--count" => {
+                count = args.next().ok_or("--count requires a number")?
+                    .parse()?;
+            }
+            "--verbose" => crate::log::verbose(),
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => {
+                return Err(format!("Unknown closest argument {:?}", arg)
+                    .into());
+            }
+        }
+    }
+    let path = path.ok_or("closest requires a replay file")?;
+
+    // Replays play on their own rules, level, and script
+    let field = error::read("replay", path)
+        .and_then(|bytes| env::play(&bytes))
+        .map_err(|err| err.in_file("replay", path))?;
+
+    let calls = field.closest_calls(count);
+    if calls.is_empty() {
+        println!("{} has no near-misses", path);
+        return Ok(());
+    }
+    let rate = field.rules.tick_rate as u64;
+    println!("{:>4} {:>8} {:>9} {:>10} {:>8}", "#", "Frame", "Time",
+        "Clearance", "Seek");
+    for (ii, (frame, clearance)) in calls.into_iter().enumerate() {
+        println!("{:>4} {:>8} {:>7.2} s {:>7.2} px {:>8}", ii + 1, frame,
+            frame as f64 / rate as f64, f32::from(clearance),
+            frame.saturating_sub(CLOSE_CALL_LEAD * rate));
+    }
+    println!("Watch one again with `mqtest {} --seek <frame>`", path);
+    Ok(())
+}
@@ -0,0 +1,254 @@
+//! `compare` subcommand, which plays two replays of the same seed and reports
+//! where they go their separate ways, how their scores differ, and what each
+//! of them gained over every segment of the run. Optionally the runs get
+//! rendered on top of each other into numbered PNGs, the longer run drawn
+//! as usual and the other as a ghost of the player, like:
+//!
+//! `mqtest compare old.bin new.bin --segment 10 --ghosts frames`
+
+use std::convert::TryFrom;
+use std::fmt::Write;
+use macroquad::prelude::*;
+use crate::{capture, error, Fxpt, GameField, Level, Replay, Result, Rules};
+use crate::{env, Script, MAX_REPLAY_FRAMES};
+
+/// Default seconds of play each segment covers
+const DEFAULT_SEGMENT: u64 = 5;
+
+/// Frames per second of the rendered ghosts
+const GHOST_FPS: u64 = 30;
+
+/// Seconds between progress updates while rendering
+const REPORT_TIME: f64 = 0.25;
+
+/// A replay played out to the end of the run
+pub(crate) struct Run {
+    /// Where the replay was loaded from
+    path: String,
+
+    /// Replay which was played
+    replay: Replay,
+
+    /// Height of the player at every physics frame, starting with where it
+    /// spawned
+    ys: Vec<Fxpt>,
+
+    /// Score at every physics frame, starting with zero
+    scores: Vec<u64>,
+
+    /// Set if the run ended at a finish line rather than in a crash
+    finished: bool,
+}
+
+impl Run {
+    /// Play `replay`, loaded from `path`, until the run is over, running out
+    /// of inputs the same way as in the game. Like [`env::play`], it stops at
+    /// the frame the replay was recorded ending on or at the cap
+    pub(crate) fn play(path: &str, replay: Replay) -> Result<Self> {
+        let mut field = new_field(&replay)?;
+        let mut ys = vec![field.player_y];
+        let mut scores = vec![field.score()];
+        let end = replay.frames.unwrap_or(MAX_REPLAY_FRAMES);
+        while !field.dead && !field.finished && field.physics_frames < end {
+            let input = replay.inputs.get(field.inputs.len()).copied();
+            field.step(input.unwrap_or(0));
+            ys.push(field.player_y);
+            scores.push(field.score());
+        }
+        env::check_over(&field, end)
+            .map_err(|err| err.in_file("replay", path))?;
+
+        Ok(Self {
+            path: path.to_string(),
+            replay,
+            ys,
+            scores,
+            finished: field.finished,
+        })
+    }
+
+    /// Number of physics frames the run lasted
+    fn frames(&self) -> u64 {
+        self.ys.len() as u64 - 1
+    }
+
+    /// Score at physics frame `frame`, or the final score once it's over
+    fn score_at(&self, frame: u64) -> u64 {
+        self.scores[(frame as usize).min(self.scores.len() - 1)]
+    }
+
+    /// Seconds into the run physics frame `frame` is at
+    fn seconds(&self, frame: u64) -> f64 {
+        frame as f64 / self.replay.rules.tick_rate as f64
+    }
+}
+
+/// Ghosts of two runs waiting to be rendered, which needs a window
+pub struct Ghosts {
+    /// Run which is drawn as usual
+    shown: Run,
+
+    /// Run only the player of which is drawn
+    ghost: Run,
+
+    /// Directory the frames get written to
+    out: String,
+}
+
+impl Ghosts {
+    /// Render every frame of the shown run with the ghost over it
+    pub async fn render(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.out).map_err(|err| error::Error::Io {
+            what: "frame directory", path: self.out.clone(), err,
+        })?;
+
+        let inputs = &self.shown.replay.inputs;
+        let mut field = new_field(&self.shown.replay)?;
+        let mut reported = get_time();
+        for frame in 0u64.. {
+            let due = frame * field.rules.tick_rate as u64 / GHOST_FPS;
+            while field.physics_frames < due && !field.dead &&
+                    !field.finished {
+                let input = inputs.get(field.inputs.len()).copied();
+                field.step(input.unwrap_or(0));
+            }
+            if field.physics_frames < due {
+                eprintln!("Rendered {} frames to {}", frame, self.out);
+                break;
+            }
+
+            field.ghost = self.ghost.ys.get(field.physics_frames as usize)
+                .copied();
+            field.build_objects();
+            capture::save("frame", &format!("{}/frame_{:06}.png", self.out,
+                frame), &capture::field_image(&field))?;
+
+            // Keep the window alive and show how far along the run is
+            if get_time() - reported >= REPORT_TIME {
+                reported = get_time();
+                clear_background(BLACK);
+                draw_text(&format!("Rendering ghosts | Frame {} | {} of {} \
+                    physics frames", frame, field.physics_frames,
+                    self.shown.frames()), 10., 30., 24., WHITE);
+                next_frame().await;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Create a game to play `replay` on, with its rules, level, script, and
+/// votes
+fn new_field(replay: &Replay) -> Result<GameField> {
+    let mut field = GameField::new(replay.rules);
+    field.level = replay.level.as_ref().map(|x| Level::load(x)).transpose()?;
+    field.script = replay.script.as_ref()
+        .map(|(path, hash)| Script::load_recorded(path, *hash))
+        .transpose()?;
+    field.votes = replay.votes.clone();
+    Ok(field)
+}
+
+/// Describe where runs `a` and `b` differ, with the score each gained over
+/// segments of `segment` seconds
+pub(crate) fn report(a: &Run, b: &Run, segment: u64) -> String {
+    let mut out = String::new();
+    for (name, run) in [("A", a), ("B", b)] {
+        let _ = writeln!(out, "{} {}: {} points in {:.2} s, {}", name,
+            run.path, run.score_at(u64::MAX), run.seconds(run.frames()),
+            if run.finished { "finished" } else { "crashed" });
+    }
+
+    // Inputs running out counts as diverging, the player gets no more thrust
+    let (inputs_a, inputs_b) = (&a.replay.inputs, &b.replay.inputs);
+    let inputs = inputs_a.iter().zip(inputs_b).position(|(x, y)| x != y)
+        .or((inputs_a.len() != inputs_b.len())
+            .then(|| inputs_a.len().min(inputs_b.len())));
+    let trajectory = a.ys.iter().zip(&b.ys).position(|(x, y)| x != y);
+    for (what, frame) in [("Inputs", inputs), ("Trajectories", trajectory)] {
+        match frame {
+            Some(frame) => {
+                let _ = writeln!(out, "{} diverge at {:.2} s (frame {})",
+                    what, a.seconds(frame as u64), frame);
+            }
+            None => {
+                let _ = writeln!(out, "{} never diverge", what);
+            }
+        }
+    }
+
+    let diff = b.score_at(u64::MAX) as i64 - a.score_at(u64::MAX) as i64;
+    let _ = writeln!(out, "B scored {:+} against A", diff);
+
+    // Segments go on until both runs are over
+    let frames = segment * a.replay.rules.tick_rate as u64;
+    let _ = writeln!(out, "{:>13} {:>8} {:>8} {:>8}", "Segment", "A", "B",
+        "B-A");
+    for start in (0..a.frames().max(b.frames())).step_by(frames as usize) {
+        let end = start + frames;
+        let gain = |run: &Run| run.score_at(end) - run.score_at(start);
+        let _ = writeln!(out, "{:>5}-{:>5} s {:>8} {:>8} {:>+8}",
+            start / a.replay.rules.tick_rate as u64,
+            end / a.replay.rules.tick_rate as u64, gain(a), gain(b),
+            gain(b) as i64 - gain(a) as i64);
+    }
+    out
+}
+
+/// Run the `compare` subcommand with the arguments following it, returning
+/// the ghosts to render if they were asked for
+pub fn run(args: &[String]) -> Result<Option<Ghosts>> {
+    let mut paths = Vec::new();
+    let mut segment = DEFAULT_SEGMENT;
+    let mut ghosts = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |what: &str| {
+            args.next().ok_or_else(|| format!("{} requires {}", arg, what))
+        };
+        match arg.as_str() {
+            "--segment" => {
+                segment = value("a number of seconds")?.parse()?;
+                if segment == 0 {
+                    return Err("The segment length has to be positive"
+                        .into());
+                }
+            }
+            "--ghosts"  => ghosts = Some(value("a directory")?.clone()),
+            "--verbose" => crate::log::verbose(),
+            _ if paths.len() < 2 && !arg.starts_with("--") => {
+                paths.push(arg.clone());
+            }
+            _ => {
+                return Err(format!("Unknown compare argument {:?}", arg)
+                    .into());
+            }
+        }
+    }
+    let [a, b] = <[String; 2]>::try_from(paths)
+        .map_err(|_| "compare requires two replay files")?;
+
+    let a = Replay::load(&a, Rules::default())
+        .and_then(|replay| Run::play(&a, replay))?;
+    let b = Replay::load(&b, Rules::default())
+        .and_then(|replay| Run::play(&b, replay))?;
+    let (rules_a, rules_b) = (&a.replay.rules, &b.replay.rules);
+    if rules_a.seed != rules_b.seed {
+        return Err(format!("The replays are on different seeds, {:016x} and \
+            {:016x}", rules_a.seed, rules_b.seed).into());
+    }
+    if rules_a.tick_rate != rules_b.tick_rate {
+        return Err(format!("The replays are at different tick rates, {} and \
+            {} Hz", rules_a.tick_rate, rules_b.tick_rate).into());
+    }
+    print!("{}", report(&a, &b, segment));
+
+    Ok(ghosts.map(|out| {
+        let (shown, ghost) = if b.frames() > a.frames() {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        Ghosts { shown, ghost, out }
+    }))
+}
@@ -0,0 +1,176 @@
+//! Debug console opened with the tilde key. Commands poke at the run in
+//! progress directly, which turns it into an unranked debug run
+
+use std::collections::VecDeque;
+use macroquad::prelude::*;
+use crate::script::Param;
+use crate::{Fxpt, GameField, Obstacle, ObstacleKind, Pickup, PickupKind};
+use crate::Result;
+use crate::{BLADE_RADIUS, FIXED_POINT_DIVISOR, GAME_FIELD_HEIGHT};
+use crate::{OBSTACLE_HEIGHT, OBSTACLE_WIDTH};
+
+/// Number of lines of output kept on screen
+const HISTORY: usize = 8;
+
+/// Size (in pixels) of the text in the console
+const TEXT_SIZE: f32 = 18.;
+
+/// Every command and its arguments, positions and values are in pixels
+const HELP: &str = "set <param> <value> | spawn <obstacle|blade|pickup> <x> \
+    <y> | seed <hex> | tp <y> | god";
+
+/// What the game has to do after a command, beyond what it did to the run
+pub enum Action {
+    /// Start a new run with the seed
+    Restart(u64),
+}
+
+/// Command line typed into, and the output of the last commands
+#[derive(Default)]
+pub struct Console {
+    /// Set while the console is open and takes the keyboard
+    open: bool,
+
+    /// Command being typed
+    line: String,
+
+    /// Commands which were run and what they printed, oldest first
+    output: VecDeque<String>,
+}
+
+impl Console {
+    /// Toggle the console on tilde, and take what's typed while it's open.
+    /// Enter runs the command on `field`
+    pub fn update(&mut self, field: &mut GameField) -> Option<Action> {
+        // Characters queue up until they're taken, so they always are
+        let mut typed = Vec::new();
+        while let Some(ch) = get_char_pressed() {
+            typed.push(ch);
+        }
+
+        if is_key_pressed(KeyCode::GraveAccent) ||
+                (self.open && is_key_pressed(KeyCode::Escape)) {
+            self.open = !self.open;
+            field.typing = self.open;
+            if self.open && !field.dead && !field.finished {
+                field.pause();
+            }
+            return None;
+        }
+        if !self.open {
+            return None;
+        }
+
+        self.line.extend(typed.into_iter().rev()
+            .filter(|x| !x.is_control() && !"`~".contains(*x)));
+        if is_key_pressed(KeyCode::Backspace) {
+            self.line.pop();
+        }
+        if !is_key_pressed(KeyCode::Enter) {
+            return None;
+        }
+
+        let line = std::mem::take(&mut self.line);
+        let (text, action) = run(field, &line)
+            .unwrap_or_else(|err| (err.to_string(), None));
+
+        // New runs start out with the console closed
+        if action.is_some() {
+            self.open = false;
+            field.typing = false;
+        }
+        for text in [format!("> {}", line), text] {
+            if self.output.len() == HISTORY {
+                self.output.pop_front();
+            }
+            self.output.push_back(text);
+        }
+        action
+    }
+
+    /// Draw the console along the bottom of the screen, if it's open
+    pub fn draw(&self) {
+        if !self.open {
+            return;
+        }
+
+        let height = (HISTORY + 1) as f32 * TEXT_SIZE + 8.;
+        let top = screen_height() - height;
+        draw_rectangle(0., top, screen_width(), height,
+            Color::from_rgba(0, 0, 0, 0xd0));
+        let prompt = format!("> {}_", self.line);
+        for (ii, line) in self.output.iter().chain([&prompt]).enumerate() {
+            let color = if ii == self.output.len() { YELLOW } else { WHITE };
+            draw_text(line, 6., top + (ii + 1) as f32 * TEXT_SIZE, TEXT_SIZE,
+                color);
+        }
+    }
+}
+
+/// Run the command `line` on `field`, returning what it prints and what's
+/// left for the game to do
+pub fn run(field: &mut GameField, line: &str)
+        -> Result<(String, Option<Action>)> {
+    // Convert pixels to the fixed point of the game field
+    let pixels = |value: &str| -> Result<Fxpt> {
+        let value: f64 = value.parse()?;
+        Ok(Fxpt((value * FIXED_POINT_DIVISOR as f64)
+            .clamp(i16::MIN as f64, i16::MAX as f64) as i16))
+    };
+
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let text = match words.as_slice() {
+        []       => return Ok((String::new(), None)),
+        ["help"] => return Ok((HELP.to_string(), None)),
+        ["seed", seed] => {
+            let seed = u64::from_str_radix(seed.trim_start_matches("0x"), 16)?;
+            return Ok((format!("Restarting with seed {:016x}", seed),
+                Some(Action::Restart(seed))));
+        }
+        ["set", name, value] => {
+            let param = Param::ALL.iter().copied()
+                .find(|x| x.name() == *name)
+                .ok_or_else(|| format!("Unknown parameter {:?}", name))?;
+
+            // Forces are in pixels like positions, the rest are counts
+            let raw = match param {
+                Param::Gravity | Param::Impulse => pixels(value)?.0 as i64,
+                _ => value.parse()?,
+            };
+            field.overrides[param as usize] = Some(raw);
+            format!("Set {} to {}", name, value)
+        }
+        ["spawn", kind, x, y] => {
+            let (x, y) = (pixels(x)?, pixels(y)?);
+            if let Some(kind) = PickupKind::from_name(kind) {
+                field.pickups.push(Pickup { x, y, kind });
+            } else if *kind == "obstacle" {
+                field.obstacles.push(
+                    Obstacle::new(x, y, OBSTACLE_WIDTH, OBSTACLE_HEIGHT));
+            } else if *kind == "blade" {
+                let size = Fxpt(BLADE_RADIUS.0 * 2);
+                let mut blade = Obstacle::new(x, y, size, size);
+                blade.kind = ObstacleKind::Blade { angle: 0, spin: 3 };
+                field.obstacles.push(blade);
+            } else {
+                return Err(format!("Unknown kind {:?}", kind).into());
+            }
+            format!("Spawned a {} at {}, {}", kind, words[2], words[3])
+        }
+        ["tp", y] => {
+            let max = GAME_FIELD_HEIGHT.0 - field.player_size().0;
+            field.player_y = Fxpt(pixels(y)?.0.clamp(0, max));
+            field.player_speed = Fxpt(0);
+            format!("Moved to {}", y)
+        }
+        ["god"] => {
+            field.god = !field.god;
+            format!("God mode {}", if field.god { "on" } else { "off" })
+        }
+        _ => return Err(format!("Unknown command {:?}, see help", line).into()),
+    };
+
+    // Anything which changed the run makes it a debug run
+    field.debug = true;
+    Ok((text, None))
+}
@@ -0,0 +1,322 @@
+//! Controllers decide the inputs of each physics frame, so the deterministic
+//! sim can be driven by a human, a replay, or a bot without touching the
+//! physics code
+
+use std::collections::VecDeque;
+use macroquad::prelude::*;
+use crate::{flipped_physics, player_physics, Difficulty, Fxpt, Obstacle, Pickup, Zone};
+use crate::{Control, ZoneKind};
+use crate::{FIXED_POINT_DIVISOR, GAME_FIELD_HEIGHT};
+use crate::{INPUT_FIRE, INPUT_THROTTLE_SHIFT, INPUT_THRUST, THROTTLE_LEVELS};
+use crate::{PLAYER_X, SHOP_ITEMS};
+
+/// Bitmask of `INPUT_*` bits for a single physics frame
+pub type InputBits = u8;
+
+/// Everything a [`Controller`] gets to see before deciding on the inputs of a
+/// physics frame
+#[allow(unused)]
+pub struct GameState<'a> {
+    /// Number of physics frames simulated so far
+    pub frame: u64,
+
+    /// Top of the player
+    pub player_y: Fxpt,
+
+    /// Vertical speed of the player, positive is downwards
+    pub player_speed: Fxpt,
+
+    /// Width and height of the player
+    pub player_size: Fxpt,
+
+    /// Difficulty in effect this frame
+    pub difficulty: Difficulty,
+
+    /// Physics frames the difficulty is evaluated at
+    pub difficulty_frames: u64,
+
+    /// Ceiling and floor segments
+    pub walls: &'a [Obstacle],
+
+    /// Obstacles in the gap between the walls
+    pub obstacles: &'a [Obstacle],
+
+    /// Pickups which haven't been collected yet
+    pub pickups: &'a [Pickup],
+
+    /// Zones which change the scroll speed or gravity
+    pub zones: &'a [Zone],
+
+    /// Shots left
+    pub ammo: u8,
+
+    /// Entry of [`SHOP_ITEMS`] picked, while the shop is open
+    pub shop: Option<u8>,
+}
+
+/// Something which decides on the inputs for each physics frame
+pub trait Controller {
+    /// Decide on the inputs for the next physics frame
+    fn decide(&mut self, state: &GameState) -> InputBits;
+
+    /// Catch up on inputs once per rendered frame, before the physics frames
+    /// due in it get decided. Inputs can come and go between physics frames
+    fn poll(&mut self) {}
+
+    /// Set if the inputs come from a person at the keyboard, who may wander
+    /// off and want the game paused
+    fn interactive(&self) -> bool {
+        false
+    }
+
+    /// Set if the inputs were recorded ahead of time
+    fn recorded(&self) -> bool {
+        false
+    }
+}
+
+/// Keys which thrust, on top of the left mouse button and touches
+const THRUST_KEYS: [KeyCode; 3] = [KeyCode::Space, KeyCode::Up, KeyCode::W];
+
+/// Keys which fire, on top of the right mouse button
+const FIRE_KEYS: [KeyCode; 2] = [KeyCode::LeftControl, KeyCode::RightControl];
+
+/// Inputs from the mouse, keyboard, and touches, all at once so a player can
+/// switch devices mid-run or get help from someone else. Digital inputs are
+/// set if they're set on any device, the throttle is the highest one
+pub struct Human {
+    /// Control scheme the inputs are for
+    control: Control,
+
+    /// Inputs pressed since the last physics frame, which count for the next
+    /// one even if they were let go of before it
+    pressed: InputBits,
+}
+
+impl Human {
+    /// Read inputs for the `control` scheme
+    pub fn new(control: Control) -> Self {
+        Self { control, pressed: 0 }
+    }
+
+    /// Digital inputs held down on any device
+    fn held() -> InputBits {
+        let mut input = 0;
+        if is_mouse_button_down(MouseButton::Left) ||
+                THRUST_KEYS.iter().any(|&x| is_key_down(x)) ||
+                touches().iter().any(|x| !matches!(x.phase,
+                    TouchPhase::Ended | TouchPhase::Cancelled)) {
+            input |= INPUT_THRUST;
+        }
+        if is_mouse_button_down(MouseButton::Right) ||
+                FIRE_KEYS.iter().any(|&x| is_key_down(x)) {
+            input |= INPUT_FIRE;
+        }
+        input
+    }
+
+    /// Digital inputs which got pressed on any device this rendered frame
+    pub fn pressed() -> InputBits {
+        let mut input = 0;
+        if is_mouse_button_pressed(MouseButton::Left) ||
+                THRUST_KEYS.iter().any(|&x| is_key_pressed(x)) ||
+                touches().iter().any(|x| x.phase == TouchPhase::Started) {
+            input |= INPUT_THRUST;
+        }
+        if is_mouse_button_pressed(MouseButton::Right) ||
+                FIRE_KEYS.iter().any(|&x| is_key_pressed(x)) {
+            input |= INPUT_FIRE;
+        }
+        input
+    }
+
+    /// Throttle level, which follows the mouse and touches from off at the
+    /// bottom of the window to all the way up at the top. Thrust keys are
+    /// full throttle
+    fn throttle() -> u8 {
+        let level = |y: f32| {
+            ((1. - y / screen_height()).clamp(0., 1.) *
+                THROTTLE_LEVELS as f32).round() as u8
+        };
+        let keys = if THRUST_KEYS.iter().any(|&x| is_key_down(x)) {
+            THROTTLE_LEVELS
+        } else {
+            0
+        };
+        touches().iter()
+            .filter(|x| {
+                !matches!(x.phase, TouchPhase::Ended | TouchPhase::Cancelled)
+            })
+            .map(|x| level(x.position.y))
+            .chain([level(mouse_position().1), keys])
+            .max().unwrap_or(0)
+    }
+}
+
+impl Controller for Human {
+    fn decide(&mut self, _state: &GameState) -> InputBits {
+        let mut input = std::mem::take(&mut self.pressed) | Self::held();
+        if self.control == Control::Throttle {
+            input |= Self::throttle() << INPUT_THROTTLE_SHIFT;
+        }
+        input
+    }
+
+    fn poll(&mut self) {
+        self.pressed |= Self::pressed();
+    }
+
+    fn interactive(&self) -> bool {
+        true
+    }
+}
+
+/// Recorded inputs from a replay, no inputs once it runs out. The input is
+/// looked up by the physics frame being simulated, so playback stays in sync
+/// with the recording however the rendered frames line up with it
+pub struct Playback(pub VecDeque<InputBits>);
+
+impl Controller for Playback {
+    fn decide(&mut self, state: &GameState) -> InputBits {
+        self.0.get(state.frame as usize).copied().unwrap_or(0)
+    }
+
+    fn recorded(&self) -> bool {
+        true
+    }
+}
+
+/// Distance past the front of the player the autopilot looks for the gap
+/// between the walls it steers towards
+const AUTOPILOT_LOOKAHEAD: Fxpt = Fxpt(60 * FIXED_POINT_DIVISOR);
+
+/// Number of physics frames the autopilot plans ahead for
+const AUTOPILOT_HORIZON: i32 = 40;
+
+/// Granularity (in physics frames) of how long the autopilot considers holding
+/// or releasing thrust for before hovering
+const AUTOPILOT_PLAN_STEP: usize = 2;
+
+/// Reference bot which plans a short way ahead with the real player physics,
+/// assuming everything on screen keeps scrolling at the current speed. It
+/// picks whatever survives the longest, and then ends up closest to the
+/// vertical center of the gap just ahead
+pub struct Autopilot;
+
+impl Controller for Autopilot {
+    fn decide(&mut self, state: &GameState) -> InputBits {
+        // Leave the shop right away, pressing thrust to get to the last
+        // entry and then fire, releasing in between presses
+        if let Some(picked) = state.shop {
+            return if state.frame % 2 == 1 {
+                0
+            } else if picked as usize == SHOP_ITEMS.len() {
+                INPUT_FIRE
+            } else {
+                INPUT_THRUST
+            };
+        }
+
+        let size = state.player_size.0 as i32;
+        let left = PLAYER_X.0 as i32;
+        let height = GAME_FIELD_HEIGHT.0 as i32;
+        let player_y = state.player_y.0 as i32;
+
+        // Gap between the walls ahead. Ceilings start at the top of the
+        // field, everything else is a floor
+        let mut top = 0;
+        let mut bottom = height;
+        let ahead = left + size + AUTOPILOT_LOOKAHEAD.0 as i32;
+        for wall in state.walls.iter().filter(|x| {
+            (x.x.0 as i32) < ahead && (x.x.0 + x.width.0) as i32 > left
+        }) {
+            if wall.y.0 == 0 {
+                top = top.max((wall.y.0 + wall.height.0) as i32);
+            } else {
+                bottom = bottom.min(wall.y.0 as i32);
+            }
+        }
+        let target = (top + bottom) / 2;
+
+        // Bounding boxes of everything which isn't behind the player yet
+        let boxes: Vec<(i32, i32, i32, i32)> = state.walls.iter()
+            .chain(state.obstacles.iter())
+            .filter(|x| (x.x.0 + x.width.0) as i32 > left)
+            .map(|x| {
+                (x.x.0 as i32, x.y.0 as i32, x.width.0 as i32,
+                 x.height.0 as i32)
+            }).collect();
+
+        // The zone the center of the player is in changes the scroll speed,
+        // flips gravity and thrust in flip zones, and changes gravity in
+        // drafts
+        let center = left + size / 2;
+        let zone = state.zones.iter().find(|x| {
+            x.x.0 as i32 <= center && center < x.end()
+        });
+        let scroll = state.difficulty.scroll_speed(state.difficulty_frames);
+        let scroll = zone.map_or(scroll, |x| x.scroll_speed(scroll)).0 as i32;
+        let flipped = zone.is_some_and(|x| x.kind == ZoneKind::Flip);
+        let physics = if flipped { flipped_physics } else { player_physics };
+        let mut difficulty = state.difficulty;
+        difficulty.gravity = Fxpt(difficulty.gravity.0 +
+            zone.map_or(0, |x| x.draft()));
+
+        // Check if the player hits anything after `frame` frames
+        let hits = |y: i32, frame: i32| {
+            boxes.iter().any(|&(x, by, width, bheight)| {
+                let x = x - scroll * frame;
+                x < left + size && x + width > left &&
+                    by < y + size && by + bheight > y
+            })
+        };
+
+        // Fly a plan which holds `hold` for `frames` frames and then hovers,
+        // returning the frames survived and how far off the target it ends.
+        // Faster tick rates plan the same amount of time ahead
+        let substeps = state.difficulty.substeps as i32;
+        let horizon = AUTOPILOT_HORIZON * substeps;
+        let fly = |hold: bool, frames: i32| {
+            let mut y = player_y;
+            let mut speed = state.player_speed;
+            for frame in 0..horizon {
+                let thrust = if frame < frames {
+                    hold
+                } else {
+                    (speed.0 > 0) != flipped
+                };
+                speed = physics(speed, thrust, &difficulty);
+                y = (y + speed.0 as i32 / substeps).clamp(0, height - size);
+                if hits(y, frame + 1) {
+                    return (frame, i32::MAX);
+                }
+            }
+            (horizon, (y + size / 2 - target).abs())
+        };
+
+        // Thrust if the best plan starts out thrusting
+        let best = (1..=horizon)
+            .step_by(AUTOPILOT_PLAN_STEP * substeps as usize)
+            .flat_map(|frames| [(true, frames), (false, frames)])
+            .max_by_key(|&(hold, frames)| {
+                let (survived, miss) = fly(hold, frames);
+                (survived, -miss)
+            });
+        let mut input = 0;
+        if best.is_some_and(|(hold, _)| hold) {
+            input |= INPUT_THRUST;
+        }
+
+        // Shots fire on presses, so pulse the button while an obstacle is in
+        // the way
+        let blocked = state.obstacles.iter().any(|x| {
+            (x.x.0 as i32) > left &&
+                (x.y.0 as i32) < player_y + size &&
+                (x.y.0 + x.height.0) as i32 > player_y
+        });
+        if blocked && state.ammo > 0 && state.frame & 1 == 0 {
+            input |= INPUT_FIRE;
+        }
+        input
+    }
+}
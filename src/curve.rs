@@ -0,0 +1,185 @@
+//! Data-driven difficulty curves. A curve is a table of points in time, each
+//! giving the gap between the walls, the obstacle cadence, and the scroll
+//! speed at that physics frame. Values are interpolated between points and
+//! held after the last one
+//!
+//! Curve files have one point per line:
+//!
+//! ```text
+//! # frame  gap  obstacle_frames  scroll_speed
+//! 0        250  30               8
+//! 2240     180  30               9.2
+//! ```
+//!
+//! The gap is in pixels and the scroll speed in (possibly fractional) pixels
+//! per physics frame
+
+use crate::{Fxpt, Result, FIXED_POINT_DIVISOR, MAX_GAP, MAX_SCROLL_SPEED};
+use crate::MIN_GAP;
+use crate::error;
+
+/// Maximum number of points in a [`Curve`], such that curves can be stored
+/// inline in the [`crate::Difficulty`]
+const MAX_POINTS: usize = 16;
+
+/// A point on a [`Curve`]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Point {
+    /// Physics frame this point is at
+    pub frame: u64,
+
+    /// Gap (in pixels) between the walls
+    pub gap: i16,
+
+    /// Minimum number of physics frames between obstacles
+    pub obstacle_frames: u64,
+
+    /// Distance the map scrolls each physics frame
+    pub scroll_speed: Fxpt,
+}
+
+/// Unused point for filling out a [`Curve`]
+const EMPTY: Point = Point {
+    frame: 0, gap: 0, obstacle_frames: 0, scroll_speed: Fxpt(0),
+};
+
+/// A piecewise-linear difficulty curve
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Curve {
+    /// Points of the curve, sorted by frame. Only the first `len` are used
+    points: [Point; MAX_POINTS],
+
+    /// Number of points in the curve
+    len: usize,
+}
+
+impl Curve {
+    /// Create a curve from `points`, making sure it's usable
+    fn new(points: &[Point]) -> Result<Self> {
+        if points.is_empty() || points.len() > MAX_POINTS {
+            return Err(format!("Difficulty curves need 1 to {} points",
+                MAX_POINTS).into());
+        }
+
+        for (idx, point) in points.iter().enumerate() {
+            if idx > 0 && point.frame <= points[idx - 1].frame {
+                return Err("Difficulty curve frames must increase".into());
+            }
+            if !(MIN_GAP..=MAX_GAP).contains(&point.gap) {
+                return Err(format!("Difficulty curve gap {} is not between \
+                    {} and {}", point.gap, MIN_GAP, MAX_GAP).into());
+            }
+            if point.obstacle_frames == 0 || point.scroll_speed.0 <= 0 {
+                return Err("Difficulty curve cadence and speed must be \
+                    positive".into());
+            }
+            if point.scroll_speed > MAX_SCROLL_SPEED {
+                return Err(format!("Difficulty curve speed {} is past the \
+                    limit of {}", f32::from(point.scroll_speed),
+                    f32::from(MAX_SCROLL_SPEED)).into());
+            }
+        }
+
+        let mut ret = Self { points: [EMPTY; MAX_POINTS], len: points.len() };
+        ret.points[..points.len()].copy_from_slice(points);
+        Ok(ret)
+    }
+
+    /// Load the curve file at `path`
+    pub fn load(path: &str) -> Result<Self> {
+        Self::parse(&error::read_to_string("difficulty curve", path)?)
+            .map_err(|err| err.in_file("difficulty curve", path))
+    }
+
+    /// Parse a curve file
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut points = Vec::new();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                continue;
+            }
+            if fields.len() != 4 {
+                return Err(format!("Malformed difficulty curve line {:?}",
+                    line).into());
+            }
+
+            let speed: f32 = fields[3].parse()?;
+            points.push(Point {
+                frame:           fields[0].parse()?,
+                gap:             fields[1].parse()?,
+                obstacle_frames: fields[2].parse()?,
+                scroll_speed:    Fxpt((speed * FIXED_POINT_DIVISOR as f32)
+                    .round() as i16),
+            });
+        }
+        Self::new(&points)
+    }
+
+    /// Decode a curve from its replay header form, see [`Curve::encode`]
+    pub fn decode(value: &str) -> Result<Self> {
+        let mut points = Vec::new();
+        for point in value.split(',') {
+            let fields: Vec<&str> = point.split(':').collect();
+            if fields.len() != 4 {
+                return Err(format!("Malformed difficulty curve point {:?}",
+                    point).into());
+            }
+            points.push(Point {
+                frame:           fields[0].parse()?,
+                gap:             fields[1].parse()?,
+                obstacle_frames: fields[2].parse()?,
+                scroll_speed:    Fxpt(fields[3].parse()?),
+            });
+        }
+        Self::new(&points)
+    }
+
+    /// Encode the curve for a replay header, as comma separated
+    /// `frame:gap:obstacle_frames:scroll_speed` points with the scroll speed
+    /// in raw fixed-point
+    pub fn encode(&self) -> String {
+        self.points().iter().map(|x| {
+            format!("{}:{}:{}:{}", x.frame, x.gap, x.obstacle_frames,
+                x.scroll_speed.0)
+        }).collect::<Vec<_>>().join(",")
+    }
+
+    /// Override the obstacle cadence of every point with `frames`
+    pub fn set_obstacle_frames(&mut self, frames: u64) {
+        for point in self.points[..self.len].iter_mut() {
+            point.obstacle_frames = frames;
+        }
+    }
+
+    /// Get the points of the curve
+    pub fn points(&self) -> &[Point] {
+        &self.points[..self.len]
+    }
+
+    /// Get the interpolated point at physics frame `frames`
+    pub fn at(&self, frames: u64) -> Point {
+        let points = self.points();
+        let Some(idx) = points.iter().position(|x| x.frame > frames) else {
+            return points[points.len() - 1];
+        };
+        if idx == 0 {
+            return points[0];
+        }
+
+        let (a, b) = (points[idx - 1], points[idx]);
+        let lerp = |x: i64, y: i64| {
+            x + (y - x) * (frames - a.frame) as i64 /
+                (b.frame - a.frame) as i64
+        };
+        Point {
+            frame:           frames,
+            gap:             lerp(a.gap as i64, b.gap as i64) as i16,
+            obstacle_frames: lerp(a.obstacle_frames as i64,
+                                  b.obstacle_frames as i64) as u64,
+            scroll_speed:    Fxpt(lerp(a.scroll_speed.0 as i64,
+                                       b.scroll_speed.0 as i64) as i16),
+        }
+    }
+}
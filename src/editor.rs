@@ -0,0 +1,446 @@
+//! In-game level editor. Scroll through a [`Level`], place, drag, and delete
+//! walls, obstacles, pickups, and zones, playtest it, and save it back out
+
+use std::convert::Infallible;
+use macroquad::prelude::*;
+use crate::level::{Block, Item, Level, Region, Wall};
+use crate::{field_transform, Fxpt, GameField, Human, PickupKind, Result};
+use crate::{Filter, Rules, Zone};
+use crate::{ZoneKind, FIXED_POINT_DIVISOR, GAME_FIELD_HEIGHT, GAME_FIELD_WIDTH};
+use crate::{OBSTACLE_HEIGHT, OBSTACLE_WIDTH, PICKUP_SIZE, ZONE_COLUMNS};
+
+/// Vertical grid (in pixels) everything placed gets snapped to
+const GRID: i16 = 5;
+
+/// Length (in columns) of newly created levels
+const NEW_LEVEL_LENGTH: u32 = 200;
+
+/// Width of a column in pixels
+const COLUMN: i16 = OBSTACLE_WIDTH.0 / FIXED_POINT_DIVISOR;
+
+/// Height of the game field in pixels
+const HEIGHT: i16 = GAME_FIELD_HEIGHT.0 / FIXED_POINT_DIVISOR;
+
+/// Number of columns visible at once
+const VISIBLE: u32 = (GAME_FIELD_WIDTH.0 / OBSTACLE_WIDTH.0) as u32;
+
+/// What gets placed when clicking
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    /// Move the top or bottom edge of the gap, whichever is closer
+    Wall,
+
+    /// Place a static obstacle
+    Obstacle,
+
+    /// Place a pickup of the given kind
+    Pickup(PickupKind),
+
+    /// Place a speed zone of the given kind
+    Zone(ZoneKind),
+}
+
+impl Tool {
+    /// Name of the tool for the HUD
+    fn name(&self) -> String {
+        match self {
+            Tool::Wall      => "wall".into(),
+            Tool::Obstacle  => "obstacle".into(),
+            Tool::Pickup(x) => format!("pickup {}", x.name()),
+            Tool::Zone(x)   => format!("zone {}", x.name()),
+        }
+    }
+
+    /// Switch to the next variant of the tool
+    fn cycle(&self) -> Self {
+        match *self {
+            Tool::Pickup(x) => {
+                let idx = PickupKind::ALL.iter().position(|&y| y == x)
+                    .unwrap_or(0);
+                Tool::Pickup(PickupKind::ALL[(idx + 1) % PickupKind::ALL.len()])
+            }
+            Tool::Zone(ZoneKind::Fast)      => Tool::Zone(ZoneKind::Slow),
+            Tool::Zone(ZoneKind::Slow)      => Tool::Zone(ZoneKind::Flip),
+            Tool::Zone(ZoneKind::Flip)      => Tool::Zone(ZoneKind::Updraft),
+            Tool::Zone(ZoneKind::Updraft)   => Tool::Zone(ZoneKind::Downdraft),
+            Tool::Zone(ZoneKind::Downdraft) => Tool::Zone(ZoneKind::Fast),
+            x => x,
+        }
+    }
+}
+
+/// Something in the level which can be dragged around
+#[derive(Clone, Copy)]
+enum Selection {
+    /// Index into [`Level::blocks`]
+    Block(usize),
+
+    /// Index into [`Level::items`]
+    Item(usize),
+}
+
+/// State of the level editor
+pub struct Editor {
+    /// Path the level gets saved to
+    path: String,
+
+    /// Level being edited
+    level: Level,
+
+    /// Rules the level gets playtested with
+    rules: Rules,
+
+    /// Leftmost visible column
+    view: u32,
+
+    /// What gets placed when clicking
+    tool: Tool,
+
+    /// Height (in pixels) of newly placed obstacles
+    obstacle_height: i16,
+
+    /// What's being dragged with the mouse
+    dragging: Option<Selection>,
+
+    /// Message shown on the HUD, like the result of the last save
+    status: String,
+}
+
+impl Editor {
+    /// Open the level at `path` for editing, starting a new level if it
+    /// doesn't exist yet
+    pub fn new(path: String, rules: Rules) -> Result<Self> {
+        let (level, status) = match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let level = Level::parse(&text)
+                    .map_err(|err| err.in_file("level", &path))?;
+                (level, "Loaded".to_string())
+            }
+            Err(err) => {
+                log_debug!("Starting a new level, couldn't read {}: {}", path,
+                    err);
+                (Level {
+                    length: NEW_LEVEL_LENGTH,
+                    ..Default::default()
+                }, "New level".to_string())
+            }
+        };
+
+        Ok(Self {
+            path,
+            level,
+            rules,
+            view:            0,
+            tool:            Tool::Wall,
+            obstacle_height: OBSTACLE_HEIGHT.0 / FIXED_POINT_DIVISOR,
+            dragging:        None,
+            status,
+        })
+    }
+
+    /// Get the column and Y coord (snapped to the [`GRID`]) under the mouse,
+    /// if it's over the game field
+    fn cursor(&self) -> Option<(u32, i16)> {
+        let (offset_x, offset_y, scale) = field_transform();
+        let (x, y) = mouse_position();
+        let x = (x - offset_x) / scale;
+        let y = (y - offset_y) / scale;
+        if x < 0. || y < 0. || x >= f32::from(GAME_FIELD_WIDTH) ||
+                y >= f32::from(GAME_FIELD_HEIGHT) {
+            return None;
+        }
+
+        let y = ((y / GRID as f32).round() as i16 * GRID).min(HEIGHT);
+        Some((self.view + x as u32 / COLUMN as u32, y))
+    }
+
+    /// Find the obstacle or pickup at `column` and `y`
+    fn hit(&self, column: u32, y: i16) -> Option<Selection> {
+        let size = PICKUP_SIZE.0 / FIXED_POINT_DIVISOR;
+        self.level.items.iter().position(|x| {
+            x.column == column && (x.y..x.y + size).contains(&y)
+        }).map(Selection::Item).or_else(|| {
+            self.level.blocks.iter().position(|x| {
+                x.column == column && (x.y..x.y + x.height).contains(&y)
+            }).map(Selection::Block)
+        })
+    }
+
+    /// Place whatever the current tool places at `column` and `y`
+    fn place(&mut self, column: u32, y: i16) {
+        let level = &mut self.level;
+        match self.tool {
+            Tool::Wall => {
+                let (mut top, mut bottom) =
+                    level.gap(column, self.rules.difficulty.start_gap);
+                if y < (top + bottom) / 2 {
+                    top = y.min(bottom - GRID);
+                } else {
+                    bottom = y.max(top + GRID);
+                }
+
+                match level.walls.iter_mut().find(|x| x.column == column) {
+                    Some(wall) => {
+                        wall.top = top;
+                        wall.bottom = bottom;
+                    }
+                    None => level.walls.push(Wall { column, top, bottom }),
+                }
+            }
+            Tool::Obstacle => {
+                let height = self.obstacle_height;
+                level.blocks.push(Block {
+                    column,
+                    y: (y - height / 2).clamp(0, HEIGHT - height),
+                    height,
+                });
+            }
+            Tool::Pickup(kind) => {
+                let size = PICKUP_SIZE.0 / FIXED_POINT_DIVISOR;
+                level.items.push(Item {
+                    column,
+                    y: (y - size / 2).clamp(0, HEIGHT - size),
+                    kind,
+                });
+            }
+            Tool::Zone(kind) => {
+                level.regions.push(Region {
+                    column,
+                    columns: ZONE_COLUMNS,
+                    kind,
+                });
+            }
+        }
+        level.sort();
+    }
+
+    /// Delete whatever is at `column` and `y`. Without an obstacle or pickup
+    /// there, the wall or zone tools delete the wall or zone in the column
+    fn delete(&mut self, column: u32, y: i16) {
+        let hit = self.hit(column, y);
+        let level = &mut self.level;
+        match hit {
+            Some(Selection::Block(idx)) => { level.blocks.remove(idx); }
+            Some(Selection::Item(idx))  => { level.items.remove(idx); }
+            None => match self.tool {
+                Tool::Wall => level.walls.retain(|x| x.column != column),
+                Tool::Zone(_) => level.regions.retain(|x| {
+                    !(x.column..x.column + x.columns as u32).contains(&column)
+                }),
+                _ => {}
+            },
+        }
+    }
+
+    /// Move the dragged obstacle or pickup to `column` and `y`
+    fn drag(&mut self, selection: Selection, column: u32, y: i16) {
+        match selection {
+            Selection::Block(idx) => {
+                let block = &mut self.level.blocks[idx];
+                block.column = column;
+                block.y =
+                    (y - block.height / 2).clamp(0, HEIGHT - block.height);
+            }
+            Selection::Item(idx) => {
+                let size = PICKUP_SIZE.0 / FIXED_POINT_DIVISOR;
+                let item = &mut self.level.items[idx];
+                item.column = column;
+                item.y = (y - size / 2).clamp(0, HEIGHT - size);
+            }
+        }
+    }
+
+    /// Handle the inputs for a frame. Returns `true` if a playtest was
+    /// requested
+    fn update(&mut self) -> bool {
+        // Scroll through the level, a screen at a time with shift held
+        let step = if is_key_down(KeyCode::LeftShift) { VISIBLE } else { 1 };
+        if is_key_pressed(KeyCode::Left) {
+            self.view = self.view.saturating_sub(step);
+        }
+        if is_key_pressed(KeyCode::Right) {
+            self.view = (self.view + step).min(self.level.length);
+        }
+
+        // Pick tools
+        if is_key_pressed(KeyCode::Key1) { self.tool = Tool::Wall; }
+        if is_key_pressed(KeyCode::Key2) { self.tool = Tool::Obstacle; }
+        if is_key_pressed(KeyCode::Key3) {
+            self.tool = Tool::Pickup(PickupKind::Coin);
+        }
+        if is_key_pressed(KeyCode::Key4) {
+            self.tool = Tool::Zone(ZoneKind::Fast);
+        }
+        if is_key_pressed(KeyCode::Tab) { self.tool = self.tool.cycle(); }
+        if is_key_pressed(KeyCode::Up) {
+            self.obstacle_height = (self.obstacle_height + GRID).min(HEIGHT);
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.obstacle_height = (self.obstacle_height - GRID).max(GRID);
+        }
+
+        if is_key_down(KeyCode::LeftControl) && is_key_pressed(KeyCode::S) {
+            self.status = match std::fs::write(&self.path,
+                    self.level.serialize()) {
+                Ok(())   => format!("Saved to {}", self.path),
+                Err(err) => {
+                    log_error!("Failed to save {}: {}", self.path, err);
+                    format!("Failed to save: {}", err)
+                }
+            };
+        }
+
+        if let Some((column, y)) = self.cursor() {
+            if is_key_pressed(KeyCode::F) {
+                self.level.length = column;
+            }
+
+            if is_mouse_button_pressed(MouseButton::Left) {
+                self.dragging = self.hit(column, y);
+                if self.dragging.is_none() {
+                    self.place(column, y);
+                }
+            } else if is_mouse_button_down(MouseButton::Left) {
+                if let Some(selection) = self.dragging {
+                    self.drag(selection, column, y);
+                }
+            }
+
+            if is_mouse_button_pressed(MouseButton::Right) {
+                self.delete(column, y);
+            }
+        }
+
+        // Indices are only stable while dragging, sort once we let go
+        if self.dragging.is_some() &&
+                !is_mouse_button_down(MouseButton::Left) {
+            self.dragging = None;
+            self.level.sort();
+        }
+
+        is_key_pressed(KeyCode::Enter)
+    }
+
+    /// Draw the visible part of the level and the HUD
+    fn draw(&self) {
+        let (offset_x, offset_y, scale) = field_transform();
+        let target_w = scale * f32::from(GAME_FIELD_WIDTH);
+        let target_h = scale * f32::from(GAME_FIELD_HEIGHT);
+
+        // Convert a column and Y coord into screen coords, if visible
+        let to_screen = |column: u32, y: i16| {
+            let x = (column as i64 - self.view as i64) * COLUMN as i64;
+            (offset_x + x as f32 * scale, offset_y + y as f32 * scale)
+        };
+        let visible = |column: u32| {
+            (self.view..self.view + VISIBLE).contains(&column)
+        };
+        let column_w = COLUMN as f32 * scale;
+
+        clear_background(BLACK);
+
+        for region in &self.level.regions {
+            let start = region.column.max(self.view);
+            let end = (region.column + region.columns as u32)
+                .min(self.view + VISIBLE);
+            if start < end {
+                let (x, y) = to_screen(start, 0);
+                let zone = Zone {
+                    x: Fxpt(0), width: Fxpt(0),
+                    kind: region.kind,
+                };
+                draw_rectangle(x, y, (end - start) as f32 * column_w, target_h,
+                    zone.color());
+            }
+        }
+
+        let wall_color = Color::from_rgba(0x80, 0x80, 0xa0, 0xff);
+        for column in self.view..self.view + VISIBLE {
+            let (top, bottom) =
+                self.level.gap(column, self.rules.difficulty.start_gap);
+            let (x, y) = to_screen(column, 0);
+            draw_rectangle(x, y, column_w, top as f32 * scale, wall_color);
+            let (_, y) = to_screen(column, bottom);
+            draw_rectangle(x, y, column_w, (HEIGHT - bottom) as f32 * scale,
+                wall_color);
+
+            // Mark columns which start a new wall gap
+            if self.level.walls.iter().any(|x| x.column == column) {
+                draw_rectangle_lines(x, offset_y, column_w, target_h, 2.,
+                    YELLOW);
+            }
+        }
+
+        for block in self.level.blocks.iter().filter(|x| visible(x.column)) {
+            let (x, y) = to_screen(block.column, block.y);
+            draw_rectangle(x, y, column_w, block.height as f32 * scale,
+                Color::from_rgba(0xff, 0xa0, 0xa0, 0xff));
+        }
+
+        let size = PICKUP_SIZE.0 / FIXED_POINT_DIVISOR;
+        for item in self.level.items.iter().filter(|x| visible(x.column)) {
+            let (x, y) = to_screen(item.column, item.y + size / 2);
+            draw_poly(x + size as f32 / 2. * scale, y, 6,
+                size as f32 / 2. * scale, 0., item.kind.color());
+        }
+
+        if visible(self.level.length) {
+            let (x, y) = to_screen(self.level.length, 0);
+            draw_rectangle(x, y, 4. * scale, target_h, WHITE);
+        }
+
+        // Highlight the column under the mouse
+        if let Some((column, y)) = self.cursor() {
+            let (x, _) = to_screen(column, 0);
+            draw_rectangle_lines(x, offset_y, column_w, target_h, 2., GREEN);
+            draw_line(x, offset_y + y as f32 * scale, x + column_w,
+                offset_y + y as f32 * scale, 2., GREEN);
+        }
+
+        draw_rectangle_lines(offset_x, offset_y, target_w, target_h, 2., BLUE);
+
+        draw_text(&tr!("editor", self.path, self.tool.name(),
+            self.obstacle_height, self.view, self.level.length, self.status),
+            0., 20., 32., WHITE);
+        draw_text(tr!("editor_help"), 0., 42., 20., GRAY);
+    }
+}
+
+/// Run the editor for the level at `path` until the game is closed, which
+/// only returns if the level couldn't be opened
+pub async fn run(path: String, rules: Rules) -> Result<Infallible> {
+    let mut editor = Editor::new(path, rules)?;
+    loop {
+        if editor.update() {
+            playtest(&editor).await?;
+        }
+
+        editor.draw();
+        next_frame().await;
+    }
+}
+
+/// Play the level being edited from the leftmost visible column until escape
+/// is pressed
+async fn playtest(editor: &Editor) -> Result<()> {
+    let start = || {
+        let mut field = GameField::new(editor.rules);
+        field.level = Some(editor.level.clone());
+        field.column = editor.view;
+        field
+    };
+
+    let mut field = start();
+    let mut human = Human::new(editor.rules.control);
+    let mut filter = Filter::default();
+    while !is_key_pressed(KeyCode::Escape) {
+        if field.render(&mut human, &mut filter)? {
+            field = start();
+        }
+
+        draw_text(tr!("playtest"), 0., 20., 32., WHITE);
+        next_frame().await;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,299 @@
+//! Gym-style environment on top of the headless simulation, so bots can be
+//! trained against the real game logic. Everything is in raw fixed point,
+//! exactly as the simulation sees it
+
+use crate::{GameField, Level, Mutator, Preset, Replay, Result, Rules};
+use crate::Script;
+use crate::{INPUT_FIRE, INPUT_THRUST, MAX_REPLAY_FRAMES, PLAYER_X};
+
+/// Action bit which thrusts upwards
+pub const ACTION_THRUST: u8 = INPUT_THRUST;
+
+/// Action bit for the fire button, shots fire when it gets pressed
+pub const ACTION_FIRE: u8 = INPUT_FIRE;
+
+/// Number of upcoming wall columns in an [`Observation`]
+pub const OBSERVED_COLUMNS: usize = 8;
+
+/// Number of upcoming obstacles in an [`Observation`]
+pub const OBSERVED_OBSTACLES: usize = 8;
+
+/// Names of the difficulty presets, for [`Env::with_preset`]
+pub fn presets() -> impl Iterator<Item = &'static str> {
+    Preset::ALL.iter().map(|x| x.name())
+}
+
+/// Names of the mutators, for [`Env::enable_mutator`]
+pub fn mutators() -> impl Iterator<Item = &'static str> {
+    Mutator::ALL.iter().map(|x| x.name())
+}
+
+/// Bounding box of an obstacle
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Rect {
+    pub x:      i16,
+    pub y:      i16,
+    pub width:  i16,
+    pub height: i16,
+}
+
+/// Gap between the ceiling and the floor of a wall column
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Column {
+    pub x:      i16,
+    pub top:    i16,
+    pub bottom: i16,
+}
+
+/// What the agent gets to see each step. Entries which aren't there are all
+/// zeros
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Observation {
+    /// Number of physics frames simulated so far
+    pub frame: u64,
+
+    /// Top of the player
+    pub player_y: i16,
+
+    /// Vertical speed of the player, positive is downwards
+    pub player_speed: i16,
+
+    /// Width and height of the player
+    pub player_size: i16,
+
+    /// Nearest wall columns which aren't fully behind the player, in order
+    pub columns: [Column; OBSERVED_COLUMNS],
+
+    /// Nearest obstacles which aren't fully behind the player, in order
+    pub obstacles: [Rect; OBSERVED_OBSTACLES],
+}
+
+/// Outcome of playing back a replay
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Verdict {
+    /// Final score
+    pub score: u64,
+
+    /// Number of physics frames simulated
+    pub frames: u64,
+
+    /// Seed and hash of the inputs, as shown on the death screen
+    pub run: String,
+
+    /// Hash of the simulation state the game ended in
+    pub state: u64,
+}
+
+/// A game which is stepped one physics frame at a time
+pub struct Env {
+    /// Rules every reset starts with, apart from the seed
+    rules: Rules,
+
+    /// Game being played
+    field: GameField,
+}
+
+impl Env {
+    /// Create an environment playing the default rules
+    pub fn new() -> Self {
+        Self::with_rules(Rules::default())
+    }
+
+    /// Create an environment playing the preset named `name`
+    pub fn with_preset(name: &str) -> Option<Self> {
+        let preset = Preset::from_name(name)?;
+        Some(Self::with_rules(Rules {
+            preset,
+            difficulty: preset.difficulty(),
+            ..Default::default()
+        }))
+    }
+
+    fn with_rules(rules: Rules) -> Self {
+        Self { rules, field: GameField::new(rules) }
+    }
+
+    /// Enable the mutator named `name` from the next [`Env::reset`] on.
+    /// Returns `false` if there is no such mutator
+    pub fn enable_mutator(&mut self, name: &str) -> bool {
+        match Mutator::from_name(name) {
+            Some(mutator) => {
+                self.rules.mutators |= mutator.bit();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Start a new game with `seed`
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.rules.seed = seed;
+        self.field = GameField::new(self.rules);
+        self.observe()
+    }
+
+    /// Simulate a physics frame with the `action` bitmask of `ACTION_*` bits.
+    /// The reward is the score gained, and the game is done once the player
+    /// is out of lives or across the finish line
+    pub fn step(&mut self, action: u8) -> (Observation, i64, bool) {
+        if self.done() {
+            return (self.observe(), 0, true);
+        }
+
+        let score = self.field.score();
+        self.field.step(action);
+        let reward = self.field.score() as i64 - score as i64;
+        (self.observe(), reward, self.done())
+    }
+
+    /// Check if the game is over
+    pub fn done(&self) -> bool {
+        self.field.dead || self.field.finished
+    }
+
+    /// Current score of the game
+    pub fn score(&self) -> u64 {
+        self.field.score()
+    }
+
+    /// Hash of the simulation state, which is the same on every platform for
+    /// the same rules and actions
+    pub fn state_hash(&self) -> u64 {
+        self.field.state_hash()
+    }
+
+    /// Play back the replay file `bytes` until the game is over, running out
+    /// of inputs the same way as in the game. Levels and scripts the replay
+    /// was played with are loaded from disk
+    pub fn verify(bytes: &[u8]) -> Result<Verdict> {
+        let field = play(bytes)?;
+        Ok(Verdict {
+            score:  field.score(),
+            frames: field.physics_frames,
+            run:    field.run_id(),
+            state:  field.state_hash(),
+        })
+    }
+
+    /// Observe the current state of the game
+    pub fn observe(&self) -> Observation {
+        let field = &self.field;
+        let mut ret = Observation {
+            frame:        field.physics_frames,
+            player_y:     field.player_y.0,
+            player_speed: field.player_speed.0,
+            player_size:  field.player_size().0,
+            ..Default::default()
+        };
+
+        // Ceilings start at the top of the field, and share their column with
+        // a floor
+        let mut columns: Vec<Column> = field.walls.iter()
+            .filter(|x| x.y.0 == 0 && x.x.0 + x.width.0 > PLAYER_X.0)
+            .map(|ceiling| {
+                let bottom = field.walls.iter()
+                    .find(|x| x.y.0 != 0 && x.x.0 == ceiling.x.0)
+                    .map_or(ceiling.height.0, |x| x.y.0);
+                Column {
+                    x:   ceiling.x.0,
+                    top: ceiling.height.0,
+                    bottom,
+                }
+            }).collect();
+        columns.sort_by_key(|x| x.x);
+        for (slot, column) in ret.columns.iter_mut().zip(columns) {
+            *slot = column;
+        }
+
+        let mut obstacles: Vec<Rect> = field.obstacles.iter()
+            .filter(|x| x.x.0 + x.width.0 > PLAYER_X.0)
+            .map(|x| Rect {
+                x:      x.x.0,
+                y:      x.y.0,
+                width:  x.width.0,
+                height: x.height.0,
+            }).collect();
+        obstacles.sort_by_key(|x| x.x);
+        for (slot, obstacle) in ret.obstacles.iter_mut().zip(obstacles) {
+            *slot = obstacle;
+        }
+
+        ret
+    }
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Check that a replay which got played back until it ended on physics frame
+/// `ended` ended on the `recorded` frame like the run it was recorded from
+pub fn check_frames(ended: u64, recorded: u64) -> Result<()> {
+    if ended != recorded {
+        return Err(format!("Replay desynced, it ended on physics frame {} \
+            but was recorded ending on {}", ended, recorded).into());
+    }
+    Ok(())
+}
+
+/// Check that a replay which got played back until it ended with the
+/// integrity hash `ended` played out like the run it was recorded from,
+/// which ended with `recorded`
+pub fn check_integrity(ended: u64, recorded: u64) -> Result<()> {
+    if ended != recorded {
+        return Err(format!("Replay desynced, it ended with integrity hash \
+            {:016x} but was recorded ending with {:016x}", ended, recorded)
+            .into());
+    }
+    Ok(())
+}
+
+/// Check that a replay which got played back until physics frame `end` is
+/// over by then, rather than still going
+pub(crate) fn check_over(field: &GameField, end: u64) -> Result<()> {
+    if !field.dead && !field.finished {
+        return Err(format!("Replay is still going on physics frame {}, \
+            where it was recorded ending or the most replays play for",
+            end).into());
+    }
+    Ok(())
+}
+
+/// Play back the replay file `bytes` like [`Env::verify`], handing back the
+/// game it ended in
+pub(crate) fn play(bytes: &[u8]) -> Result<GameField> {
+    let replay = Replay::parse(bytes, Rules::default())?;
+    let mut field = GameField::new(replay.rules);
+    field.level = replay.level.as_ref()
+        .map(|path| Level::load(path))
+        .transpose()?;
+    field.script = replay.script.as_ref()
+        .map(|(path, hash)| Script::load_recorded(path, *hash))
+        .transpose()?;
+    field.votes = replay.votes;
+
+    // Replays which record the frame they ended on don't get played past it,
+    // and no replay gets played past the cap
+    let end = replay.frames.unwrap_or(MAX_REPLAY_FRAMES);
+    for input in replay.inputs.iter().copied()
+            .chain(std::iter::repeat(0)) {
+        if field.dead || field.finished || field.physics_frames >= end {
+            break;
+        }
+        field.step(input);
+    }
+    if let Some(frames) = replay.frames {
+        check_frames(field.physics_frames, frames)?;
+    }
+    check_over(&field, end)?;
+    if let Some(integrity) = replay.integrity {
+        check_integrity(field.integrity, integrity)?;
+    }
+
+    Ok(field)
+}
@@ -0,0 +1,121 @@
+//! Errors from loading replays, levels, and difficulty curves, and from bad
+//! command line arguments
+
+use std::fmt;
+use std::io;
+
+/// Everything which can go wrong outside of the simulation
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a `what` file at `path` failed
+    Io { what: &'static str, path: String, err: io::Error },
+
+    /// The `what` file at `path` couldn't be parsed
+    Corrupt { what: &'static str, path: String, msg: String },
+
+    /// A value, argument, or part of a file which doesn't make sense
+    Invalid(String),
+}
+
+/// Result with an [`Error`]
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Blame an [`Error::Invalid`] on the `what` file at `path`
+    pub fn in_file(self, what: &'static str, path: &str) -> Self {
+        match self {
+            Error::Invalid(msg) => Error::Corrupt {
+                what, path: path.to_string(), msg,
+            },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { what, path, err }
+                    if err.kind() == io::ErrorKind::NotFound => {
+                write!(f, "Couldn't find the {} file {}", what, path)
+            }
+            Error::Io { what, path, err } => {
+                write!(f, "Couldn't access the {} file {}: {}", what, path,
+                    err)
+            }
+            Error::Corrupt { what, path, msg } => {
+                write!(f, "The {} file {} is corrupt: {}", what, path, msg)
+            }
+            Error::Invalid(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Self {
+        Error::Invalid(msg)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(msg: &str) -> Self {
+        Error::Invalid(msg.to_string())
+    }
+}
+
+impl From<std::num::ParseIntError> for Error {
+    fn from(err: std::num::ParseIntError) -> Self {
+        Error::Invalid(err.to_string())
+    }
+}
+
+impl From<std::num::ParseFloatError> for Error {
+    fn from(err: std::num::ParseFloatError) -> Self {
+        Error::Invalid(err.to_string())
+    }
+}
+
+impl From<std::str::Utf8Error> for Error {
+    fn from(err: std::str::Utf8Error) -> Self {
+        Error::Invalid(err.to_string())
+    }
+}
+
+/// Read the `what` file at `path`, see [`crate::storage`]
+pub fn read(what: &'static str, path: &str) -> Result<Vec<u8>> {
+    crate::storage::read(path).map_err(|err| Error::Io {
+        what, path: path.to_string(), err,
+    })
+}
+
+/// Read the `what` file at `path` as text
+pub fn read_to_string(what: &'static str, path: &str) -> Result<String> {
+    String::from_utf8(read(what, path)?).map_err(|err| Error::Io {
+        what, path: path.to_string(),
+        err: io::Error::new(io::ErrorKind::InvalidData, err),
+    })
+}
+
+/// Write `contents` to the `what` file at `path`
+pub fn write(what: &'static str, path: &str, contents: impl AsRef<[u8]>)
+        -> Result<()> {
+    crate::storage::write(path, contents.as_ref()).map_err(|err| Error::Io {
+        what, path: path.to_string(), err,
+    })
+}
+
+/// Remove the `what` file at `path`
+pub fn remove(what: &'static str, path: &str) -> Result<()> {
+    crate::storage::remove(path).map_err(|err| Error::Io {
+        what, path: path.to_string(), err,
+    })
+}
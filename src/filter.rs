@@ -0,0 +1,196 @@
+//! Post-processing of the game field. When a filter is on, the field gets
+//! drawn into a render target first, which then goes to the screen through
+//! the filter's material
+//!
+//! Custom filters are GLSL 100 fragment shaders, which get the field as the
+//! `Texture` sampler at the `uv` varying, along with these uniforms:
+//!
+//! - `float Time`, seconds since the run started
+//! - `float Score`, the current score
+//! - `float Flash`, which jumps to 1 on deaths and shield hits and fades out
+
+use macroquad::prelude::*;
+use miniquad::{ShaderError, UniformType};
+use crate::{error, Result};
+use crate::GAME_FIELD_HEIGHT;
+
+/// Vertex shader passing the texture coords through
+const VERTEX: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+"#;
+
+/// CRT look with a scanline per game field row, a slight barrel distortion,
+/// and a vignette. `LINES` gets replaced with the number of rows
+const CRT: &str = r#"#version 100
+precision mediump float;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform sampler2D Texture;
+
+void main() {
+    // Bulge the picture out from the middle, leaving the corners black
+    vec2 centered = uv * 2.0 - 1.0;
+    centered *= 1.0 + dot(centered, centered) * 0.03;
+    vec2 coord = centered * 0.5 + 0.5;
+    if (coord.x < 0.0 || coord.x > 1.0 || coord.y < 0.0 || coord.y > 1.0) {
+        gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec3 rgb = texture2D(Texture, coord).rgb;
+    rgb *= 0.8 + 0.2 * sin(coord.y * LINES * 6.2831853);
+    rgb *= 1.0 - dot(centered, centered) * 0.2;
+    gl_FragColor = vec4(rgb, 1.0) * color;
+}
+"#;
+
+/// Uniforms passed to the filters
+pub struct Uniforms {
+    /// Seconds since the run started
+    pub time: f32,
+
+    /// Current score
+    pub score: f32,
+
+    /// 1 right after a death or shield hit, fading out to 0
+    pub flash: f32,
+}
+
+/// Post-processing of the game field
+#[derive(Default)]
+pub struct Filter {
+    /// Draw the field through the filter, which is the custom filter if
+    /// there is one, or else the CRT filter
+    pub enabled: bool,
+
+    /// The filter material, once it's been compiled
+    material: Option<Material>,
+
+    /// Custom filter, which replaces the CRT filter
+    custom: Option<Material>,
+
+    /// Render target the field gets drawn into, kept around between frames
+    /// as long as the field stays the same size
+    target: Option<RenderTarget>,
+}
+
+impl Filter {
+    /// Create a filter, which is on if `enabled`
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, ..Default::default() }
+    }
+
+    /// Load the fragment shader at `path` as a custom filter and turn it on
+    pub fn load(&mut self, path: &str) -> Result<()> {
+        let shader = error::read_to_string("shader", path)?;
+        let material = compile(&shader)
+            .map_err(|err| error::Error::from(err).in_file("shader", path))?;
+        self.custom = Some(material);
+        self.enabled = true;
+        Ok(())
+    }
+
+    /// Material to draw the field with, if any
+    fn material(&mut self) -> Option<Material> {
+        if !self.enabled {
+            return None;
+        }
+        if self.custom.is_some() {
+            return self.custom;
+        }
+
+        if self.material.is_none() {
+            let lines = format!("{:.1}", f32::from(GAME_FIELD_HEIGHT));
+            match compile(&CRT.replace("LINES", &lines)) {
+                Ok(material) => self.material = Some(material),
+                Err(err) => {
+                    log_error!("Disabling the CRT filter, it failed to \
+                        compile: {}", err);
+                    self.enabled = false;
+                }
+            }
+        }
+        self.material
+    }
+
+    /// Draw the game field with its top left at (`x`, `y`) and `width` by
+    /// `height` pixels big on the screen, with `draw` drawing the field with
+    /// its top left at the coords it's given
+    pub fn draw(&mut self, x: f32, y: f32, width: f32, height: f32,
+            uniforms: &Uniforms, draw: impl FnOnce(f32, f32)) {
+        let size = (width as u32, height as u32);
+        let material = match self.material() {
+            Some(material) if size.0 > 0 && size.1 > 0 => material,
+            _ => {
+                draw(x, y);
+                return;
+            }
+        };
+
+        let target = match self.target {
+            Some(target) if (target.texture.width() as u32,
+                    target.texture.height() as u32) == size => target,
+            _ => {
+                if let Some(old) = self.target.take() {
+                    old.texture.delete();
+                }
+                let target = render_target(size.0, size.1);
+                self.target = Some(target);
+                target
+            }
+        };
+
+        set_camera(&Camera2D {
+            render_target: Some(target),
+            ..Camera2D::from_display_rect(Rect::new(0., 0., width, height))
+        });
+        draw_rectangle(0., 0., width, height, BLACK);
+        draw(0., 0.);
+        set_default_camera();
+
+        material.set_uniform("Time", uniforms.time);
+        material.set_uniform("Score", uniforms.score);
+        material.set_uniform("Flash", uniforms.flash);
+        gl_use_material(material);
+
+        // Render targets come out upside down
+        draw_texture_ex(target.texture, x, y, WHITE, DrawTextureParams {
+            dest_size: Some(vec2(width, height)),
+            flip_y:    true,
+            ..Default::default()
+        });
+        gl_use_default_material();
+    }
+}
+
+/// Compile the fragment shader `shader` into a filter material
+fn compile(shader: &str) -> std::result::Result<Material, String> {
+    load_material(VERTEX, shader, MaterialParams {
+        uniforms: vec![
+            ("Time".to_string(),  UniformType::Float1),
+            ("Score".to_string(), UniformType::Float1),
+            ("Flash".to_string(), UniformType::Float1),
+        ],
+        ..Default::default()
+    }).map_err(|err| match err {
+        ShaderError::CompilationError { error_message, .. } => error_message,
+        ShaderError::LinkError(msg) => msg,
+        other => format!("{:?}", other),
+    })
+}
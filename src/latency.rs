@@ -0,0 +1,115 @@
+//! Debug overlay toggled with F6, measuring the input latency from a press of
+//! thrust or fire showing up to the physics frame which consumes it. Presses
+//! only show up at the start of a rendered frame, so however long a press
+//! waited for that isn't part of it, which is the same for every build
+
+use std::collections::VecDeque;
+use macroquad::prelude::*;
+use crate::{GameField, Human};
+
+/// Number of presses the distribution is made of
+const HISTORY: usize = 200;
+
+/// Latency (in seconds) covered by each bar of the histogram
+const BUCKET_TIME: f32 = 0.001;
+
+/// Number of bars in the histogram, the last one takes everything slower
+const BUCKETS: usize = 34;
+
+/// Seconds after which a press which no physics frame consumed is dropped,
+/// like when the run is paused
+const STALE_TIME: f64 = 0.5;
+
+/// Size (in pixels) of the overlay
+const WIDTH:  f32 = BUCKETS as f32 * 9.;
+const HEIGHT: f32 = 60.;
+
+/// Latencies of the recent presses
+#[derive(Default)]
+pub struct Latency {
+    /// Set while the overlay is shown
+    shown: bool,
+
+    /// Real times (in seconds) presses showed up at which haven't been
+    /// consumed yet, oldest first
+    pending: VecDeque<f64>,
+
+    /// Latencies (in seconds) of the last [`HISTORY`] presses, oldest first
+    samples: VecDeque<f32>,
+}
+
+impl Latency {
+    /// Match presses showing up in the frame `field` just rendered with the
+    /// physics frames which consumed them, toggling the overlay on F6
+    pub fn update(&mut self, field: &GameField) {
+        if is_key_pressed(KeyCode::F6) {
+            self.shown = !self.shown;
+        }
+
+        if Human::pressed() != 0 {
+            self.pending.push_back(field.last_render);
+        }
+
+        for &tick in &field.press_ticks {
+            let Some(seen) = self.pending.pop_front() else { break };
+            if self.samples.len() == HISTORY {
+                self.samples.pop_front();
+            }
+            self.samples.push_back((tick - seen) as f32);
+        }
+        self.pending.retain(|x| field.last_render - x < STALE_TIME);
+    }
+
+    /// Draw the latency histogram on the right below the profiler, if it's
+    /// shown
+    pub fn draw(&self) {
+        if !self.shown {
+            return;
+        }
+
+        let x = screen_width() - WIDTH - 10.;
+        let y = 240.;
+        draw_rectangle(x, y, WIDTH, HEIGHT + 42.,
+            Color::from_rgba(0, 0, 0, 0xc0));
+
+        // Bars scaled to the most common latency, with a line one physics
+        // frame in
+        let mut buckets = [0u32; BUCKETS];
+        for &sample in &self.samples {
+            let bucket = (sample.max(0.) / BUCKET_TIME) as usize;
+            buckets[bucket.min(BUCKETS - 1)] += 1;
+        }
+        let most = buckets.iter().copied().max().unwrap_or(0).max(1);
+        let bottom = y + HEIGHT;
+        let bar = WIDTH / BUCKETS as f32;
+        for (ii, &count) in buckets.iter().enumerate() {
+            let height = count as f32 / most as f32 * (HEIGHT - 4.);
+            draw_rectangle(x + ii as f32 * bar, bottom - height, bar - 1.,
+                height, SKYBLUE);
+        }
+        let tick = x + bar / (BUCKET_TIME * crate::PHYSICS_RATE as f32);
+        draw_line(tick, y, tick, bottom, 1., YELLOW);
+
+        let mut sorted: Vec<f32> = self.samples.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let percentile = |pct: usize| {
+            sorted.get((sorted.len() * pct / 100).min(sorted.len()
+                .saturating_sub(1))).map_or(0., |x| x * 1000.)
+        };
+        let platform = if cfg!(target_arch = "wasm32") {
+            "WASM"
+        } else {
+            "native"
+        };
+        let lines = [
+            format!("Input latency ({}) over {} presses", platform,
+                sorted.len()),
+            format!("Median {:5.2} ms | p95 {:5.2} ms | Max {:5.2} ms",
+                percentile(50), percentile(95), percentile(100)),
+        ];
+        for (ii, line) in lines.iter().enumerate() {
+            draw_text(line, x + 4., bottom + 16. + ii as f32 * 16., 16.,
+                WHITE);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Hand-made levels. A level is a text file with one entry per line, placed
+//! by wall column (each [`crate::OBSTACLE_WIDTH`] wide) from the start of the
+//! level:
+//!
+//! ```text
+//! # Comments start with a hash
+//! length   200                # Columns until the finish line
+//! wall     0   40 260         # Gap from Y 40 to 260 from column 0 on
+//! obstacle 20  120 60         # Obstacle at Y 120, 60 pixels tall
+//! pickup   30  140 coin       # shield, slow, shrink, magnet, or coin
+//! zone     50  24  fast       # 24 column fast, slow, flip, updraft, or
+//!                             # downdraft zone
+//! ```
+//!
+//! All positions and sizes are in pixels of the game field
+
+use crate::{Error, PickupKind, Result, ZoneKind};
+use crate::error;
+use crate::{FIXED_POINT_DIVISOR, GAME_FIELD_HEIGHT};
+
+/// Gap between the walls from a column on, until the next [`Wall`]
+#[derive(Clone, Copy)]
+pub struct Wall {
+    /// Column the gap starts at
+    pub column: u32,
+
+    /// Y coord of the bottom of the top wall
+    pub top: i16,
+
+    /// Y coord of the top of the bottom wall
+    pub bottom: i16,
+}
+
+/// A static obstacle placed in a column
+#[derive(Clone, Copy)]
+pub struct Block {
+    /// Column the obstacle is placed in
+    pub column: u32,
+
+    /// Y coord of the top of the obstacle
+    pub y: i16,
+
+    /// Height of the obstacle
+    pub height: i16,
+}
+
+/// A pickup placed in a column
+#[derive(Clone, Copy)]
+pub struct Item {
+    /// Column the pickup is placed in
+    pub column: u32,
+
+    /// Y coord of the top of the pickup
+    pub y: i16,
+
+    /// What the pickup does
+    pub kind: PickupKind,
+}
+
+/// A speed zone starting at a column
+#[derive(Clone, Copy)]
+pub struct Region {
+    /// Column the zone starts at
+    pub column: u32,
+
+    /// Number of columns the zone spans
+    pub columns: i16,
+
+    /// What the zone does
+    pub kind: ZoneKind,
+}
+
+/// A level which is played to its finish line rather than generated
+#[derive(Clone, Default)]
+pub struct Level {
+    /// Number of columns before the finish line
+    pub length: u32,
+
+    /// Wall gaps, sorted by column
+    pub walls: Vec<Wall>,
+
+    /// Obstacles, sorted by column
+    pub blocks: Vec<Block>,
+
+    /// Pickups, sorted by column
+    pub items: Vec<Item>,
+
+    /// Speed zones, sorted by column
+    pub regions: Vec<Region>,
+}
+
+impl Level {
+    /// Load the level file at `path`
+    pub fn load(path: &str) -> Result<Self> {
+        Self::parse(&error::read_to_string("level", path)?)
+            .map_err(|err| err.in_file("level", path))
+    }
+
+    /// Parse a level file
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut level = Self::default();
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let Some(&kind) = fields.first() else { continue };
+            let err = |msg: &str| -> Error {
+                format!("Level line {}: {}", line_no + 1, msg).into()
+            };
+
+            // Every entry has a fixed number of fields
+            let expected = match kind {
+                "length"   => 2,
+                "wall"     => 4,
+                "obstacle" => 4,
+                "pickup"   => 4,
+                "zone"     => 4,
+                _ => return Err(err(&format!("Unknown entry {:?}", kind))),
+            };
+            if fields.len() != expected {
+                return Err(err(&format!("Expected {} fields for {:?}",
+                    expected - 1, kind)));
+            }
+
+            // Parse a Y coord or size, which has to fit in the game field
+            let pixels = |x: &str| -> Result<i16> {
+                let val: i16 = x.parse()?;
+                if !(0..=GAME_FIELD_HEIGHT.0 / FIXED_POINT_DIVISOR)
+                        .contains(&val) {
+                    return Err(err(&format!("{} is out of the field", val)));
+                }
+                Ok(val)
+            };
+
+            match kind {
+                "length" => level.length = fields[1].parse()?,
+                "wall" => {
+                    let top = pixels(fields[2])?;
+                    let bottom = pixels(fields[3])?;
+                    if top >= bottom {
+                        return Err(err("Wall gap is empty"));
+                    }
+                    level.walls.push(Wall {
+                        column: fields[1].parse()?, top, bottom,
+                    });
+                }
+                "obstacle" => {
+                    level.blocks.push(Block {
+                        column: fields[1].parse()?,
+                        y:      pixels(fields[2])?,
+                        height: pixels(fields[3])?,
+                    });
+                }
+                "pickup" => {
+                    level.items.push(Item {
+                        column: fields[1].parse()?,
+                        y:      pixels(fields[2])?,
+                        kind:   PickupKind::from_name(fields[3])
+                            .ok_or_else(|| err(&format!("Unknown pickup {:?}",
+                                fields[3])))?,
+                    });
+                }
+                "zone" => {
+                    level.regions.push(Region {
+                        column:  fields[1].parse()?,
+                        columns: fields[2].parse()?,
+                        kind:    ZoneKind::from_name(fields[3])
+                            .ok_or_else(|| err(&format!("Unknown zone {:?}",
+                                fields[3])))?,
+                    });
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        if level.length == 0 {
+            return Err("Level has no length".into());
+        }
+
+        level.sort();
+        Ok(level)
+    }
+
+    /// Serialize the level into the text of a level file
+    pub fn serialize(&self) -> String {
+        let mut ret = format!("length   {}\n", self.length);
+        for wall in &self.walls {
+            ret += &format!("wall     {:<4} {:<4} {}\n",
+                wall.column, wall.top, wall.bottom);
+        }
+        for block in &self.blocks {
+            ret += &format!("obstacle {:<4} {:<4} {}\n",
+                block.column, block.y, block.height);
+        }
+        for item in &self.items {
+            ret += &format!("pickup   {:<4} {:<4} {}\n",
+                item.column, item.y, item.kind.name());
+        }
+        for region in &self.regions {
+            ret += &format!("zone     {:<4} {:<4} {}\n",
+                region.column, region.columns, region.kind.name());
+        }
+        ret
+    }
+
+    /// Sort all entries by column, as they're expected to be
+    pub fn sort(&mut self) {
+        self.walls.sort_by_key(|x| x.column);
+        self.blocks.sort_by_key(|x| x.column);
+        self.items.sort_by_key(|x| x.column);
+        self.regions.sort_by_key(|x| x.column);
+    }
+
+    /// Get the top and bottom of the gap between the walls at `column`.
+    /// Before the first [`Wall`] the gap is `default` pixels in the center
+    pub fn gap(&self, column: u32, default: i16) -> (i16, i16) {
+        self.walls.iter().rev().find(|x| x.column <= column)
+            .map_or_else(|| {
+                let height = GAME_FIELD_HEIGHT.0 / FIXED_POINT_DIVISOR;
+                ((height - default) / 2, (height + default) / 2)
+            }, |x| (x.top, x.bottom))
+    }
+}
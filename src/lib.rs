@@ -0,0 +1,5745 @@
+#[macro_use]
+pub mod log;
+#[macro_use]
+mod locale;
+pub mod bench;
+mod batch;
+mod camera;
+mod capture;
+mod career;
+pub mod closest;
+pub mod compare;
+mod console;
+mod controller;
+mod curve;
+mod editor;
+pub mod env;
+pub mod error;
+mod filter;
+mod latency;
+mod level;
+mod menu;
+mod mods;
+mod patterns;
+mod portrait;
+mod presence;
+mod preview;
+mod profiler;
+mod rank;
+pub mod render_replay;
+mod replay;
+mod rumble;
+mod sandbox;
+mod script;
+mod settings;
+mod storage;
+pub mod solve;
+pub mod state;
+mod streamer;
+mod twitch;
+mod url;
+#[cfg(test)]
+mod tests;
+
+use std::collections::VecDeque;
+use macroquad::prelude::*;
+use macroquad::audio::{load_sound, play_sound, PlaySoundParams, Sound};
+use controller::{Autopilot, Controller, GameState, Human, Playback};
+use batch::Batch;
+use camera::Camera;
+use capture::Clip;
+use console::{Action, Console};
+use curve::Curve;
+use error::{Error, Result};
+use filter::{Filter, Uniforms};
+use latency::Latency;
+use level::Level;
+use mods::{Palette, Registry, MODS_DIR};
+use replay::Replay;
+use rumble::Rumble;
+use sandbox::{Sandbox, Tuning};
+use script::{Hook, Param, Script, Values};
+use patterns::PATTERNS;
+use presence::Presence;
+use preview::Preview;
+use profiler::Profiler;
+use rank::{Ladder, Rank};
+use settings::{Settings, Watcher};
+use state::Suspended;
+use streamer::{Streamer, Ticker};
+use twitch::Twitch;
+
+/// Enables scaling of the internal game field to the output screen size
+const SCALE_OUTPUT: bool = true;
+
+/// The divisor we use for fixed point conversion
+const FIXED_POINT_SHIFT:   u32 = 5;
+const FIXED_POINT_DIVISOR: i16 = 1 << FIXED_POINT_SHIFT;
+
+/// Width of the internal game field
+const GAME_FIELD_WIDTH:  Fxpt = Fxpt(400 * FIXED_POINT_DIVISOR);
+
+/// Height of the internal game field
+const GAME_FIELD_HEIGHT: Fxpt = Fxpt(300 * FIXED_POINT_DIVISOR);
+
+/// Player X coord
+const PLAYER_X: Fxpt = Fxpt(100 * FIXED_POINT_DIVISOR);
+
+/// Width and height dimension of the players collision square
+const PLAYER_SIZE: Fxpt = Fxpt(48 * FIXED_POINT_DIVISOR);
+
+/// The width of a wall or obstacle
+const OBSTACLE_WIDTH: Fxpt = Fxpt(25 * FIXED_POINT_DIVISOR);
+
+/// Gravity the player experiences
+const GRAVITY: Fxpt = Fxpt((1.6 * FIXED_POINT_DIVISOR as f32) as i16);
+
+/// Friction the player experiences
+const FRICTION: Fxpt = Fxpt((0.9 * FIXED_POINT_DIVISOR as f32) as i16);
+
+/// Speed change upon input on each frame
+const INPUT_IMPULSE: Fxpt = Fxpt(2 * FIXED_POINT_DIVISOR);
+
+/// Input bit which is set while the player is thrusting upwards
+const INPUT_THRUST: u8 = 1 << 0;
+
+/// Input bit which is set while the player is holding the fire button
+const INPUT_FIRE: u8 = 1 << 1;
+
+/// Shift of the input bits holding the throttle, from 0 to
+/// [`THROTTLE_LEVELS`], in [`Control::Throttle`]
+const INPUT_THROTTLE_SHIFT: u32 = 4;
+
+/// Throttle level which thrusts as hard as holding thrust does
+const THROTTLE_LEVELS: u8 = 15;
+
+/// Speed change upon each tap in [`Control::Tap`]
+const TAP_IMPULSE: Fxpt = Fxpt(6 * FIXED_POINT_DIVISOR);
+
+/// Number of shots the player starts with
+const START_AMMO: u8 = 5;
+
+/// Width and height of a shot fired by the player
+const BULLET_SIZE: Fxpt = Fxpt(6 * FIXED_POINT_DIVISOR);
+
+/// Distance a shot fired by the player moves forward each physics frame
+const BULLET_SPEED: Fxpt = Fxpt(12 * FIXED_POINT_DIVISOR);
+
+/// Width and height dimension of a pickup's collision square
+const PICKUP_SIZE: Fxpt = Fxpt(16 * FIXED_POINT_DIVISOR);
+
+/// One in this many generated wall columns (which do not also get an
+/// obstacle) will contain a power-up pickup
+const PICKUP_RARITY: u64 = 384;
+
+/// One in this many generated wall columns (which do not also get an
+/// obstacle or power-up) will contain a coin
+const COIN_RARITY: u64 = 12;
+
+/// Number of physics frames the screen flashes for when a shield is consumed
+const SHIELD_FLASH_FRAMES: u8 = 12;
+
+/// Seconds the flash passed to filters takes to fade out after a death
+const DEATH_FLASH_TIME: f64 = 0.5;
+
+/// Number of physics frames the map scrolls at half speed for after a
+/// slow-time pickup is collected
+const SLOW_TIME_FRAMES: u16 = 180;
+
+/// Number of physics frames the player stays at half size for after a shrink
+/// pickup is collected
+const SHRINK_FRAMES: u16 = 300;
+
+/// Number of physics frames coins are attracted to the player for after a
+/// magnet pickup is collected
+const MAGNET_FRAMES: u16 = 420;
+
+/// Coins with a center within this distance of the player's center are pulled
+/// in by the magnet
+const MAGNET_RADIUS: Fxpt = Fxpt(120 * FIXED_POINT_DIVISOR);
+
+/// Maximum distance a coin is pulled on each axis per physics frame
+const MAGNET_PULL: Fxpt = Fxpt(6 * FIXED_POINT_DIVISOR);
+
+/// Score awarded for passing an obstacle
+const PASS_BONUS: u64 = 25;
+
+/// Score awarded for collecting a coin
+const COIN_BONUS: u64 = 50;
+
+/// Score taken away for touching the top or bottom of the field with
+/// [`Edges::Penalty`]
+const EDGE_PENALTY: u64 = 100;
+
+/// Score awarded for passing an obstacle with less than
+/// [`NEAR_MISS_DISTANCE`] of vertical clearance
+const NEAR_MISS_BONUS: u64 = 100;
+
+/// Vertical clearance to an obstacle which counts as a near-miss
+const NEAR_MISS_DISTANCE: Fxpt = Fxpt(8 * FIXED_POINT_DIVISOR);
+
+/// Number of physics frames a score popup is displayed for
+const POPUP_FRAMES: u8 = 30;
+
+/// Number of combo obstacles required for each step of the score multiplier
+const COMBO_STEP: u64 = 5;
+
+/// Number of physics frames the player cannot collide after respawning
+const INVULNERABLE_FRAMES: u16 = 120;
+
+/// One in this many generated wall columns will start a speed zone
+const ZONE_RARITY: u64 = 160;
+
+/// Number of wall columns a speed zone spans
+const ZONE_COLUMNS: i16 = 24;
+
+/// One in this many generated wall columns will start a draft, when they're
+/// turned on
+const DRAFT_RARITY: u64 = 96;
+
+/// Number of wall columns a draft spans
+const DRAFT_COLUMNS: i16 = 4;
+
+/// How much drafts take away from or add to the gravity
+const DRAFT_FORCE: Fxpt = Fxpt(FIXED_POINT_DIVISOR / 4);
+
+/// Most wall columns which can be generated past the right edge of the
+/// field, any more and their coords wouldn't fit in an [`Fxpt`]
+const MAX_LOOKAHEAD: u16 = 16;
+
+/// Latest physics frame practice runs can start on, ten minutes in
+const MAX_START_FRAME: u64 = 36000;
+
+/// Most physics frames a replay gets played back for, an hour at the fastest
+/// of the [`TICK_RATES`]. Runs which bounce never end on their own
+const MAX_REPLAY_FRAMES: u64 = 120 * 60 * 60;
+
+/// Least gap (in pixels) between the walls the rules can have, room for two
+/// players so obstacles fit between them with a way past
+const MIN_GAP: i16 = PLAYER_SIZE.0 / FIXED_POINT_DIVISOR * 2;
+
+/// Most gap (in pixels) between the walls the rules can have, the whole
+/// height of the field
+const MAX_GAP: i16 = GAME_FIELD_HEIGHT.0 / FIXED_POINT_DIVISOR;
+
+/// Most scroll speed the rules can have, such that fast zones can double it
+/// and the map can scroll by it without leaving an [`Fxpt`]
+const MAX_SCROLL_SPEED: Fxpt = Fxpt(32 * FIXED_POINT_DIVISOR);
+
+/// Most gravity or impulse the rules can have, such that the crafts and
+/// mutators strengthening them keep the player's speed in an [`Fxpt`]
+const MAX_FORCE: Fxpt = Fxpt(8 * FIXED_POINT_DIVISOR);
+
+/// Most friction the rules can have, any more and the player would never
+/// reach a terminal speed
+const MAX_FRICTION: Fxpt = Fxpt(FIXED_POINT_DIVISOR - 1);
+
+/// Height of an obstacle in the middle of the corridor
+const OBSTACLE_HEIGHT: Fxpt = Fxpt(60 * FIXED_POINT_DIVISOR);
+
+/// Physics frame after which obstacles may start oscillating
+const OSCILLATE_START: u64 = 600;
+
+/// One in this many obstacles oscillate (once they're allowed to)
+const OSCILLATE_RARITY: u64 = 4;
+
+/// Largest distance an oscillating obstacle moves away from its center
+const OSCILLATE_AMPLITUDE: Fxpt = Fxpt(40 * FIXED_POINT_DIVISOR);
+
+/// Physics frame after which spinning blades may appear
+const BLADE_START: u64 = 1800;
+
+/// One in this many obstacles are blades (once they're allowed to be)
+const BLADE_RARITY: u64 = 5;
+
+/// Distance from the center of a blade to its tips
+const BLADE_RADIUS: Fxpt = Fxpt(30 * FIXED_POINT_DIVISOR);
+
+/// Physics frame after which closing gates may appear
+const GATE_START: u64 = 900;
+
+/// One in this many obstacles are closing gates (once they're allowed to be)
+const GATE_RARITY: u64 = 6;
+
+/// Distance ahead of the player at which gates start closing
+const GATE_CLOSE_DISTANCE: Fxpt = Fxpt(300 * FIXED_POINT_DIVISOR);
+
+/// Distance behind the player at which gates are fully open again
+const GATE_REOPEN_DISTANCE: Fxpt = Fxpt(100 * FIXED_POINT_DIVISOR);
+
+/// Size of the opening left between a pair of gates when they're closed
+const GATE_OPENING: Fxpt = Fxpt(72 * FIXED_POINT_DIVISOR);
+
+/// Physics frame after which tunnel set pieces may appear
+const TUNNEL_START: u64 = 600;
+
+/// One in this many generated wall columns start a tunnel (once they're
+/// allowed to)
+const TUNNEL_RARITY: u64 = 200;
+
+/// Number of wall columns a tunnel spans, including tapering in and out
+const TUNNEL_COLUMNS: u16 = 64;
+
+/// Number of wall columns it takes to taper into and out of a tunnel
+const TUNNEL_TAPER: u16 = 12;
+
+/// Gap between the walls in the narrowest part of a tunnel. This is always
+/// comfortably larger than the player so tunnels are passable
+const TUNNEL_GAP: Fxpt = Fxpt(88 * FIXED_POINT_DIVISOR);
+
+/// Physics frame after which authored patterns may appear
+const PATTERN_START: u64 = 300;
+
+/// Number of wall columns generated between authored patterns
+const PATTERN_INTERVAL: u16 = 240;
+
+/// Width of the finish line at the end of a [`Level`]
+const FINISH_WIDTH: Fxpt = Fxpt(8 * FIXED_POINT_DIVISOR);
+
+/// Number of physics frames in a stage before its finish line appears
+const STAGE_FRAMES: u64 = 2000;
+
+/// Number of physics frames at [`PHYSICS_RATE`] between checkpoints, where
+/// the score gets split against the high score run
+const CHECKPOINT_FRAMES: u64 = 1000;
+
+/// Most splits listed on the death screen, the latest ones
+const SUMMARY_SPLITS: usize = 8;
+
+/// Amount (in pixels) the starting gap shrinks by each stage
+const STAGE_GAP_STEP: i16 = 15;
+
+/// Amount the starting scroll speed increases by each stage
+const STAGE_SCROLL_STEP: Fxpt = Fxpt(FIXED_POINT_DIVISOR / 2);
+
+/// Score awarded for completing a stage, multiplied by the stage number
+const STAGE_BONUS: u64 = 1000;
+
+/// Every this many stages in [`Mode::Stage`] the shop opens between them
+const SHOP_STAGES: u32 = 2;
+
+/// Boosts the shop sells for the rest of the run, with their price in coins.
+/// Past the last one is the entry which leaves the shop
+const SHOP_ITEMS: [(PickupKind, u64); 3] = [
+    (PickupKind::Shield,   15),
+    (PickupKind::Shrink,   10),
+    (PickupKind::SlowTime, 10),
+];
+
+/// Every this many stages in [`Mode::Stage`] is a boss stage
+const BOSS_STAGES: u32 = 3;
+
+/// Number of physics frames the boss has to be survived for
+const BOSS_FRAMES: u16 = 900;
+
+/// Number of hits from the player's shots it takes to defeat the boss
+const BOSS_HEALTH: u8 = 6;
+
+/// Ammo the player is given (if they have less) when a boss shows up
+const BOSS_AMMO: u8 = 8;
+
+/// Size of the boss's collision square
+const BOSS_SIZE: Fxpt = Fxpt(64 * FIXED_POINT_DIVISOR);
+
+/// X coord the boss sits at once it has moved in
+const BOSS_X: Fxpt = Fxpt(300 * FIXED_POINT_DIVISOR);
+
+/// Distance the boss keeps from the top and bottom of the field
+const BOSS_MARGIN: Fxpt = Fxpt(20 * FIXED_POINT_DIVISOR);
+
+/// Number of physics frames between boss attacks
+const BOSS_ATTACK_FRAMES: u16 = 50;
+
+/// Gap (in pixels) between the walls while the boss is around
+const BOSS_GAP: i16 = 280;
+
+/// Score awarded for defeating the boss rather than just surviving it
+const BOSS_BONUS: u64 = 2500;
+
+/// Size of the player with the [`Mutator::Tiny`] mutator
+const TINY_SIZE: Fxpt = Fxpt(24 * FIXED_POINT_DIVISOR);
+
+/// Size of the player with the [`Mutator::Giant`] mutator
+const GIANT_SIZE: Fxpt = Fxpt(72 * FIXED_POINT_DIVISOR);
+
+/// Fraction of the player's speed kept every frame with [`Mutator::Icy`]
+const ICY_FRICTION: Fxpt = Fxpt(FIXED_POINT_DIVISOR - 1);
+
+/// Minimum number of physics frames between obstacles with
+/// [`Mutator::Swarm`]
+const SWARM_OBSTACLE_FRAMES: u64 = 10;
+
+/// Number of physics frames between flashes of [`Mutator::InvisibleWalls`]
+const INVISIBLE_FLASH_PERIOD: u64 = 90;
+
+/// Number of physics frames the walls are visible for in each flash
+const INVISIBLE_FLASH_FRAMES: u64 = 12;
+
+/// Pixels each level of [`Upgrade::Gap`] widens the starting gap by
+const UPGRADE_GAP: i16 = 8;
+
+/// Least gravity the [`Craft::Glider`] lightens the gravity to, any less and
+/// the fixed point rounding barely lets the player fall
+const GLIDER_MIN_GRAVITY: Fxpt = Fxpt(FIXED_POINT_DIVISOR * 5 / 4);
+
+/// Friction of the [`Craft::Stunt`]
+const STUNT_FRICTION: Fxpt = Fxpt(FIXED_POINT_DIVISOR * 27 / 32);
+
+/// Number of physics frames at [`PHYSICS_RATE`] each biome lasts, when
+/// they're turned on
+const BIOME_FRAMES: u64 = 2000;
+
+/// Pixels the scenery of a biome scrolls by every physics frame, slower than
+/// the walls so it looks far away
+const BIOME_PARALLAX: i32 = 2;
+
+/// Fraction of the player's speed kept, reversed, when bouncing off
+/// something with [`Mutator::Bounce`]
+const BOUNCE_RESTITUTION: Fxpt = Fxpt(FIXED_POINT_DIVISOR / 2);
+
+/// Number of physics frames the player cannot collide after a bounce, to get
+/// clear of what they hit
+const BOUNCE_FRAMES: u16 = 30;
+
+/// Score taken away for each bounce with [`Mutator::Bounce`]
+const BOUNCE_PENALTY: u64 = 50;
+
+/// Distance (in pixels) ahead of the player which has to be clear for a voted
+/// in mutator to be applied
+const VOTE_SAFE_DISTANCE: Fxpt = Fxpt(60 * FIXED_POINT_DIVISOR);
+
+/// Radius of the circle around the player which is visible with
+/// [`Mutator::Night`]
+const NIGHT_RADIUS: Fxpt = Fxpt(70 * FIXED_POINT_DIVISOR);
+
+/// Height of the strips the darkness of [`Mutator::Night`] is drawn with
+const NIGHT_STRIP: Fxpt = Fxpt(2 * FIXED_POINT_DIVISOR);
+
+/// Number of physics frames adaptive mode looks at before adjusting
+const ADAPT_WINDOW: u64 = 600;
+
+/// Pixels of gap adaptive mode adjusts by at a time
+const ADAPT_STEP: i16 = 5;
+
+/// Most pixels of gap adaptive mode will give or take away
+const ADAPT_MAX: i16 = 40;
+
+/// Number of near-misses in a window for adaptive mode to consider the player
+/// to be struggling
+const ADAPT_NEAR_MISSES: u32 = 3;
+
+/// Pixels of [`Difficulty::ease`] per pixel per frame of scroll speed taken
+/// off
+const EASE_SPEED_RATIO: i16 = 16;
+
+/// File the best time for each stage is stored in, one line per stage
+const STAGE_TIMES_FILE: &str = "stage_times.txt";
+
+/// File the inputs of runs which were aborted from the pause menu are
+/// archived to, so they never overwrite the high score replay
+const ABORTED_REPLAY_FILE: &str = "aborted.bin";
+
+/// File the inputs of the high score run with the standard craft are written
+/// to, see [`high_score_file`]
+const HIGH_SCORE_REPLAY_FILE: &str = "inputs.bin";
+
+/// File the run in progress is saved to, so it can be resumed after the game
+/// gets closed
+const SUSPEND_FILE: &str = "suspended.txt";
+
+/// Sound of the click on every physics frame thrust registers on
+const CLICK_FILE: &str = "config/click.wav";
+
+/// Volume of the click, from 0 to 1
+const CLICK_VOLUME: f32 = 0.3;
+
+/// Real time (in seconds) between saves of the run in progress. There's no
+/// event for the window or the tab getting closed, so runs get saved as they
+/// go, and right away when paused
+const SUSPEND_SAVE_TIME: f64 = 1.;
+
+/// Time (in seconds) before a death practice runs pick back up at
+const PRACTICE_REWIND: u64 = 3;
+
+/// Points scored after continuing a casual run count for this fraction
+const CONTINUE_DIVISOR: u64 = 2;
+
+/// Number of closest calls listed after a death
+const CLOSEST_CALLS: usize = 3;
+
+/// Keys which jump replay playback to each of the closest calls
+const CLOSEST_CALL_KEYS: [KeyCode; CLOSEST_CALLS] =
+    [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3];
+
+/// Time (in seconds) before a close call replay playback jumps to, to see it
+/// coming
+const CLOSE_CALL_LEAD: u64 = 1;
+
+/// Time (in seconds) between rendered frames after which we assume the window
+/// lost focus or the tab was hidden, and pause the run
+const FOCUS_LOST_TIME: f64 = 0.25;
+
+/// Time (in seconds) of the countdown before a paused run resumes
+const RESUME_COUNTDOWN: f64 = 3.;
+
+/// Physics frames simulated per second of real time, unless the run is
+/// played at another of the [`TICK_RATES`]. Every constant counted in physics
+/// frames or moving by some distance every physics frame is given at this
+/// rate
+const PHYSICS_RATE: f64 = 60.;
+
+/// Physics frames per second runs can be played at, the first is the ranked
+/// default. Each has to be a multiple of [`PHYSICS_RATE`]
+const TICK_RATES: [u16; 2] = [60, 120];
+
+/// Most physics frames simulated in a single rendered frame when catching up
+/// after a slow frame
+const MAX_CATCH_UP_FRAMES: f64 = 4.;
+
+/// Physics frame after which turrets may appear on the walls
+const TURRET_START: u64 = 1200;
+
+/// One in this many generated wall columns get a turret (once they're
+/// allowed to)
+const TURRET_RARITY: u64 = 48;
+
+/// Width and height of a turret mounted on a wall
+const TURRET_WIDTH:  Fxpt = Fxpt(16 * FIXED_POINT_DIVISOR);
+const TURRET_HEIGHT: Fxpt = Fxpt(12 * FIXED_POINT_DIVISOR);
+
+/// Number of physics frames between shots of a turret
+const TURRET_COOLDOWN: u16 = 90;
+
+/// Width and height of a projectile fired by a turret
+const PROJECTILE_SIZE: Fxpt = Fxpt(8 * FIXED_POINT_DIVISOR);
+
+/// Horizontal speed of a projectile relative to the map
+const PROJECTILE_SPEED: Fxpt = Fxpt(2 * FIXED_POINT_DIVISOR);
+
+/// Quarter wave of a sine with 64 steps, scaled such that 1.0 is 256
+const QUARTER_SINE: [i16; 65] = [
+      0,   6,  13,  19,  25,  31,  38,  44,  50,  56,  62,  68,  74,  80,
+     86,  92,  98, 104, 109, 115, 121, 126, 132, 137, 142, 147, 152, 157,
+    162, 167, 172, 177, 181, 185, 190, 194, 198, 202, 206, 209, 213, 216,
+    220, 223, 226, 229, 231, 234, 237, 239, 241, 243, 245, 247, 248, 250,
+    251, 252, 253, 254, 255, 255, 256, 256, 256,
+];
+
+/// Apply a physics frame to the player's vertical `speed`, thrusting upwards
+/// if `thrust` is set, with the gravity, impulse, and friction of `difficulty`.
+/// At faster tick rates each frame only goes part of the way to the speed a
+/// frame at [`PHYSICS_RATE`] would end on, which keeps the terminal speeds
+fn player_physics(speed: Fxpt, thrust: bool, difficulty: &Difficulty) -> Fxpt {
+    let target = if thrust {
+        Fxpt(speed.0 - difficulty.impulse.0)
+    } else {
+        speed
+    };
+    let target = Fxpt(target.0 + difficulty.gravity.0);
+    let target = (target.0 >> FIXED_POINT_SHIFT) * difficulty.friction.0;
+    Fxpt(speed.0 + (target - speed.0) / difficulty.substeps as i16)
+}
+
+/// Update the player speed with gravity and thrust flipped. The speed is
+/// mirrored rather than the forces, so the fixed point rounding doesn't favor
+/// one direction
+fn flipped_physics(speed: Fxpt, thrust: bool, difficulty: &Difficulty)
+        -> Fxpt {
+    Fxpt(-player_physics(Fxpt(-speed.0), thrust, difficulty).0)
+}
+
+/// Simulate the player physics until the player reaches terminal velocity
+/// both while thrusting and while falling, giving the furthest the player can
+/// move up and down in a single physics frame at [`PHYSICS_RATE`]
+fn terminal_speeds(difficulty: &Difficulty) -> (Fxpt, Fxpt) {
+    let (mut up, mut down) = (Fxpt(0), Fxpt(0));
+    for _ in 0..256 {
+        up = player_physics(up, true, difficulty);
+        down = player_physics(down, false, difficulty);
+    }
+    (Fxpt(-up.0), down)
+}
+
+/// Deterministic sine where a full turn is 256 and the result is scaled such
+/// that 1.0 is 256
+fn sine(angle: u8) -> i16 {
+    let idx = (angle & 63) as usize;
+    match angle >> 6 {
+        0 => QUARTER_SINE[idx],
+        1 => QUARTER_SINE[64 - idx],
+        2 => -QUARTER_SINE[idx],
+        _ => -QUARTER_SINE[64 - idx],
+    }
+}
+
+/// A fixed point integer, converting to a float is done by dividing by
+/// [`FIXED_POINT_DIVISOR`]
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+struct Fxpt(i16);
+
+impl From<i16> for Fxpt {
+    fn from(val: i16) -> Self {
+        Fxpt(val * FIXED_POINT_DIVISOR)
+    }
+}
+
+impl From<Fxpt> for f32 {
+    fn from(val: Fxpt) -> Self {
+        // Every `i16` fits exactly in an `f32`, and so does dividing it by a
+        // power of two
+        val.0 as f32 / FIXED_POINT_DIVISOR as f32
+    }
+}
+
+#[derive(Clone)]
+struct Rng(u64);
+
+/// Seed used for the level generation unless another one is picked
+const DEFAULT_SEED: u64 = 0x1337133713371337;
+
+/// FNV-1a 64-bit offset basis, the initial state of [`fnv1a`]
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// Continue an FNV-1a hash in `hash` over `bytes`
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &x| {
+        (hash ^ x as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl Rng {
+    /// Create a new RNG from `seed`. Xorshift gets stuck on zero, so that
+    /// gets swapped out for one
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn rand(&mut self) -> u64 {
+        let ret = self.0;
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 43;
+        ret
+    }
+}
+
+/// An object to render onto the screen
+#[derive(Clone, Copy)]
+#[allow(unused)]
+enum Object {
+    /// Draw a rectangle
+    Rectangle { x: Fxpt, y: Fxpt, width: Fxpt, height: Fxpt, color: Color },
+
+    /// Draw a polygon
+    Polygon {
+        x: Fxpt, y: Fxpt, sides: u8,
+        radius: Fxpt, rotation: Fxpt, color: Color,
+    },
+
+    /// Draw the outline of the first `segments` edges of a polygon with
+    /// `sides` edges, going clockwise starting from the top
+    Ring {
+        x: Fxpt, y: Fxpt, sides: u8, segments: u8,
+        radius: Fxpt, thickness: Fxpt, color: Color,
+    },
+
+    /// Draw a signed number (eg. "+100") centered on `x` with the baseline
+    /// at `y`
+    Number { x: Fxpt, y: Fxpt, value: i32, size: Fxpt, color: Color },
+
+    /// Draw a filled quadrilateral with the corners in winding order
+    Quad { points: [(Fxpt, Fxpt); 4], color: Color },
+
+    /// Draw a line from `from` to `to`
+    Line {
+        from: (Fxpt, Fxpt), to: (Fxpt, Fxpt), thickness: Fxpt, color: Color,
+    },
+
+    /// Draw `texture` stretched over a rectangle, flipped horizontally if
+    /// `flip` is set
+    Sprite {
+        x: Fxpt, y: Fxpt, width: Fxpt, height: Fxpt,
+        texture: Texture2D, flip: bool,
+    },
+}
+
+impl Object {
+    /// Get the object flipped horizontally across the center of the game
+    /// field
+    fn mirrored(self) -> Self {
+        let flip = |x: Fxpt| Fxpt(GAME_FIELD_WIDTH.0 - x.0);
+        match self {
+            Object::Rectangle { x, y, width, height, color } => {
+                Object::Rectangle {
+                    x: Fxpt(flip(x).0 - width.0), y, width, height, color,
+                }
+            }
+            Object::Polygon { x, y, sides, radius, rotation, color } => {
+                Object::Polygon {
+                    x: flip(x), y, sides, radius,
+                    rotation: Fxpt(-rotation.0), color,
+                }
+            }
+            Object::Ring { x, y, sides, segments, radius, thickness, color } => {
+                Object::Ring {
+                    x: flip(x), y, sides, segments, radius, thickness, color,
+                }
+            }
+            Object::Number { x, y, value, size, color } => {
+                Object::Number { x: flip(x), y, value, size, color }
+            }
+            Object::Line { from, to, thickness, color } => {
+                Object::Line {
+                    from: (flip(from.0), from.1), to: (flip(to.0), to.1),
+                    thickness, color,
+                }
+            }
+            Object::Quad { points, color } => {
+                Object::Quad {
+                    points: points.map(|(x, y)| (flip(x), y)),
+                    color,
+                }
+            }
+            Object::Sprite { x, y, width, height, texture, flip: flipped } => {
+                Object::Sprite {
+                    x: Fxpt(flip(x).0 - width.0), y, width, height, texture,
+                    flip: !flipped,
+                }
+            }
+        }
+    }
+}
+
+/// Different types of obstacles and their state
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ObstacleKind {
+    /// Does not move relative to the map
+    Static,
+
+    /// Moves up and down sinusoidally around `center_y`
+    Oscillating {
+        /// Y coord of the top of the obstacle when at the center of its swing
+        center_y: Fxpt,
+
+        /// Largest distance moved away from `center_y`
+        amplitude: Fxpt,
+
+        /// Offset into the swing, a full turn is 256
+        phase: u8,
+    },
+
+    /// A spinning triangular blade of [`BLADE_RADIUS`] centered in the
+    /// obstacle's box
+    Blade {
+        /// Current rotation, a full turn is 256
+        angle: u8,
+
+        /// Rotation applied every physics frame, negative spins
+        /// counter-clockwise
+        spin: i8,
+    },
+
+    /// One half of a pair of gates which extend out of the walls, closing
+    /// the gap as they approach the player and reopening after
+    Gate {
+        /// Y coord of the edge of the wall the gate extends from
+        anchor: Fxpt,
+
+        /// Distance the gate extends when fully closed
+        extent: Fxpt,
+
+        /// Set if extending down from the top wall
+        ceiling: bool,
+    },
+
+    /// A wall whose edge slopes linearly from `left` at the left side of the
+    /// box to `right` at the right side of the box
+    Slope {
+        /// Y coord of the edge of the wall on the left and right side
+        left:  Fxpt,
+        right: Fxpt,
+
+        /// Set if this is a top wall, filled above the edge, otherwise it's
+        /// a bottom wall, filled below the edge
+        ceiling: bool,
+    },
+
+    /// Mounted on a wall, periodically fires a [`ObstacleKind::Projectile`]
+    /// at the player
+    Turret {
+        /// Number of physics frames until the next shot
+        cooldown: u16,
+
+        /// Set if mounted on the top wall, shooting downwards
+        ceiling: bool,
+    },
+
+    /// A shot fired by a turret or the player
+    Projectile {
+        /// Distance moved each physics frame, on top of any scrolling
+        vx: Fxpt,
+        vy: Fxpt,
+    },
+}
+
+impl ObstacleKind {
+    /// Name of the kind of obstacle for logs
+    fn name(&self) -> &'static str {
+        match self {
+            ObstacleKind::Static             => "static",
+            ObstacleKind::Oscillating { .. } => "oscillating",
+            ObstacleKind::Blade { .. }       => "blade",
+            ObstacleKind::Gate { .. }        => "gate",
+            ObstacleKind::Slope { .. }       => "slope",
+            ObstacleKind::Turret { .. }      => "turret",
+            ObstacleKind::Projectile { .. }  => "projectile",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Obstacle {
+    x:      Fxpt,
+    y:      Fxpt,
+    width:  Fxpt,
+    height: Fxpt,
+
+    /// What type of obstacle this is
+    kind: ObstacleKind,
+
+    /// Set once the obstacle has fully scrolled past the player
+    passed: bool,
+
+    /// Smallest vertical clearance between the player and this obstacle
+    /// while they were side by side, `None` if they have not been yet
+    clearance: Option<Fxpt>,
+}
+
+impl Obstacle {
+    /// Create a new obstacle with the top left at (`x`, `y`)
+    fn new(x: Fxpt, y: Fxpt, width: Fxpt, height: Fxpt) -> Self {
+        Self {
+            x, y, width, height,
+            kind:      ObstacleKind::Static,
+            passed:    false,
+            clearance: None,
+        }
+    }
+
+    /// Update the position of the obstacle based on its kind for the physics
+    /// frame `frames`, with `substeps` physics frames per physics frame at
+    /// [`PHYSICS_RATE`]
+    fn update(&mut self, frames: u64, substeps: u64) {
+        let tick = frames.is_multiple_of(substeps);
+        match self.kind {
+            ObstacleKind::Static => {}
+            ObstacleKind::Oscillating { center_y, amplitude, phase } => {
+                // A full swing takes 128 frames at the physics rate
+                let angle = ((frames * 2 / substeps) as u8)
+                    .wrapping_add(phase);
+                let offset = amplitude.0 as i32 * sine(angle) as i32 / 256;
+                self.y = Fxpt(center_y.0 + offset as i16);
+            }
+            ObstacleKind::Blade { ref mut angle, spin } => {
+                if tick {
+                    *angle = angle.wrapping_add(spin as u8);
+                }
+            }
+            ObstacleKind::Gate { anchor, extent, ceiling } => {
+                // Close linearly while approaching the player, fully closed
+                // when next to the player and reopen once past
+                let dist = (self.x.0 - PLAYER_X.0) as i32;
+                let closed = if dist >= 0 {
+                    (GATE_CLOSE_DISTANCE.0 as i32 - dist).max(0) * 256 /
+                        GATE_CLOSE_DISTANCE.0 as i32
+                } else {
+                    (GATE_REOPEN_DISTANCE.0 as i32 + dist).max(0) * 256 /
+                        GATE_REOPEN_DISTANCE.0 as i32
+                };
+
+                self.height = Fxpt((extent.0 as i32 * closed / 256) as i16);
+                self.y = if ceiling {
+                    anchor
+                } else {
+                    Fxpt(anchor.0 - self.height.0)
+                };
+            }
+            ObstacleKind::Slope { .. } => {}
+            ObstacleKind::Turret { ref mut cooldown, .. } => {
+                if tick {
+                    *cooldown = cooldown.saturating_sub(1);
+                }
+            }
+            ObstacleKind::Projectile { vx, vy } => {
+                self.x = Fxpt(self.x.0 + vx.0);
+                self.y = Fxpt(self.y.0 + vy.0);
+            }
+        }
+    }
+
+    /// Y coord of the edge of a [`ObstacleKind::Slope`] at `x`, which must
+    /// be within the box of the obstacle
+    fn slope_edge(&self, x: i16) -> i16 {
+        match self.kind {
+            ObstacleKind::Slope { left, right, .. } => {
+                let dist = (x - self.x.0) as i32;
+                left.0 + ((right.0 - left.0) as i32 * dist /
+                    self.width.0.max(1) as i32) as i16
+            }
+            _ => self.y.0,
+        }
+    }
+
+    /// Returns `true` if `other` collides with this obstacle. Most obstacles
+    /// are just their box, but blades are approximated by a square at their
+    /// center plus a small square towards the tip of each of their arms,
+    /// rotating with the blade, and slopes are checked against their edge
+    fn hits(&self, other: &Obstacle) -> bool {
+        let angle = match self.kind {
+            ObstacleKind::Blade { angle, .. } => angle,
+            ObstacleKind::Slope { ceiling, .. } => {
+                let x1 = self.x.0.max(other.x.0);
+                let x2 = (self.x.0 + self.width.0)
+                    .min(other.x.0 + other.width.0);
+                if x1 >= x2 {
+                    return false;
+                }
+
+                // The edge is a line, so its extremes are at the ends
+                let (a, b) = (self.slope_edge(x1), self.slope_edge(x2));
+                return if ceiling {
+                    a.max(b) > other.y.0
+                } else {
+                    a.min(b) < other.y.0 + other.height.0
+                };
+            }
+            _ => return self.overlaps(other),
+        };
+
+        let cx = self.x.0 + self.width.0 / 2;
+        let cy = self.y.0 + self.height.0 / 2;
+
+        let core = BLADE_RADIUS.0;
+        let hub = Obstacle::new(
+            Fxpt(cx - core / 2), Fxpt(cy - core / 2), Fxpt(core), Fxpt(core));
+        if hub.overlaps(other) {
+            return true;
+        }
+
+        // Arms are a third of a turn apart
+        let tip = BLADE_RADIUS.0 / 3;
+        [0u8, 85, 171].iter().any(|&arm| {
+            let arm = angle.wrapping_add(arm);
+            let dist = BLADE_RADIUS.0 as i32 * 3 / 4;
+            let ax = cx as i32 + dist * sine(arm.wrapping_add(64)) as i32 / 256;
+            let ay = cy as i32 + dist * sine(arm) as i32 / 256;
+            Obstacle::new(
+                Fxpt(ax as i16 - tip / 2), Fxpt(ay as i16 - tip / 2),
+                Fxpt(tip), Fxpt(tip)).overlaps(other)
+        })
+    }
+
+    /// Returns `true` if `self` and `other` overlap. Boxes which only share an
+    /// edge do not overlap
+    fn overlaps(&self, other: &Obstacle) -> bool {
+        let a1 = self.x.0;
+        let a2 = self.x.0 + self.width.0;
+        let b1 = other.x.0;
+        let b2 = other.x.0 + other.width.0;
+
+        let c1 = self.y.0;
+        let c2 = self.y.0 + self.height.0;
+        let d1 = other.y.0;
+        let d2 = other.y.0 + other.height.0;
+
+        a1.max(b1) < a2.min(b2) && c1.max(d1) < c2.min(d2)
+    }
+}
+
+/// Different types of pickups the player can collect
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PickupKind {
+    /// Absorbs exactly one collision, destroying the obstacle which was hit
+    Shield,
+
+    /// Halves the scroll speed of the map for [`SLOW_TIME_FRAMES`]
+    SlowTime,
+
+    /// Halves the size of the player for [`SHRINK_FRAMES`]
+    Shrink,
+
+    /// Pulls nearby coins towards the player for [`MAGNET_FRAMES`]
+    Magnet,
+
+    /// A coin which just counts towards the coin total
+    Coin,
+}
+
+impl PickupKind {
+    /// Color used to render pickups of this kind
+    fn color(&self) -> Color {
+        match self {
+            PickupKind::Shield   => Color::from_rgba(0x80, 0xc0, 0xff, 0xff),
+            PickupKind::SlowTime => Color::from_rgba(0xc0, 0x80, 0xff, 0xff),
+            PickupKind::Shrink   => Color::from_rgba(0x80, 0xff, 0x80, 0xff),
+            PickupKind::Magnet   => Color::from_rgba(0xff, 0x60, 0x60, 0xff),
+            PickupKind::Coin     => Color::from_rgba(0xff, 0xd7, 0x00, 0xff),
+        }
+    }
+
+    /// Every kind of pickup
+    const ALL: [PickupKind; 5] = [
+        PickupKind::Shield, PickupKind::SlowTime, PickupKind::Shrink,
+        PickupKind::Magnet, PickupKind::Coin,
+    ];
+
+    /// Name of the pickup as used in level files
+    fn name(&self) -> &'static str {
+        match self {
+            PickupKind::Shield   => "shield",
+            PickupKind::SlowTime => "slow",
+            PickupKind::Shrink   => "shrink",
+            PickupKind::Magnet   => "magnet",
+            PickupKind::Coin     => "coin",
+        }
+    }
+
+    /// Look up a pickup by its [`PickupKind::name`]
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|x| x.name() == name)
+    }
+}
+
+/// A collectable item which scrolls with the map
+#[derive(Clone, Copy)]
+struct Pickup {
+    /// Top left coord of the pickup's collision square of [`PICKUP_SIZE`]
+    x: Fxpt,
+    y: Fxpt,
+
+    /// What this pickup does when collected
+    kind: PickupKind,
+}
+
+impl Pickup {
+    /// Get the collision box of the pickup
+    fn bounds(&self) -> Obstacle {
+        Obstacle::new(self.x, self.y, PICKUP_SIZE, PICKUP_SIZE)
+    }
+}
+
+/// Different types of speed zones
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ZoneKind {
+    /// The map scrolls at double speed
+    Fast,
+
+    /// The map scrolls at half speed
+    Slow,
+
+    /// Gravity pulls the player up towards the ceiling and thrusting pushes
+    /// them down
+    Flip,
+
+    /// Gravity is weaker by [`DRAFT_FORCE`]
+    Updraft,
+
+    /// Gravity is stronger by [`DRAFT_FORCE`]
+    Downdraft,
+}
+
+impl ZoneKind {
+    /// Name of the zone as used in level files
+    fn name(&self) -> &'static str {
+        match self {
+            ZoneKind::Fast      => "fast",
+            ZoneKind::Slow      => "slow",
+            ZoneKind::Flip      => "flip",
+            ZoneKind::Updraft   => "updraft",
+            ZoneKind::Downdraft => "downdraft",
+        }
+    }
+
+    /// Look up a zone by its [`ZoneKind::name`]
+    fn from_name(name: &str) -> Option<Self> {
+        [ZoneKind::Fast, ZoneKind::Slow, ZoneKind::Flip, ZoneKind::Updraft,
+         ZoneKind::Downdraft].iter().copied().find(|x| x.name() == name)
+    }
+}
+
+/// A section of the level where the scroll speed or gravity changes while the
+/// player is inside of it
+#[derive(Clone, Copy)]
+struct Zone {
+    /// Left edge of the zone
+    x: Fxpt,
+
+    /// Width of the zone, zones always span the full height of the field
+    width: Fxpt,
+
+    /// What this zone does to the scroll speed or gravity
+    kind: ZoneKind,
+}
+
+impl Zone {
+    /// X coord of the right edge of the zone, which doesn't always fit in an
+    /// [`Fxpt`] when it's far ahead
+    fn end(&self) -> i32 {
+        self.x.0 as i32 + self.width.0 as i32
+    }
+
+    /// Check if the X coord `x` is in the zone
+    fn contains(&self, x: i16) -> bool {
+        self.x.0 <= x && (x as i32) < self.end()
+    }
+
+    /// Apply the effect of this zone to the scroll speed
+    fn scroll_speed(&self, speed: Fxpt) -> Fxpt {
+        match self.kind {
+            ZoneKind::Fast => Fxpt(speed.0 * 2),
+            ZoneKind::Slow => Fxpt(speed.0 / 2),
+            _              => speed,
+        }
+    }
+
+    /// Change to the gravity inside of this zone
+    fn draft(&self) -> i16 {
+        match self.kind {
+            ZoneKind::Updraft   => -DRAFT_FORCE.0,
+            ZoneKind::Downdraft => DRAFT_FORCE.0,
+            _                   => 0,
+        }
+    }
+
+    /// Color used to tint the zone
+    fn color(&self) -> Color {
+        match self.kind {
+            ZoneKind::Fast => Color::from_rgba(0xff, 0x40, 0x40, 0x30),
+            ZoneKind::Slow => Color::from_rgba(0x40, 0x80, 0xff, 0x30),
+            ZoneKind::Flip => Color::from_rgba(0x40, 0xff, 0x80, 0x30),
+            ZoneKind::Updraft | ZoneKind::Downdraft =>
+                Color::from_rgba(0xff, 0xff, 0xff, 0x18),
+        }
+    }
+}
+
+/// A score popup which rises and fades out over [`POPUP_FRAMES`]
+#[derive(Clone, Copy)]
+struct Popup {
+    /// Center of the bottom of the text
+    x: Fxpt,
+    y: Fxpt,
+
+    /// Score which was awarded
+    value: i32,
+
+    /// Number of physics frames this popup has existed for
+    age: u8,
+}
+
+/// A large enemy which moves up and down at the right of the field during a
+/// boss stage, attacking in a repeating pattern
+#[derive(Clone, Copy)]
+struct Boss {
+    /// Top left coord of the boss's collision square of [`BOSS_SIZE`]
+    x: Fxpt,
+    y: Fxpt,
+
+    /// Hits remaining until the boss is defeated
+    health: u8,
+
+    /// Number of physics frames the boss has been around for
+    age: u16,
+
+    /// Number of attacks made so far, which picks the next attack
+    attacks: u16,
+}
+
+impl Boss {
+    /// Create a new boss just off the right of the field
+    fn new() -> Self {
+        Self {
+            x:       GAME_FIELD_WIDTH,
+            y:       Fxpt((GAME_FIELD_HEIGHT.0 - BOSS_SIZE.0) / 2),
+            health:  BOSS_HEALTH,
+            age:     0,
+            attacks: 0,
+        }
+    }
+
+    /// Get the collision box of the boss
+    fn bounds(&self) -> Obstacle {
+        Obstacle::new(self.x, self.y, BOSS_SIZE, BOSS_SIZE)
+    }
+}
+
+/// Rulesets the game can be played with
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// The default, any collision is the end of the run
+    Score,
+
+    /// The player gets multiple lives and respawns after a collision
+    Casual,
+
+    /// The run is split into stages of [`STAGE_FRAMES`], each harder than
+    /// the last and ending at a finish line
+    Stage,
+
+    /// Plays like [`Mode::Score`] with the [`Upgrade`]s bought with coins
+    /// banked from earlier career runs. These runs are unranked
+    Career,
+}
+
+impl Mode {
+    /// Number of lives the player starts with
+    fn lives(&self) -> u8 {
+        match self {
+            Mode::Score  => 1,
+            Mode::Casual => 3,
+            Mode::Stage  => 1,
+            Mode::Career => 1,
+        }
+    }
+
+    /// Name of the mode as used on the command line and in replay headers
+    fn name(&self) -> &'static str {
+        match self {
+            Mode::Score  => "score",
+            Mode::Casual => "casual",
+            Mode::Stage  => "stage",
+            Mode::Career => "career",
+        }
+    }
+
+    /// Look up a mode by its [`Mode::name`]
+    fn from_name(name: &str) -> Option<Self> {
+        [Mode::Score, Mode::Casual, Mode::Stage, Mode::Career].iter()
+            .copied().find(|x| x.name() == name)
+    }
+}
+
+/// How the thrust input moves the player
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Control {
+    /// The default, thrusting for as long as thrust is held
+    Hold,
+
+    /// Each press of thrust kicks the player up by [`TAP_IMPULSE`], holding
+    /// it does nothing more
+    Tap,
+
+    /// Thrust in proportion to the throttle level in the input bits at
+    /// [`INPUT_THROTTLE_SHIFT`], the thrust bit is ignored
+    Throttle,
+}
+
+impl Control {
+    /// Name of the control scheme as used on the command line and in replay
+    /// headers
+    fn name(&self) -> &'static str {
+        match self {
+            Control::Hold     => "hold",
+            Control::Tap      => "tap",
+            Control::Throttle => "throttle",
+        }
+    }
+
+    /// Look up a control scheme by its [`Control::name`]
+    fn from_name(name: &str) -> Option<Self> {
+        [Control::Hold, Control::Tap, Control::Throttle].iter().copied()
+            .find(|x| x.name() == name)
+    }
+}
+
+/// What touching the top or bottom of the field does to the player
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Edges {
+    /// The default, the player rides along the edge and only loses the combo
+    Safe,
+
+    /// Each touch also takes [`EDGE_PENALTY`] off the score
+    Penalty,
+
+    /// The edges hit like walls do, as in the classic helicopter game.
+    /// Shields don't absorb them
+    Lethal,
+}
+
+impl Edges {
+    /// Name of the edge rule as used on the command line and in replay
+    /// headers
+    fn name(&self) -> &'static str {
+        match self {
+            Edges::Safe    => "safe",
+            Edges::Penalty => "penalty",
+            Edges::Lethal  => "lethal",
+        }
+    }
+
+    /// Look up an edge rule by its [`Edges::name`]
+    fn from_name(name: &str) -> Option<Self> {
+        [Edges::Safe, Edges::Penalty, Edges::Lethal].iter().copied()
+            .find(|x| x.name() == name)
+    }
+}
+
+/// Upgrades bought with the coins banked in [`Mode::Career`], which only ever
+/// apply to career runs
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Upgrade {
+    /// The player starts with a shield
+    Shield,
+
+    /// The walls start [`UPGRADE_GAP`] further apart per level
+    Gap,
+
+    /// The player gets another life per level
+    Life,
+}
+
+impl Upgrade {
+    /// Every upgrade, in the order they're listed in the career shop
+    const ALL: [Upgrade; 3] = [Upgrade::Shield, Upgrade::Gap, Upgrade::Life];
+
+    /// Name of the upgrade as used in the career profile and replay headers
+    fn name(&self) -> &'static str {
+        match self {
+            Upgrade::Shield => "shield",
+            Upgrade::Gap    => "gap",
+            Upgrade::Life   => "life",
+        }
+    }
+
+    /// Name of the upgrade shown to players
+    fn title(&self) -> &'static str {
+        tr!(&format!("upgrade_{}", self.name()))
+    }
+
+    /// Look up an upgrade by its [`Upgrade::name`]
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|x| x.name() == name)
+    }
+
+    /// Most levels of the upgrade which can be bought
+    fn max_level(&self) -> u8 {
+        match self {
+            Upgrade::Shield => 1,
+            Upgrade::Gap    => 3,
+            Upgrade::Life   => 2,
+        }
+    }
+
+    /// Coins the next level costs once `level` levels were bought
+    fn cost(&self, level: u8) -> u64 {
+        let base = match self {
+            Upgrade::Shield => 25,
+            Upgrade::Gap    => 15,
+            Upgrade::Life   => 40,
+        };
+        base * (level as u64 + 1)
+    }
+}
+
+/// Helicopter the player flies, each tuning the physics of the preset its own
+/// way. Every craft has its own high score
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Craft {
+    /// The physics of the preset as they are
+    Standard,
+
+    /// More gravity and more impulse, so it drops and climbs hard
+    Heavy,
+
+    /// Less gravity and less impulse, drifting slowly either way
+    Glider,
+
+    /// Holds on to less of its speed, so it stops and turns around quickly
+    Stunt,
+}
+
+impl Craft {
+    /// Every craft, in the order the menu cycles through them
+    const ALL: [Craft; 4] =
+        [Craft::Standard, Craft::Heavy, Craft::Glider, Craft::Stunt];
+
+    /// Name of the craft as used on the command line and in replay headers
+    fn name(&self) -> &'static str {
+        match self {
+            Craft::Standard => "standard",
+            Craft::Heavy    => "heavy",
+            Craft::Glider   => "glider",
+            Craft::Stunt    => "stunt",
+        }
+    }
+
+    /// Name of the craft shown to players
+    fn title(&self) -> &'static str {
+        tr!(&format!("craft_{}", self.name()))
+    }
+
+    /// Look up a craft by its [`Craft::name`]
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|x| x.name() == name)
+    }
+
+    /// Tune the player physics of `difficulty` for this craft. The impulse
+    /// changes by as much as the gravity does, so thrusting still climbs as
+    /// fast against it
+    fn apply(&self, difficulty: &mut Difficulty) {
+        let gravity = difficulty.gravity;
+        match self {
+            Craft::Standard => {}
+            Craft::Heavy  => difficulty.gravity = Fxpt(gravity.0 * 5 / 4),
+            Craft::Glider => {
+                let least = GLIDER_MIN_GRAVITY.min(gravity);
+                difficulty.gravity = Fxpt(gravity.0 * 3 / 4).max(least);
+            }
+            Craft::Stunt  => difficulty.friction = STUNT_FRICTION,
+        }
+        difficulty.impulse = Fxpt(difficulty.impulse.0 +
+            difficulty.gravity.0 - gravity.0);
+    }
+}
+
+/// Scenery the endless run passes through when [`Rules::biomes`] are on,
+/// each with its own colors and one twist to the game
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Biome {
+    /// Slippery, the player keeps more of their speed
+    IceCave,
+
+    /// Heat rising off the lava lightens the gravity
+    Lava,
+
+    /// Trees crowd in, obstacles come more often
+    Forest,
+}
+
+impl Biome {
+    /// Every biome, in the order they get picked from
+    const ALL: [Biome; 3] = [Biome::IceCave, Biome::Lava, Biome::Forest];
+
+    /// Name of the biome as used in states
+    fn name(&self) -> &'static str {
+        match self {
+            Biome::IceCave => "ice_cave",
+            Biome::Lava    => "lava",
+            Biome::Forest  => "forest",
+        }
+    }
+
+    /// Name of the biome shown to players
+    fn title(&self) -> &'static str {
+        tr!(&format!("biome_{}", self.name()))
+    }
+
+    /// Look up a biome by its [`Biome::name`]
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|x| x.name() == name)
+    }
+
+    /// Apply the twist of this biome to `difficulty`
+    fn apply(&self, difficulty: &mut Difficulty) {
+        match self {
+            Biome::IceCave => difficulty.friction = ICY_FRICTION,
+            Biome::Lava    => {
+                let gravity = difficulty.gravity;
+                let least = GLIDER_MIN_GRAVITY.min(gravity);
+                difficulty.gravity = Fxpt(gravity.0 * 3 / 4).max(least);
+            }
+            Biome::Forest  => difficulty.obstacle_frames =
+                (difficulty.obstacle_frames * 3 / 4).max(1),
+        }
+    }
+
+    /// Color of the walls and obstacles at `x` through the palette, which
+    /// varies a little around the base color of the biome
+    fn palette(&self, x: f32) -> (u8, u8, u8) {
+        const TAU: f32 = core::f32::consts::PI * 2.0;
+        let (r, g, b) = match self {
+            Biome::IceCave => (176., 216., 240.),
+            Biome::Lava    => (224., 88.,  40.),
+            Biome::Forest  => (72.,  168., 80.),
+        };
+        let shade = (x * TAU).sin() * 24.;
+        ((r + shade) as u8, (g + shade) as u8, (b + shade) as u8)
+    }
+
+    /// Tint over the sky and the color of the scenery in the background
+    fn backdrop(&self) -> (Color, Color) {
+        match self {
+            Biome::IceCave => (Color::from_rgba(0x20, 0x40, 0x70, 0x40),
+                               Color::from_rgba(0xc0, 0xe8, 0xff, 0x30)),
+            Biome::Lava    => (Color::from_rgba(0x70, 0x10, 0x00, 0x40),
+                               Color::from_rgba(0xff, 0x80, 0x20, 0x40)),
+            Biome::Forest  => (Color::from_rgba(0x10, 0x40, 0x18, 0x40),
+                               Color::from_rgba(0x40, 0x90, 0x40, 0x30)),
+        }
+    }
+
+    /// Sides of the shapes making up the scenery: icicles, embers, and
+    /// trees, and the way they point
+    fn scenery(&self) -> (u8, i16) {
+        match self {
+            Biome::IceCave => (3, 90),
+            Biome::Lava    => (4, 45),
+            Biome::Forest  => (3, 270),
+        }
+    }
+}
+
+/// Parameters controlling how the game gets harder as it goes on
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Difficulty {
+    /// Gap (in pixels) between the walls at the start of the game
+    start_gap: i16,
+
+    /// Smallest gap (in pixels) the walls will shrink to
+    min_gap: i16,
+
+    /// Number of physics frames it takes for the gap to shrink by one pixel
+    gap_shrink_frames: u64,
+
+    /// Distance the map scrolls towards the player each physics frame at the
+    /// start of the game
+    scroll_speed: Fxpt,
+
+    /// Fastest the map will scroll
+    max_scroll_speed: Fxpt,
+
+    /// Number of physics frames it takes for the scroll speed to increase by
+    /// the smallest fixed-point step
+    scroll_ramp_frames: u64,
+
+    /// Downwards acceleration applied to the player every physics frame
+    gravity: Fxpt,
+
+    /// Upwards acceleration applied to the player while thrusting
+    impulse: Fxpt,
+
+    /// Fraction of the player's speed kept every physics frame
+    friction: Fxpt,
+
+    /// Minimum number of physics frames between obstacles
+    obstacle_frames: u64,
+
+    /// Curve loaded from config which, if set, replaces the gap, obstacle
+    /// cadence, and scroll speed parameters above
+    curve: Option<Curve>,
+
+    /// Extra pixels of gap given by [`Rules::adaptive`] mode, which also
+    /// slows down scrolling. Negative values tighten the game up instead
+    ease: i16,
+
+    /// Physics frames simulated per physics frame at [`PHYSICS_RATE`].
+    /// Player speeds stay in pixels per physics frame at [`PHYSICS_RATE`],
+    /// and [`player_physics`] only gets them part of the way each frame
+    substeps: u64,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self {
+            // We start at a 250 pixel gap, descend to a 180 pixel gap at a
+            // rate of one pixel per second, which is approx 70 seconds until
+            // minimum size.
+            start_gap:          250,
+            min_gap:            180,
+            gap_shrink_frames:  32,
+
+            // Start at 8 pixels per frame and speed up by a pixel every ~32
+            // seconds until we are at 12 pixels per frame
+            scroll_speed:       Fxpt::from(8),
+            max_scroll_speed:   Fxpt::from(12),
+            scroll_ramp_frames: 60,
+
+            gravity:            GRAVITY,
+            impulse:            INPUT_IMPULSE,
+            friction:           FRICTION,
+            obstacle_frames:    30,
+            curve:              None,
+            ease:               0,
+            substeps:           1,
+        }
+    }
+}
+
+impl Difficulty {
+    /// Gap between the walls after `frames` physics frames
+    fn gap(&self, frames: u64) -> Fxpt {
+        let gap = if let Some(curve) = &self.curve {
+            curve.at(frames).gap
+        } else {
+            let max_reduction = (self.start_gap - self.min_gap).max(0) as u64;
+            let reduction = (frames / self.gap_shrink_frames.max(1))
+                .min(max_reduction) as i16;
+            self.start_gap - reduction
+        };
+
+        if self.ease == 0 {
+            return Fxpt::from(gap);
+        }
+        Fxpt::from((gap + self.ease).clamp(MIN_GAP, MAX_GAP))
+    }
+
+    /// Distance the map scrolls in a physics frame after `frames` physics
+    /// frames
+    fn scroll_speed(&self, frames: u64) -> Fxpt {
+        let speed = if let Some(curve) = &self.curve {
+            curve.at(frames).scroll_speed
+        } else {
+            let max_ramp =
+                (self.max_scroll_speed.0 - self.scroll_speed.0).max(0) as u64;
+            let ramp = (frames / self.scroll_ramp_frames.max(1))
+                .min(max_ramp) as i16;
+            Fxpt(self.scroll_speed.0 + ramp)
+        };
+
+        let speed = if self.ease == 0 {
+            speed
+        } else {
+            Fxpt((speed.0 - self.ease * FIXED_POINT_DIVISOR /
+                EASE_SPEED_RATIO).max(FIXED_POINT_DIVISOR))
+        };
+        Fxpt(speed.0 / self.substeps as i16)
+    }
+
+    /// Minimum number of physics frames between obstacles after `frames`
+    /// physics frames
+    fn obstacle_frames(&self, frames: u64) -> u64 {
+        self.curve.map_or(self.obstacle_frames,
+            |x| x.at(frames).obstacle_frames) * self.substeps
+    }
+
+    /// Difficulty of `stage` (counting from zero) in [`Mode::Stage`]. Each
+    /// stage starts out narrower and faster than the last, then ramps up as
+    /// usual within the stage. Curves are followed as-is for every stage
+    fn for_stage(&self, stage: u32) -> Self {
+        let stage = stage.min(i16::MAX as u32) as i16;
+        let start_gap = self.start_gap
+            .saturating_sub(STAGE_GAP_STEP.saturating_mul(stage))
+            .max(self.min_gap);
+        let scroll_speed = Fxpt(self.scroll_speed.0
+            .saturating_add(STAGE_SCROLL_STEP.0.saturating_mul(stage))
+            .min(self.max_scroll_speed.0.max(self.scroll_speed.0)));
+
+        Self { start_gap, scroll_speed, ..*self }
+    }
+}
+
+/// Named sets of [`Difficulty`] parameters which can be picked from the menu.
+/// Scores are only comparable between runs with the same preset
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Preset {
+    Moon,
+    Easy,
+    Normal,
+    Hard,
+    Nightmare,
+}
+
+impl Preset {
+    /// Every preset, from easiest to hardest
+    const ALL: [Preset; 5] = [
+        Preset::Moon, Preset::Easy, Preset::Normal, Preset::Hard,
+        Preset::Nightmare,
+    ];
+
+    /// Name of the preset as used on the command line and in replay headers
+    fn name(&self) -> &'static str {
+        match self {
+            Preset::Moon      => "moon",
+            Preset::Easy      => "easy",
+            Preset::Normal    => "normal",
+            Preset::Hard      => "hard",
+            Preset::Nightmare => "nightmare",
+        }
+    }
+
+    /// Name of the preset shown to players
+    fn title(&self) -> &'static str {
+        tr!(&format!("preset_{}", self.name()))
+    }
+
+    /// Look up a preset by its [`Preset::name`]
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|x| x.name() == name)
+    }
+
+    /// Get the difficulty parameters of this preset
+    fn difficulty(&self) -> Difficulty {
+        let fx = |x: f32| Fxpt((x * FIXED_POINT_DIVISOR as f32) as i16);
+        match self {
+            // Low gravity and a weak impulse, so the player floats and
+            // drifts rather than dropping. Gravity under a pixel never gets
+            // the player falling through the fixed point rounding
+            Preset::Moon => Difficulty {
+                start_gap:          280,
+                min_gap:            220,
+                gap_shrink_frames:  64,
+                scroll_speed:       Fxpt::from(5),
+                max_scroll_speed:   Fxpt::from(8),
+                scroll_ramp_frames: 100,
+                gravity:            fx(1.1),
+                impulse:            fx(1.5),
+                friction:           FRICTION,
+                obstacle_frames:    48,
+                curve:              None,
+                ease:               0,
+                substeps:           1,
+            },
+            Preset::Easy => Difficulty {
+                start_gap:          280,
+                min_gap:            210,
+                gap_shrink_frames:  48,
+                scroll_speed:       Fxpt::from(6),
+                max_scroll_speed:   Fxpt::from(10),
+                scroll_ramp_frames: 80,
+                gravity:            fx(1.3),
+                impulse:            fx(1.7),
+                friction:           FRICTION,
+                obstacle_frames:    40,
+                curve:              None,
+                ease:               0,
+                substeps:           1,
+            },
+            Preset::Normal => Difficulty::default(),
+            Preset::Hard => Difficulty {
+                start_gap:          220,
+                min_gap:            160,
+                gap_shrink_frames:  24,
+                scroll_speed:       Fxpt::from(9),
+                max_scroll_speed:   Fxpt::from(14),
+                scroll_ramp_frames: 50,
+                gravity:            fx(1.9),
+                impulse:            fx(2.4),
+                friction:           FRICTION,
+                obstacle_frames:    24,
+                curve:              None,
+                ease:               0,
+                substeps:           1,
+            },
+            Preset::Nightmare => Difficulty {
+                start_gap:          200,
+                min_gap:            140,
+                gap_shrink_frames:  16,
+                scroll_speed:       Fxpt::from(10),
+                max_scroll_speed:   Fxpt::from(16),
+                scroll_ramp_frames: 40,
+                gravity:            fx(2.2),
+                impulse:            fx(2.8),
+                friction:           FRICTION,
+                obstacle_frames:    18,
+                curve:              None,
+                ease:               0,
+                substeps:           1,
+            },
+        }
+    }
+}
+
+/// Modifiers which can be applied on top of any mode and difficulty
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mutator {
+    /// Gravity is doubled, the impulse goes up by as much so thrusting still
+    /// climbs at the same rate
+    DoubleGravity,
+
+    /// The player barely slows down, sliding around
+    Icy,
+
+    /// The player is [`TINY_SIZE`]
+    Tiny,
+
+    /// The player is [`GIANT_SIZE`]
+    Giant,
+
+    /// Obstacles come every [`SWARM_OBSTACLE_FRAMES`]
+    Swarm,
+
+    /// Walls are only visible during periodic flashes
+    InvisibleWalls,
+
+    /// Only a circle of [`NIGHT_RADIUS`] around the player and the outline
+    /// of the next obstacle are visible
+    Night,
+
+    /// Hitting something bounces the player back off it, costing
+    /// [`BOUNCE_PENALTY`] rather than a life. These runs are unranked
+    Bounce,
+}
+
+impl Mutator {
+    /// Every mutator
+    const ALL: [Mutator; 8] = [
+        Mutator::DoubleGravity, Mutator::Icy, Mutator::Tiny, Mutator::Giant,
+        Mutator::Swarm, Mutator::InvisibleWalls, Mutator::Night,
+        Mutator::Bounce,
+    ];
+
+    /// Name of the mutator as used on the command line and in replay headers
+    fn name(&self) -> &'static str {
+        match self {
+            Mutator::DoubleGravity  => "double_gravity",
+            Mutator::Icy            => "icy",
+            Mutator::Tiny           => "tiny",
+            Mutator::Giant          => "giant",
+            Mutator::Swarm          => "swarm",
+            Mutator::InvisibleWalls => "invisible_walls",
+            Mutator::Night          => "night",
+            Mutator::Bounce         => "bounce",
+        }
+    }
+
+    /// Name of the mutator shown to players
+    fn title(&self) -> &'static str {
+        tr!(&format!("mutator_{}", self.name()))
+    }
+
+    /// Look up a mutator by its [`Mutator::name`]
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|x| x.name() == name)
+    }
+
+    /// Bit of the mutator in [`Rules::mutators`]
+    fn bit(&self) -> u32 {
+        1 << *self as u32
+    }
+}
+
+/// Everything which affects the simulation of a run. Two runs with the same
+/// rules and inputs will play out exactly the same, thus these are recorded
+/// in the header of replays
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Rules {
+    /// Ruleset the game is played with
+    mode: Mode,
+
+    /// Preset the difficulty was picked from
+    preset: Preset,
+
+    /// How the game ramps up over time
+    difficulty: Difficulty,
+
+    /// Connect adjacent wall columns with slopes rather than steps
+    smooth_walls: bool,
+
+    /// Generate updraft and downdraft zones
+    drafts: bool,
+
+    /// Pass through a new [`Biome`] every [`BIOME_FRAMES`], picked from the
+    /// level RNG
+    biomes: bool,
+
+    /// Bitmask of [`Mutator::bit`]s of the active mutators
+    mutators: u32,
+
+    /// Seed for the level generation
+    seed: u64,
+
+    /// If set, the game eases off or tightens up based on how the player is
+    /// doing, starting from this [`Difficulty::ease`]. These runs are
+    /// unranked
+    adaptive: Option<i16>,
+
+    /// Number of wall columns generated past the right edge of the field, up
+    /// to [`MAX_LOOKAHEAD`]
+    lookahead: u16,
+
+    /// How the thrust input moves the player
+    control: Control,
+
+    /// What touching the top or bottom of the field does
+    edges: Edges,
+
+    /// Helicopter the player flies
+    craft: Craft,
+
+    /// Levels bought of each of [`Upgrade::ALL`], which only apply in
+    /// [`Mode::Career`]
+    upgrades: [u8; Upgrade::ALL.len()],
+
+    /// Physics frames simulated per second, one of the [`TICK_RATES`]. Runs
+    /// at faster rates feel smoother and tighter, and are unranked
+    tick_rate: u16,
+
+    /// Physics frame practice runs start on, up to [`MAX_START_FRAME`]. The
+    /// game fast-forwards to it with the player held out of harm's way, so
+    /// the difficulty and generator are where they'd be that late in a run
+    start_frame: u64,
+
+    /// Physics frame a [`Mode::Casual`] run died on and was continued from,
+    /// which replays of the run continue from the same way. Continued runs
+    /// are unranked
+    continued: Option<u64>,
+}
+
+impl Rules {
+    /// Check if `mutator` is active
+    fn has(&self, mutator: Mutator) -> bool {
+        self.mutators & mutator.bit() != 0
+    }
+
+    /// Level of `upgrade` the run gets, none outside of [`Mode::Career`]
+    fn upgrade(&self, upgrade: Upgrade) -> u8 {
+        if self.mode == Mode::Career {
+            self.upgrades[upgrade as usize]
+        } else {
+            0
+        }
+    }
+
+    /// Number of lives the player starts with
+    fn lives(&self) -> u8 {
+        self.mode.lives() + self.upgrade(Upgrade::Life)
+    }
+
+    /// Physics frames simulated per physics frame at [`PHYSICS_RATE`]
+    fn substeps(&self) -> u64 {
+        self.tick_rate as u64 / PHYSICS_RATE as u64
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            mode:         Mode::Score,
+            preset:       Preset::Normal,
+            difficulty:   Difficulty::default(),
+            smooth_walls: false,
+            drafts:       false,
+            biomes:       false,
+            mutators:     0,
+            seed:         DEFAULT_SEED,
+            adaptive:     None,
+            lookahead:    0,
+            control:      Control::Hold,
+            edges:        Edges::Safe,
+            craft:        Craft::Standard,
+            upgrades:     [0; Upgrade::ALL.len()],
+            tick_rate:    TICK_RATES[0],
+            start_frame:  0,
+            continued:    None,
+        }
+    }
+}
+
+/// The game field which is used for the deterministic game. All dimensions
+/// and positions are based on fixed-point
+#[derive(Clone)]
+struct GameField {
+    /// Random number generator for the game
+    rng: Rng,
+
+    /// Number of frames rendered (first frame during rendering will observe
+    /// this as zero). Thus, this is incremented _after_ rendering is complete
+    frames: u64,
+
+    /// Number of physics frames
+    physics_frames: u64,
+
+    /// Player Y coord
+    player_y: Fxpt,
+
+    /// Player speed
+    player_speed: Fxpt,
+
+    /// Real time (in seconds) which has passed but hasn't been simulated yet
+    physics_time: f64,
+
+    /// Real time (in seconds) spent simulating physics frames during the last
+    /// rendered frame, and the number of physics frames simulated
+    tick_time: f64,
+    ticks:     u32,
+
+    /// Real times (in seconds) the physics frames simulated during the last
+    /// rendered frame which got a new press of any input ran at
+    press_ticks: Vec<f64>,
+
+    /// Start time (in seconds) of the first rendered frame
+    start_time: f64,
+
+    /// Time (in seconds) of the last rendered frame
+    last_render: f64,
+
+    /// Time (in seconds) of the first frame rendered after dying
+    died_at: Option<f64>,
+
+    /// What part of the field is shown
+    camera: Camera,
+
+    /// Upcoming walls and obstacles shown above the field, if shown
+    preview: Option<Preview>,
+
+    /// List of [`Object`]s to draw
+    objects: Vec<Object>,
+
+    walls: Vec<Obstacle>,
+    obstacles: Vec<Obstacle>,
+
+    /// Pickups which have not yet been collected
+    pickups: Vec<Pickup>,
+
+    /// Speed zones which have not yet scrolled off screen
+    zones: Vec<Zone>,
+
+    /// Shots fired by the player. These are [`ObstacleKind::Projectile`]s
+    /// which do not scroll with the map
+    bullets: Vec<Obstacle>,
+
+    /// Number of shots the player has left
+    ammo: u8,
+
+    /// Set if the player has a shield which will absorb the next collision
+    shield: bool,
+
+    /// Number of physics frames remaining for the screen flash
+    flash_frames: u8,
+
+    /// Number of physics frames remaining of half-speed scrolling
+    slow_frames: u16,
+
+    /// Number of physics frames remaining of the player being half size
+    shrink_frames: u16,
+
+    /// Number of physics frames remaining of coins being pulled in
+    magnet_frames: u16,
+
+    /// Number of coins collected
+    coins: u64,
+
+    /// Score awarded on top of the number of survived physics frames
+    bonus: u64,
+
+    /// Score taken away by bounces with [`Mutator::Bounce`]
+    penalty: u64,
+
+    /// Score popups currently on screen
+    popups: Vec<Popup>,
+
+    /// Number of obstacles passed since the player last touched the top or
+    /// bottom of the game field
+    combo: u64,
+
+    /// Rules this game is being played with, with the mutators applied
+    rules: Rules,
+
+    /// Size of the player when not shrunk
+    base_size: Fxpt,
+
+    /// Rules the game was started with, before any mutators were applied
+    base_rules: Rules,
+
+    /// Physics frames mutators were voted in on, each replacing the one
+    /// before it. Live runs record these, replays play them back
+    votes: Vec<(u64, Mutator)>,
+
+    /// Number of [`GameField::votes`] applied so far
+    applied_votes: usize,
+
+    /// Mutator which won a vote, waiting for a safe point to be applied
+    pending_vote: Option<Mutator>,
+
+    /// Number of lives remaining, including the current one
+    lives: u8,
+
+    /// Number of physics frames remaining where collisions are ignored
+    invulnerable_frames: u16,
+
+    /// Set while the player touches the top or bottom of the field, so
+    /// [`Edges::Penalty`] only counts new touches
+    on_edge: bool,
+
+    wall_skew: Fxpt,
+
+    /// Number of wall columns remaining to be generated in the current
+    /// tunnel set piece
+    tunnel: u16,
+
+    /// Index into [`PATTERNS`] of the pattern currently being generated and
+    /// how many of its columns have been generated
+    pattern: Option<(usize, u16)>,
+
+    /// Number of wall columns to generate before the next pattern
+    pattern_cooldown: u16,
+
+    /// Range of Y coords of the top of the player which can be reached at
+    /// the last generated wall column
+    reachable: (Fxpt, Fxpt),
+
+    /// Top and bottom of the gap of the last two generated wall columns,
+    /// newest last
+    gap_history: [(Fxpt, Fxpt); 2],
+
+    /// Physics frame of the last generated obstacle
+    last_obstacle: u64,
+
+    /// Tracks if we lost
+    dead: bool,
+
+    /// Level being played, rather than generating an endless one
+    level: Option<Level>,
+
+    /// Script with custom rules, see [`script`]
+    script: Option<Script>,
+
+    /// Values the debug console set each of [`Param::ALL`] to, which take
+    /// precedence over the script's
+    overrides: [Option<i64>; Param::ALL.len()],
+
+    /// Collisions never cost a life
+    god: bool,
+
+    /// Walls keep getting generated, but obstacles don't
+    freeze_spawn: bool,
+
+    /// Set once the debug console changed the run, which makes it unranked
+    debug: bool,
+
+    /// Image the player gets drawn with from a content pack, see [`mods`]
+    skin: Option<Texture2D>,
+
+    /// Sound played on every physics frame thrust registers on, so the
+    /// input cadence and dropped inputs can be heard. This only affects
+    /// presentation
+    click: Option<Sound>,
+
+    /// Rank of the player on the ladder, shown on the death screen
+    rank: Option<Rank>,
+
+    /// Colors of the obstacles from a content pack instead of the rainbow
+    palette: Option<Palette>,
+
+    /// Number of wall columns generated so far
+    column: u32,
+
+    /// X coord of the finish line once it has been generated
+    finish: Option<Fxpt>,
+
+    /// Tracks if we crossed the finish line of the level
+    finished: bool,
+
+    /// Current stage in [`Mode::Stage`], counting from zero
+    stage: u32,
+
+    /// Physics frame the current stage started on
+    stage_start: u64,
+
+    /// Entry of [`SHOP_ITEMS`] picked while the shop between stages is open.
+    /// The world waits while it is, thrust moves to the next entry and fire
+    /// buys the picked one
+    shop: Option<u8>,
+
+    /// Score the run had when it was continued, the points after it count
+    /// for a [`CONTINUE_DIVISOR`]th
+    continue_score: Option<u64>,
+
+    /// Number of physics frames spent in the shop, which don't count towards
+    /// the score
+    shop_frames: u64,
+
+    /// Biome the run is passing through, while [`Rules::biomes`] are on
+    biome: Option<Biome>,
+
+    /// Number of physics frames it took to complete each stage so far
+    stage_times: Vec<u64>,
+
+    /// Score at every checkpoint passed so far
+    splits: Vec<u64>,
+
+    /// X coord of the flag of the last checkpoint passed, until it scrolls
+    /// off the field
+    flag: Option<Fxpt>,
+
+    /// Boss which is currently around
+    boss: Option<Boss>,
+
+    /// Current [`Difficulty::ease`] in adaptive mode
+    ease: i16,
+
+    /// Number of lives lost in the current adaptive mode window
+    window_deaths: u32,
+
+    /// Number of near-misses in the current adaptive mode window
+    window_near_misses: u32,
+
+    /// Number of near-misses and collected pickups over the whole run
+    near_misses:       u64,
+    pickups_collected: u64,
+
+    /// Physics frame and clearance of every near-miss over the whole run
+    close_calls: Vec<(u64, Fxpt)>,
+
+    /// Draw the game field mirrored, with the player on the right and the map
+    /// scrolling in from the left. This only affects rendering
+    mirror: bool,
+
+    /// Draw the collision boxes, the near-miss box, and the edges of the gap
+    /// in wireframe over everything. This only affects rendering
+    hitboxes: bool,
+
+    /// Height of the player of another run to draw faintly alongside this
+    /// one, for comparing runs. This only affects rendering
+    ghost: Option<Fxpt>,
+
+    /// Score at every checkpoint of the high score run, to split against.
+    /// This only affects rendering
+    best_splits: Vec<u64>,
+
+    /// Streamer mode options for the layout, this only affects rendering
+    streamer: Streamer,
+
+    /// Set while the run is paused, the simulation doesn't step
+    paused: bool,
+
+    /// Set if the run was paused because the window lost focus, in which case
+    /// any click or keypress resumes
+    focus_lost: bool,
+
+    /// Set while the debug console takes the keyboard, so keys don't do
+    /// anything else
+    typing: bool,
+
+    /// Frame-step mode, where physics frames only advance one at a time on
+    /// presses of Period rather than with real time
+    stepping: bool,
+
+    /// Time (in seconds) the countdown after resuming a paused run ends
+    resume_at: Option<f64>,
+
+    /// Size of the screen in pixels as of the last rendered frame
+    screen: (f32, f32),
+
+    /// Tracks the input bitmask (of `INPUT_*` bits) each physics frame
+    inputs: VecDeque<u8>,
+
+    /// Running [`fnv1a`] hash of the seed followed by the inputs, which
+    /// identifies the run
+    run_hash: u64,
+
+    /// Running [`fnv1a`] hash of the seed followed by each physics frame's
+    /// input and digest of the state it left the simulation in, so two
+    /// people can verify they saw the identical run play out
+    integrity: u64,
+}
+
+/// Apply the craft and mutators of `rules` to its difficulty, returning the
+/// resulting rules and the size of the player
+fn mutated(mut rules: Rules) -> (Rules, Fxpt) {
+    let mut difficulty = rules.difficulty;
+    rules.craft.apply(&mut difficulty);
+    difficulty.start_gap = (difficulty.start_gap +
+        rules.upgrade(Upgrade::Gap) as i16 * UPGRADE_GAP).min(MAX_GAP);
+    if rules.has(Mutator::DoubleGravity) {
+        difficulty.impulse = Fxpt(difficulty.impulse.0 +
+            difficulty.gravity.0);
+        difficulty.gravity = Fxpt(difficulty.gravity.0 * 2);
+    }
+    if rules.has(Mutator::Icy) {
+        difficulty.friction = ICY_FRICTION;
+    }
+    if rules.has(Mutator::Swarm) {
+        difficulty.obstacle_frames = SWARM_OBSTACLE_FRAMES;
+        if let Some(curve) = difficulty.curve.as_mut() {
+            curve.set_obstacle_frames(SWARM_OBSTACLE_FRAMES);
+        }
+    }
+    rules.difficulty = difficulty;
+    let base_size = if rules.has(Mutator::Giant) {
+        GIANT_SIZE
+    } else if rules.has(Mutator::Tiny) {
+        TINY_SIZE
+    } else {
+        PLAYER_SIZE
+    };
+    (rules, base_size)
+}
+
+impl GameField {
+    fn new(base_rules: Rules) -> Self {
+        let (rules, base_size) = mutated(base_rules);
+
+        Self {
+            rng:            Rng::new(rules.seed),
+            frames:         0,
+            physics_frames: 0,
+            player_y:       Fxpt(GAME_FIELD_HEIGHT.0 / 2),
+            objects:        Vec::new(),
+            player_speed:   Fxpt(0),
+            physics_time:   0.,
+            tick_time:      0.,
+            press_ticks:    Vec::new(),
+            ticks:          0,
+            start_time:     0.,
+            last_render:    0.,
+            died_at:        None,
+            camera:         Camera::default(),
+            preview:        None,
+            walls:          Vec::new(),
+            obstacles:      Vec::new(),
+            pickups:        Vec::new(),
+            zones:          Vec::new(),
+            bullets:        Vec::new(),
+            ammo:           START_AMMO,
+            shield:         rules.upgrade(Upgrade::Shield) > 0,
+            flash_frames:   0,
+            slow_frames:    0,
+            shrink_frames:  0,
+            magnet_frames:  0,
+            coins:          0,
+            bonus:          0,
+            penalty:        0,
+            popups:         Vec::new(),
+            combo:          0,
+            rules,
+            base_size,
+            base_rules,
+            votes:          Vec::new(),
+            applied_votes:  0,
+            pending_vote:   None,
+            lives:          rules.lives(),
+            on_edge:        false,
+            invulnerable_frames: 0,
+            last_obstacle:  0,
+            wall_skew:      Fxpt(0),
+            tunnel:         0,
+            pattern:        None,
+            pattern_cooldown: PATTERN_INTERVAL,
+            reachable:      (Fxpt(GAME_FIELD_HEIGHT.0 / 2),
+                             Fxpt(GAME_FIELD_HEIGHT.0 / 2)),
+            gap_history:    [(Fxpt(0), GAME_FIELD_HEIGHT); 2],
+            dead:           false,
+            level:          None,
+            script:         None,
+            overrides:      [None; Param::ALL.len()],
+            god:            false,
+            freeze_spawn:   false,
+            debug:          false,
+            skin:           None,
+            click:          None,
+            rank:           None,
+            palette:        None,
+            column:         0,
+            finish:         None,
+            finished:       false,
+            stage:          0,
+            stage_start:    0,
+            shop:           None,
+            shop_frames:    0,
+            continue_score: None,
+            biome:          None,
+            stage_times:    Vec::new(),
+            splits:         Vec::new(),
+            flag:           None,
+            boss:           None,
+            ease:           rules.adaptive.unwrap_or(0),
+            window_deaths:  0,
+            window_near_misses: 0,
+            near_misses:        0,
+            close_calls:        Vec::new(),
+            pickups_collected:  0,
+            mirror:         false,
+            hitboxes:       false,
+            ghost:          None,
+            best_splits:    Vec::new(),
+            streamer:       Streamer::default(),
+            paused:         false,
+            focus_lost:     false,
+            typing:         false,
+            stepping:       false,
+            resume_at:      None,
+            screen:         (0., 0.),
+            inputs:         VecDeque::new(),
+            run_hash:       fnv1a(FNV_OFFSET, &rules.seed.to_le_bytes()),
+            integrity:      fnv1a(fnv1a(FNV_OFFSET, b"integrity"),
+                &rules.seed.to_le_bytes()),
+        }
+    }
+
+    /// Current width and height of the player, which is the `base_size`
+    /// unless the player is shrunk
+    fn player_size(&self) -> Fxpt {
+        if self.shrink_frames > 0 {
+            Fxpt(self.base_size.0 / 2)
+        } else {
+            self.base_size
+        }
+    }
+
+    /// Get the offset into a gap of `gap` pixels for an obstacle of `height`
+    /// at `location`, moving it against a wall if it wouldn't leave room for
+    /// the player on either side
+    fn leave_room(&self, location: i16, height: i16, gap: i16) -> i16 {
+        let size = self.base_size.0;
+        let below = gap - height - location;
+        if location >= size || below >= size {
+            location
+        } else if location < below {
+            0
+        } else {
+            gap - height
+        }
+    }
+
+    /// Get the collision box of the player
+    fn player_bounds(&self) -> Obstacle {
+        Obstacle::new(PLAYER_X, self.player_y,
+            self.player_size(), self.player_size())
+    }
+
+    /// Current score, survived physics frames at [`PHYSICS_RATE`] plus any
+    /// bonuses
+    fn score(&self) -> u64 {
+        let score = (self.elapsed() + self.bonus).saturating_sub(self.penalty);
+        self.continue_score.map_or(score, |at| {
+            at + score.saturating_sub(at) / CONTINUE_DIVISOR
+        })
+    }
+
+    /// Number of physics frames simulated outside of the shop, counted at
+    /// [`PHYSICS_RATE`]
+    fn elapsed(&self) -> u64 {
+        (self.physics_frames - self.shop_frames) / self.rules.substeps()
+    }
+
+    /// Up to `count` of the near-misses with the least clearance, closest
+    /// first, and earliest first between equally close ones
+    fn closest_calls(&self, count: usize) -> Vec<(u64, Fxpt)> {
+        let mut calls = self.close_calls.clone();
+        calls.sort_by_key(|&(frame, clearance)| (clearance.0, frame));
+        calls.truncate(count);
+        calls
+    }
+
+    /// Play the inputs of this run again on a fresh game with the same rules,
+    /// level, script, and votes, stopping `seconds` before where it is now
+    fn rewound(&self, seconds: u64) -> Self {
+        let mut field = GameField::new(self.base_rules);
+        field.level = self.level.clone();
+        field.script = self.script.as_ref().map(Script::restarted);
+        field.votes = self.votes.clone();
+        let frames = self.physics_frames
+            .saturating_sub(seconds * self.rules.tick_rate as u64);
+        for &input in self.inputs.iter().take(frames as usize) {
+            field.step(input);
+        }
+        field.fast_forward();
+        field
+    }
+
+    /// Score at checkpoint `idx` compared to the high score run, positive
+    /// when ahead of it
+    fn split_delta(&self, idx: usize) -> Option<i64> {
+        let split = *self.splits.get(idx)?;
+        let best = *self.best_splits.get(idx)?;
+        Some(split as i64 - best as i64)
+    }
+
+    /// Short identifier of the run, the seed and a hash of the seed and
+    /// inputs, for players to report and cross-check runs with
+    fn run_id(&self) -> String {
+        format!("{:016x}-{:08x}", self.rules.seed, self.run_hash as u32)
+    }
+
+    /// Short form of the integrity hash, as shown on the death screen
+    fn integrity_id(&self) -> String {
+        format!("{:08x}", self.integrity as u32)
+    }
+
+    /// Cheap [`fnv1a`] digest of the parts of the state which change every
+    /// physics frame, for the integrity hash
+    fn digest(&self) -> u64 {
+        let mut hash = fnv1a(FNV_OFFSET, &self.rng.0.to_le_bytes());
+        for value in [self.physics_frames, self.bonus, self.coins,
+                self.obstacles.len() as u64] {
+            hash = fnv1a(hash, &value.to_le_bytes());
+        }
+        for value in [self.player_y, self.player_speed] {
+            hash = fnv1a(hash, &value.0.to_le_bytes());
+        }
+        fnv1a(hash, &[self.lives, self.ammo, self.dead as u8,
+            self.finished as u8])
+    }
+
+    /// [`fnv1a`] hash of the state of the simulation which matters for how
+    /// the rest of the run plays out, so runs which drift apart get caught
+    fn state_hash(&self) -> u64 {
+        let mut hash = fnv1a(FNV_OFFSET, &self.rng.0.to_le_bytes());
+        for value in [self.physics_frames, self.bonus, self.coins] {
+            hash = fnv1a(hash, &value.to_le_bytes());
+        }
+        for value in [self.player_y, self.player_speed] {
+            hash = fnv1a(hash, &value.0.to_le_bytes());
+        }
+        hash = fnv1a(hash, &[self.lives, self.ammo]);
+        if let Some(script) = &self.script {
+            hash = script.state_hash(hash);
+        }
+        for obstacle in self.walls.iter().chain(self.obstacles.iter()) {
+            for value in [obstacle.x, obstacle.y, obstacle.width,
+                    obstacle.height] {
+                hash = fnv1a(hash, &value.0.to_le_bytes());
+            }
+        }
+        hash
+    }
+
+    /// Score multiplier for pickups and near-misses based on the combo
+    fn multiplier(&self) -> u64 {
+        1 + self.combo / COMBO_STEP
+    }
+
+    /// Move on to a biome other than the current one, picked from the level
+    /// RNG so replays pass through the same ones
+    fn next_biome(&mut self) {
+        let others: Vec<Biome> = Biome::ALL.iter().copied()
+            .filter(|&x| Some(x) != self.biome).collect();
+        let idx = self.rng.rand() % others.len() as u64;
+        self.biome = Some(others[idx as usize]);
+    }
+
+    /// Get the difficulty the game is currently at and the number of physics
+    /// frames at [`PHYSICS_RATE`] it has been ramping up for
+    fn difficulty(&self) -> (Difficulty, u64) {
+        let (difficulty, frames) = match self.rules.mode {
+            Mode::Stage => (self.rules.difficulty.for_stage(self.stage),
+                            self.physics_frames - self.stage_start),
+            _ => (self.rules.difficulty, self.physics_frames),
+        };
+        let mut difficulty = difficulty;
+        difficulty.substeps = self.rules.substeps();
+        difficulty.ease = self.ease;
+        if let Some(biome) = self.biome {
+            biome.apply(&mut difficulty);
+        }
+        self.scripted(&mut difficulty);
+        (difficulty, frames / self.rules.substeps())
+    }
+
+    /// Override the parameters of `difficulty` the script set
+    fn scripted(&self, difficulty: &mut Difficulty) {
+        let clamp = |x: i64| Fxpt(x.clamp(i16::MIN as i64, i16::MAX as i64)
+            as i16);
+        if let Some(gravity) = self.script_param(Param::Gravity) {
+            difficulty.gravity = clamp(gravity);
+        }
+        if let Some(impulse) = self.script_param(Param::Impulse) {
+            difficulty.impulse = clamp(impulse);
+        }
+        if let Some(frames) = self.script_param(Param::ObstacleFrames) {
+            let frames = frames.max(1) as u64;
+            difficulty.obstacle_frames = frames;
+            if let Some(curve) = difficulty.curve.as_mut() {
+                curve.set_obstacle_frames(frames);
+            }
+        }
+    }
+
+    /// Value the debug console or the script set `param` to, if either did
+    fn script_param(&self, param: Param) -> Option<i64> {
+        self.overrides[param as usize]
+            .or_else(|| self.script.as_ref().and_then(|x| x.param(param)))
+    }
+
+    /// Run the rules of the script for `hook`, if there is a script, with
+    /// `obstacle` being the one which just spawned
+    fn run_script(&mut self, hook: Hook, obstacle: Option<Obstacle>) {
+        if self.script.is_none() {
+            return;
+        }
+
+        let pixels = |x: Fxpt| (x.0 / FIXED_POINT_DIVISOR) as i64;
+        let obstacle = obstacle.unwrap_or(Obstacle::new(Fxpt(0), Fxpt(0),
+            Fxpt(0), Fxpt(0)));
+        let (difficulty, frames) = self.difficulty();
+        let values = Values {
+            frame:           self.physics_frames as i64,
+            score:           self.score() as i64,
+            lives:           self.lives as i64,
+            coins:           self.coins as i64,
+            combo:           self.combo as i64,
+            stage:           self.stage as i64,
+            player_y:        pixels(self.player_y),
+            obstacle_y:      pixels(obstacle.y),
+            obstacle_height: pixels(obstacle.height),
+            params:          [
+                PASS_BONUS as i64,
+                NEAR_MISS_BONUS as i64,
+                self.rules.difficulty.gravity.0 as i64,
+                self.rules.difficulty.impulse.0 as i64,
+                difficulty.obstacle_frames(frames) as i64,
+            ],
+        };
+        let awards = self.script.as_mut()
+            .map_or_else(Vec::new, |x| x.run(hook, &values));
+        for value in awards {
+            self.award(value);
+        }
+    }
+
+    /// Ease off if the player has been dying or scraping by over the last
+    /// window, or tighten up if they've had it easy
+    fn adapt(&mut self) {
+        if self.rules.adaptive.is_none() ||
+                !self.elapsed().is_multiple_of(ADAPT_WINDOW) {
+            return;
+        }
+
+        let step = if self.window_deaths > 0 {
+            ADAPT_STEP * 2
+        } else if self.window_near_misses >= ADAPT_NEAR_MISSES {
+            ADAPT_STEP
+        } else {
+            -ADAPT_STEP
+        };
+        self.ease = (self.ease + step).clamp(-ADAPT_MAX, ADAPT_MAX);
+        self.window_deaths = 0;
+        self.window_near_misses = 0;
+    }
+
+    /// Award the bonus for the stage we just crossed the finish line of and
+    /// move on to the next one
+    fn complete_stage(&mut self) {
+        self.stage_times.push(self.physics_frames - self.stage_start);
+        self.stage += 1;
+        self.stage_start = self.physics_frames;
+        self.finish = None;
+        self.award(STAGE_BONUS * self.stage as u64);
+
+        log_debug!("Frame {}: stage {} complete", self.physics_frames,
+            self.stage);
+        if Self::boss_stage(self.stage) {
+            log_debug!("Frame {}: boss arrives", self.physics_frames);
+            self.boss = Some(Boss::new());
+            self.ammo = self.ammo.max(BOSS_AMMO);
+        }
+    }
+
+    /// Take the `input` of a physics frame in the shop, `prev_input` being
+    /// the one before it. Only presses count, so holding a button doesn't
+    /// run through the entries
+    fn shop_input(&mut self, input: u8, prev_input: u8) {
+        let Some(picked) = self.shop else { return };
+        let fire = |x: u8| x & INPUT_FIRE != 0;
+        let thrust = |x: u8| {
+            x & INPUT_THRUST != 0 || x >> INPUT_THROTTLE_SHIFT != 0
+        };
+
+        if fire(input) && !fire(prev_input) {
+            match SHOP_ITEMS.get(picked as usize) {
+                Some(&(kind, price)) if self.coins >= price => {
+                    log_debug!("Frame {}: bought {} for {} coins",
+                        self.physics_frames, kind.name(), price);
+                    self.coins -= price;
+                    self.collect(kind);
+                }
+                Some(_) => {}
+                None => {
+                    // The next stage starts once the shop is left
+                    log_debug!("Frame {}: left the shop",
+                        self.physics_frames);
+                    self.shop = None;
+                    self.stage_start = self.physics_frames + 1;
+                }
+            }
+        } else if thrust(input) && !thrust(prev_input) {
+            self.shop = Some((picked + 1) % (SHOP_ITEMS.len() as u8 + 1));
+        }
+    }
+
+    /// Apply the effect of collecting a pickup of `kind`
+    fn collect(&mut self, kind: PickupKind) {
+        match kind {
+            PickupKind::Shield   => self.shield = true,
+            PickupKind::SlowTime => self.slow_frames = SLOW_TIME_FRAMES,
+            PickupKind::Shrink   => {
+                // Shrink around our center
+                if self.shrink_frames == 0 {
+                    self.player_y =
+                        Fxpt(self.player_y.0 + self.base_size.0 / 4);
+                }
+                self.shrink_frames = SHRINK_FRAMES;
+            }
+            PickupKind::Magnet   => self.magnet_frames = MAGNET_FRAMES,
+            PickupKind::Coin     => {
+                self.coins += 1;
+                self.award(COIN_BONUS * self.multiplier());
+            }
+        }
+    }
+
+    /// Check if `stage` is one where a boss has to be beaten
+    fn boss_stage(stage: u32) -> bool {
+        stage % BOSS_STAGES == BOSS_STAGES - 1
+    }
+
+    /// Move the boss, make its attacks, and send it off once it has been
+    /// survived for long enough
+    fn update_boss(&mut self, scroll: Fxpt) {
+        let Some(mut boss) = self.boss else { return };
+        let substeps = self.rules.substeps() as u16;
+        let speed = PROJECTILE_SPEED.0 / substeps as i16;
+
+        boss.age += 1;
+        if boss.age >= BOSS_FRAMES * substeps {
+            self.boss = None;
+            return;
+        }
+
+        // Slide in from the right, then bob up and down
+        boss.x = Fxpt((boss.x.0 - FIXED_POINT_DIVISOR / substeps as i16)
+            .max(BOSS_X.0));
+        let center = (GAME_FIELD_HEIGHT.0 - BOSS_SIZE.0) / 2;
+        boss.y = Fxpt(center + (sine((boss.age * 2 / substeps) as u8) as i32 *
+            (center - BOSS_MARGIN.0) as i32 / 256) as i16);
+
+        if boss.x == BOSS_X &&
+                boss.age.is_multiple_of(BOSS_ATTACK_FRAMES * substeps) {
+            let x = boss.x.0 - PROJECTILE_SIZE.0;
+            let y = boss.y.0 + (BOSS_SIZE.0 - PROJECTILE_SIZE.0) / 2;
+            let shot = |y: i16, vy: i16| {
+                let mut shot = Obstacle::new(Fxpt(x), Fxpt(y),
+                    PROJECTILE_SIZE, PROJECTILE_SIZE);
+                shot.kind = ObstacleKind::Projectile {
+                    vx: Fxpt(-speed),
+                    vy: Fxpt(vy),
+                };
+                shot
+            };
+
+            match boss.attacks % 3 {
+                // A single shot aimed at the player
+                0 => {
+                    let target_x = PLAYER_X.0 + self.player_size().0 / 2;
+                    let target_y = self.player_y.0 + (self.player_size().0 -
+                        PROJECTILE_SIZE.0) / 2;
+                    let frames = ((x - target_x) /
+                        (scroll.0 + speed).max(1)).max(1);
+                    self.obstacles.push(shot(y, (target_y - y) / frames));
+                }
+
+                // A spread of three shots
+                1 => {
+                    for vy in -1..=1 {
+                        self.obstacles.push(shot(y,
+                            vy * FIXED_POINT_DIVISOR / substeps as i16));
+                    }
+                }
+
+                // A wall of shots in front of the boss
+                _ => {
+                    for offset in [-3, -1, 1, 3] {
+                        self.obstacles.push(
+                            shot(y + offset * PROJECTILE_SIZE.0 * 2, 0));
+                    }
+                }
+            }
+            boss.attacks += 1;
+        }
+
+        self.boss = Some(boss);
+    }
+
+    /// Lose a life, clearing whatever we collided with and moving the player
+    /// to the center of the gap they are in
+    fn respawn(&mut self) {
+        self.lives -= 1;
+
+        let player = self.player_bounds();
+        self.obstacles.retain(|x| !x.hits(&player));
+        self.walls.retain(|x| !x.hits(&player));
+
+        self.center_in_gap();
+        self.combo = 0;
+        self.invulnerable_frames = INVULNERABLE_FRAMES;
+    }
+
+    /// Check if the run died in [`Mode::Casual`] and hasn't been continued
+    /// yet, so it can be
+    fn can_continue(&self) -> bool {
+        self.dead && self.rules.mode == Mode::Casual &&
+            self.rules.continued.is_none()
+    }
+
+    /// Continue the run from the death it just had, marking the rules of it
+    /// so replays of it continue at the same frame
+    fn continue_run(&mut self) {
+        log_info!("Run {} continued on frame {} with score {}",
+            self.run_id(), self.physics_frames, self.score());
+        self.rules.continued = Some(self.physics_frames);
+        self.base_rules.continued = Some(self.physics_frames);
+        self.revive();
+    }
+
+    /// Bring the player back to life with a single life left, clearing the
+    /// obstacles on the field and moving them to the center of the gap
+    fn revive(&mut self) {
+        self.obstacles.retain(|x| x.x >= GAME_FIELD_WIDTH);
+        self.center_in_gap();
+        self.continue_score = Some(self.score());
+        self.dead = false;
+        self.lives = 1;
+        self.combo = 0;
+        self.invulnerable_frames = INVULNERABLE_FRAMES;
+        self.died_at = None;
+    }
+
+    /// Stop the player in the center of the gap they are in
+    fn center_in_gap(&mut self) {
+        // Find the walls in the column at the center of the player
+        let size = self.player_size();
+        let center = PLAYER_X.0 + size.0 / 2;
+        let column = |x: &&Obstacle| {
+            x.x.0 <= center && center < x.x.0 + x.width.0
+        };
+        let top = self.walls.iter().filter(column)
+            .filter(|x| x.y.0 == 0)
+            .map(|x| x.y.0 + x.height.0)
+            .max().unwrap_or(0);
+        let bottom = self.walls.iter().filter(column)
+            .filter(|x| x.y.0 > 0)
+            .map(|x| x.y.0)
+            .min().unwrap_or(GAME_FIELD_HEIGHT.0);
+
+        self.player_y = Fxpt((top + bottom - size.0) / 2);
+        self.player_speed = Fxpt(0);
+    }
+
+    /// Simulate the physics frames before [`Rules::start_frame`] right away
+    fn fast_forward(&mut self) {
+        while self.physics_frames < self.rules.start_frame {
+            self.step(0);
+        }
+    }
+
+    /// Given the `top` and `bottom` of the gap of a new wall column, check if
+    /// there's a way for the player to get from where they could be at the
+    /// previous column into it. If there isn't, the gap is widened just enough
+    /// for there to be one. Returns the (possibly widened) `top` and `bottom`.
+    ///
+    /// Only walls are considered, obstacles in the gap always leave room
+    /// for the player on at least one side
+    fn ensure_reachable(&mut self, top: Fxpt, bottom: Fxpt) -> (Fxpt, Fxpt) {
+        let size = self.base_size.0;
+
+        // Number of physics frames the player has to move between columns
+        let (difficulty, frames) = self.difficulty();
+        let scroll = difficulty.scroll_speed(frames);
+        let frames = (OBSTACLE_WIDTH.0 / scroll.0.max(1)).max(1);
+
+        // Everywhere the player could be by the time they get to this column
+        let (up, down) = terminal_speeds(&difficulty);
+        let (lo, hi) = self.reachable;
+        let (frames, substeps) = (frames as i32, difficulty.substeps as i32);
+        let lo = (lo.0 as i32 - up.0 as i32 * frames / substeps).max(0) as i16;
+        let hi = (hi.0 as i32 + down.0 as i32 * frames / substeps)
+            .min((GAME_FIELD_HEIGHT.0 - size) as i32) as i16;
+
+        // The player is wider than a column, so they have to fit through the
+        // previous columns too
+        let prev_top = self.gap_history[0].0.max(self.gap_history[1].0);
+        let prev_bottom = self.gap_history[0].1.min(self.gap_history[1].1);
+
+        let mut top = top;
+        let mut bottom = bottom;
+        let allowed_lo = top.max(prev_top).0;
+        let allowed_hi = bottom.min(prev_bottom).0 - size;
+        if allowed_lo.max(lo) > allowed_hi.min(hi) {
+            // Widen the gap to fit somewhere we know the player can be
+            let (reach_lo, reach_hi) = self.reachable;
+            let target = ((top.0 + bottom.0 - size) / 2)
+                .clamp(reach_lo.0, reach_hi.0)
+                .clamp(prev_top.0, (prev_bottom.0 - size).max(prev_top.0));
+            top = top.min(Fxpt(target));
+            bottom = bottom.max(Fxpt(target + size));
+        }
+
+        let allowed_lo = top.max(prev_top).0;
+        let allowed_hi = bottom.min(prev_bottom).0 - size;
+        self.reachable = (Fxpt(allowed_lo.max(lo)),
+                          Fxpt(allowed_hi.min(hi).max(allowed_lo.max(lo))));
+        self.gap_history = [self.gap_history[1], (top, bottom)];
+
+        (top, bottom)
+    }
+
+    /// Generate a new column of walls at `column_x`, along with any
+    /// obstacles, pickups, and zones which start in it
+    fn generate_column(&mut self, column_x: Fxpt) {
+        self.column += 1;
+        if self.level.is_some() {
+            self.generate_level_column(column_x);
+            return;
+        }
+
+        // Put the finish line down once the stage has gone on long enough,
+        // or once the boss is gone in boss stages
+        if self.rules.mode == Mode::Stage && self.finish.is_none() &&
+                self.boss.is_none() &&
+                ((self.physics_frames - self.stage_start) /
+                    self.rules.substeps() >= STAGE_FRAMES ||
+                 Self::boss_stage(self.stage)) {
+            self.finish = Some(column_x);
+        }
+
+        // Compute the gap to use between the walls
+        let (difficulty, frames) = self.difficulty();
+        let mut gap = difficulty.gap(frames);
+
+        // The corridor opens up while there's a boss around
+        let boss = self.boss.is_some();
+        if boss {
+            gap = Fxpt::from(BOSS_GAP);
+            self.wall_skew = Fxpt(0);
+        }
+
+        // Occasionally start a tunnel, where the walls taper into a long
+        // tight corridor with nothing else in it and then back out
+        if self.tunnel == 0 && self.pattern.is_none() && !boss &&
+                self.elapsed() >= TUNNEL_START &&
+                self.rng.rand().is_multiple_of(TUNNEL_RARITY) {
+            self.tunnel = TUNNEL_COLUMNS;
+            log_debug!("Column {}: tunnel starts", self.column);
+        }
+        let in_tunnel = self.tunnel > 0;
+        if in_tunnel {
+            let column = TUNNEL_COLUMNS - self.tunnel;
+            let taper = column.min(TUNNEL_COLUMNS - 1 - column)
+                .min(TUNNEL_TAPER) as i32;
+            if gap > TUNNEL_GAP {
+                gap = Fxpt(gap.0 - ((gap.0 - TUNNEL_GAP.0) as i32 * taper /
+                    TUNNEL_TAPER as i32) as i16);
+            }
+            self.tunnel -= 1;
+        }
+
+        let wall_size = Fxpt((GAME_FIELD_HEIGHT.0 - gap.0) / 2);
+
+        // Occasionally start a speed zone, as long as we're not still
+        // in the previous one
+        if self.rng.rand().is_multiple_of(ZONE_RARITY) &&
+                self.zones.last().is_none_or(|x| {
+                    x.end() <= column_x.0 as i32
+                }) {
+            self.zones.push(Zone {
+                x:     column_x,
+                width: Fxpt(OBSTACLE_WIDTH.0 * ZONE_COLUMNS),
+                kind:  match self.rng.rand() % 3 {
+                    0 => ZoneKind::Fast,
+                    1 => ZoneKind::Slow,
+                    _ => ZoneKind::Flip,
+                },
+            });
+            log_debug!("Column {}: {} zone starts", self.column,
+                self.zones.last().map_or("", |x| x.kind.name()));
+        }
+
+        // Drafts only roll when they're on, so the generation is unchanged
+        // otherwise
+        if self.rules.drafts &&
+                self.rng.rand().is_multiple_of(DRAFT_RARITY) &&
+                self.zones.last().is_none_or(|x| {
+                    x.end() <= column_x.0 as i32
+                }) {
+            self.zones.push(Zone {
+                x:     column_x,
+                width: Fxpt(OBSTACLE_WIDTH.0 * DRAFT_COLUMNS),
+                kind:  if self.rng.rand() & 1 == 0 {
+                    ZoneKind::Updraft
+                } else {
+                    ZoneKind::Downdraft
+                },
+            });
+            log_debug!("Column {}: {} starts", self.column,
+                self.zones.last().map_or("", |x| x.kind.name()));
+        }
+
+        // Tunnels only wander gently so they're always passable, and the
+        // corridor stays straight during boss fights
+        let max_step = if boss { 0 } else if in_tunnel { 2 } else { 8 };
+        self.wall_skew = Fxpt((self.wall_skew.0 +
+            self.rng.rand() as i16 % (FIXED_POINT_DIVISOR * max_step).max(1))
+            .clamp(-wall_size.0, wall_size.0));
+
+        // Make sure the player can actually make it through the new column
+        let (prev_top, prev_bottom) = self.gap_history[1];
+        let (top, bottom) = self.ensure_reachable(
+            Fxpt(wall_size.0 + self.wall_skew.0),
+            Fxpt(GAME_FIELD_HEIGHT.0 - (wall_size.0 - self.wall_skew.0)));
+
+        if self.rules.smooth_walls {
+            // Slope from the edges of the previous column to ours
+            let mut ceiling = Obstacle::new(column_x, Fxpt(0),
+                OBSTACLE_WIDTH, top.max(prev_top));
+            ceiling.kind = ObstacleKind::Slope {
+                left: prev_top, right: top, ceiling: true,
+            };
+            self.walls.push(ceiling);
+
+            let y = bottom.min(prev_bottom);
+            let mut floor = Obstacle::new(column_x, y,
+                OBSTACLE_WIDTH, Fxpt(GAME_FIELD_HEIGHT.0 - y.0));
+            floor.kind = ObstacleKind::Slope {
+                left: prev_bottom, right: bottom, ceiling: false,
+            };
+            self.walls.push(floor);
+        } else {
+            self.walls.push(Obstacle::new(
+                column_x,
+                Fxpt(0),
+                OBSTACLE_WIDTH,
+                top,
+            ));
+            
+            self.walls.push(Obstacle::new(
+                column_x,
+                bottom,
+                OBSTACLE_WIDTH,
+                Fxpt(GAME_FIELD_HEIGHT.0 - bottom.0),
+            ));
+        }
+
+        // Everything else gets placed in the (possibly widened) gap
+        let gap = Fxpt(bottom.0 - top.0);
+
+        // Nothing else goes in tunnels or boss fights
+        if in_tunnel || boss {
+            return;
+        }
+
+        // Stitch in an authored pattern every so often
+        self.pattern_cooldown = self.pattern_cooldown.saturating_sub(1);
+        if self.pattern.is_none() && self.pattern_cooldown == 0 &&
+                self.elapsed() >= PATTERN_START {
+            let idx = (self.rng.rand() % PATTERNS.len() as u64) as usize;
+            self.pattern = Some((idx, 0));
+            self.pattern_cooldown = PATTERN_INTERVAL;
+            log_debug!("Column {}: pattern {} starts", self.column, idx);
+        }
+
+        // Patterns replace all other obstacles and pickups while they last
+        if let Some((idx, column)) = self.pattern {
+            let pattern = &PATTERNS[idx];
+            for piece in pattern.pieces.iter().filter(|x| x.column == column) {
+                // Scale pieces down to fit narrower gaps or bigger players
+                let room = patterns::DESIGN_GAP * FIXED_POINT_DIVISOR -
+                    PLAYER_SIZE.0;
+                let height = Fxpt::from(piece.height);
+                let height = Fxpt((height.0 as i32 *
+                    (gap.0 - self.base_size.0) as i32 / room as i32)
+                    .clamp(0, height.0 as i32) as i16);
+                let y = top.0 + ((gap.0 - height.0) as i32 *
+                    piece.position as i32 / 256) as i16;
+                self.obstacles.push(
+                    Obstacle::new(column_x, Fxpt(y), OBSTACLE_WIDTH, height));
+            }
+
+            self.pattern = if column + 1 < pattern.columns {
+                Some((idx, column + 1))
+            } else {
+                None
+            };
+            self.last_obstacle = self.physics_frames;
+            return;
+        }
+
+        // Occasionally mount a turret on the top or bottom wall
+        if self.elapsed() >= TURRET_START &&
+                self.rng.rand().is_multiple_of(TURRET_RARITY) {
+            let x = Fxpt(column_x.0 +
+                (OBSTACLE_WIDTH.0 - TURRET_WIDTH.0) / 2);
+            let ceiling = self.rng.rand().is_multiple_of(2);
+            let y = if ceiling {
+                top
+            } else {
+                Fxpt(bottom.0 - TURRET_HEIGHT.0)
+            };
+
+            let mut turret =
+                Obstacle::new(x, y, TURRET_WIDTH, TURRET_HEIGHT);
+            turret.kind = ObstacleKind::Turret {
+                cooldown: (self.rng.rand() %
+                    TURRET_COOLDOWN as u64) as u16,
+                ceiling,
+            };
+            log_debug!("Column {}: turret on the {}", self.column,
+                if ceiling { "ceiling" } else { "floor" });
+            self.obstacles.push(turret);
+        }
+
+        if self.physics_frames - self.last_obstacle >=
+                difficulty.obstacle_frames(frames) {
+            let location = ((self.rng.rand() as u16) %
+                (gap.0 - OBSTACLE_HEIGHT.0) as u16) as i16;
+            let location =
+                self.leave_room(location, OBSTACLE_HEIGHT.0, gap.0);
+
+            let mut obstacle = Obstacle::new(
+                column_x,
+                Fxpt(top.0 + location),
+                OBSTACLE_WIDTH,
+                OBSTACLE_HEIGHT,
+            );
+
+            // Blades get placed anywhere they fully fit in the gap
+            if self.elapsed() >= BLADE_START &&
+                    self.rng.rand().is_multiple_of(BLADE_RARITY) {
+                let size = BLADE_RADIUS.0 * 2;
+                let location = ((self.rng.rand() as u16) %
+                    (gap.0 - size) as u16) as i16;
+                let location = self.leave_room(location, size, gap.0);
+                obstacle = Obstacle::new(
+                    obstacle.x,
+                    Fxpt(top.0 + location),
+                    Fxpt(size), Fxpt(size));
+                obstacle.kind = ObstacleKind::Blade {
+                    angle: self.rng.rand() as u8,
+                    spin:  if self.rng.rand().is_multiple_of(2) {
+                        3
+                    } else {
+                        -3
+                    },
+                };
+            } else if self.elapsed() >= GATE_START &&
+                    self.rng.rand().is_multiple_of(GATE_RARITY) {
+                // Pick where the opening will be once closed, which can't
+                // be any wider than the gap
+                let opening_size = GATE_OPENING.0
+                    .max(self.base_size.0 * 3 / 2).min(gap.0 - 1);
+                let opening = top.0 + ((self.rng.rand() as u16) %
+                    (gap.0 - opening_size) as u16) as i16;
+
+                let mut lower = obstacle;
+                lower.kind = ObstacleKind::Gate {
+                    anchor:  bottom,
+                    extent:  Fxpt(bottom.0 -
+                        (opening + opening_size)),
+                    ceiling: false,
+                };
+                lower.update(self.physics_frames, self.rules.substeps());
+                self.obstacles.push(lower);
+
+                obstacle.kind = ObstacleKind::Gate {
+                    anchor:  top,
+                    extent:  Fxpt(opening - top.0),
+                    ceiling: true,
+                };
+            } else if self.elapsed() >= OSCILLATE_START &&
+                    self.rng.rand()
+                        .is_multiple_of(OSCILLATE_RARITY) {
+                let center_y = Fxpt(top.0 +
+                    (gap.0 - OBSTACLE_HEIGHT.0) / 2);
+                obstacle.kind = ObstacleKind::Oscillating {
+                    center_y,
+                    amplitude: Fxpt(OSCILLATE_AMPLITUDE.0
+                        .min((gap.0 - OBSTACLE_HEIGHT.0) / 2)),
+                    phase: self.rng.rand() as u8,
+                };
+            }
+
+            obstacle.update(self.physics_frames, self.rules.substeps());
+            log_debug!("Column {}: {} obstacle at {} in a gap of {}",
+                self.column, obstacle.kind.name(), obstacle.y.0 - top.0,
+                gap.0);
+            self.obstacles.push(obstacle);
+            self.run_script(Hook::Spawn, Some(obstacle));
+
+            self.last_obstacle = self.physics_frames;
+        } else {
+            let kind = if self.rng.rand()
+                    .is_multiple_of(PICKUP_RARITY) {
+                Some(match self.rng.rand() % 4 {
+                    0 => PickupKind::Shield,
+                    1 => PickupKind::SlowTime,
+                    2 => PickupKind::Shrink,
+                    _ => PickupKind::Magnet,
+                })
+            } else if self.rng.rand().is_multiple_of(COIN_RARITY) {
+                Some(PickupKind::Coin)
+            } else {
+                None
+            };
+
+            // Place the pickup in the center of the gap
+            if let Some(kind) = kind {
+                self.pickups.push(Pickup {
+                    x:    column_x,
+                    y:    Fxpt(top.0 +
+                               (gap.0 - PICKUP_SIZE.0) / 2),
+                    kind,
+                });
+            }
+        }
+    }
+
+    /// Generate the next column of walls, obstacles, pickups, and zones of
+    /// the [`Level`] being played at `column_x`
+    fn generate_level_column(&mut self, column_x: Fxpt) {
+        let Some(level) = self.level.as_ref() else { return };
+        let column = self.column - 1;
+
+        // Levels without walls at the start use the usual starting gap
+        let (top, bottom) =
+            level.gap(column, self.rules.difficulty.start_gap);
+        let (top, bottom) = (Fxpt::from(top), Fxpt::from(bottom));
+
+        if column == level.length {
+            self.finish = Some(column_x);
+        }
+
+        // Things past the finish line never get generated
+        let spawned = self.obstacles.len();
+        if column < level.length {
+            for block in level.blocks.iter().filter(|x| x.column == column) {
+                self.obstacles.push(Obstacle::new(column_x,
+                    Fxpt::from(block.y), OBSTACLE_WIDTH,
+                    Fxpt::from(block.height)));
+            }
+            for item in level.items.iter().filter(|x| x.column == column) {
+                self.pickups.push(Pickup {
+                    x:    column_x,
+                    y:    Fxpt::from(item.y),
+                    kind: item.kind,
+                });
+            }
+            for region in level.regions.iter().filter(|x| x.column == column) {
+                self.zones.push(Zone {
+                    x:     column_x,
+                    width: Fxpt(OBSTACLE_WIDTH.0 * region.columns),
+                    kind:  region.kind,
+                });
+            }
+        }
+        for idx in spawned..self.obstacles.len() {
+            self.run_script(Hook::Spawn, Some(self.obstacles[idx]));
+        }
+
+        let (prev_top, prev_bottom) = self.gap_history[1];
+        self.gap_history = [self.gap_history[1], (top, bottom)];
+        if self.rules.smooth_walls {
+            let mut ceiling = Obstacle::new(column_x, Fxpt(0),
+                OBSTACLE_WIDTH, top.max(prev_top));
+            ceiling.kind = ObstacleKind::Slope {
+                left: prev_top, right: top, ceiling: true,
+            };
+            self.walls.push(ceiling);
+
+            let y = bottom.min(prev_bottom);
+            let mut floor = Obstacle::new(column_x, y,
+                OBSTACLE_WIDTH, Fxpt(GAME_FIELD_HEIGHT.0 - y.0));
+            floor.kind = ObstacleKind::Slope {
+                left: prev_bottom, right: bottom, ceiling: false,
+            };
+            self.walls.push(floor);
+        } else {
+            self.walls.push(Obstacle::new(column_x, Fxpt(0),
+                OBSTACLE_WIDTH, top));
+            self.walls.push(Obstacle::new(column_x, bottom,
+                OBSTACLE_WIDTH, Fxpt(GAME_FIELD_HEIGHT.0 - bottom.0)));
+        }
+    }
+
+    /// Award `value` bonus score and spawn a popup for it above the player
+    fn award(&mut self, value: u64) {
+        self.bonus += value;
+        self.popups.push(Popup {
+            x:     Fxpt(PLAYER_X.0 + self.player_size().0 / 2),
+            y:     self.player_y,
+            value: value as i32,
+            age:   0,
+        });
+    }
+
+    /// Get a list of the active power-ups for display on the HUD
+    fn powerup_status(&self) -> String {
+        let mut status = String::new();
+        if self.shield {
+            status += &format!(" | {}", tr!("shield"));
+        }
+        if self.slow_frames > 0 {
+            status += &format!(" | {}",
+                tr!("slow", format!("{:3}", self.slow_frames)));
+        }
+        if self.shrink_frames > 0 {
+            status += &format!(" | {}",
+                tr!("shrink", format!("{:3}", self.shrink_frames)));
+        }
+        if self.magnet_frames > 0 {
+            status += &format!(" | {}",
+                tr!("magnet", format!("{:3}", self.magnet_frames)));
+        }
+        if self.ammo > 0 {
+            status += &format!(" | {}", tr!("ammo", self.ammo));
+        }
+        status
+    }
+
+    /// Draw a player where ([`PLAYER_X`], `self.player_y`) is the top left
+    /// coord of the players collision square which is `base_size` (or half
+    /// that while shrunk)
+    fn draw_player(&mut self) {
+        let size = self.player_size();
+
+        // Blink while invulnerable
+        if (self.invulnerable_frames / 4) % 2 == 1 {
+            return;
+        }
+
+        // Draw the shield behind the player
+        if self.shield {
+            self.objects.push(Object::Polygon {
+                x:        Fxpt(PLAYER_X.0 + size.0 / 2),
+                y:        Fxpt(self.player_y.0 + size.0 / 2),
+                sides:    6,
+                radius:   Fxpt(size.0 * 3 / 4),
+                rotation: Fxpt(0),
+                color:    Color::from_rgba(0x80, 0xc0, 0xff, 0x80),
+            });
+        }
+
+        // Draw a ring around the player showing the remaining shrink time
+        if self.shrink_frames > 0 {
+            const SIDES: u8 = 32;
+            self.objects.push(Object::Ring {
+                x:         Fxpt(PLAYER_X.0 + size.0 / 2),
+                y:         Fxpt(self.player_y.0 + size.0 / 2),
+                sides:     SIDES,
+                segments:  (self.shrink_frames as u32 * SIDES as u32)
+                    .div_ceil(SHRINK_FRAMES as u32) as u8,
+                radius:    size,
+                thickness: Fxpt::from(2),
+                color:     PickupKind::Shrink.color(),
+            });
+        }
+
+        // Skins stretch over the player, with the default player as a
+        // fallback
+        if let Some(texture) = self.skin {
+            self.objects.push(Object::Sprite {
+                x:      PLAYER_X,
+                y:      self.player_y,
+                width:  size,
+                height: size,
+                texture,
+                flip:   false,
+            });
+            return;
+        }
+
+        // Default player
+        self.objects.push(Object::Rectangle {
+            x:      PLAYER_X,
+            y:      self.player_y,
+            width:  size,
+            height: size,
+            color:  Color::from_rgba(
+                (self.physics_frames as u8).wrapping_mul(3),
+                (self.physics_frames as u8).wrapping_mul(7),
+                (self.physics_frames as u8).wrapping_mul(5), 0xff),
+        });
+    }
+
+    /// Cover the game field in darkness, other than a circle around the
+    /// player, and outline the next obstacle coming up
+    fn draw_darkness(&mut self) {
+        let size = self.player_size();
+        let cx = PLAYER_X.0 + size.0 / 2;
+        let cy = self.player_y.0 + size.0 / 2;
+        let radius = NIGHT_RADIUS.0 as f32;
+        let dark = Color::from_rgba(0, 0, 0, 0xff);
+
+        // Build the darkness out of horizontal strips, leaving a gap for the
+        // chord of the circle in each
+        for y in (0..GAME_FIELD_HEIGHT.0).step_by(NIGHT_STRIP.0 as usize) {
+            let dy = (y + NIGHT_STRIP.0 / 2 - cy) as f32;
+            let half = (radius * radius - dy * dy).max(0.).sqrt() as i16;
+            let (left, right) = if half > 0 {
+                (cx - half, cx + half)
+            } else {
+                (GAME_FIELD_WIDTH.0, GAME_FIELD_WIDTH.0)
+            };
+
+            for (x, end) in [(0, left), (right, GAME_FIELD_WIDTH.0)] {
+                let (x, end) = (x.max(0), end.min(GAME_FIELD_WIDTH.0));
+                if end > x {
+                    self.objects.push(Object::Rectangle {
+                        x:      Fxpt(x),
+                        y:      Fxpt(y),
+                        width:  Fxpt(end - x),
+                        height: NIGHT_STRIP,
+                        color:  dark,
+                    });
+                }
+            }
+        }
+
+        // Faint silhouette of the next obstacle ahead of the player
+        let next = self.obstacles.iter().filter(|x| {
+            x.x.0 >= PLAYER_X.0 + size.0 &&
+                !matches!(x.kind, ObstacleKind::Projectile { .. })
+        }).min_by_key(|x| x.x);
+        if let Some(next) = next {
+            let line = FIXED_POINT_DIVISOR;
+            let color = Color::from_rgba(0x80, 0x80, 0x80, 0x60);
+            let (x, y, w, h) = (next.x.0, next.y.0, next.width.0, next.height.0);
+            for (x, y, w, h) in [
+                (x, y, w, line), (x, y + h - line, w, line),
+                (x, y, line, h), (x + w - line, y, line, h),
+            ] {
+                self.objects.push(Object::Rectangle {
+                    x: Fxpt(x), y: Fxpt(y), width: Fxpt(w), height: Fxpt(h),
+                    color,
+                });
+            }
+        }
+    }
+
+    /// A color generator from Desu_Used
+    fn pastel_rainbow(x: f32) -> (u8, u8, u8) {
+        const TAU: f32 = core::f32::consts::PI * 2.0;
+        let r = (x * TAU + 0.274).sin() * 40.0 + 213.0;
+        let g = (x * TAU - 1.616).sin() * 40.0 + 213.0;
+        let b = (x * TAU - 3.918).sin() * 46.0 + 207.0;
+        (r as u8, g as u8, b as u8)
+    }
+
+    /// Pause the run
+    fn pause(&mut self) {
+        self.paused    = true;
+        self.resume_at = None;
+    }
+
+    /// Resume a paused run after a countdown
+    fn resume(&mut self) {
+        self.paused     = false;
+        self.focus_lost = false;
+        self.resume_at  = Some(get_time() + RESUME_COUNTDOWN);
+    }
+
+    /// Check if `player` hits any wall, obstacle, or the boss
+    fn collides(&self, player: &Obstacle) -> bool {
+        self.obstacles.iter()
+            .chain(self.walls.iter())
+            .any(|x| x.hits(player)) ||
+            self.boss.is_some_and(|x| x.bounds().overlaps(player))
+    }
+
+    /// Queue up `mutator` to replace the last voted in one at the next safe
+    /// point
+    fn vote(&mut self, mutator: Mutator) {
+        self.pending_vote = Some(mutator);
+    }
+
+    /// Check if there's room for the player to switch to `mutator` without
+    /// running into anything for the next [`VOTE_SAFE_DISTANCE`]
+    fn safe_for(&self, mutator: Mutator) -> bool {
+        let (_, size) = mutated(Rules {
+            mutators: self.base_rules.mutators | mutator.bit(),
+            ..self.base_rules
+        });
+        let size = size.max(self.player_size());
+        let area = Obstacle::new(PLAYER_X, self.player_y,
+            Fxpt(size.0 + VOTE_SAFE_DISTANCE.0), size);
+        area.y.0 + size.0 <= GAME_FIELD_HEIGHT.0 && !self.collides(&area)
+    }
+
+    /// Record the pending vote if it's safe to apply now, and apply any votes
+    /// due on this physics frame
+    fn apply_votes(&mut self) {
+        if let Some(mutator) = self.pending_vote {
+            if self.safe_for(mutator) {
+                self.votes.push((self.physics_frames, mutator));
+                self.pending_vote = None;
+            }
+        }
+
+        while let Some(&(frame, mutator)) =
+                self.votes.get(self.applied_votes) {
+            if frame > self.physics_frames {
+                break;
+            }
+            self.applied_votes += 1;
+
+            let (rules, base_size) = mutated(Rules {
+                mutators: self.base_rules.mutators | mutator.bit(),
+                ..self.base_rules
+            });
+            self.rules     = rules;
+            self.base_size = base_size;
+            log_info!("Voted in {} on frame {}", mutator.name(), frame);
+        }
+    }
+
+    /// Everything a [`Controller`] gets to see of the game right now
+    fn state(&self) -> GameState<'_> {
+        let (difficulty, difficulty_frames) = self.difficulty();
+        GameState {
+            frame:        self.physics_frames,
+            player_y:     self.player_y,
+            player_speed: self.player_speed,
+            player_size:  self.player_size(),
+            difficulty,
+            difficulty_frames,
+            walls:        &self.walls,
+            obstacles:    &self.obstacles,
+            pickups:      &self.pickups,
+            zones:        &self.zones,
+            ammo:         self.ammo,
+            shop:         self.shop,
+        }
+    }
+
+    /// Simulate a single physics frame with the `input` bitmask of `INPUT_*`
+    /// bits
+    fn step(&mut self, input: u8) {
+        let prev_input = self.inputs.back().copied().unwrap_or(0);
+        self.inputs.push_back(input);
+
+        // Timers count physics frames at `PHYSICS_RATE`, so they only tick
+        // on every `substeps`th frame, while speeds get divided up
+        let substeps = self.rules.substeps();
+        let tick = self.physics_frames.is_multiple_of(substeps) as u16;
+
+        // Nothing counts for or against the player before the start frame
+        let fast_forward = self.physics_frames < self.rules.start_frame;
+        self.run_hash = fnv1a(self.run_hash, &[input]);
+
+        // The world waits while the shop is open, the inputs only shop
+        if self.shop.is_some() {
+            self.shop_input(input, prev_input);
+            self.physics_frames += 1;
+            self.shop_frames += 1;
+            self.integrity = fnv1a(fnv1a(self.integrity, &[input]),
+                &self.digest().to_le_bytes());
+            return;
+        }
+        self.apply_votes();
+        if self.physics_frames == 0 {
+            self.run_script(Hook::Start, None);
+        }
+        self.run_script(Hook::Tick, None);
+
+        // Move on to another biome every `BIOME_FRAMES` outside of the shop
+        if self.rules.biomes && (self.physics_frames - self.shop_frames)
+                .is_multiple_of(BIOME_FRAMES * substeps) {
+            self.next_biome();
+        }
+
+        // Fire a shot when the fire button gets pressed
+        if input & INPUT_FIRE != 0 && prev_input & INPUT_FIRE == 0 &&
+                self.ammo > 0 {
+            self.ammo -= 1;
+
+            let size = self.player_size();
+            let mut bullet = Obstacle::new(
+                Fxpt(PLAYER_X.0 + size.0),
+                Fxpt(self.player_y.0 + (size.0 - BULLET_SIZE.0) / 2),
+                BULLET_SIZE, BULLET_SIZE);
+            bullet.kind = ObstacleKind::Projectile {
+                vx: Fxpt(BULLET_SPEED.0 / substeps as i16),
+                vy: Fxpt(0),
+            };
+            self.bullets.push(bullet);
+        }
+        
+        // Compute how far the map moves this frame, based on the zone
+        // the center of the player is in. Slow-time only affects the map,
+        // the player physics stay the same
+        let (difficulty, frames) = self.difficulty();
+        let scroll = difficulty.scroll_speed(frames);
+        let center = PLAYER_X.0 + self.player_size().0 / 2;
+        let scroll = self.zones.iter()
+            .find(|x| x.contains(center))
+            .map_or(scroll, |x| x.scroll_speed(scroll));
+        let scroll = if self.slow_frames > 0 {
+            self.slow_frames -= tick;
+            Fxpt(scroll.0 / 2)
+        } else {
+            scroll
+        };
+
+        // Grow back to full size around our center when shrink runs out
+        if self.shrink_frames > 0 {
+            self.shrink_frames -= tick;
+            if self.shrink_frames == 0 {
+                self.player_y =
+                    Fxpt(self.player_y.0 - self.base_size.0 / 4);
+            }
+        }
+
+        // Move the map (walls, obstacles, and pickups)
+        for obstacle in self.walls.iter_mut()
+                .chain(self.obstacles.iter_mut()) {
+            obstacle.x = Fxpt(obstacle.x.0 - scroll.0);
+        }
+        for pickup in self.pickups.iter_mut() {
+            pickup.x = Fxpt(pickup.x.0 - scroll.0);
+        }
+        for zone in self.zones.iter_mut() {
+            zone.x = Fxpt(zone.x.0 - scroll.0);
+        }
+        if let Some(finish) = self.finish.as_mut() {
+            *finish = Fxpt(finish.0 - scroll.0);
+        }
+        if let Some(flag) = self.flag.as_mut() {
+            *flag = Fxpt(flag.0 - scroll.0);
+        }
+        self.flag = self.flag.filter(|x| *x >= Fxpt(0));
+
+        // Move obstacles which move on their own
+        for obstacle in self.obstacles.iter_mut() {
+            obstacle.update(self.physics_frames, substeps);
+        }
+
+        // Fire turrets which are on screen and ahead of the player. Shots
+        // are aimed such that they reach the player's column where the
+        // player currently is
+        let target_x = PLAYER_X.0 + self.player_size().0 / 2;
+        let target_y = self.player_y.0 + self.player_size().0 / 2;
+        let mut shots = Vec::new();
+        for turret in self.obstacles.iter_mut() {
+            if let ObstacleKind::Turret {
+                ref mut cooldown, ceiling
+            } = turret.kind {
+                let x = turret.x.0 + (turret.width.0 -
+                    PROJECTILE_SIZE.0) / 2;
+                if *cooldown > 0 || x <= target_x ||
+                        turret.x.0 + turret.width.0 >
+                        GAME_FIELD_WIDTH.0 {
+                    continue;
+                }
+                *cooldown = TURRET_COOLDOWN;
+
+                let y = if ceiling {
+                    turret.y.0 + turret.height.0
+                } else {
+                    turret.y.0 - PROJECTILE_SIZE.0
+                };
+                let speed = PROJECTILE_SPEED.0 / substeps as i16;
+                let frames = ((x - target_x) /
+                    (scroll.0 + speed).max(1)).max(1);
+
+                let mut shot = Obstacle::new(Fxpt(x), Fxpt(y),
+                    PROJECTILE_SIZE, PROJECTILE_SIZE);
+                shot.kind = ObstacleKind::Projectile {
+                    vx: Fxpt(-speed),
+                    vy: Fxpt((target_y - PROJECTILE_SIZE.0 / 2 - y) /
+                        frames),
+                };
+                shots.push(shot);
+            }
+        }
+        self.obstacles.extend(shots);
+
+        // Move the boss and make its attacks
+        self.update_boss(scroll);
+
+        // Projectiles are destroyed when they hit a wall
+        let walls = &self.walls;
+        self.obstacles.retain(|x| {
+            !matches!(x.kind, ObstacleKind::Projectile { .. }) ||
+                !walls.iter().any(|wall| wall.hits(x))
+        });
+
+        // Move the player's shots, each destroys the first obstacle it
+        // hits. Walls can't be destroyed, but stop shots
+        let mut bullets = std::mem::take(&mut self.bullets);
+        bullets.retain_mut(|bullet| {
+            bullet.update(self.physics_frames, substeps);
+
+            if let Some(boss) = self.boss.as_mut() {
+                if boss.bounds().overlaps(bullet) {
+                    boss.health = boss.health.saturating_sub(1);
+                    return false;
+                }
+            }
+
+            if let Some(idx) = self.obstacles.iter()
+                    .position(|x| x.hits(bullet)) {
+                self.obstacles.remove(idx);
+                false
+            } else {
+                !self.walls.iter().any(|x| x.hits(bullet)) &&
+                    bullet.x < GAME_FIELD_WIDTH
+            }
+        });
+        self.bullets = bullets;
+
+        if self.boss.is_some_and(|x| x.health == 0) {
+            self.boss = None;
+            self.award(BOSS_BONUS);
+        }
+
+        // Create walls up to the lookahead past the right edge, fast zones
+        // can scroll by more than a column in a single frame
+        let frontier = Fxpt(GAME_FIELD_WIDTH.0 + OBSTACLE_WIDTH.0 *
+            (self.rules.lookahead.min(MAX_LOOKAHEAD) as i16 - 1));
+        let obstacles = self.obstacles.len();
+        loop {
+            let last_x = self.walls.get(
+                self.walls.len().wrapping_sub(1))
+                .map(|x| x.x)
+                .unwrap_or(Fxpt(GAME_FIELD_WIDTH.0 - OBSTACLE_WIDTH.0));
+            if last_x > frontier {
+                break;
+            }
+            self.generate_column(Fxpt(last_x.0 + OBSTACLE_WIDTH.0));
+        }
+
+        // Generate as usual with frozen spawns, so the walls are the same
+        // for the seed, and only then drop the new obstacles
+        if self.freeze_spawn {
+            self.obstacles.truncate(obstacles);
+        }
+
+        // Cull walls and obstacles which are off screen
+        self.walls.retain(|x| {
+            Fxpt(x.x.0 + x.width.0) > Fxpt(0)
+        });
+        self.obstacles.retain(|x| {
+            Fxpt(x.x.0 + x.width.0) > Fxpt(0) &&
+                Fxpt(x.y.0 + x.height.0) > Fxpt(0) &&
+                x.y < GAME_FIELD_HEIGHT
+        });
+        self.pickups.retain(|x| {
+            Fxpt(x.x.0 + PICKUP_SIZE.0) > Fxpt(0)
+        });
+        self.zones.retain(|x| x.end() > 0);
+
+        // Apply physics, thrusting upwards if we're flying
+        // Gravity and thrust are flipped while the center of the player
+        // is in a flip zone
+        let center = PLAYER_X.0 + self.player_size().0 / 2;
+        let flipped = self.zones.iter().any(|x| {
+            x.kind == ZoneKind::Flip && x.contains(center)
+        });
+        let physics = if flipped { flipped_physics } else { player_physics };
+        let mut player_difficulty = self.rules.difficulty;
+        player_difficulty.substeps = substeps;
+        if let Some(biome) = self.biome {
+            biome.apply(&mut player_difficulty);
+        }
+        self.scripted(&mut player_difficulty);
+
+        // Drafts the center of the player is in weaken or strengthen gravity
+        let draft: i16 = self.zones.iter()
+            .filter(|x| x.contains(center))
+            .map(|x| x.draft()).sum();
+        player_difficulty.gravity = Fxpt(player_difficulty.gravity.0 + draft);
+
+        // Taps and the throttle kick the player up directly rather than
+        // thrusting like holding does
+        let thrust = input & INPUT_THRUST != 0;
+        let (thrust, kick) = match self.rules.control {
+            Control::Hold     => (thrust, 0),
+            Control::Tap      => (false,
+                if thrust && prev_input & INPUT_THRUST == 0 {
+                    TAP_IMPULSE.0
+                } else {
+                    0
+                }),
+            Control::Throttle => (false,
+                (player_difficulty.impulse.0 as i32 *
+                 (input >> INPUT_THROTTLE_SHIFT) as i32 /
+                 (THROTTLE_LEVELS as u64 * substeps) as i32) as i16),
+        };
+        let speed = if flipped {
+            Fxpt(self.player_speed.0.saturating_add(kick))
+        } else {
+            Fxpt(self.player_speed.0.saturating_sub(kick))
+        };
+        self.player_speed = physics(speed, thrust, &player_difficulty);
+
+        // Adjust player position
+        let prev_y = self.player_y;
+        self.player_y = Fxpt(self.player_y.0 +
+            self.player_speed.0 / substeps as i16);
+
+        // Bound player, touching the bounds breaks the combo. Depending on
+        // the rules, each new touch costs score too, or the touch is lethal
+        let max_y = GAME_FIELD_HEIGHT.0 - self.player_size().0;
+        let edge = self.player_y.0 <= 0 || self.player_y.0 >= max_y;
+        if edge {
+            self.combo = 0;
+            if self.rules.edges == Edges::Penalty && !fast_forward &&
+                    !self.on_edge {
+                self.bonus = self.bonus.saturating_sub(EDGE_PENALTY);
+            }
+        }
+        self.on_edge = edge;
+        self.player_y = Fxpt(self.player_y.0.clamp(0, max_y));
+
+        // Pull nearby coins towards the player's center
+        if self.magnet_frames > 0 {
+            self.magnet_frames -= tick;
+
+            let size = self.player_size();
+            let px = PLAYER_X.0 + size.0 / 2;
+            let py = self.player_y.0 + size.0 / 2;
+            for coin in self.pickups.iter_mut()
+                    .filter(|x| x.kind == PickupKind::Coin) {
+                let dx = px - (coin.x.0 + PICKUP_SIZE.0 / 2);
+                let dy = py - (coin.y.0 + PICKUP_SIZE.0 / 2);
+
+                // Squared distances do not fit in an `i16`
+                let dist = (dx as i32).pow(2) + (dy as i32).pow(2);
+                if dist <= (MAGNET_RADIUS.0 as i32).pow(2) {
+                    let pull = MAGNET_PULL.0 / substeps as i16;
+                    coin.x = Fxpt(coin.x.0 + dx.clamp(-pull, pull));
+                    coin.y = Fxpt(coin.y.0 + dy.clamp(-pull, pull));
+                }
+            }
+        }
+
+        // Collect pickups
+        let player = self.player_bounds();
+        let mut collected = Vec::new();
+        self.pickups.retain(|x| {
+            if !fast_forward && x.bounds().overlaps(&player) {
+                collected.push(x.kind);
+                false
+            } else {
+                true
+            }
+        });
+        self.pickups_collected += collected.len() as u64;
+        for kind in collected {
+            self.collect(kind);
+        }
+
+        // Tick down the screen flash
+        self.flash_frames = self.flash_frames.saturating_sub(tick as u8);
+
+        // Nothing can hit us for a bit after we respawn
+        let invulnerable = self.invulnerable_frames > 0;
+        self.invulnerable_frames =
+            self.invulnerable_frames.saturating_sub(tick);
+
+        // A shield absorbs one collision by destroying everything we hit
+        if self.shield && !invulnerable {
+            let (obstacles, walls) = (self.obstacles.len(), self.walls.len());
+            self.obstacles.retain(|x| !x.hits(&player));
+            self.walls.retain(|x| !x.hits(&player));
+            if self.obstacles.len() < obstacles || self.walls.len() < walls {
+                self.shield = false;
+                log_debug!("Frame {}: shield absorbed a collision",
+                    self.physics_frames);
+                self.flash_frames = SHIELD_FLASH_FRAMES;
+            }
+        }
+
+        // Check collisions, losing a life if we have any to spare
+        let edge = edge && self.rules.edges == Edges::Lethal;
+        let hit = !invulnerable && !self.god &&
+            (edge || self.collides(&player));
+        if hit && self.rules.has(Mutator::Bounce) {
+            // Back off to where the player came from, heading the other way
+            // with some of the speed lost
+            log_debug!("Frame {}: bounced at y {}", self.physics_frames,
+                self.player_y.0);
+            self.player_y = prev_y;
+            self.player_speed = Fxpt(-((self.player_speed.0 as i32 *
+                BOUNCE_RESTITUTION.0 as i32) >> FIXED_POINT_SHIFT) as i16);
+            self.invulnerable_frames = BOUNCE_FRAMES;
+            self.penalty += BOUNCE_PENALTY;
+            self.combo = 0;
+        } else if hit {
+            self.window_deaths += 1;
+            log_debug!("Frame {}: collision at y {} with {} lives left",
+                self.physics_frames, self.player_y.0, self.lives - 1);
+            if self.lives > 1 {
+                self.respawn();
+            } else {
+                log_info!("Run {} died on frame {} with score {}",
+                    self.run_id(), self.physics_frames, self.score());
+                self.dead = true;
+            }
+            self.run_script(Hook::Death, None);
+        }
+        let player = self.player_bounds();
+
+        // Track clearance to obstacles next to us and award bonuses for
+        // the ones we get past
+        let mut passed = 0;
+        let mut near_misses = 0;
+        for obstacle in self.obstacles.iter_mut().filter(|x| {
+            !x.passed && !matches!(x.kind, ObstacleKind::Projectile { .. })
+        }) {
+            let right = obstacle.x.0 + obstacle.width.0;
+            if obstacle.x.0 < player.x.0 + player.width.0 &&
+                    right > player.x.0 {
+                let clearance = Fxpt((obstacle.y.0 -
+                    (player.y.0 + player.height.0))
+                    .max(player.y.0 - (obstacle.y.0 + obstacle.height.0)));
+                obstacle.clearance = Some(obstacle.clearance
+                    .map_or(clearance, |x| x.min(clearance)));
+            }
+
+            if right <= player.x.0 && !self.dead {
+                obstacle.passed = true;
+                passed += 1;
+                if let Some(clearance) = obstacle.clearance
+                        .filter(|&x| x.0 >= 0 && x < NEAR_MISS_DISTANCE) {
+                    near_misses += 1;
+                    if !fast_forward {
+                        self.close_calls.push(
+                            (self.physics_frames, clearance));
+                    }
+                }
+            }
+        }
+        if fast_forward {
+            (passed, near_misses) = (0, 0);
+        }
+        let bonus = |param, default| {
+            self.script_param(param).map_or(default, |x| x.max(0) as u64)
+        };
+        let (pass_bonus, near_miss_bonus) = (bonus(Param::PassBonus,
+            PASS_BONUS), bonus(Param::NearMissBonus, NEAR_MISS_BONUS));
+        for _ in 0..passed {
+            self.combo += 1;
+            self.award(pass_bonus);
+        }
+        for _ in 0..near_misses {
+            self.award(near_miss_bonus * self.multiplier());
+        }
+        self.window_near_misses += near_misses;
+        self.near_misses += near_misses as u64;
+
+        // The level or stage is complete once the player is across the
+        // finish line
+        if !self.dead && self.finish.is_some_and(|x| {
+            x.0 + FINISH_WIDTH.0 <= player.x.0
+        }) {
+            if self.rules.mode == Mode::Stage && self.level.is_none() {
+                self.complete_stage();
+                if !fast_forward && self.stage.is_multiple_of(SHOP_STAGES) {
+                    log_debug!("Frame {}: shop opens", self.physics_frames);
+                    self.shop = Some(0);
+                }
+            } else {
+                self.finished = true;
+            }
+        }
+
+        // Float popups upwards and remove them once they faded out
+        for popup in self.popups.iter_mut() {
+            popup.y = Fxpt(popup.y.0 - FIXED_POINT_DIVISOR / substeps as i16);
+            popup.age += tick as u8;
+        }
+        self.popups.retain(|x| x.age < POPUP_FRAMES);
+
+        // Hold the player in the gap while fast-forwarding, and give them
+        // a moment to get going once it's over
+        if fast_forward {
+            self.center_in_gap();
+            self.invulnerable_frames = INVULNERABLE_FRAMES;
+        }
+
+        // Update physics frames
+        self.physics_frames += 1;
+        self.adapt();
+
+        // Plant a flag at every checkpoint and split the score there
+        if (self.physics_frames - self.shop_frames)
+                .is_multiple_of(CHECKPOINT_FRAMES * substeps) {
+            self.splits.push(self.score());
+            self.flag = Some(PLAYER_X);
+        }
+
+        // Fold the input and where it got the simulation to into the
+        // integrity hash
+        self.integrity = fnv1a(fnv1a(self.integrity, &[input]),
+            &self.digest().to_le_bytes());
+
+        // Replays of continued runs pick back up where the player continued
+        if self.dead && self.rules.continued == Some(self.physics_frames) {
+            self.revive();
+        }
+    }
+
+    /// Build the render objects for the current state of the game
+    fn build_objects(&mut self) {
+        // Clear all render objects
+        self.objects.clear();
+
+        // Tint the sky of the biome and scroll its scenery by slowly,
+        // scattered over the field so it doesn't line up in rows
+        if let Some(biome) = self.biome {
+            let (sky, color) = biome.backdrop();
+            self.objects.push(Object::Rectangle {
+                x:      Fxpt(0),
+                y:      Fxpt(0),
+                width:  GAME_FIELD_WIDTH,
+                height: GAME_FIELD_HEIGHT,
+                color:  sky,
+            });
+
+            let (sides, rotation) = biome.scenery();
+            let spacing = 48 * FIXED_POINT_DIVISOR as i32;
+            let width = GAME_FIELD_WIDTH.0 as i32 + spacing;
+            let scrolled = (self.physics_frames / self.rules.substeps() *
+                (BIOME_PARALLAX * FIXED_POINT_DIVISOR as i32) as u64 %
+                width as u64) as i32;
+            for ii in 0..width / spacing {
+                let x = (ii * spacing - scrolled).rem_euclid(width) -
+                    spacing / 2;
+                let y = (ii * 71 * FIXED_POINT_DIVISOR as i32)
+                    .rem_euclid(GAME_FIELD_HEIGHT.0 as i32);
+                self.objects.push(Object::Polygon {
+                    x:        Fxpt(x as i16),
+                    y:        Fxpt(y as i16),
+                    sides,
+                    radius:   Fxpt::from(12),
+                    rotation: Fxpt::from(rotation),
+                    color,
+                });
+            }
+        }
+
+        // Tint zones, clipped to the game field
+        for zone in &self.zones {
+            let x = zone.x.0.max(0);
+            let end = zone.end().min(GAME_FIELD_WIDTH.0 as i32) as i16;
+            if end > x {
+                self.objects.push(Object::Rectangle {
+                    x:      Fxpt(x),
+                    y:      Fxpt(0),
+                    width:  Fxpt(end - x),
+                    height: GAME_FIELD_HEIGHT,
+                    color:  zone.color(),
+                });
+            }
+
+            // Mark flip zones with arrows pointing up down the middle
+            if zone.kind == ZoneKind::Flip {
+                let spacing = OBSTACLE_WIDTH.0 * 2;
+                for ii in 0..zone.width.0 / spacing {
+                    let x = zone.x.0 as i32 +
+                        (spacing / 2 + ii * spacing) as i32;
+                    if !(0..GAME_FIELD_WIDTH.0 as i32).contains(&x) {
+                        continue;
+                    }
+
+                    self.objects.push(Object::Polygon {
+                        x:        Fxpt(x as i16),
+                        y:        Fxpt(GAME_FIELD_HEIGHT.0 / 2),
+                        sides:    3,
+                        radius:   Fxpt::from(8),
+                        rotation: Fxpt::from(270),
+                        color:    Color::from_rgba(0x40, 0xff, 0x80, 0x80),
+                    });
+                }
+            }
+
+            // Shimmer drafts with faint streaks rising or sinking through
+            // them, staggered so they don't move as one
+            if zone.draft() != 0 {
+                let spacing = 8 * FIXED_POINT_DIVISOR as i32;
+                let streak = 16 * FIXED_POINT_DIVISOR as i32;
+                let height = GAME_FIELD_HEIGHT.0 as i32 - streak;
+                let rise = (self.physics_frames % height as u64) as i32 *
+                    zone.draft().signum() as i32 * FIXED_POINT_DIVISOR as i32;
+                for ii in 0..zone.width.0 as i32 / spacing {
+                    let x = zone.x.0 as i32 + spacing / 2 + ii * spacing;
+                    if !(0..GAME_FIELD_WIDTH.0 as i32).contains(&x) {
+                        continue;
+                    }
+
+                    let y = (ii * 71 * FIXED_POINT_DIVISOR as i32 + rise)
+                        .rem_euclid(height);
+                    self.objects.push(Object::Rectangle {
+                        x:      Fxpt(x as i16),
+                        y:      Fxpt(y as i16),
+                        width:  Fxpt::from(1),
+                        height: Fxpt(streak as i16),
+                        color:  Color::from_rgba(0xff, 0xff, 0xff, 0x40),
+                    });
+                }
+            }
+        }
+
+        // Walls only show up in periodic flashes when they're invisible
+        let walls: &[Obstacle] = if self.rules.has(Mutator::InvisibleWalls) &&
+                self.elapsed() % INVISIBLE_FLASH_PERIOD >=
+                INVISIBLE_FLASH_FRAMES {
+            &[]
+        } else {
+            &self.walls
+        };
+
+        // Draw obstacles, leaving out the ones generated ahead of the field
+        for &obstacle in self.obstacles.iter().chain(walls.iter())
+                .filter(|x| x.x < GAME_FIELD_WIDTH) {
+            let cycle = f32::from(obstacle.x) * 0.003;
+            let (r, g, b) = match (self.biome, self.palette.as_ref()) {
+                (Some(biome), _) => biome.palette(cycle),
+                (None, Some(x))  => x.at(cycle),
+                (None, None)     => Self::pastel_rainbow(cycle),
+            };
+
+            if let ObstacleKind::Projectile { .. } = obstacle.kind {
+                self.objects.push(Object::Polygon {
+                    x:        Fxpt(obstacle.x.0 + obstacle.width.0 / 2),
+                    y:        Fxpt(obstacle.y.0 + obstacle.height.0 / 2),
+                    sides:    8,
+                    radius:   Fxpt(obstacle.width.0 / 2),
+                    rotation: Fxpt(0),
+                    color:    Color::from_rgba(0xff, 0x40, 0x40, 0xff),
+                });
+                continue;
+            }
+
+            if let ObstacleKind::Blade { angle, .. } = obstacle.kind {
+                self.objects.push(Object::Polygon {
+                    x:        Fxpt(obstacle.x.0 + obstacle.width.0 / 2),
+                    y:        Fxpt(obstacle.y.0 + obstacle.height.0 / 2),
+                    sides:    3,
+                    radius:   BLADE_RADIUS,
+                    rotation: Fxpt((angle as i32 * 360 * FIXED_POINT_DIVISOR
+                        as i32 / 256) as i16),
+                    color:    Color::from_rgba(r, g, b, 0xff),
+                });
+                continue;
+            }
+
+            // Recompute the start and end to make sure we don't render outside
+            // the game window
+            let x = obstacle.x.0.max(0);
+            let end =
+                (obstacle.x.0 + obstacle.width.0).min(GAME_FIELD_WIDTH.0);
+            if end <= x {
+                continue;
+            }
+
+            if let ObstacleKind::Slope { ceiling, .. } = obstacle.kind {
+                let (left, right) =
+                    (obstacle.slope_edge(x), obstacle.slope_edge(end));
+                let fill = if ceiling { 0 } else { GAME_FIELD_HEIGHT.0 };
+                self.objects.push(Object::Quad {
+                    points: [
+                        (Fxpt(x),   Fxpt(fill)),
+                        (Fxpt(end), Fxpt(fill)),
+                        (Fxpt(end), Fxpt(right)),
+                        (Fxpt(x),   Fxpt(left)),
+                    ],
+                    color: Color::from_rgba(r, g, b, 0xff),
+                });
+                continue;
+            }
+
+            self.objects.push(Object::Rectangle {
+                x:      Fxpt(x),
+                y:      obstacle.y,
+                width:  Fxpt(end - x),
+                height: obstacle.height,
+                color:  Color::from_rgba(r, g, b, 0xff),
+            });
+        }
+        
+        // Draw a checkered finish line
+        if let Some(finish) = self.finish {
+            let square = FINISH_WIDTH.0 / 2;
+            for row in 0..GAME_FIELD_HEIGHT.0 / square {
+                for col in 0..2 {
+                    let x = finish.0 + col * square;
+                    if x < 0 || x + square > GAME_FIELD_WIDTH.0 {
+                        continue;
+                    }
+
+                    self.objects.push(Object::Rectangle {
+                        x:      Fxpt(x),
+                        y:      Fxpt(row * square),
+                        width:  Fxpt(square),
+                        height: Fxpt(square),
+                        color:  if (row + col) % 2 == 0 {
+                            WHITE
+                        } else {
+                            BLACK
+                        },
+                    });
+                }
+            }
+        }
+
+        // Draw the flag of the last checkpoint, in green when it was passed
+        // ahead of the high score run and red when behind, with the split
+        if let Some(flag) = self.flag {
+            let delta = self.split_delta(self.splits.len().wrapping_sub(1));
+            let color = match delta {
+                Some(x) if x < 0 => RED,
+                Some(_)          => GREEN,
+                None             => WHITE,
+            };
+            self.objects.push(Object::Rectangle {
+                x:      flag,
+                y:      Fxpt(0),
+                width:  Fxpt::from(2),
+                height: GAME_FIELD_HEIGHT,
+                color:  Color::from_rgba(0xff, 0xff, 0xff, 0x60),
+            });
+            self.objects.push(Object::Polygon {
+                x:        Fxpt(flag.0 + Fxpt::from(10).0),
+                y:        Fxpt::from(60),
+                sides:    3,
+                radius:   Fxpt::from(12),
+                rotation: Fxpt(0),
+                color,
+            });
+            if let Some(delta) = delta {
+                self.objects.push(Object::Number {
+                    x:     flag,
+                    y:     Fxpt::from(100),
+                    value: delta.clamp(i32::MIN as i64, i32::MAX as i64)
+                        as i32,
+                    size:  Fxpt::from(20),
+                    color,
+                });
+            }
+        }
+
+        // Draw the boss with its health bar above it
+        if let Some(boss) = self.boss {
+            self.objects.push(Object::Polygon {
+                x:        Fxpt(boss.x.0 + BOSS_SIZE.0 / 2),
+                y:        Fxpt(boss.y.0 + BOSS_SIZE.0 / 2),
+                sides:    8,
+                radius:   Fxpt(BOSS_SIZE.0 / 2),
+                rotation: Fxpt::from(boss.age as i16 % 360),
+                color:    Color::from_rgba(0xb0, 0x40, 0xe0, 0xff),
+            });
+            self.objects.push(Object::Rectangle {
+                x:      boss.x,
+                y:      Fxpt(boss.y.0 - FIXED_POINT_DIVISOR * 8),
+                width:  Fxpt((BOSS_SIZE.0 as i32 * boss.health as i32 /
+                    BOSS_HEALTH as i32) as i16),
+                height: Fxpt(FIXED_POINT_DIVISOR * 4),
+                color:  Color::from_rgba(0xff, 0x40, 0x40, 0xff),
+            });
+        }
+
+        // Draw the player's shots
+        for bullet in &self.bullets {
+            self.objects.push(Object::Polygon {
+                x:        Fxpt(bullet.x.0 + bullet.width.0 / 2),
+                y:        Fxpt(bullet.y.0 + bullet.height.0 / 2),
+                sides:    8,
+                radius:   Fxpt(bullet.width.0 / 2),
+                rotation: Fxpt(0),
+                color:    Color::from_rgba(0xff, 0xff, 0x80, 0xff),
+            });
+        }
+
+        // Draw pickups
+        for pickup in self.pickups.iter().filter(|x| x.x < GAME_FIELD_WIDTH) {
+            let color = pickup.kind.color();
+
+            self.objects.push(Object::Polygon {
+                x:        Fxpt(pickup.x.0 + PICKUP_SIZE.0 / 2),
+                y:        Fxpt(pickup.y.0 + PICKUP_SIZE.0 / 2),
+                sides:    6,
+                radius:   Fxpt(PICKUP_SIZE.0 / 2),
+                rotation: Fxpt(0),
+                color,
+            });
+        }
+
+        // Draw the other run being compared against behind the player
+        if let Some(y) = self.ghost {
+            self.objects.push(Object::Rectangle {
+                x:      PLAYER_X,
+                y,
+                width:  self.player_size(),
+                height: self.player_size(),
+                color:  Color::from_rgba(0xff, 0xff, 0xff, 0x60),
+            });
+        }
+
+        // Add the player to the object list
+        self.draw_player();
+
+        // Draw score popups
+        for popup in &self.popups {
+            self.objects.push(Object::Number {
+                x:     popup.x,
+                y:     popup.y,
+                value: popup.value,
+                size:  Fxpt::from(16),
+                color: Color::from_rgba(0xff, 0xff, 0xff,
+                    ((POPUP_FRAMES - popup.age) as u32 * 0xff /
+                     POPUP_FRAMES as u32) as u8),
+            });
+        }
+
+        // Darken everything but a circle around the player at night
+        if self.rules.has(Mutator::Night) {
+            self.draw_darkness();
+        }
+
+        // Draw a row of remaining lives in the top left of the field
+        if self.rules.lives() > 1 {
+            for life in 0..self.lives as i16 {
+                self.objects.push(Object::Rectangle {
+                    x:      Fxpt::from(8 + life * 16),
+                    y:      Fxpt::from(8),
+                    width:  Fxpt::from(12),
+                    height: Fxpt::from(12),
+                    color:  Color::from_rgba(0xff, 0x60, 0x80, 0xc0),
+                });
+            }
+        }
+
+        // Flash the whole field when a shield gets consumed
+        if self.flash_frames > 0 {
+            self.objects.push(Object::Rectangle {
+                x:      Fxpt(0),
+                y:      Fxpt(0),
+                width:  GAME_FIELD_WIDTH,
+                height: GAME_FIELD_HEIGHT,
+                color:  Color::from_rgba(0xff, 0xff, 0xff,
+                    (self.flash_frames as u32 * 0xc0 /
+                     SHIELD_FLASH_FRAMES as u32) as u8),
+            });
+        }
+        
+        if self.hitboxes {
+            self.draw_hitboxes();
+        }
+
+        // Flip everything over in mirror mode
+        if self.mirror {
+            for object in self.objects.iter_mut() {
+                *object = object.mirrored();
+            }
+        }
+    }
+
+    /// Add wireframes of everything collisions are checked against to the
+    /// object list
+    fn draw_hitboxes(&mut self) {
+        let thickness = Fxpt(FIXED_POINT_DIVISOR);
+        let outline = |x: Fxpt, y: Fxpt, width: Fxpt, height: Fxpt,
+                color: Color| {
+            let (right, bottom) = (Fxpt(x.0 + width.0), Fxpt(y.0 + height.0));
+            [
+                ((x, y), (right, y)), ((right, y), (right, bottom)),
+                ((right, bottom), (x, bottom)), ((x, bottom), (x, y)),
+            ].map(|(from, to)| Object::Line { from, to, thickness, color })
+        };
+
+        // Bounding boxes of the map and the player's shots
+        for obstacle in self.walls.iter().chain(self.obstacles.iter())
+                .chain(self.bullets.iter()) {
+            self.objects.extend(outline(obstacle.x, obstacle.y,
+                obstacle.width, obstacle.height, RED));
+        }
+        for pickup in &self.pickups {
+            self.objects.extend(outline(pickup.x, pickup.y,
+                PICKUP_SIZE, PICKUP_SIZE, SKYBLUE));
+        }
+
+        // The player, and how close obstacles have to be for a near-miss
+        let player = self.player_bounds();
+        self.objects.extend(outline(player.x,
+            Fxpt(player.y.0 - NEAR_MISS_DISTANCE.0), player.width,
+            Fxpt(player.height.0 + NEAR_MISS_DISTANCE.0 * 2), ORANGE));
+        self.objects.extend(outline(player.x, player.y, player.width,
+            player.height, YELLOW));
+
+        // Edges of the gap between the walls, following slopes
+        for wall in &self.walls {
+            let right = Fxpt(wall.x.0 + wall.width.0);
+            let (from, to) = match wall.kind {
+                ObstacleKind::Slope { left, right: end, .. } => {
+                    ((wall.x, left), (right, end))
+                }
+                _ => {
+                    let y = if wall.y.0 == 0 {
+                        Fxpt(wall.y.0 + wall.height.0)
+                    } else {
+                        wall.y
+                    };
+                    ((wall.x, y), (right, y))
+                }
+            };
+            self.objects.push(Object::Line {
+                from, to, thickness, color: GREEN,
+            });
+        }
+    }
+
+    /// Draw the render objects of the last frame, with the top left of the
+    /// game field at (`offset_x`, `offset_y`) and `scale` screen pixels to
+    /// each game field pixel
+    fn draw_objects(&self, offset_x: f32, offset_y: f32, scale: f32) {
+        let point = |x: Fxpt, y: Fxpt| {
+            vec2(f32::from(x) * scale + offset_x,
+                 f32::from(y) * scale + offset_y)
+        };
+
+        // Shapes all go into one mesh, only text interrupts it to keep
+        // everything layered in order
+        let mut batch = Batch::default();
+        for object in &self.objects {
+            match *object {
+                Object::Rectangle { x, y, width, height, color } => {
+                    let corner = point(x, y);
+                    batch.rectangle(corner.x, corner.y,
+                        f32::from(width)  * scale,
+                        f32::from(height) * scale,
+                        color);
+                }
+                Object::Polygon { x, y, sides, radius, rotation, color } => {
+                    batch.poly(point(x, y), sides, f32::from(radius) * scale,
+                        rotation.into(), color);
+                }
+                Object::Ring {
+                    x, y, sides, segments, radius, thickness, color
+                } => {
+                    let center = point(x, y);
+                    let radius = f32::from(radius) * scale;
+                    let point = |ii: u8| {
+                        let angle = ii as f32 / sides as f32 *
+                            core::f32::consts::PI * 2. -
+                            core::f32::consts::FRAC_PI_2;
+                        center + vec2(angle.cos(), angle.sin()) * radius
+                    };
+
+                    for ii in 0..segments.min(sides) {
+                        batch.line(point(ii), point(ii + 1),
+                            f32::from(thickness) * scale, color);
+                    }
+                }
+                Object::Quad { points, color } => {
+                    batch.quad(points.map(|(x, y)| point(x, y)), color);
+                }
+                Object::Line { from, to, thickness, color } => {
+                    batch.line(point(from.0, from.1), point(to.0, to.1),
+                        f32::from(thickness) * scale, color);
+                }
+                Object::Sprite { x, y, width, height, texture, flip } => {
+                    batch.flush();
+                    let corner = point(x, y);
+                    draw_texture_ex(texture, corner.x, corner.y, WHITE,
+                            DrawTextureParams {
+                        dest_size: Some(vec2(f32::from(width) * scale,
+                            f32::from(height) * scale)),
+                        flip_x: flip,
+                        ..Default::default()
+                    });
+                }
+                Object::Number { x, y, value, size, color } => {
+                    batch.flush();
+                    let text = format!("{:+}", value);
+                    let size = f32::from(size) * scale;
+                    let width = measure_text(&text, None, size as u16, 1.).width;
+                    let pos = point(x, y);
+                    draw_text(&text, pos.x - width / 2., pos.y, size, color);
+                }
+            }
+        }
+        batch.flush();
+    }
+
+    /// [`field_transform`] making room for the preview and streamer mode
+    fn layout(&self) -> (f32, f32, f32) {
+        let preview = if self.preview.is_some() { preview::HEIGHT } else { 0. };
+        field_layout(preview + self.streamer.top(), self.streamer.bottom(),
+            self.streamer.margin)
+    }
+
+    /// Render a frame through `filter`, simulating a physics frame with the
+    /// inputs from `controller` when it's time to
+    fn render(&mut self, controller: &mut dyn Controller, filter: &mut Filter)
+            -> Result<bool> {
+        let (offset_x, offset_y, scale) = self.layout();
+        let target_w = scale * f32::from(GAME_FIELD_WIDTH);
+        let target_h = scale * f32::from(GAME_FIELD_HEIGHT);
+            
+        if (self.dead || self.finished) && !self.typing &&
+                is_key_pressed(KeyCode::Space) {
+            return Ok(true);
+        }
+
+        let time = get_time();
+        if self.frames == 0 {
+            self.start_time  = time;
+            self.last_render = time;
+        }
+        let elapsed = time - self.last_render;
+
+        // There are no focus events, but frames stall while the window is
+        // minimized or the tab is hidden, so a long stall pauses the run
+        if !self.dead && !self.finished && !self.paused &&
+                controller.interactive() &&
+                elapsed >= FOCUS_LOST_TIME {
+            self.pause();
+            self.focus_lost = true;
+        }
+        self.last_render = time;
+
+        // The field jumps around when the window gets resized or the phone
+        // rotated, so the run waits for the player to find it again
+        let screen = (screen_width(), screen_height());
+        if self.frames > 0 && screen != self.screen && !self.dead &&
+                !self.finished && !self.paused && controller.interactive() {
+            self.pause();
+            self.focus_lost = true;
+        }
+        self.screen = screen;
+        if self.focus_lost && !self.typing &&
+                (get_last_key_pressed().is_some() ||
+                 is_mouse_button_pressed(MouseButton::Left)) {
+            self.resume();
+        }
+        if self.resume_at.is_some_and(|x| time >= x) {
+            self.resume_at = None;
+        }
+
+        // F8 toggles frame-step mode, which makes it a debug run
+        if !self.typing && is_key_pressed(KeyCode::F8) {
+            self.stepping = !self.stepping;
+        }
+        self.debug |= self.stepping;
+
+        // Real time only decides how many physics frames are due, the
+        // simulation itself only ever sees whole frames of inputs. In
+        // frame-step mode it's presses of Period instead
+        let frame_time = 1. / self.rules.tick_rate as f64;
+        if !self.paused && self.resume_at.is_none() {
+            self.physics_time = if !self.stepping {
+                (self.physics_time + elapsed)
+                    .min(MAX_CATCH_UP_FRAMES / PHYSICS_RATE)
+            } else if !self.typing && is_key_pressed(KeyCode::Period) {
+                frame_time
+            } else {
+                0.
+            };
+            controller.poll();
+        }
+        let tick_start = get_time();
+        self.ticks = 0;
+        self.press_ticks.clear();
+        while !self.dead && !self.finished && !self.paused &&
+                self.resume_at.is_none() &&
+                self.physics_time >= frame_time {
+            self.physics_time -= frame_time;
+
+            // Get the inputs for this frame
+            let input = controller.decide(&self.state());
+            if input & !self.inputs.back().copied().unwrap_or(0) != 0 {
+                self.press_ticks.push(get_time());
+            }
+            self.step(input);
+            self.ticks += 1;
+
+            // Thrust registers with the thrust bit or any throttle at all
+            if let Some(click) = self.click.filter(|_| {
+                input & INPUT_THRUST != 0 || input >> INPUT_THROTTLE_SHIFT != 0
+            }) {
+                play_sound(click, PlaySoundParams {
+                    looped: false,
+                    volume: CLICK_VOLUME,
+                });
+            }
+        }
+        self.tick_time = get_time() - tick_start;
+
+        self.build_objects();
+
+        // Frame the player, relative to the mirrored field in mirror mode
+        let (difficulty, frames) = self.difficulty();
+        let speed = difficulty.scroll_speed(frames).0 as f32 /
+            difficulty.scroll_speed(0).0 as f32;
+        let size = f32::from(self.player_size());
+        let player_x = f32::from(PLAYER_X) + size / 2.;
+        let player_x = if self.mirror {
+            f32::from(GAME_FIELD_WIDTH) - player_x
+        } else {
+            player_x
+        };
+        self.camera.update(player_x, f32::from(self.player_y) + size / 2.,
+            f32::from(GAME_FIELD_WIDTH), f32::from(GAME_FIELD_HEIGHT), speed,
+            elapsed.min(MAX_CATCH_UP_FRAMES / PHYSICS_RATE) as f32);
+
+        // Clear the background, the field is black even when the rest of
+        // the screen is there to be keyed out
+        clear_background(self.streamer.background);
+        draw_rectangle(offset_x, offset_y, target_w, target_h, BLACK);
+
+        if self.dead && self.died_at.is_none() {
+            self.died_at = Some(time);
+        }
+        let death_flash = self.died_at
+            .map_or(0., |x| 1. - (time - x) / DEATH_FLASH_TIME).max(0.);
+        let uniforms = Uniforms {
+            time:  (time - self.start_time) as f32,
+            score: self.score() as f32,
+            flash: (self.flash_frames as f32 / SHIELD_FLASH_FRAMES as f32)
+                .max(death_flash as f32),
+        };
+        let camera = self.camera;
+        filter.draw(offset_x, offset_y, target_w, target_h, &uniforms,
+            |x, y| {
+                let (x, y, scale) = camera.transform(x, y, scale);
+                self.draw_objects(x, y, scale)
+            });
+
+        // Black out whatever the camera shows past the edges of the field
+        if camera.cropped() {
+            let (screen_w, screen_h) = (screen_width(), screen_height());
+            let bottom = offset_y + target_h;
+            let right = offset_x + target_w;
+            let color = self.streamer.background;
+            draw_rectangle(0., 0., screen_w, offset_y, color);
+            draw_rectangle(0., bottom, screen_w, screen_h - bottom, color);
+            draw_rectangle(0., offset_y, offset_x, target_h, color);
+            draw_rectangle(right, offset_y, screen_w - right, target_h, color);
+        }
+
+        // Draw the game field bounding box
+        draw_rectangle_lines(offset_x, offset_y, target_w, target_h, 2., BLUE);
+
+        if portrait::active() && controller.interactive() {
+            let thrusting = self.inputs.back()
+                .is_some_and(|x| x & INPUT_THRUST != 0);
+            portrait::draw_thrust_zone(thrusting, self.streamer.margin);
+        }
+
+        // Show what's coming up in the band above the field, with the big
+        // score above that
+        let mut band = offset_y;
+        if let Some(mut preview) = self.preview.take() {
+            band -= preview::HEIGHT;
+            preview.update(self);
+            preview.draw(offset_x, band, target_w, preview::HEIGHT - 4.,
+                self.mirror);
+            self.preview = Some(preview);
+        }
+        self.streamer.draw_score(self, offset_x, band - streamer::SCORE_HEIGHT,
+            target_w);
+        self.streamer.draw_inputs(self);
+
+        // Show the combo in the top right of the game field
+        if self.combo > 0 {
+            let text = tr!("combo", self.combo, self.multiplier());
+            let size = 24. * scale;
+            let width = measure_text(&text, None, size as u16, 1.).width;
+            draw_text(&text, offset_x + target_w - width - 8. * scale,
+                offset_y + size, size, Color::from_rgba(0xff, 0xd7, 0x00, 0xc0));
+        }
+
+        // Announce the end of the level
+        if self.finished {
+            let text = tr!("level_complete");
+            let size = 48. * scale;
+            let width = measure_text(text, None, size as u16, 1.).width;
+            draw_text(text, offset_x + (target_w - width) / 2.,
+                offset_y + target_h / 2., size, WHITE);
+        }
+
+        // Shop between stages, with the picked entry marked
+        if let Some(picked) = self.shop {
+            let mut lines = vec![
+                (tr!("shop").to_string(), 48., WHITE),
+                (tr!("shop_coins", self.coins), 24., GOLD),
+            ];
+            let entries = SHOP_ITEMS.iter().map(|&(kind, price)| {
+                (tr!("shop_item", tr!(&format!("shop_{}", kind.name())),
+                    price), self.coins >= price)
+            }).chain(std::iter::once((tr!("shop_leave").to_string(), true)));
+            for (ii, (text, affordable)) in entries.enumerate() {
+                let color = if !affordable {
+                    DARKGRAY
+                } else if ii == picked as usize {
+                    YELLOW
+                } else {
+                    GRAY
+                };
+                let text = if ii == picked as usize {
+                    format!("> {} <", text)
+                } else {
+                    text
+                };
+                lines.push((text, 20., color));
+            }
+            lines.push((tr!("shop_help").to_string(), 16., GRAY));
+
+            let mut y = offset_y + target_h / 4.;
+            for (text, size, color) in lines.iter() {
+                let size = size * scale;
+                let width = measure_text(text, None, size as u16, 1.).width;
+                draw_text(text, offset_x + (target_w - width) / 2., y, size,
+                    *color);
+                y += size * 1.2;
+            }
+        }
+
+        // Pause menu
+        if self.paused {
+            let mut lines = vec![
+                (tr!("paused"), 48.),
+                (if self.focus_lost {
+                    tr!("resume_focus")
+                } else {
+                    tr!("resume")
+                }, 20.),
+                (tr!("restart"), 20.),
+            ];
+            if !controller.recorded() {
+                lines.push((tr!("quit_to_title"), 20.));
+            }
+            let mut y = offset_y + target_h / 3.;
+            for (text, size) in lines.iter() {
+                let size = size * scale;
+                let width = measure_text(text, None, size as u16, 1.).width;
+                draw_text(text, offset_x + (target_w - width) / 2., y, size,
+                    WHITE);
+                y += size * 1.2;
+            }
+        }
+
+        // Countdown before a paused run resumes
+        if let Some(resume_at) = self.resume_at {
+            let text = format!("{}", (resume_at - time).ceil() as u32);
+            let size = 96. * scale;
+            let width = measure_text(&text, None, size as u16, 1.).width;
+            draw_text(&text, offset_x + (target_w - width) / 2.,
+                offset_y + target_h / 2., size, WHITE);
+        }
+
+        // Death screen with everything needed to report the run
+        if self.dead {
+            let mut lines = vec![
+                (tr!("game_over").to_string(), 48.),
+                (tr!("final_score", self.score()), 32.),
+                (tr!("final_run", self.run_id()), 24.),
+                (tr!("final_integrity", self.integrity_id()), 20.),
+            ];
+            if let Some(rank) = self.rank {
+                lines.push((tr!("final_rank", rank.title()), 20.));
+            }
+            if !self.splits.is_empty() {
+                let first = self.splits.len().saturating_sub(SUMMARY_SPLITS);
+                let splits = (first..self.splits.len()).map(|idx| {
+                    tr!("split", idx + 1, self.split_delta(idx).map_or_else(
+                        || self.splits[idx].to_string(),
+                        |delta| format!("{:+}", delta)))
+                }).collect::<Vec<_>>().join(" | ");
+                lines.push((tr!("final_splits", splits), 20.));
+            }
+            if let Some(frame) = self.rules.continued {
+                lines.push((tr!("final_continued", frame), 20.));
+            }
+            lines.push((tr!("restart_dead").to_string(), 20.));
+            if controller.interactive() {
+                lines.push((tr!("practice_dead", PRACTICE_REWIND), 20.));
+                if self.can_continue() {
+                    lines.push((tr!("continue_dead", CONTINUE_DIVISOR),
+                        20.));
+                }
+            }
+
+            // Recorded runs can be watched again from the closest calls
+            let calls = self.closest_calls(CLOSEST_CALLS);
+            for (ii, &(frame, clearance)) in calls.iter().enumerate() {
+                lines.push((tr!("closest_call", ii + 1,
+                    format!("{:.2}", f32::from(clearance)),
+                    format!("{:.1}", frame as f64 /
+                        self.rules.tick_rate as f64)), 20.));
+            }
+            if controller.recorded() && !calls.is_empty() {
+                lines.push((tr!("closest_jump", calls.len()), 20.));
+            }
+            let mut y = offset_y + target_h / 3.;
+            for (text, size) in lines.iter() {
+                let size = size * scale;
+                let width = measure_text(text, None, size as u16, 1.).width;
+                draw_text(text, offset_x + (target_w - width) / 2., y, size,
+                    WHITE);
+                y += size * 1.2;
+            }
+        }
+
+        // End of rendering
+        self.frames += 1;
+        Ok(false)
+    }
+}
+
+/// Get the screen coords of the top left of the game field and the scale
+/// from game field pixels to screen pixels
+fn field_transform() -> (f32, f32, f32) {
+    field_layout(0., 0., 0.)
+}
+
+/// [`field_transform`] leaving `top` and `bottom` more screen pixels free
+/// above and below the field, and `margin` free all around the screen
+fn field_layout(top: f32, bottom: f32, margin: f32) -> (f32, f32, f32) {
+    // Upright phones have a bigger HUD and the thrust zone at the bottom
+    let portrait = portrait::active();
+    let (hud, bottom) = if portrait {
+        (portrait::HUD_HEIGHT,
+         bottom + screen_height() * portrait::THRUST_ZONE)
+    } else {
+        (50., bottom)
+    };
+    let offset_x = 10. + margin;
+    let offset_y = hud + margin + top;
+    // Windows too small for the margins still get a sliver of a field rather
+    // than one drawn flipped
+    let (target_w, target_h) = if SCALE_OUTPUT {
+        ((screen_width() - offset_x - 10. - margin).max(1.),
+         (screen_height() - offset_y - 10. - margin - bottom).max(1.))
+    } else {
+        (f32::from(GAME_FIELD_WIDTH), f32::from(GAME_FIELD_HEIGHT))
+    };
+    let scale_x  = target_w / f32::from(GAME_FIELD_WIDTH);
+    let scale_y  = target_h / f32::from(GAME_FIELD_HEIGHT);
+
+    // Pick the smaller of the two scales and maintain aspect ratio, which
+    // gets letterboxed in the middle when upright
+    let scale = scale_x.min(scale_y);
+    if portrait && SCALE_OUTPUT {
+        (offset_x + (target_w - scale * f32::from(GAME_FIELD_WIDTH)) / 2.,
+         offset_y + (target_h - scale * f32::from(GAME_FIELD_HEIGHT)) / 2.,
+         scale)
+    } else {
+        (offset_x, offset_y, scale)
+    }
+}
+
+/// Load the run saved to [`SUSPEND_FILE`], if there is one which still loads
+fn load_suspended() -> Option<Suspended> {
+    let text = error::read_to_string("suspended run", SUSPEND_FILE)
+        .inspect_err(|err| log_debug!("No suspended run: {}", err))
+        .ok()?;
+    state::load(&text)
+        .map_err(|err| err.in_file("suspended run", SUSPEND_FILE))
+        .inspect_err(|err| log_warn!("Discarding {}", err))
+        .ok()
+}
+
+/// Remove the run saved to [`SUSPEND_FILE`], it's over
+fn clear_suspended() {
+    if let Err(err) = error::remove("suspended run", SUSPEND_FILE) {
+        log_error!("{}", err);
+    }
+}
+
+/// File the high score replay of `craft` is kept in, every craft competes for
+/// a high score of its own
+fn high_score_file(craft: Craft) -> String {
+    match craft {
+        Craft::Standard => HIGH_SCORE_REPLAY_FILE.to_string(),
+        _ => format!("inputs_{}.bin", craft.name()),
+    }
+}
+
+/// High score of `craft`, which is whatever its high score replay plays out
+/// to, and the splits of that run at its checkpoints
+fn load_high_score(craft: Craft) -> (u64, Vec<u64>) {
+    error::read("replay", &high_score_file(craft))
+        .and_then(|bytes| env::play(&bytes))
+        .map_or_else(|err| {
+            log_debug!("No high score loaded: {}", err);
+            (0, Vec::new())
+        }, |x| (x.score(), x.splits))
+}
+
+/// Seed of the daily challenge, which is the same for everyone on the same
+/// day in UTC
+fn daily_seed() -> u64 {
+    let day = (miniquad::date::now() / (24. * 60. * 60.)) as u64;
+    fnv1a(fnv1a(FNV_OFFSET, b"daily"), &day.to_le_bytes())
+}
+
+pub async fn game() -> Result<()> {
+    // Parse flags, run the replay file if there is a non-flag arg
+    let mut rules = Rules::default();
+    let mut replay: Option<VecDeque<u8>> = None;
+    let mut votes = Vec::new();
+    let mut replay_frames: Option<u64> = None;
+    let mut replay_integrity: Option<u64> = None;
+    let mut seek: Option<u64> = None;
+    let mut assert_frames = false;
+    let mut god = false;
+    let mut freeze_spawn = false;
+    let mut step = false;
+    let mut twitch_channel: Option<String> = None;
+    let mut level_path: Option<String> = None;
+    let mut script_path: Option<String> = None;
+    let mut script_hash: Option<u64> = None;
+    let mut edit_path: Option<String> = None;
+    let mut preset: Option<Preset> = None;
+    let mut curve: Option<Curve> = None;
+    let mut tuning: Option<Tuning> = None;
+    let mut tuning_watcher: Option<Watcher> = None;
+    let mut sandbox: Option<Sandbox> = None;
+    let mut mutators: Option<u32> = None;
+    let mut mirror = false;
+    let mut demo = false;
+
+    // Files which failed to load, shown before the title menu and skipped
+    let mut load_errors = Vec::new();
+
+    // A missing settings file just means nothing was changed
+    let mut settings = match Settings::load(settings::SETTINGS_FILE) {
+        Ok(settings) => settings,
+        Err(Error::Io { err, .. })
+                if err.kind() == std::io::ErrorKind::NotFound => {
+            Settings::default()
+        }
+        Err(err) => {
+            load_errors.push(err);
+            Settings::default()
+        }
+    };
+    let mut args = url::args().into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--casual" => rules.mode = Mode::Casual,
+            "--career" => rules.mode = Mode::Career,
+            "--stage"  => rules.mode = Mode::Stage,
+            "--mode"   => {
+                let name = args.next().ok_or("--mode requires a mode")?;
+                if name == "daily" {
+                    rules.seed = daily_seed();
+                } else {
+                    rules.mode = Mode::from_name(&name)
+                        .ok_or_else(|| format!("Unknown mode {:?}", name))?;
+                }
+            }
+            "--smooth" => rules.smooth_walls = true,
+            "--drafts" => rules.drafts = true,
+            "--biomes" => rules.biomes = true,
+            "--adaptive" => rules.adaptive = Some(0),
+            "--control" => {
+                let name = args.next()
+                    .ok_or("--control requires hold, tap, or throttle")?;
+                rules.control = Control::from_name(&name)
+                    .ok_or_else(|| format!("Unknown control {:?}", name))?;
+            }
+            "--edges" => {
+                let name = args.next()
+                    .ok_or("--edges requires safe, penalty, or lethal")?;
+                rules.edges = Edges::from_name(&name)
+                    .ok_or_else(|| format!("Unknown edges {:?}", name))?;
+            }
+            "--craft" => {
+                let name = args.next().ok_or("--craft requires a craft")?;
+                rules.craft = Craft::from_name(&name)
+                    .ok_or_else(|| format!("Unknown craft {:?}", name))?;
+            }
+            "--start-frame" => {
+                let frame = args.next()
+                    .ok_or("--start-frame requires a physics frame")?;
+                rules.start_frame = frame.parse()?;
+                if rules.start_frame > MAX_START_FRAME {
+                    return Err(format!("Runs start by physics frame {} at \
+                        the latest", MAX_START_FRAME).into());
+                }
+            }
+            "--seek" => {
+                let frame = args.next()
+                    .ok_or("--seek requires a physics frame")?;
+                seek = Some(frame.parse()?);
+            }
+            "--tick-rate" => {
+                let rate = args.next()
+                    .ok_or("--tick-rate requires a rate in Hz")?;
+                rules.tick_rate = rate.parse()?;
+                if !TICK_RATES.contains(&rules.tick_rate) {
+                    return Err(format!("The tick rate has to be one of {:?}",
+                        TICK_RATES).into());
+                }
+            }
+            "--lookahead" => {
+                let columns = args.next()
+                    .ok_or("--lookahead requires a number of columns")?;
+                rules.lookahead = columns.parse()?;
+                if rules.lookahead > MAX_LOOKAHEAD {
+                    return Err(format!("The lookahead is at most {} columns",
+                        MAX_LOOKAHEAD).into());
+                }
+            }
+            "--mirror" => mirror = true,
+            "--demo"   => demo = true,
+            "--assert-frames" => assert_frames = true,
+            "--god"           => god = true,
+            "--freeze-spawn"  => freeze_spawn = true,
+            "--step"          => step = true,
+            "--crt"    => settings.crt = true,
+            "--dynamic-camera" => settings.dynamic_camera = true,
+            "--preview" => settings.preview = true,
+            "--click"  => settings.click = true,
+            "--streamer" => Streamer::enable(&mut settings),
+            "--shader" => {
+                settings.shader = Some(args.next()
+                    .ok_or("--shader requires a shader file")?);
+            }
+            "--twitch" => {
+                twitch_channel = Some(args.next()
+                    .ok_or("--twitch requires a channel")?);
+            }
+            "--verbose" => log::verbose(),
+            "--seed"   => {
+                let seed = args.next().ok_or("--seed requires a seed")?;
+                rules.seed = u64::from_str_radix(
+                    seed.trim_start_matches("0x"), 16)?;
+            }
+            "--level"  => {
+                level_path = Some(args.next()
+                    .ok_or("--level requires a level file")?);
+            }
+            "--preset" => {
+                let name = args.next()
+                    .ok_or("--preset requires a preset name")?;
+                preset = Some(Preset::from_name(&name)
+                    .ok_or_else(|| format!("Unknown preset {:?}", name))?);
+            }
+            "--mutator" => {
+                let name = args.next()
+                    .ok_or("--mutator requires a mutator name")?;
+                let mutator = Mutator::from_name(&name)
+                    .ok_or_else(|| format!("Unknown mutator {:?}", name))?;
+                mutators = Some(mutators.unwrap_or(0) | mutator.bit());
+            }
+            "--mutators" => {
+                let names = args.next()
+                    .ok_or("--mutators requires a list of mutator names")?;
+                for name in names.split(',') {
+                    let mutator = Mutator::from_name(name).ok_or_else(|| {
+                        format!("Unknown mutator {:?}", name)
+                    })?;
+                    mutators = Some(mutators.unwrap_or(0) | mutator.bit());
+                }
+            }
+            "--curve"  => {
+                let path = args.next()
+                    .ok_or("--curve requires a difficulty curve file")?;
+                match Curve::load(&path) {
+                    Ok(loaded) => {
+                        curve = Some(loaded);
+                        log_info!("Loaded difficulty curve {}", path);
+                    }
+                    Err(err) => load_errors.push(err),
+                }
+            }
+            "--tuning" => {
+                let path = args.next()
+                    .ok_or("--tuning requires a tuning file")?;
+                match Tuning::load(&path) {
+                    Ok(loaded) => {
+                        tuning = Some(loaded);
+                        log_info!("Loaded tuning {}", path);
+                    }
+                    Err(err) => load_errors.push(err),
+                }
+                tuning_watcher = Some(Watcher::new(&path));
+            }
+            "--sandbox" => sandbox = Some(Sandbox::default()),
+            "--script" => {
+                script_path = Some(args.next()
+                    .ok_or("--script requires a script file")?);
+            }
+            "--edit"   => {
+                edit_path = Some(args.next()
+                    .ok_or("--edit requires a level file")?);
+            }
+            _ => {
+                // Replays are always played back with their own rules and
+                // level
+                let loaded = match Replay::load(&arg, rules) {
+                    Ok(loaded) => loaded,
+                    Err(err) => {
+                        load_errors.push(err);
+                        continue;
+                    }
+                };
+                log_info!("Loaded replay {} with {} inputs on seed {:016x}",
+                    arg, loaded.inputs.len(), loaded.rules.seed);
+                rules = loaded.rules;
+                replay = Some(loaded.inputs);
+                votes = loaded.votes;
+                replay_frames = loaded.frames;
+                replay_integrity = loaded.integrity;
+                level_path = loaded.level.or(level_path);
+                if let Some((path, hash)) = loaded.script {
+                    script_path = Some(path);
+                    script_hash = Some(hash);
+                }
+            }
+        }
+    }
+
+    // Replays can't be played back without the level they were played on
+    let mut level = match level_path.as_ref().map(|path| Level::load(path))
+            .transpose() {
+        Ok(level) => level,
+        Err(err) => {
+            load_errors.push(err);
+            replay = None;
+            level_path = None;
+            None
+        }
+    };
+    if let Some(path) = &level_path {
+        log_info!("Loaded level {}", path);
+    }
+
+    // Nor with any script but the one they were recorded with
+    let mut script = match script_path.as_ref().map(|path| match script_hash {
+        Some(hash) => Script::load_recorded(path, hash),
+        None       => Script::load(path),
+    }).transpose() {
+        Ok(script) => script,
+        Err(err) => {
+            load_errors.push(err);
+            replay = None;
+            None
+        }
+    };
+    if let Some(script) = &script {
+        log_info!("Loaded script {} with hash {:016x}", script.path,
+            script.hash);
+    }
+
+    locale::select(&settings.language);
+
+    // Post-processing of the field, F4 toggles the filter
+    let mut filter = Filter::new(settings.crt);
+    let mut hitboxes = false;
+    if let Some(path) = &settings.shader {
+        if let Err(err) = filter.load(path) {
+            load_errors.push(err);
+        }
+    }
+
+    // Sound of the input click, which is skipped if it can't be loaded
+    let mut click = None;
+    if settings.click {
+        match load_sound(CLICK_FILE).await {
+            Ok(sound) => click = Some(sound),
+            Err(err)  => load_errors.push(err.to_string().into()),
+        }
+    }
+
+    for err in load_errors.iter() {
+        log_error!("{}", err);
+        menu::error(err).await;
+    }
+
+    // The high score carries over from whatever the high score replay plays
+    // out to, and gets loaded again whenever the craft changes
+    let (mut high_score, mut best_splits) = load_high_score(rules.craft);
+
+    // Debug overlay which sticks around between runs
+    let mut profiler = Profiler::default();
+    let mut latency = Latency::default();
+    let mut console = Console::default();
+    let mut presence = Presence::connect(settings.discord.as_deref());
+    let mut twitch = Twitch::connect(twitch_channel.as_deref());
+    let mut ticker = Ticker::default();
+    let mut packs = Registry::discover(MODS_DIR);
+
+    // The settings and tuning files get loaded again when they change, the
+    // tuning only applies from the next run on so runs stay replayable
+    let mut settings_watcher = Watcher::new(settings::SETTINGS_FILE);
+    let mut retune = false;
+
+    // The run which was in progress when the game got closed can be picked
+    // back up, unless something else was asked for
+    let mut resumed = None;
+
+    // Run rewound to shortly before the last death, which gets practiced
+    // next instead of starting over
+    let mut practice: Option<GameField> = None;
+    if replay.is_none() && edit_path.is_none() && !demo {
+        if let Some(suspended) = load_suspended() {
+            let field = &suspended.field;
+            if menu::resume(field.score(),
+                    (field.elapsed() as f64 / PHYSICS_RATE) as u64).await {
+                resumed = Some(suspended);
+            } else {
+                clear_suspended();
+            }
+        }
+    }
+
+    // Content picked from packs replaces the level and curve given on the
+    // command line, and adds a skin and palette
+    let given_level = (level_path.clone(), level.clone());
+    let mut skin = None;
+    let mut palette: Option<Palette> = None;
+
+
+    // Coins banked and upgrades bought in career runs
+    let mut profile = career::Profile::load();
+
+    // Scores of the recent ranked runs, which the rank goes by
+    let mut ladder = Ladder::load();
+
+    // Best number of physics frames each stage was completed in
+    let mut best_times: Vec<u64> =
+        error::read_to_string("stage times", STAGE_TIMES_FILE)
+        .inspect_err(|err| {
+            log_debug!("No stage times loaded: {}", err);
+        })
+        .unwrap_or_default().lines()
+        .filter_map(|x| x.trim().parse()
+            .inspect_err(|_| log_warn!("Ignoring bad stage time {:?}", x)).ok())
+        .collect();
+
+    'title: loop {
+        // Pick the preset and mutators from the menu unless they were given or
+        // we're replaying. They're only skipped the first time around
+        if replay.is_none() && resumed.is_none() {
+            let (picked, picked_mutators, picked_craft, picked_demo) =
+                    match (preset, mutators) {
+                (None, _) | (_, None) => {
+                    menu::choose(preset.unwrap_or(rules.preset),
+                        mutators.unwrap_or(rules.mutators), rules.craft,
+                        &mut packs).await
+                }
+                (Some(preset), Some(mutators)) => {
+                    (preset, mutators, rules.craft, demo)
+                }
+            };
+            demo = picked_demo;
+            rules.preset = picked;
+            rules.mutators = picked_mutators;
+            if picked_craft != rules.craft {
+                rules.craft = picked_craft;
+                (high_score, best_splits) = load_high_score(rules.craft);
+            }
+            rules.difficulty = picked.difficulty();
+            preset = None;
+            mutators = None;
+
+            let picked = match packs.load() {
+                Ok(picked) => picked,
+                Err(err) => {
+                    log_error!("{}", err);
+                    menu::error(&err).await;
+                    continue 'title;
+                }
+            };
+            (level_path, level) = match picked.level {
+                Some((path, loaded)) => (Some(path), Some(loaded)),
+                None => given_level.clone(),
+            };
+            rules.difficulty.curve = picked.curve.or(curve);
+            if let Some(tuning) = tuning {
+                tuning.apply(&mut rules.difficulty);
+            }
+            skin = picked.skin;
+            palette = picked.palette;
+
+            // Career runs fly with whatever was bought in the shop
+            if rules.mode == Mode::Career && !demo {
+                career::shop(&mut profile).await;
+                rules.upgrades = profile.upgrades;
+            }
+        }
+
+        if let Some(path) = edit_path.take() {
+            // The editor only returns if the level couldn't be opened
+            let Err(err) = editor::run(path, rules).await;
+            log_error!("{}", err);
+            menu::error(&err).await;
+            continue 'title;
+        }
+
+        'restart: loop {
+            // A tuning which changed during the last run applies from here
+            if std::mem::take(&mut retune) && replay.is_none() {
+                if let Some(tuning) = tuning {
+                    tuning.apply(&mut rules.difficulty);
+                }
+            }
+
+            // Resumed runs pick up with the rules, level, and script they
+            // were started with, and wait for the player to find the field.
+            // Practice runs wait the same way
+            let practicing = practice.is_some() || rules.start_frame > 0;
+            let mut field = match (resumed.take(), practice.take()) {
+                (Some(suspended), _) => {
+                    let mut field = suspended.field;
+                    if field.base_rules.craft != rules.craft {
+                        (high_score, best_splits) =
+                            load_high_score(field.base_rules.craft);
+                    }
+                    rules = field.base_rules;
+                    level_path = suspended.level;
+                    level = field.level.clone();
+                    script = field.script.as_ref().map(Script::restarted);
+                    field.pause();
+                    field.focus_lost = true;
+                    field
+                }
+                (None, Some(mut field)) => {
+                    field.pause();
+                    field.focus_lost = true;
+                    field
+                }
+                (None, None) => {
+                    let mut field = GameField::new(rules);
+                    field.level = level.clone();
+                    field.script = script.clone();
+                    field.fast_forward();
+                    field
+                }
+            };
+            let mut clip = Clip::default();
+            let mut rumble = Rumble::new(settings.rumble);
+            let mut controller: Box<dyn Controller> = match &replay {
+                Some(replay) => {
+                    field.votes = votes.clone();
+                    Box::new(Playback(replay.clone()))
+                }
+                None if demo => Box::new(Autopilot),
+                None         => Box::new(Human::new(rules.control)),
+            };
+
+            // Replay playback can start partway through
+            if let (Some(frame), Some(_)) = (seek.take(), &replay) {
+                while field.physics_frames < frame && !field.dead &&
+                        !field.finished {
+                    field.step(controller.decide(&field.state()));
+                }
+            }
+            field.skin = skin;
+            field.click = click.filter(|_| settings.click);
+            field.rank = Some(ladder.rank());
+            field.palette = palette.clone();
+            field.best_splits = best_splits.clone();
+            field.mirror = mirror;
+            field.god = god;
+            field.freeze_spawn = freeze_spawn;
+            field.stepping = step;
+            field.debug |= god || freeze_spawn;
+            field.hitboxes = hitboxes;
+            field.camera.dynamic = settings.dynamic_camera;
+            field.preview = settings.preview.then(Preview::default);
+            field.streamer = Streamer::new(&settings);
+
+            let mut new_score = false;
+
+            // Tracks if the result of the replay has been reported
+            let mut verified = false;
+
+            // Tracks if the coins of a career run have been banked
+            let mut banked = false;
+
+            // Tracks if a ranked run has been recorded on the ladder
+            let mut laddered = false;
+
+            // High score before this run, which gets restored if the run is
+            // aborted
+            let start_high_score = high_score;
+
+            // Real time and physics frame the run was last saved to
+            // [`SUSPEND_FILE`] at
+            let mut suspended_at: Option<(f64, u64)> = None;
+
+            loop {
+                // The debug console takes the keyboard while it's open, and
+                // can ask for a new run with another seed
+                let console_seed = if replay.is_none() && !demo {
+                    console.update(&mut field)
+                        .map(|Action::Restart(seed)| seed)
+                } else {
+                    None
+                };
+                let keys = !field.typing;
+
+                // Changed settings only change how the game looks, so they
+                // apply right away
+                if settings_watcher.changed(get_time()) {
+                    match Settings::load(&settings_watcher.path) {
+                        Ok(loaded) => {
+                            log_info!("Reloaded {}", settings_watcher.path);
+                            locale::select(&loaded.language);
+                            if loaded.crt != settings.crt {
+                                filter.enabled = loaded.crt;
+                            }
+                            if let Some(path) = loaded.shader.as_ref()
+                                    .filter(|&x| Some(x) !=
+                                        settings.shader.as_ref()) {
+                                if let Err(err) = filter.load(path) {
+                                    log_error!("{}", err);
+                                }
+                            }
+                            if loaded.click && click.is_none() {
+                                match load_sound(CLICK_FILE).await {
+                                    Ok(sound) => click = Some(sound),
+                                    Err(err)  => log_error!("{}", err),
+                                }
+                            }
+                            if loaded.discord != settings.discord {
+                                presence = Presence::connect(
+                                    loaded.discord.as_deref());
+                            }
+                            if loaded.preview != settings.preview {
+                                field.preview =
+                                    loaded.preview.then(Preview::default);
+                            }
+                            field.click = click.filter(|_| loaded.click);
+                            field.camera.dynamic = loaded.dynamic_camera;
+                            field.streamer = Streamer::new(&loaded);
+                            rumble = Rumble::new(loaded.rumble);
+                            settings = loaded;
+                        }
+                        Err(err) => log_error!("{}", err),
+                    }
+                }
+
+                // A changed tuning waits for the next run
+                if let Some(watcher) = tuning_watcher.as_mut() {
+                    if watcher.changed(get_time()) {
+                        match Tuning::load(&watcher.path) {
+                            Ok(loaded) => {
+                                log_info!("Reloaded {}, applying it from \
+                                    the next run", watcher.path);
+                                tuning = Some(loaded);
+                                retune = true;
+                            }
+                            Err(err) => log_error!("{}", err),
+                        }
+                    }
+                }
+
+                // Sandbox sliders tune the run, and the runs after it
+                if let (Some(sandbox), None, false) =
+                        (sandbox.as_mut(), &replay, demo) {
+                    sandbox.update(&mut field);
+                    rules.difficulty = field.base_rules.difficulty;
+                }
+
+                // Escape or P pauses a live run, from where it can be restarted
+                // or quit back to the title menu
+                let live = !field.dead && !field.finished;
+                if live && keys && (is_key_pressed(KeyCode::Escape) ||
+                                    is_key_pressed(KeyCode::P)) {
+                    if field.paused {
+                        field.resume();
+                    } else {
+                        field.pause();
+                    }
+                }
+                let quit = field.paused && replay.is_none() && keys &&
+                    is_key_pressed(KeyCode::T);
+
+                // R restarts right away with the same seed, Shift+R with a
+                // fresh one. F after a death practices the last few seconds
+                let quick_restart = keys && is_key_pressed(KeyCode::R);
+                let practice_pressed = field.dead && replay.is_none() &&
+                    !demo && keys && is_key_pressed(KeyCode::F);
+
+                // C after a casual death continues the run once, which gives
+                // up the high score it set
+                if field.can_continue() && replay.is_none() && !demo &&
+                        keys && is_key_pressed(KeyCode::C) {
+                    field.continue_run();
+                    high_score = start_high_score;
+                    new_score = false;
+                }
+
+                // Number keys after a replay's death watch it again from
+                // shortly before one of its closest calls
+                let call = if field.dead && replay.is_some() && keys {
+                    CLOSEST_CALL_KEYS.iter().zip(
+                        field.closest_calls(CLOSEST_CALLS))
+                        .find(|(&key, _)| is_key_pressed(key))
+                        .map(|(_, (frame, _))| frame.saturating_sub(
+                            CLOSE_CALL_LEAD * rules.tick_rate as u64))
+                } else {
+                    None
+                };
+                if field.render(controller.as_mut(), &mut filter)? ||
+                        quick_restart || quit || practice_pressed ||
+                        console_seed.is_some() || call.is_some() {
+                    // Aborted runs never count for the high score, their inputs
+                    // get archived separately from the high score replay.
+                    // Debug runs can't be replayed
+                    if live && replay.is_none() && !demo && !field.debug {
+                        high_score = start_high_score;
+                        new_score = false;
+                        match error::write("replay", ABORTED_REPLAY_FILE,
+                                Replay {
+                            rules: Rules {
+                                continued: field.base_rules.continued,
+                                ..rules
+                            },
+                            level:     level_path.clone(),
+                            inputs:    field.inputs.clone(),
+                            votes:     field.votes.clone(),
+                            script:    script.as_ref()
+                                .map(|x| (x.path.clone(), x.hash)),
+                            frames:    (field.dead || field.finished)
+                                .then_some(field.physics_frames),
+                            integrity: (field.dead || field.finished)
+                                .then_some(field.integrity),
+                        }.serialize()) {
+                            Ok(()) => log_info!("Wrote aborted run {} to {}",
+                                field.run_id(), ABORTED_REPLAY_FILE),
+                            Err(err) => log_error!("{}", err),
+                        }
+                    }
+
+                    if new_score {
+                        let path = high_score_file(rules.craft);
+                        match error::write("replay", &path, Replay {
+                            rules,
+                            level:     level_path.clone(),
+                            inputs:    field.inputs.clone(),
+                            votes:     field.votes.clone(),
+                            script:    script.as_ref()
+                                .map(|x| (x.path.clone(), x.hash)),
+                            frames:    (field.dead || field.finished)
+                                .then_some(field.physics_frames),
+                            integrity: (field.dead || field.finished)
+                                .then_some(field.integrity),
+                        }.serialize()) {
+                            Ok(()) => log_info!("Wrote high score run {} to {}",
+                                field.run_id(), path),
+                            Err(err) => log_error!("{}", err),
+                        }
+                        best_splits = field.splits.clone();
+                    }
+
+                    // Adaptive mode carries over to the next run, easing off
+                    // after a death
+                    if let (Some(_), None) = (rules.adaptive, &replay) {
+                        let ease = if field.dead {
+                            field.ease + ADAPT_STEP * 2
+                        } else {
+                            field.ease
+                        };
+                        rules.adaptive =
+                            Some(ease.clamp(-ADAPT_MAX, ADAPT_MAX));
+                    }
+
+                    if quick_restart && replay.is_none() &&
+                            (is_key_down(KeyCode::LeftShift) ||
+                             is_key_down(KeyCode::RightShift)) {
+                        rules.seed = fnv1a(FNV_OFFSET,
+                            &miniquad::date::now().to_bits().to_le_bytes());
+                    }
+
+                    if suspended_at.is_some() {
+                        clear_suspended();
+                    }
+                    if practice_pressed {
+                        practice = Some(field.rewound(PRACTICE_REWIND));
+                    }
+                    if let Some(seed) = console_seed {
+                        rules.seed = seed;
+                    }
+                    seek = call;
+                    if quit {
+                        continue 'title;
+                    }
+                    continue 'restart;
+                }
+
+                // Keep live runs saved so they can be resumed, and forget
+                // them once they're over. Practice runs aren't worth keeping
+                if replay.is_none() && !demo && !practicing {
+                    let now = get_time();
+                    if field.dead || field.finished {
+                        if suspended_at.take().is_some() {
+                            clear_suspended();
+                        }
+                    } else if field.physics_frames > 0 && !field.debug &&
+                            suspended_at.is_none_or(|(time, frames)| {
+                        frames != field.physics_frames &&
+                            (field.paused || now - time >= SUSPEND_SAVE_TIME)
+                    }) {
+                        let text = state::save(&Suspended {
+                            level: level_path.clone(),
+                            field: field.clone(),
+                        });
+                        if let Err(err) = error::write("suspended run",
+                                SUSPEND_FILE, text) {
+                            log_error!("{}", err);
+                        }
+                        suspended_at = Some((now, field.physics_frames));
+                    }
+                }
+
+                // Adaptive runs, practice runs, career runs, and runs at
+                // faster tick rates are unranked, so they never count towards
+                // high scores or best times
+                let ranked = rules.adaptive.is_none() && !demo &&
+                    !practicing && !field.debug &&
+                    rules.tick_rate == TICK_RATES[0] &&
+                    !field.rules.has(Mutator::Bounce) &&
+                    rules.mode != Mode::Career &&
+                    field.rules.continued.is_none();
+
+                // Ranked runs move the player on the ladder once they're over
+                if ranked && !laddered && (field.dead || field.finished) {
+                    ladder.record(field.score());
+                    field.rank = Some(ladder.rank());
+                    laddered = true;
+                }
+
+                // Career runs bank their coins once they're over
+                if rules.mode == Mode::Career && replay.is_none() && !demo &&
+                        !practicing && !field.debug && !banked &&
+                        (field.dead || field.finished) {
+                    profile.bank(field.coins);
+                    banked = true;
+                }
+           
+                if ranked && field.score() > high_score {
+                    new_score = true;
+
+                    high_score = field.score();
+                }
+
+                // Record stage times as the stages get completed
+                let stage_times = &field.stage_times;
+                if ranked && (best_times.len() < stage_times.len() ||
+                        stage_times.last().zip(best_times.get(
+                            stage_times.len().wrapping_sub(1)))
+                        .is_some_and(|(time, best)| time < best)) {
+                    let stage = stage_times.len() - 1;
+                    best_times.resize(best_times.len().max(stage + 1),
+                        u64::MAX);
+                    best_times[stage] = stage_times[stage];
+
+                    if let Err(err) = error::write("stage times",
+                            STAGE_TIMES_FILE, best_times.iter()
+                            .map(|x| format!("{}\n", x)).collect::<String>()) {
+                        log_error!("{}", err);
+                    }
+                }
+
+                let stage_status = if rules.mode == Mode::Stage {
+                    format!(" | {}", tr!("stage", field.stage + 1,
+                        best_times.get(field.stage as usize)
+                            .map_or("-".to_string(), |x| x.to_string())))
+                } else {
+                    String::new()
+                };
+                let stage_status = match field.biome {
+                    Some(biome) => format!("{} | {}", stage_status,
+                        biome.title()),
+                    None => stage_status,
+                };
+
+                let difficulty = if ranked {
+                    rules.preset.title().to_string()
+                } else if demo {
+                    tr!("hud_demo", rules.preset.title())
+                } else if sandbox.is_some() && field.debug {
+                    tr!("hud_sandbox", rules.preset.title())
+                } else if field.debug {
+                    tr!("hud_debug", rules.preset.title())
+                } else if practicing {
+                    tr!("hud_practice", rules.preset.title())
+                } else if field.rules.has(Mutator::Bounce) {
+                    tr!("hud_bounce", rules.preset.title())
+                } else if rules.mode == Mode::Career {
+                    tr!("hud_career", rules.preset.title())
+                } else if field.rules.continued.is_some() {
+                    tr!("hud_continued", rules.preset.title())
+                } else if rules.adaptive.is_none() {
+                    tr!("hud_tick_rate", rules.preset.title(), rules.tick_rate)
+                } else {
+                    tr!("hud_adaptive", rules.preset.title(),
+                        format!("{:+}", field.ease))
+                };
+
+                let margin = field.streamer.margin;
+                if portrait::active() {
+                    portrait::draw_hud(&field, high_score, margin);
+                } else {
+                    draw_text(&tr!("hud",
+                        format!("{:9.3}", field.frames as f64 /
+                            (get_time() - field.start_time)),
+                        difficulty, format!("{:10}", field.score()),
+                        format!("{:10}", high_score),
+                        format!("{:5}", field.coins),
+                        format!("{:10.3}{}{}", field.player_speed.0,
+                            field.powerup_status(), stage_status)),
+                        margin, margin + 20., 32., WHITE);
+                    let run = tr!("hud_run", format!("{:016x}", rules.seed),
+                        field.run_id());
+                    draw_text(&run, margin, margin + 42., 20., GRAY);
+                    let width = measure_text(&run, None, 20, 1.).width;
+                    rank::draw_badge(ladder.rank(), margin + width + 12.,
+                        margin + 36., 14.);
+
+                    // Split of the last checkpoint against the high score
+                    // run, speedrun style
+                    let last = field.splits.len().wrapping_sub(1);
+                    if let Some(delta) = field.split_delta(last) {
+                        draw_text(&tr!("hud_split", last + 1,
+                            format!("{:+}", delta)), margin, margin + 64.,
+                            20., if delta < 0 { RED } else { GREEN });
+                    }
+                }
+                ticker.update(&field);
+                ticker.draw(&field);
+                profiler.update();
+                profiler.draw(&field);
+                latency.update(&field);
+                latency.draw();
+                if is_key_pressed(KeyCode::F4) {
+                    filter.enabled = !filter.enabled;
+                }
+                if is_key_pressed(KeyCode::F7) {
+                    hitboxes = !hitboxes;
+                    field.hitboxes = hitboxes;
+                }
+                capture::update(&field);
+                clip.update(&field);
+                rumble.update(&field);
+                presence.update(&field);
+                if replay.is_none() {
+                    twitch.update(&mut field);
+                    twitch.draw();
+                }
+                if let (Some(sandbox), None, false) =
+                        (&sandbox, &replay, demo) {
+                    sandbox.draw(&field);
+                }
+                console.draw();
+
+                // Print the outcome of replays so scores can be cross-checked
+                if replay.is_some() && !verified &&
+                        (field.dead || field.finished) {
+                    println!("Replay verified: score {} frames {} run {} \
+                        integrity {}", field.score(), field.physics_frames,
+                        field.run_id(), field.integrity_id());
+                    verified = true;
+
+                    // With --assert-frames a desynced replay is fatal, so
+                    // playback can be checked from scripts
+                    let checks = replay_frames
+                        .map(|frames| {
+                            env::check_frames(field.physics_frames, frames)
+                        })
+                        .into_iter()
+                        .chain(replay_integrity.map(|integrity| {
+                            env::check_integrity(field.integrity, integrity)
+                        }));
+                    for check in checks {
+                        match check {
+                            Err(err) if assert_frames => return Err(err),
+                            Err(err) => log_error!("{}", err),
+                            Ok(()) => {}
+                        }
+                    }
+                }
+
+                next_frame().await;
+            }
+        }
+    }
+}
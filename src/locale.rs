@@ -0,0 +1,83 @@
+//! Text shown to players, in the language picked in the settings. Every text
+//! has a key, with the texts of each language in a file of `key text` lines
+//! in `config/lang`, where `#` at the start of a line is a comment. The files
+//! are built into the game so they work on WASM too. Each `{}` in a text gets
+//! filled in with a value in order, and keys a language is missing fall back
+//! to English
+
+use std::collections::HashMap;
+use std::fmt::{Display, Write};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Code and text file of every language, English first
+pub const LANGUAGES: [(&str, &str); 2] = [
+    ("en", include_str!("../config/lang/en.txt")),
+    ("de", include_str!("../config/lang/de.txt")),
+];
+
+/// Index into [`LANGUAGES`] of the language texts are shown in
+static LANGUAGE: AtomicUsize = AtomicUsize::new(0);
+
+/// Parsed texts of each of [`LANGUAGES`]
+static TABLES: OnceLock<Vec<HashMap<&str, &str>>> = OnceLock::new();
+
+/// Parse the text file of a language into its texts by key
+pub fn parse(file: &str) -> HashMap<&str, &str> {
+    file.lines()
+        .filter(|x| !x.starts_with('#'))
+        .filter_map(|x| x.split_once(char::is_whitespace))
+        .map(|(key, text)| (key, text.trim()))
+        .collect()
+}
+
+/// Check if `code` is one of [`LANGUAGES`]
+pub fn exists(code: &str) -> bool {
+    LANGUAGES.iter().any(|x| x.0 == code)
+}
+
+/// Show texts in the language `code` from now on, unknown languages are
+/// ignored
+pub fn select(code: &str) {
+    if let Some(idx) = LANGUAGES.iter().position(|x| x.0 == code) {
+        LANGUAGE.store(idx, Ordering::Relaxed);
+    }
+}
+
+/// Get the text for `key` in the current language, or `?` if there's no
+/// such key at all
+pub fn text(key: &str) -> &'static str {
+    let tables = TABLES.get_or_init(|| {
+        LANGUAGES.iter().map(|x| parse(x.1)).collect()
+    });
+    tables[LANGUAGE.load(Ordering::Relaxed)].get(key)
+        .or_else(|| tables[0].get(key))
+        .copied()
+        .unwrap_or("?")
+}
+
+/// Fill in the `{}`s in `text` with `args` in order
+pub fn fill(text: &str, args: &[&dyn Display]) -> String {
+    let mut parts = text.split("{}");
+    let mut ret = parts.next().unwrap_or("").to_string();
+    let mut args = args.iter();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            let _ = write!(ret, "{}", arg);
+        }
+        ret += part;
+    }
+    ret
+}
+
+/// Get the text for a key in the current language, with the `{}`s filled in
+/// if there are any values to fill them with
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::locale::text($key)
+    };
+    ($key:expr, $($arg:expr),+ $(,)?) => {
+        $crate::locale::fill($crate::locale::text($key),
+            &[$(&$arg as &dyn std::fmt::Display),+])
+    };
+}
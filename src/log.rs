@@ -0,0 +1,110 @@
+//! Leveled logging to stderr, or to the browser console on WASM. Only
+//! warnings and errors are shown unless `RUST_LOG` or `--verbose` ask for more
+
+use std::cell::Cell;
+use std::fmt::Arguments;
+use std::sync::atomic::{AtomicUsize, Ordering};
+pub use macroquad::miniquad::log::Level;
+
+/// Most verbose [`Level`] which gets logged
+static MAX_LEVEL: AtomicUsize = AtomicUsize::new(Level::Warn as usize);
+
+thread_local! {
+    /// Set while logging is muted on this thread
+    static MUTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Name of `level` as used in `RUST_LOG` and in front of messages
+fn name(level: Level) -> &'static str {
+    match level {
+        Level::Error => "error",
+        Level::Warn  => "warn",
+        Level::Info  => "info",
+        Level::Debug => "debug",
+        Level::Trace => "trace",
+    }
+}
+
+/// Set the level from `RUST_LOG`, which is either a level name or a comma
+/// separated list of `target=level` directives, of which only those without
+/// a target or for this crate count
+pub fn init() {
+    let Ok(filter) = std::env::var("RUST_LOG") else { return };
+    for directive in filter.split(',') {
+        let level = match directive.split_once('=') {
+            Some((env!("CARGO_PKG_NAME"), level)) => level,
+            Some(_) => continue,
+            None    => directive,
+        };
+        let level = level.trim();
+        if level.eq_ignore_ascii_case("off") {
+            MAX_LEVEL.store(0, Ordering::Relaxed);
+        } else if let Some(level) = [Level::Error, Level::Warn, Level::Info,
+                Level::Debug, Level::Trace].iter().copied()
+                .find(|x| name(*x).eq_ignore_ascii_case(level)) {
+            MAX_LEVEL.store(level as usize, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Log everything up to debug messages, for `--verbose`
+pub fn verbose() {
+    MAX_LEVEL.fetch_max(Level::Debug as usize, Ordering::Relaxed);
+}
+
+/// Check if messages at `level` get logged
+pub fn enabled(level: Level) -> bool {
+    level as usize <= MAX_LEVEL.load(Ordering::Relaxed) &&
+        !MUTED.with(|x| x.get())
+}
+
+/// Run `f` without logging anything on this thread, for simulations which
+/// only look ahead and would repeat what the real one logs
+pub fn muted<T>(f: impl FnOnce() -> T) -> T {
+    let was_muted = MUTED.with(|x| x.replace(true));
+    let ret = f();
+    MUTED.with(|x| x.set(was_muted));
+    ret
+}
+
+/// Log `args` from `module` at `level`, use the macros rather than this
+pub fn log(level: Level, module: &'static str, args: Arguments) {
+    if !enabled(level) {
+        return;
+    }
+    let message = format!("[{:5} {}] {}", name(level), module, args);
+    macroquad::miniquad::log::__private_api_log_lit(&message, level,
+        &(module, module, file!(), line!()));
+}
+
+/// Log an error which is being recovered from
+macro_rules! log_error {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Error, module_path!(),
+            format_args!($($arg)+))
+    };
+}
+
+/// Log something which is probably wrong, but doesn't stop anything
+macro_rules! log_warn {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Warn, module_path!(),
+            format_args!($($arg)+))
+    };
+}
+
+/// Log something which is nice to know
+macro_rules! log_info {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Info, module_path!(),
+            format_args!($($arg)+))
+    };
+}
+
+/// Log what the game decided and why, for tracking down bugs
+macro_rules! log_debug {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Debug, module_path!(),
+            format_args!($($arg)+))
+    };
+}
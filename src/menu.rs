@@ -0,0 +1,229 @@
+//! Title menu shown before a run, where the difficulty preset, mutators,
+//! craft, and content from packs get picked, or the autopilot gets to show
+//! off
+
+use macroquad::prelude::*;
+use crate::{field_transform, mods, Craft, Error, Mutator, Preset, Registry};
+use crate::{GAME_FIELD_HEIGHT, GAME_FIELD_WIDTH};
+
+/// Keys which toggle each of [`Mutator::ALL`]
+const MUTATOR_KEYS: [KeyCode; 8] = [
+    KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8,
+];
+
+/// Short description of each preset for the menu
+fn description(preset: Preset) -> &'static str {
+    tr!(&format!("preset_{}_description", preset.name()))
+}
+
+/// Show the menu until a preset is picked, starting with `current` selected,
+/// the `mutators` bitmask active, and `craft` picked. C cycles through the
+/// crafts, M opens the picker of the content in `packs`. Returns the preset,
+/// the mutators, the craft, and if the run is a CPU demo, which plays the
+/// last highlighted preset
+pub async fn choose(current: Preset, mutators: u32, craft: Craft,
+        packs: &mut Registry) -> (Preset, u32, Craft, bool) {
+    let mut mutators = mutators;
+    let mut craft = craft;
+    let mut selected = Preset::ALL.iter().position(|&x| x == current)
+        .unwrap_or(0);
+    let mut preset = current;
+    let mut last_mouse = mouse_position();
+
+    // The presets followed by the CPU demo
+    let entries = Preset::ALL.len() + 1;
+
+    loop {
+        let (offset_x, offset_y, scale) = field_transform();
+        let target_w = scale * f32::from(GAME_FIELD_WIDTH);
+        let target_h = scale * f32::from(GAME_FIELD_HEIGHT);
+
+        // Layout of the entries, used for both drawing and mouse picking
+        let entry_h = 26. * scale;
+        let entry_y = |idx: usize| {
+            offset_y + target_h * 0.3 + idx as f32 * entry_h
+        };
+
+        // M opens the content pack picker, skipping the frame it gets closed
+        // on so the same key doesn't start a run
+        if !packs.is_empty() && is_key_pressed(KeyCode::M) {
+            mods::choose(packs).await;
+            next_frame().await;
+            continue;
+        }
+
+        if is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::W) {
+            selected = selected.saturating_sub(1);
+        }
+        if is_key_pressed(KeyCode::Down) || is_key_pressed(KeyCode::S) {
+            selected = (selected + 1).min(entries - 1);
+        }
+
+        if is_key_pressed(KeyCode::C) {
+            let idx = Craft::ALL.iter().position(|&x| x == craft)
+                .unwrap_or(0);
+            craft = Craft::ALL[(idx + 1) % Craft::ALL.len()];
+        }
+
+        // Toggle mutators, the player can't be both tiny and giant
+        for (mutator, key) in Mutator::ALL.iter().zip(MUTATOR_KEYS) {
+            if is_key_pressed(key) {
+                mutators ^= mutator.bit();
+                match mutator {
+                    Mutator::Tiny  => mutators &= !Mutator::Giant.bit(),
+                    Mutator::Giant => mutators &= !Mutator::Tiny.bit(),
+                    _ => {}
+                }
+            }
+        }
+
+        // Moving the mouse over an entry selects it, clicking starts it
+        let mouse = mouse_position();
+        let hovered = (0..entries).find(|&idx| {
+            (entry_y(idx) - entry_h * 0.75..entry_y(idx) + entry_h * 0.25)
+                .contains(&mouse.1)
+        });
+        if let Some(idx) = hovered {
+            if mouse != last_mouse {
+                selected = idx;
+            }
+        }
+        last_mouse = mouse;
+
+        if let Some(&highlighted) = Preset::ALL.get(selected) {
+            preset = highlighted;
+        }
+        let clicked = hovered.is_some() &&
+            is_mouse_button_pressed(MouseButton::Left);
+        if clicked || is_key_pressed(KeyCode::Enter) ||
+                is_key_pressed(KeyCode::Space) {
+            return (preset, mutators, craft, selected == Preset::ALL.len());
+        }
+
+        clear_background(BLACK);
+        draw_rectangle_lines(offset_x, offset_y, target_w, target_h, 2., BLUE);
+
+        let centered = |text: &str, y: f32, size: f32, color: Color| {
+            let width = measure_text(text, None, size as u16, 1.).width;
+            draw_text(text, offset_x + (target_w - width) / 2., y, size,
+                color);
+        };
+
+        centered(tr!("title"), offset_y + target_h / 6., 56. * scale, WHITE);
+        if !packs.is_empty() {
+            draw_text(tr!("mods_hint"), offset_x + 8. * scale,
+                offset_y + 20. * scale, 14. * scale, DARKGRAY);
+        }
+        let text = tr!("craft_hint", craft.title());
+        let width = measure_text(&text, None, (14. * scale) as u16, 1.).width;
+        draw_text(&text, offset_x + target_w - width - 8. * scale,
+            offset_y + 20. * scale, 14. * scale, GRAY);
+        let names = Preset::ALL.iter().map(|x| x.title())
+            .chain(std::iter::once(tr!("demo")));
+        for (idx, name) in names.enumerate() {
+            let color = if idx == selected { YELLOW } else { GRAY };
+            let name = name.to_uppercase();
+            let text = if idx == selected {
+                format!("> {} <", name)
+            } else {
+                name
+            };
+            centered(&text, entry_y(idx), 24. * scale, color);
+        }
+        let text = if selected == Preset::ALL.len() {
+            tr!("demo_description", preset.title())
+        } else {
+            description(preset).to_string()
+        };
+        centered(&text, offset_y + target_h * 0.82, 20. * scale, WHITE);
+
+        // Row of mutators, lit up when active
+        let size = 14. * scale;
+        let labels: Vec<String> = Mutator::ALL.iter().enumerate()
+            .map(|(idx, x)| format!("{} {}  ", idx + 1, x.title()))
+            .collect();
+        let width: f32 = labels.iter()
+            .map(|x| measure_text(x, None, size as u16, 1.).width).sum();
+        let mut x = offset_x + (target_w - width) / 2.;
+        for (label, mutator) in labels.iter().zip(Mutator::ALL) {
+            let color = if mutators & mutator.bit() != 0 {
+                GREEN
+            } else {
+                DARKGRAY
+            };
+            draw_text(label, x, offset_y + target_h * 0.93, size, color);
+            x += measure_text(label, None, size as u16, 1.).width;
+        }
+
+        next_frame().await;
+    }
+}
+
+/// Show `err` until a key or mouse button is pressed, for files which failed
+/// to load and got skipped
+pub async fn error(err: &Error) {
+    loop {
+        if get_last_key_pressed().is_some() ||
+                is_mouse_button_pressed(MouseButton::Left) {
+            return;
+        }
+
+        let (offset_x, offset_y, scale) = field_transform();
+        let target_w = scale * f32::from(GAME_FIELD_WIDTH);
+        let target_h = scale * f32::from(GAME_FIELD_HEIGHT);
+
+        clear_background(BLACK);
+        draw_rectangle_lines(offset_x, offset_y, target_w, target_h, 2., RED);
+
+        let centered = |text: &str, y: f32, size: f32, color: Color| {
+            let width = measure_text(text, None, size as u16, 1.).width;
+            draw_text(text, offset_x + (target_w - width) / 2., y, size,
+                color);
+        };
+
+        centered(tr!("error"), offset_y + target_h / 3., 56. * scale, RED);
+        centered(&err.to_string(), offset_y + target_h / 2., 20. * scale,
+            WHITE);
+        centered(tr!("press_any_key"), offset_y + target_h * 0.82,
+            20. * scale, GRAY);
+
+        next_frame().await;
+    }
+}
+
+/// Offer to resume the run which was in progress when the game was closed,
+/// `seconds` into it with `score`. Returns if it should be resumed
+pub async fn resume(score: u64, seconds: u64) -> bool {
+    loop {
+        if is_key_pressed(KeyCode::Enter) ||
+                is_mouse_button_pressed(MouseButton::Left) {
+            return true;
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            return false;
+        }
+
+        let (offset_x, offset_y, scale) = field_transform();
+        let target_w = scale * f32::from(GAME_FIELD_WIDTH);
+        let target_h = scale * f32::from(GAME_FIELD_HEIGHT);
+
+        clear_background(BLACK);
+        draw_rectangle_lines(offset_x, offset_y, target_w, target_h, 2., BLUE);
+
+        let centered = |text: &str, y: f32, size: f32, color: Color| {
+            let width = measure_text(text, None, size as u16, 1.).width;
+            draw_text(text, offset_x + (target_w - width) / 2., y, size,
+                color);
+        };
+
+        centered(tr!("resume_run"), offset_y + target_h / 3., 48. * scale,
+            WHITE);
+        centered(&tr!("resume_run_status", score, seconds),
+            offset_y + target_h / 2., 24. * scale, YELLOW);
+        centered(tr!("resume_run_help"), offset_y + target_h * 0.82,
+            20. * scale, GRAY);
+
+        next_frame().await;
+    }
+}
@@ -0,0 +1,346 @@
+//! Content packs, so community content can be played without forking the
+//! game. Every directory in [`MODS_DIR`] is a pack, with its content sorted
+//! into a directory for each kind:
+//!
+//! ```text
+//! mods/neon/levels/tunnel.txt     Level files, see `level.rs`
+//! mods/neon/curves/brutal.txt     Difficulty curves, see `curve.rs`
+//! mods/neon/skins/ship.png        Images the player is drawn with
+//! mods/neon/palettes/neon.txt     Colors the obstacles cycle through
+//! ```
+//!
+//! Palettes have one hex `rrggbb` color per line, where `#` starts a comment.
+//! Packs are discovered at startup and the content is picked with M on the
+//! title menu, at most one of each kind. Files only get loaded once they're
+//! picked, so a broken one doesn't keep the rest from being played. The game
+//! has no sounds yet, so sound packs are skipped. WASM has no directories to
+//! discover packs in
+
+use macroquad::prelude::*;
+use crate::{Curve, Error, Level, Result};
+use crate::error;
+use crate::settings::parse_color;
+use crate::{field_transform, GAME_FIELD_HEIGHT, GAME_FIELD_WIDTH};
+
+/// Directory content packs are discovered in
+pub const MODS_DIR: &str = "mods";
+
+/// Kinds of content a pack can have
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Level,
+    Curve,
+    Skin,
+    Palette,
+}
+
+impl Kind {
+    /// Every kind of content, in the order they're shown in
+    pub const ALL: [Kind; 4] = [Kind::Level, Kind::Curve, Kind::Skin,
+        Kind::Palette];
+
+    /// Directory of a pack with content of this kind
+    fn dir(&self) -> &'static str {
+        match self {
+            Kind::Level   => "levels",
+            Kind::Curve   => "curves",
+            Kind::Skin    => "skins",
+            Kind::Palette => "palettes",
+        }
+    }
+
+    /// Extension of the files of this kind
+    fn extension(&self) -> &'static str {
+        match self {
+            Kind::Skin => "png",
+            _          => "txt",
+        }
+    }
+
+    /// Localized name of the kind
+    fn title(&self) -> &'static str {
+        tr!(&format!("mods_{}", self.dir()))
+    }
+}
+
+/// A file of content from a pack
+pub struct Content {
+    pub kind: Kind,
+
+    /// Name shown when picking, `pack/file` without the extension
+    pub name: String,
+
+    /// Path of the file
+    pub path: String,
+}
+
+/// Colors obstacles cycle through along the field instead of the rainbow
+#[derive(Clone)]
+pub struct Palette(Vec<Color>);
+
+impl Palette {
+    /// Load the palette file at `path`
+    pub fn load(path: &str) -> Result<Self> {
+        Self::parse(&error::read_to_string("palette", path)?)
+            .map_err(|err| err.in_file("palette", path))
+    }
+
+    /// Parse a palette file
+    pub fn parse(text: &str) -> Result<Self> {
+        let colors = text.lines()
+            .map(|x| x.split('#').next().unwrap_or("").trim())
+            .filter(|x| !x.is_empty())
+            .map(|x| {
+                let [r, g, b] = parse_color(x)?;
+                Ok(Color::from_rgba(r, g, b, 0xff))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if colors.is_empty() {
+            return Err("Palette has no colors".into());
+        }
+        Ok(Self(colors))
+    }
+
+    /// Color at `x` along the cycle, blending between neighbouring colors.
+    /// Every whole number is a full cycle, like [`crate::GameField`]'s
+    /// rainbow
+    pub fn at(&self, x: f32) -> (u8, u8, u8) {
+        let pos = x.rem_euclid(1.) * self.0.len() as f32;
+        let from = self.0[pos as usize % self.0.len()];
+        let to = self.0[(pos as usize + 1) % self.0.len()];
+        let blend = |from: f32, to: f32| {
+            ((from + (to - from) * pos.fract()) * 255.) as u8
+        };
+        (blend(from.r, to.r), blend(from.g, to.g), blend(from.b, to.b))
+    }
+}
+
+/// Content picked from the packs, loaded
+#[derive(Default)]
+pub struct Picked {
+    /// Path of the level and the level
+    pub level: Option<(String, Level)>,
+
+    pub curve: Option<Curve>,
+
+    pub skin: Option<Texture2D>,
+
+    pub palette: Option<Palette>,
+}
+
+/// Every content pack discovered, and the content picked from them
+#[derive(Default)]
+pub struct Registry {
+    /// Content of all packs, sorted by pack and then by kind
+    content: Vec<Content>,
+
+    /// Index into `content` picked for each of [`Kind::ALL`]
+    picked: [Option<usize>; Kind::ALL.len()],
+
+    /// Skins which were loaded so far, by index into `content`. Textures
+    /// aren't freed when dropped, so they're only loaded once
+    skins: Vec<(usize, Texture2D)>,
+}
+
+impl Registry {
+    /// Discover the packs in `dir`, skipping anything which isn't content
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn discover(dir: &str) -> Self {
+        let mut ret = Self::default();
+        let packs = match sorted_dir(dir) {
+            Ok(packs) => packs,
+            Err(err) => {
+                log_debug!("No content packs loaded: {}", err);
+                return ret;
+            }
+        };
+
+        for (pack, pack_path) in packs {
+            let dirs = match sorted_dir(&pack_path) {
+                Ok(dirs) => dirs,
+                Err(err) => {
+                    log_warn!("Skipping content pack {}: {}", pack, err);
+                    continue;
+                }
+            };
+            for (name, path) in dirs {
+                let Some(kind) = Kind::ALL.iter().find(|x| x.dir() == name)
+                else {
+                    log_warn!("Skipping {}, it's not a kind of content the \
+                        game has", path);
+                    continue;
+                };
+                let files = sorted_dir(&path).unwrap_or_else(|err| {
+                    log_warn!("Skipping {}: {}", path, err);
+                    Vec::new()
+                });
+                for (file, path) in files {
+                    match file.strip_suffix(kind.extension())
+                            .and_then(|x| x.strip_suffix('.')) {
+                        Some(stem) => ret.content.push(Content {
+                            kind: *kind,
+                            name: format!("{}/{}", pack, stem),
+                            path,
+                        }),
+                        None => log_warn!("Skipping {}, {} need to be .{} \
+                            files", path, kind.dir(), kind.extension()),
+                    }
+                }
+            }
+        }
+        log_info!("Discovered {} files of content in {}", ret.content.len(),
+            dir);
+        ret
+    }
+
+    /// WASM has no directories to discover packs in
+    #[cfg(target_arch = "wasm32")]
+    pub fn discover(_dir: &str) -> Self {
+        Self::default()
+    }
+
+    /// Check if no content was discovered
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    /// Content picked of `kind`, if any
+    pub fn picked(&self, kind: Kind) -> Option<&Content> {
+        self.picked[kind as usize].map(|idx| &self.content[idx])
+    }
+
+    /// Pick the next content of `kind`, going back the other way if `back`
+    /// is set, with nothing picked between the last and the first
+    fn cycle(&mut self, kind: Kind, back: bool) {
+        let choices: Vec<usize> = (0..self.content.len())
+            .filter(|&idx| self.content[idx].kind == kind)
+            .collect();
+        let pos = self.picked[kind as usize]
+            .and_then(|x| choices.iter().position(|&y| y == x))
+            .map_or(0, |x| x + 1);
+        let count = choices.len() + 1;
+        let step = if back { count - 1 } else { 1 };
+        let pos = (pos + step) % count;
+        self.picked[kind as usize] = pos.checked_sub(1).map(|x| choices[x]);
+    }
+
+    /// Load all picked content
+    pub fn load(&mut self) -> Result<Picked> {
+        let mut ret = Picked::default();
+        if let Some(level) = self.picked(Kind::Level) {
+            ret.level = Some((level.path.clone(), Level::load(&level.path)?));
+        }
+        if let Some(curve) = self.picked(Kind::Curve) {
+            ret.curve = Some(Curve::load(&curve.path)?);
+        }
+        if let Some(palette) = self.picked(Kind::Palette) {
+            ret.palette = Some(Palette::load(&palette.path)?);
+        }
+        if let Some(idx) = self.picked[Kind::Skin as usize] {
+            let skin = match self.skins.iter().find(|x| x.0 == idx) {
+                Some(&(_, skin)) => skin,
+                None => {
+                    let skin = load_skin(&self.content[idx].path)?;
+                    self.skins.push((idx, skin));
+                    skin
+                }
+            };
+            ret.skin = Some(skin);
+        }
+        Ok(ret)
+    }
+}
+
+/// Names and paths of the entries of the directory at `path`, sorted by name
+#[cfg(not(target_arch = "wasm32"))]
+fn sorted_dir(path: &str) -> std::io::Result<Vec<(String, String)>> {
+    let mut ret = std::fs::read_dir(path)?
+        .map(|entry| {
+            let entry = entry?;
+            Ok((entry.file_name().to_string_lossy().into_owned(),
+                entry.path().to_string_lossy().into_owned()))
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+    ret.sort();
+    Ok(ret)
+}
+
+/// Load the PNG skin at `path`
+fn load_skin(path: &str) -> Result<Texture2D> {
+    let bytes = error::read("skin", path)?;
+    let img = ::image::load_from_memory_with_format(&bytes,
+            ::image::ImageFormat::Png)
+        .map_err(|err| Error::from(err.to_string()).in_file("skin", path))?
+        .to_rgba8();
+    let skin = Texture2D::from_rgba8(img.width() as u16, img.height() as u16,
+        &img.into_raw());
+    skin.set_filter(FilterMode::Nearest);
+    Ok(skin)
+}
+
+/// Show the content of `registry` until Escape or Enter is pressed, picking
+/// one of each kind with the arrow keys or by clicking
+pub async fn choose(registry: &mut Registry) {
+    let mut selected: usize = 0;
+    loop {
+        let (offset_x, offset_y, scale) = field_transform();
+        let target_w = scale * f32::from(GAME_FIELD_WIDTH);
+        let target_h = scale * f32::from(GAME_FIELD_HEIGHT);
+        let entry_h = 40. * scale;
+        let entry_y = |idx: usize| {
+            offset_y + target_h * 0.3 + idx as f32 * entry_h
+        };
+
+        if is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::Enter) {
+            return;
+        }
+        if is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::W) {
+            selected = selected.saturating_sub(1);
+        }
+        if is_key_pressed(KeyCode::Down) || is_key_pressed(KeyCode::S) {
+            selected = (selected + 1).min(Kind::ALL.len() - 1);
+        }
+        let kind = Kind::ALL[selected];
+        if is_key_pressed(KeyCode::Left) || is_key_pressed(KeyCode::A) {
+            registry.cycle(kind, true);
+        }
+        if is_key_pressed(KeyCode::Right) || is_key_pressed(KeyCode::D) {
+            registry.cycle(kind, false);
+        }
+
+        // Clicking an entry cycles through its content
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let mouse = mouse_position().1;
+            if let Some(idx) = (0..Kind::ALL.len()).find(|&idx| {
+                (entry_y(idx) - entry_h * 0.75..entry_y(idx) + entry_h * 0.25)
+                    .contains(&mouse)
+            }) {
+                selected = idx;
+                registry.cycle(Kind::ALL[idx], false);
+            }
+        }
+
+        clear_background(BLACK);
+        draw_rectangle_lines(offset_x, offset_y, target_w, target_h, 2., BLUE);
+
+        let centered = |text: &str, y: f32, size: f32, color: Color| {
+            let width = measure_text(text, None, size as u16, 1.).width;
+            draw_text(text, offset_x + (target_w - width) / 2., y, size,
+                color);
+        };
+
+        centered(tr!("mods_title"), offset_y + target_h / 6., 40. * scale,
+            WHITE);
+        for (idx, kind) in Kind::ALL.iter().enumerate() {
+            let color = if idx == selected { YELLOW } else { GRAY };
+            let picked = registry.picked(*kind)
+                .map_or(tr!("mods_none"), |x| &x.name);
+            centered(&format!("{}: < {} >", kind.title(), picked),
+                entry_y(idx), 24. * scale, color);
+        }
+        centered(tr!("mods_help"), offset_y + target_h * 0.9, 16. * scale,
+            DARKGRAY);
+
+        next_frame().await;
+    }
+}
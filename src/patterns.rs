@@ -0,0 +1,73 @@
+//! Hand-authored obstacle patterns which get stitched into the generated level
+//! at intervals, giving endless mode some recognizable moments
+
+/// A single obstacle in a [`Pattern`]
+pub struct Piece {
+    /// Wall column the piece is placed in, relative to the start of the
+    /// pattern
+    pub column: u16,
+
+    /// Position within the gap, 0 is touching the top wall and 256 is
+    /// touching the bottom wall
+    pub position: u16,
+
+    /// Height of the piece in pixels
+    pub height: i16,
+}
+
+/// A fixed arrangement of obstacles
+pub struct Pattern {
+    /// Number of wall columns the pattern spans
+    pub columns: u16,
+
+    /// Obstacles which make up the pattern
+    pub pieces: &'static [Piece],
+}
+
+/// Shorthand for declaring a [`Piece`]
+const fn piece(column: u16, position: u16, height: i16) -> Piece {
+    Piece { column, position, height }
+}
+
+/// Gap (in pixels) the pieces of patterns are sized for. In narrower gaps, or
+/// with a bigger player, pieces get scaled down to still leave room
+pub const DESIGN_GAP: i16 = 180;
+
+/// All patterns which can be stitched into the level
+pub const PATTERNS: &[Pattern] = &[
+    // Weave between obstacles alternating on the top and bottom
+    Pattern {
+        columns: 40,
+        pieces:  &[
+            piece( 0,   0, 80),
+            piece(10, 256, 80),
+            piece(20,   0, 80),
+            piece(30, 256, 80),
+        ],
+    },
+
+    // Follow a line of obstacles stepping down the gap
+    Pattern {
+        columns: 36,
+        pieces:  &[
+            piece( 0,  32, 40),
+            piece( 7,  80, 40),
+            piece(14, 128, 40),
+            piece(21, 176, 40),
+            piece(28, 224, 40),
+        ],
+    },
+
+    // Squeeze through the middle of obstacles closing in from both sides
+    Pattern {
+        columns: 32,
+        pieces:  &[
+            piece( 0,   0, 50),
+            piece( 0, 256, 50),
+            piece(12,   0, 50),
+            piece(12, 256, 50),
+            piece(24,   0, 50),
+            piece(24, 256, 50),
+        ],
+    },
+];
@@ -0,0 +1,57 @@
+//! Layout for phones held upright. The field gets letterboxed in the middle,
+//! the score moves to the top in big, and the bottom of the screen is a thrust
+//! zone to rest a thumb on. Any touch still thrusts, the zone is just where
+//! it's comfortable
+
+use macroquad::prelude::*;
+use crate::GameField;
+
+/// Height in screen pixels of the HUD at the top
+pub const HUD_HEIGHT: f32 = 90.;
+
+/// Fraction of the screen height the thrust zone at the bottom takes up
+pub const THRUST_ZONE: f32 = 0.22;
+
+/// Check if the screen is taller than it's wide
+pub fn active() -> bool {
+    screen_height() > screen_width()
+}
+
+/// Size of HUD text, which grows with the screen so it's readable on phones
+fn text_size() -> f32 {
+    (screen_width() / 12.).clamp(32., 72.)
+}
+
+/// Draw the HUD for `field` at the top of the screen, within `margin`
+pub fn draw_hud(field: &GameField, high_score: u64, margin: f32) {
+    let size = text_size();
+    let centered = |text: &str, y: f32, size: f32, color: Color| {
+        let width = measure_text(text, None, size as u16, 1.).width;
+        draw_text(text, (screen_width() - width) / 2., y, size, color);
+    };
+    centered(&tr!("portrait_score", field.score()), margin + size, size,
+        WHITE);
+    centered(&tr!("portrait_status", high_score, field.coins,
+        field.powerup_status()), margin + size + size * 0.6, size * 0.5,
+        GRAY);
+}
+
+/// Draw the thrust zone at the bottom of the screen within `margin`, lit up
+/// while thrusting
+pub fn draw_thrust_zone(thrusting: bool, margin: f32) {
+    let height = screen_height() * THRUST_ZONE;
+    let y = screen_height() - height - margin;
+    let width = screen_width() - margin * 2.;
+    let alpha = if thrusting { 0x60 } else { 0x28 };
+    draw_rectangle(margin, y, width, height,
+        Color::from_rgba(0x40, 0x80, 0xff, alpha));
+    draw_rectangle_lines(margin, y, width, height, 2.,
+        Color::from_rgba(0x40, 0x80, 0xff, 0x80));
+
+    let text = tr!("thrust_zone");
+    let size = text_size() * 0.6;
+    let dims = measure_text(text, None, size as u16, 1.);
+    draw_text(text, margin + (width - dims.width) / 2.,
+        y + (height + dims.offset_y) / 2., size,
+        Color::from_rgba(0xff, 0xff, 0xff, 0xa0));
+}
@@ -0,0 +1,168 @@
+//! Discord Rich Presence, showing friends the mode, score, and time of the
+//! run being played. It's only built with the `discord` feature, and only
+//! works on Unix-like systems, where it talks to the Discord client over its
+//! local IPC socket. It stays off until a `discord` application ID is set in
+//! the settings
+
+use crate::GameField;
+#[cfg(all(feature = "discord", unix))]
+use std::convert::TryInto;
+#[cfg(all(feature = "discord", unix))]
+use std::io::{self, Read, Write};
+#[cfg(all(feature = "discord", unix))]
+use std::os::unix::net::UnixStream;
+#[cfg(all(feature = "discord", unix))]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Opcodes of the frames sent to the Discord client
+#[cfg(all(feature = "discord", unix))]
+const OP_HANDSHAKE: u32 = 0;
+#[cfg(all(feature = "discord", unix))]
+const OP_FRAME: u32 = 1;
+
+/// Seconds to wait for the Discord client to answer the handshake
+#[cfg(all(feature = "discord", unix))]
+const HANDSHAKE_TIMEOUT: u64 = 2;
+
+/// Connection to the Discord client, if there is one
+#[derive(Default)]
+pub struct Presence {
+    /// Socket to the Discord client
+    #[cfg(all(feature = "discord", unix))]
+    socket: Option<UnixStream>,
+
+    /// Start time of the run which was last published and whether it was
+    /// over, to only publish when a run starts or ends
+    published: Option<(u64, bool)>,
+}
+
+impl Presence {
+    /// Connect to the Discord client as the application `app_id`, if there
+    /// is one. Failing to connect just leaves the presence off
+    pub fn connect(app_id: Option<&str>) -> Self {
+        let Some(app_id) = app_id else { return Self::default() };
+
+        #[cfg(all(feature = "discord", unix))]
+        match handshake(app_id) {
+            Ok(socket) => {
+                log_info!("Connected to Discord as application {}", app_id);
+                return Self { socket: Some(socket), published: None };
+            }
+            Err(err) => log_warn!("Couldn't connect to Discord: {}", err),
+        }
+
+        #[cfg(not(all(feature = "discord", unix)))]
+        log_warn!("Ignoring the Discord application {}, this build has no \
+            Discord support", app_id);
+
+        Self::default()
+    }
+
+    /// Publish the run in `field` if it just started or ended
+    pub fn update(&mut self, field: &GameField) {
+        let state = (field.start_time.to_bits(), field.dead || field.finished);
+        if field.frames == 0 || self.published == Some(state) {
+            return;
+        }
+        self.published = Some(state);
+
+        #[cfg(all(feature = "discord", unix))]
+        if let Some(socket) = self.socket.as_mut() {
+            if let Err(err) = publish(socket, field) {
+                log_warn!("Lost the connection to Discord: {}", err);
+                self.socket = None;
+            }
+        }
+    }
+}
+
+/// Open the Discord client's IPC socket and introduce ourselves as `app_id`
+#[cfg(all(feature = "discord", unix))]
+fn handshake(app_id: &str) -> io::Result<UnixStream> {
+    // The socket is in the first of these which is set, or else `/tmp`
+    let dir = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"].iter()
+        .find_map(std::env::var_os)
+        .unwrap_or_else(|| "/tmp".into());
+    let mut socket = (0..10)
+        .find_map(|ii| {
+            UnixStream::connect(std::path::Path::new(&dir)
+                .join(format!("discord-ipc-{}", ii))).ok()
+        })
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound,
+            "Discord isn't running"))?;
+
+    send(&mut socket, OP_HANDSHAKE, &format!(
+        r#"{{"v":1,"client_id":{}}}"#, json_string(app_id)))?;
+    socket.set_read_timeout(Some(Duration::from_secs(HANDSHAKE_TIMEOUT)))?;
+    let mut header = [0u8; 8];
+    socket.read_exact(&mut header)?;
+    let len = u32::from_le_bytes(header[4..].try_into().unwrap());
+    io::copy(&mut (&mut socket).take(len as u64), &mut io::sink())?;
+    if u32::from_le_bytes(header[..4].try_into().unwrap()) != OP_FRAME {
+        return Err(io::Error::new(io::ErrorKind::ConnectionRefused,
+            "Discord turned down the handshake"));
+    }
+
+    // Replies to activity updates are never looked at, they just get thrown
+    // away so they don't pile up
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+/// Set the activity to the run in `field`
+#[cfg(all(feature = "discord", unix))]
+fn publish(socket: &mut UnixStream, field: &GameField) -> io::Result<()> {
+    let mut junk = [0u8; 4096];
+    loop {
+        match socket.read(&mut junk) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(_) => {}
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+            Err(err) => return Err(err),
+        }
+    }
+
+    let details = format!("{} mode, {}", field.rules.mode.name(),
+        field.rules.preset.name());
+    let activity = if field.dead || field.finished {
+        format!(r#"{{"details":{},"state":{}}}"#, json_string(&details),
+            json_string(&format!("Finished with {}", field.score())))
+    } else {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_secs());
+        format!(concat!(r#"{{"details":{},"state":"Flying","#,
+            r#""timestamps":{{"start":{}}}}}"#), json_string(&details), now)
+    };
+    send(socket, OP_FRAME, &format!(concat!(r#"{{"cmd":"SET_ACTIVITY","#,
+        r#""args":{{"pid":{},"activity":{}}},"nonce":"{}"}}"#),
+        std::process::id(), activity, field.start_time.to_bits()))
+}
+
+/// Send a frame of `opcode` with the JSON `payload`
+#[cfg(all(feature = "discord", unix))]
+fn send(socket: &mut UnixStream, opcode: u32, payload: &str)
+        -> io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 8);
+    frame.extend_from_slice(&opcode.to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(payload.as_bytes());
+    socket.write_all(&frame)
+}
+
+/// Quote `text` as a JSON string
+#[cfg(all(feature = "discord", unix))]
+fn json_string(text: &str) -> String {
+    let mut ret = String::from("\"");
+    for chr in text.chars() {
+        match chr {
+            '"'  => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            chr if (chr as u32) < 0x20 => {
+                ret.push_str(&format!("\\u{:04x}", chr as u32));
+            }
+            chr  => ret.push(chr),
+        }
+    }
+    ret.push('"');
+    ret
+}
@@ -0,0 +1,147 @@
+//! Preview band above the game field, showing a miniature of the walls and
+//! obstacles coming up in the next couple of screens. What's within the
+//! [`crate::Rules::lookahead`] has been generated already and is exact. For
+//! the rest, a copy of the field runs ahead to find it, which makes that part
+//! a forecast: it assumes the player keeps still and doesn't pick anything up
+//! on the way
+
+use macroquad::prelude::*;
+use crate::batch::Batch;
+use crate::{GameField, Obstacle, ObstacleKind, Fxpt};
+use crate::{GAME_FIELD_HEIGHT, GAME_FIELD_WIDTH, OBSTACLE_WIDTH};
+
+/// Height of the preview band in screen pixels
+pub const HEIGHT: f32 = 30.;
+
+/// Number of screens of the map the preview covers
+const SCREENS: i32 = 2;
+
+/// Most physics frames the copy of the field runs ahead per screen, in case
+/// the map stops scrolling
+const MAX_FRAMES: u32 = 1200;
+
+/// Walls and obstacles coming up
+#[derive(Clone, Default)]
+pub struct Preview {
+    /// Number of wall columns the field had generated when the preview was
+    /// made, it gets made again once there's a new one
+    column: Option<u32>,
+
+    /// Walls and obstacles ahead, with X coords in game field pixels past
+    /// the right edge of the field
+    walls:     Vec<Obstacle>,
+    obstacles: Vec<Obstacle>,
+}
+
+/// X coord in fixed-point just past the newest wall column of `field`
+fn right_edge(field: &GameField) -> i32 {
+    field.walls.last().map_or(GAME_FIELD_WIDTH.0 as i32,
+        |x| x.x.0 as i32 + OBSTACLE_WIDTH.0 as i32)
+}
+
+impl Preview {
+    /// Look ahead of `field` if it generated a new wall column since the
+    /// last time
+    pub fn update(&mut self, field: &GameField) {
+        if self.column == Some(field.column) || field.dead || field.finished {
+            return;
+        }
+        self.column = Some(field.column);
+        self.walls.clear();
+        self.obstacles.clear();
+
+        let mut shadow = field.clone();
+        shadow.invulnerable_frames = u16::MAX;
+        let edge = right_edge(field) - GAME_FIELD_WIDTH.0 as i32;
+        crate::log::muted(|| {
+            for screen in 0..SCREENS {
+                // Run until the next screen of the map has been generated,
+                // which it may be already
+                let missing = ((screen + 1) * GAME_FIELD_WIDTH.0 as i32 -
+                    edge).max(0);
+                let target = field.column + ((missing +
+                    OBSTACLE_WIDTH.0 as i32 - 1) / OBSTACLE_WIDTH.0 as i32)
+                    as u32;
+                for _ in 0..MAX_FRAMES {
+                    if shadow.column >= target || shadow.finished {
+                        break;
+                    }
+                    shadow.step(0);
+                }
+
+                // Keep what's on that screen, moved to where it is relative
+                // to the real field
+                let shift = (shadow.column - field.column) as i32 *
+                    OBSTACLE_WIDTH.0 as i32 - right_edge(&shadow) + edge;
+                let range = screen * GAME_FIELD_WIDTH.0 as i32..
+                    (screen + 1) * GAME_FIELD_WIDTH.0 as i32;
+                let ahead = |x: &Obstacle| {
+                    let x = x.x.0 as i32 + shift;
+                    range.contains(&x).then_some(x)
+                };
+                for wall in &shadow.walls {
+                    if let Some(x) = ahead(wall) {
+                        self.walls.push(Obstacle {
+                            x: Fxpt(x as i16), ..*wall
+                        });
+                    }
+                }
+                for obstacle in shadow.obstacles.iter().filter(|x| {
+                    !matches!(x.kind, ObstacleKind::Projectile { .. })
+                }) {
+                    if let Some(x) = ahead(obstacle) {
+                        self.obstacles.push(Obstacle {
+                            x: Fxpt(x as i16), ..*obstacle
+                        });
+                    }
+                }
+                if shadow.finished {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Draw the preview into the `width` by `height` screen pixels with
+    /// their top left at (`x`, `y`), with the map coming in from the left
+    /// when `mirror`ed
+    pub fn draw(&self, x: f32, y: f32, width: f32, height: f32,
+            mirror: bool) {
+        draw_rectangle(x, y, width, height,
+            Color::from_rgba(0x00, 0x00, 0x20, 0xff));
+
+        let scale_x = width / (f32::from(GAME_FIELD_WIDTH) * SCREENS as f32);
+        let scale_y = height / f32::from(GAME_FIELD_HEIGHT);
+        let point = |px: f32, py: f32| {
+            let px = px * scale_x;
+            let px = if mirror { x + width - px } else { x + px };
+            vec2(px, y + py * scale_y)
+        };
+
+        let mut batch = Batch::default();
+        let shapes = self.walls.iter().map(|x| (x, LIGHTGRAY))
+            .chain(self.obstacles.iter().map(|x| (x, ORANGE)));
+        for (shape, color) in shapes {
+            let left = f32::from(shape.x);
+            let right = (left + f32::from(shape.width))
+                .min(f32::from(GAME_FIELD_WIDTH) * SCREENS as f32);
+            let top = f32::from(shape.y);
+            let bottom = top + f32::from(shape.height);
+            let (left_top, left_bottom, right_top, right_bottom) =
+                    match shape.kind {
+                ObstacleKind::Slope { left: l, right: r, ceiling: true } =>
+                    (top, f32::from(l), top, f32::from(r)),
+                ObstacleKind::Slope { left: l, right: r, ceiling: false } =>
+                    (f32::from(l), bottom, f32::from(r), bottom),
+                _ => (top, bottom, top, bottom),
+            };
+            batch.quad([
+                point(left, left_top), point(right, right_top),
+                point(right, right_bottom), point(left, left_bottom),
+            ], color);
+        }
+        batch.flush();
+
+        draw_rectangle_lines(x, y, width, height, 1., BLUE);
+    }
+}
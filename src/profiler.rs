@@ -0,0 +1,93 @@
+//! Debug overlay toggled with F3, showing where the time of each frame goes
+//! to help track down stutters
+
+use std::collections::VecDeque;
+use macroquad::prelude::*;
+use crate::bench::allocations;
+use crate::GameField;
+
+/// Number of frames shown in the frame time graph
+const HISTORY: usize = 240;
+
+/// Frame time (in seconds) at the top of the graph
+const GRAPH_MAX: f32 = 1. / 20.;
+
+/// Size (in pixels) of the frame time graph
+const GRAPH_WIDTH:  f32 = HISTORY as f32;
+const GRAPH_HEIGHT: f32 = 80.;
+
+/// Frame times and allocations of the recent frames
+#[derive(Default)]
+pub struct Profiler {
+    /// Set while the overlay is shown
+    shown: bool,
+
+    /// Times (in seconds) of the last [`HISTORY`] frames, oldest first
+    frame_times: VecDeque<f32>,
+
+    /// Total number of allocations as of the last frame
+    allocs: u64,
+
+    /// Number of allocations made during the last frame
+    frame_allocs: u64,
+}
+
+impl Profiler {
+    /// Record the frame which just finished, toggling the overlay on F3
+    pub fn update(&mut self) {
+        if is_key_pressed(KeyCode::F3) {
+            self.shown = !self.shown;
+        }
+
+        if self.frame_times.len() == HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(get_frame_time());
+
+        let (allocs, _) = allocations();
+        self.frame_allocs = allocs - self.allocs;
+        self.allocs = allocs;
+    }
+
+    /// Draw the overlay for `field` in the top right, if it's shown
+    pub fn draw(&self, field: &GameField) {
+        if !self.shown {
+            return;
+        }
+
+        let x = screen_width() - GRAPH_WIDTH - 10.;
+        let y = 60.;
+        draw_rectangle(x, y, GRAPH_WIDTH, GRAPH_HEIGHT + 90.,
+            Color::from_rgba(0, 0, 0, 0xc0));
+
+        // Frame times as bars from the bottom of the graph, with a line at
+        // 60 fps. Anything slower than that drops physics frames
+        let bottom = y + GRAPH_HEIGHT;
+        for (ii, &time) in self.frame_times.iter().enumerate() {
+            let height = (time / GRAPH_MAX).min(1.) * GRAPH_HEIGHT;
+            let color = if time > 1. / 55. { RED } else { GREEN };
+            draw_line(x + ii as f32, bottom, x + ii as f32, bottom - height,
+                1., color);
+        }
+        let target = bottom - GRAPH_HEIGHT / (GRAPH_MAX * 60.);
+        draw_line(x, target, x + GRAPH_WIDTH, target, 1., YELLOW);
+
+        let worst = self.frame_times.iter().copied().fold(0., f32::max);
+        let lines = [
+            format!("Frame {:6.2} ms (worst {:6.2} ms)",
+                get_frame_time() * 1000., worst * 1000.),
+            format!("Physics {:6.3} ms for {} ticks",
+                field.tick_time * 1000., field.ticks),
+            format!("Walls {} | Obstacles {} | Pickups {}",
+                field.walls.len(), field.obstacles.len(),
+                field.pickups.len()),
+            format!("Bullets {} | Draw objects {}",
+                field.bullets.len(), field.objects.len()),
+            format!("Allocations {} this frame", self.frame_allocs),
+        ];
+        for (ii, line) in lines.iter().enumerate() {
+            draw_text(line, x + 4., bottom + 18. + ii as f32 * 16., 16.,
+                WHITE);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Rank ladder from Bronze up to Diamond, going by the best scores of the
+//! recent ranked runs so it follows how the player is doing lately rather
+//! than one lucky run. The scores are kept in a file, one per line, oldest
+//! first
+
+use macroquad::prelude::*;
+use crate::error;
+
+/// File the scores of the recent ranked runs are kept in
+const LADDER_FILE: &str = "ladder.txt";
+
+/// Number of recent ranked runs the rank is worked out from
+const RECENT_RUNS: usize = 10;
+
+/// Number of the best of the recent runs which get averaged into the rank
+const BEST_RUNS: usize = 3;
+
+/// Ranks on the ladder, from the bottom up
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rank {
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+    Diamond,
+}
+
+impl Rank {
+    /// Every rank, from the bottom up
+    const ALL: [Rank; 5] = [
+        Rank::Bronze, Rank::Silver, Rank::Gold, Rank::Platinum, Rank::Diamond,
+    ];
+
+    /// Name of the rank as used in translations
+    fn name(&self) -> &'static str {
+        match self {
+            Rank::Bronze   => "bronze",
+            Rank::Silver   => "silver",
+            Rank::Gold     => "gold",
+            Rank::Platinum => "platinum",
+            Rank::Diamond  => "diamond",
+        }
+    }
+
+    /// Rank the average of the best [`BEST_RUNS`] of `scores` reaches, runs
+    /// which haven't been played yet count as zero
+    pub fn of(scores: &[u64]) -> Self {
+        let mut best = scores.to_vec();
+        best.sort_unstable_by(|a, b| b.cmp(a));
+        let average = best.iter().take(BEST_RUNS).sum::<u64>() /
+            BEST_RUNS as u64;
+        Rank::ALL.iter().copied().rev()
+            .find(|x| average >= x.threshold())
+            .unwrap_or(Rank::Bronze)
+    }
+
+    /// Name of the rank shown to players
+    pub fn title(&self) -> &'static str {
+        tr!(&format!("rank_{}", self.name()))
+    }
+
+    /// Average of the best recent scores which reaches this rank
+    fn threshold(&self) -> u64 {
+        match self {
+            Rank::Bronze   => 0,
+            Rank::Silver   => 2000,
+            Rank::Gold     => 5000,
+            Rank::Platinum => 10000,
+            Rank::Diamond  => 20000,
+        }
+    }
+
+    /// Color of the badge of this rank
+    fn color(&self) -> Color {
+        match self {
+            Rank::Bronze   => Color::from_rgba(0xcd, 0x7f, 0x32, 0xff),
+            Rank::Silver   => Color::from_rgba(0xc0, 0xc0, 0xc0, 0xff),
+            Rank::Gold     => Color::from_rgba(0xff, 0xd7, 0x00, 0xff),
+            Rank::Platinum => Color::from_rgba(0x80, 0xe0, 0xd0, 0xff),
+            Rank::Diamond  => Color::from_rgba(0xb0, 0xe0, 0xff, 0xff),
+        }
+    }
+}
+
+/// Scores of the recent ranked runs, oldest first
+#[derive(Default)]
+pub struct Ladder {
+    scores: Vec<u64>,
+}
+
+impl Ladder {
+    /// Load the recent scores, lines which aren't scores get skipped
+    pub fn load() -> Self {
+        let scores = error::read_to_string("ladder", LADDER_FILE)
+            .inspect_err(|err| log_debug!("No ladder loaded: {}", err))
+            .unwrap_or_default().lines()
+            .filter_map(|x| x.trim().parse()
+                .inspect_err(|_| log_warn!("Ignoring bad score {:?}", x))
+                .ok())
+            .collect();
+        Self { scores }
+    }
+
+    /// Record the `score` of a ranked run which just ended, dropping the
+    /// oldest run once there are more than [`RECENT_RUNS`]
+    pub fn record(&mut self, score: u64) {
+        let before = self.rank();
+        self.scores.push(score);
+        let excess = self.scores.len().saturating_sub(RECENT_RUNS);
+        self.scores.drain(..excess);
+        if self.rank() != before {
+            log_info!("Rank changed from {} to {}", before.title(),
+                self.rank().title());
+        }
+
+        if let Err(err) = error::write("ladder", LADDER_FILE, self.scores
+                .iter().map(|x| format!("{}\n", x)).collect::<String>()) {
+            log_error!("{}", err);
+        }
+    }
+
+    /// Rank the recent runs earn
+    pub fn rank(&self) -> Rank {
+        Rank::of(&self.scores)
+    }
+}
+
+/// Draw the badge of `rank` with its title, the badge centered vertically on
+/// `y` and starting at `x`, `size` screen pixels tall
+pub fn draw_badge(rank: Rank, x: f32, y: f32, size: f32) {
+    let radius = size / 2.;
+    draw_poly(x + radius, y, 6, radius, 0., rank.color());
+    draw_poly_lines(x + radius, y, 6, radius, 0., 1., BLACK);
+
+    // A dot for every rank above the bottom one
+    let dots = Rank::ALL.iter().position(|&x| x == rank).unwrap_or(0);
+    for dot in 0..dots {
+        let dx = (dot as f32 - (dots - 1) as f32 / 2.) * radius / 2.;
+        draw_circle(x + radius + dx, y, radius / 8., BLACK);
+    }
+    draw_text(rank.title(), x + size * 1.25, y + size / 3., size,
+        rank.color());
+}
@@ -0,0 +1,121 @@
+//! `render-replay` subcommand, which plays a replay back as fast as it can
+//! and writes the game field out frame by frame, to be turned into a video.
+//! Frames are either numbered PNGs or raw RGBA on stdout for piping into
+//! ffmpeg, like:
+//!
+//! `mqtest render-replay inputs.bin --out - --size 1280x960 --fps 30 |
+//!  ffmpeg -f rawvideo -pix_fmt rgba -s 1280x960 -r 30 -i - run.mp4`
+
+use std::io::Write;
+use macroquad::prelude::*;
+use crate::{capture, error, GameField, Level, Replay, Result, Rules};
+use crate::Script;
+
+/// Default directory the frames get written to, `-` writes them to stdout
+const DEFAULT_OUT: &str = "frames";
+
+/// Default size of the frames
+const DEFAULT_SIZE: (u32, u32) = (800, 600);
+
+/// Default frames per second of the video
+const DEFAULT_FPS: u32 = 60;
+
+/// Seconds between progress updates
+const REPORT_TIME: f64 = 0.25;
+
+/// Run the `render-replay` subcommand with the arguments following it, which
+/// needs a window to render with
+pub async fn run(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let mut out = DEFAULT_OUT.to_string();
+    let mut size = DEFAULT_SIZE;
+    let mut fps = DEFAULT_FPS;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |what: &str| {
+            args.next().ok_or_else(|| format!("{} requires {}", arg, what))
+        };
+        match arg.as_str() {
+            "--out"  => out = value("a directory or -")?.clone(),
+            "--size" => {
+                let value = value("a size like 800x600")?;
+                let (width, height) = value.split_once('x')
+                    .ok_or_else(|| format!("Malformed size {:?}", value))?;
+                size = (width.parse()?, height.parse()?);
+                if size.0 == 0 || size.1 == 0 {
+                    return Err(format!("Empty size {:?}", value).into());
+                }
+            }
+            "--fps"  => {
+                fps = value("a frame rate")?.parse()?;
+                if fps == 0 {
+                    return Err("The frame rate has to be positive".into());
+                }
+            }
+            "--verbose" => crate::log::verbose(),
+            _ if path.is_none() && !arg.starts_with("--") => {
+                path = Some(arg.clone());
+            }
+            _ => {
+                return Err(format!("Unknown render-replay argument {:?}",
+                    arg).into());
+            }
+        }
+    }
+    let path = path.ok_or("render-replay requires a replay file")?;
+
+    // Replays play on their own rules, level, and script
+    let replay = Replay::load(&path, Rules::default())?;
+    let mut field = GameField::new(replay.rules);
+    field.level = replay.level.as_ref().map(|x| Level::load(x)).transpose()?;
+    field.script = replay.script.as_ref()
+        .map(|(path, hash)| Script::load_recorded(path, *hash))
+        .transpose()?;
+    field.votes = replay.votes.clone();
+    let to_stdout = out == "-";
+    if !to_stdout {
+        std::fs::create_dir_all(&out).map_err(|err| error::Error::Io {
+            what: "frame directory", path: out.clone(), err,
+        })?;
+    }
+
+    // Each video frame shows the physics frame at its time, running out of
+    // inputs the same way as in the game
+    let mut stdout = std::io::stdout().lock();
+    let mut reported = get_time();
+    for frame in 0u64.. {
+        let due = frame * field.rules.tick_rate as u64 / fps as u64;
+        while field.physics_frames < due && !field.dead && !field.finished {
+            let input = replay.inputs.get(field.inputs.len()).copied();
+            field.step(input.unwrap_or(0));
+        }
+        if field.physics_frames < due {
+            eprintln!("Rendered {} frames of {} to {}", frame, path, out);
+            break;
+        }
+
+        field.build_objects();
+        let shot = capture::field_image_sized(&field, size.0, size.1);
+        if to_stdout {
+            stdout.write_all(&capture::upright(&shot))
+                .map_err(|err| error::Error::Io {
+                    what: "video", path: out.clone(), err,
+                })?;
+        } else {
+            capture::save("frame", &format!("{}/frame_{:06}.png", out,
+                frame), &shot)?;
+        }
+
+        // Keep the window alive and show how far along the replay is
+        if get_time() - reported >= REPORT_TIME {
+            reported = get_time();
+            clear_background(BLACK);
+            draw_text(&format!("Rendering {} | Frame {} | {} of {} inputs",
+                path, frame, field.physics_frames.min(
+                    replay.inputs.len() as u64), replay.inputs.len()),
+                10., 30., 24., WHITE);
+            next_frame().await;
+        }
+    }
+    Ok(())
+}
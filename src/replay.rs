@@ -0,0 +1,391 @@
+//! Replay files. A replay is a single header line describing the [`Rules`] of
+//! the run, followed by one input byte per physics frame
+
+use std::collections::VecDeque;
+use crate::{Control, Craft, Curve, Edges, Fxpt, Mode, Mutator, Preset};
+use crate::{Result, Rules, Upgrade};
+use crate::error;
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use crate::{INPUT_THRUST, MAX_LOOKAHEAD, MAX_START_FRAME, TICK_RATES};
+use crate::{ADAPT_MAX, MAX_FORCE, MAX_FRICTION, MAX_GAP, MAX_SCROLL_SPEED};
+use crate::{MAX_REPLAY_FRAMES, MIN_GAP};
+
+/// Magic at the start of a replay header. Files without it are treated as
+/// raw input bytes
+const MAGIC: &str = "HELIREPLAY";
+
+/// Version of the replay header format. Version 1 (and replays without a
+/// header) store inputs as ASCII `'0'` and `'1'` for thrusting, version 2
+/// stores a bitmask of `INPUT_*` bits
+const VERSION: u32 = 2;
+
+/// A recorded run
+pub struct Replay {
+    /// Rules the run was played with
+    pub rules: Rules,
+
+    /// Path of the level file the run was played on, if any
+    pub level: Option<String>,
+
+    /// Bitmask of `INPUT_*` bits for each physics frame
+    pub inputs: VecDeque<u8>,
+
+    /// Physics frames mutators were voted in on, see [`crate::GameField`]
+    pub votes: Vec<(u64, Mutator)>,
+
+    /// Path and hash of the script the run was played with, if any
+    pub script: Option<(String, u64)>,
+
+    /// Physics frame the run ended on, if it did. Playing the replay back has
+    /// to end on the same frame
+    pub frames: Option<u64>,
+
+    /// Integrity hash the run ended with, if it did, see
+    /// [`crate::GameField`]. Playing the replay back has to end with the same
+    /// hash
+    pub integrity: Option<u64>,
+}
+
+impl Replay {
+    /// Load the replay file at `path`, see [`Replay::parse`]
+    pub fn load(path: &str, default: Rules) -> Result<Self> {
+        Self::parse(&error::read("replay", path)?, default)
+            .map_err(|err| err.in_file("replay", path))
+    }
+
+    /// Parse a replay file. Files from before replays had a header are played
+    /// with the `default` rules
+    pub fn parse(bytes: &[u8], default: Rules) -> Result<Self> {
+        if !bytes.starts_with(MAGIC.as_bytes()) {
+            return Ok(Self {
+                rules:     default,
+                level:     None,
+                inputs:    ascii_inputs(bytes),
+                votes:     Vec::new(),
+                script:    None,
+                frames:    None,
+                integrity: None,
+            });
+        }
+
+        // Split off the header line
+        let header_len = bytes.iter().position(|&x| x == b'\n')
+            .ok_or("Replay header is not terminated")?;
+        let header = std::str::from_utf8(&bytes[..header_len])?;
+        let inputs = &bytes[header_len + 1..];
+
+        let mut fields = header.split_whitespace().skip(1);
+        let version: u32 = fields.next()
+            .ok_or("Replay header is missing a version")?.parse()?;
+        let inputs = match version {
+            1 => ascii_inputs(inputs),
+            VERSION => inputs.to_vec().into(),
+            _ => {
+                return Err(format!("Unsupported replay version {}",
+                    version).into());
+            }
+        };
+
+        // Everything else is `key=value` pairs, missing keys keep their
+        // defaults
+        let mut rules = Rules::default();
+        let mut level = None;
+        let mut votes = Vec::new();
+        let mut script = None;
+        let mut script_hash = None;
+        let mut frames = None;
+        let mut integrity = None;
+        for field in fields {
+            let (key, value) = field.split_once('=')
+                .ok_or_else(|| format!("Malformed replay field {:?}", field))?;
+
+            match key {
+                "votes"       => {
+                    for vote in value.split(',') {
+                        let (frame, name) = vote.split_once(':')
+                            .ok_or_else(|| {
+                                format!("Malformed vote {:?}", vote)
+                            })?;
+                        votes.push((frame.parse()?, Mutator::from_name(name)
+                            .ok_or_else(|| {
+                                format!("Unknown mutator {:?}", name)
+                            })?));
+                    }
+                }
+                "level"       => level = Some(value.to_string()),
+                "frames"      => {
+                    frames = Some(parse_in(key, value,
+                        0..=MAX_REPLAY_FRAMES)?);
+                }
+                "integrity"   =>
+                    integrity = Some(u64::from_str_radix(value, 16)?),
+                "script"      => script = Some(value.to_string()),
+                "script_hash" =>
+                    script_hash = Some(u64::from_str_radix(value, 16)?),
+                _ if parse_rule(&mut rules, key, value)? => {}
+                _ => {
+                    return Err(format!("Unknown replay field {:?}",
+                        key).into());
+                }
+            }
+        }
+
+        let script = match (script, script_hash) {
+            (Some(path), Some(hash)) => Some((path, hash)),
+            (None, None) => None,
+            _ => return Err("Replay script needs a path and a hash".into()),
+        };
+
+        Ok(Self { rules, level, inputs, votes, script, frames, integrity })
+    }
+
+    /// Serialize the replay into the bytes of a replay file
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut fields = rule_fields(&self.rules);
+        if !self.votes.is_empty() {
+            fields.push(("votes", self.votes.iter()
+                .map(|(frame, mutator)| {
+                    format!("{}:{}", frame, mutator.name())
+                })
+                .collect::<Vec<_>>().join(",")));
+        }
+        if let Some(level) = &self.level {
+            fields.push(("level", level.clone()));
+        }
+        if let Some((path, hash)) = &self.script {
+            fields.push(("script", path.clone()));
+            fields.push(("script_hash", format!("{:016x}", hash)));
+        }
+        if let Some(frames) = self.frames {
+            fields.push(("frames", frames.to_string()));
+        }
+        if let Some(integrity) = self.integrity {
+            fields.push(("integrity", format!("{:016x}", integrity)));
+        }
+
+        let mut header = format!("{} {}", MAGIC, VERSION);
+        for (key, value) in fields.iter() {
+            header += &format!(" {}={}", key, value);
+        }
+        header.push('\n');
+
+        let mut ret = header.into_bytes();
+        ret.extend(self.inputs.iter());
+        ret
+    }
+}
+
+/// Header fields describing `rules`, see [`parse_rule`]
+pub fn rule_fields(rules: &Rules) -> Vec<(&'static str, String)> {
+    let difficulty = &rules.difficulty;
+    let mut fields = vec![
+        ("mode",        rules.mode.name().to_string()),
+        ("preset",      rules.preset.name().to_string()),
+        ("start_gap",   difficulty.start_gap.to_string()),
+        ("min_gap",     difficulty.min_gap.to_string()),
+        ("gap_shrink",  difficulty.gap_shrink_frames.to_string()),
+        ("scroll",      difficulty.scroll_speed.0.to_string()),
+        ("max_scroll",  difficulty.max_scroll_speed.0.to_string()),
+        ("scroll_ramp", difficulty.scroll_ramp_frames.to_string()),
+        ("gravity",     difficulty.gravity.0.to_string()),
+        ("impulse",     difficulty.impulse.0.to_string()),
+        ("friction",    difficulty.friction.0.to_string()),
+        ("obstacles",   difficulty.obstacle_frames.to_string()),
+        ("smooth",      (rules.smooth_walls as u8).to_string()),
+        ("seed",        format!("{:016x}", rules.seed)),
+    ];
+    if let Some(curve) = &difficulty.curve {
+        fields.push(("curve", curve.encode()));
+    }
+    if rules.drafts {
+        fields.push(("drafts", "1".to_string()));
+    }
+    if rules.biomes {
+        fields.push(("biomes", "1".to_string()));
+    }
+    if rules.mutators != 0 {
+        fields.push(("mutators", Mutator::ALL.iter()
+            .filter(|x| rules.has(**x))
+            .map(|x| x.name())
+            .collect::<Vec<_>>().join(",")));
+    }
+    if let Some(ease) = rules.adaptive {
+        fields.push(("adaptive", ease.to_string()));
+    }
+    if rules.control != Control::Hold {
+        fields.push(("control", rules.control.name().to_string()));
+    }
+    if rules.upgrades.iter().any(|&x| x != 0) {
+        fields.push(("upgrades", Upgrade::ALL.iter()
+            .zip(rules.upgrades)
+            .filter(|(_, level)| *level != 0)
+            .map(|(x, level)| format!("{}:{}", x.name(), level))
+            .collect::<Vec<_>>().join(",")));
+    }
+    if rules.craft != Craft::Standard {
+        fields.push(("craft", rules.craft.name().to_string()));
+    }
+    if rules.edges != Edges::Safe {
+        fields.push(("edges", rules.edges.name().to_string()));
+    }
+    if rules.lookahead != 0 {
+        fields.push(("lookahead", rules.lookahead.to_string()));
+    }
+    if rules.tick_rate != TICK_RATES[0] {
+        fields.push(("tick", rules.tick_rate.to_string()));
+    }
+    if rules.start_frame != 0 {
+        fields.push(("start", rules.start_frame.to_string()));
+    }
+    if let Some(frame) = rules.continued {
+        fields.push(("continued", frame.to_string()));
+    }
+    fields
+}
+
+/// Parse the header field `key` describing part of `rules`, returning if it
+/// was one. The preset resets the difficulty, so it has to come before any
+/// difficulty fields
+pub fn parse_rule(rules: &mut Rules, key: &str, value: &str) -> Result<bool> {
+    let difficulty = &mut rules.difficulty;
+    match key {
+        "mode" => {
+            rules.mode = Mode::from_name(value)
+                .ok_or_else(|| format!("Unknown mode {:?}", value))?;
+        }
+        "control" => {
+            rules.control = Control::from_name(value)
+                .ok_or_else(|| format!("Unknown control {:?}", value))?;
+        }
+        "upgrades" => rules.upgrades = parse_upgrades(value)?,
+        "craft" => {
+            rules.craft = Craft::from_name(value)
+                .ok_or_else(|| format!("Unknown craft {:?}", value))?;
+        }
+        "edges" => {
+            rules.edges = Edges::from_name(value)
+                .ok_or_else(|| format!("Unknown edges {:?}", value))?;
+        }
+        "preset" => {
+            rules.preset = Preset::from_name(value)
+                .ok_or_else(|| format!("Unknown preset {:?}", value))?;
+            *difficulty = rules.preset.difficulty();
+        }
+        "start_gap"   => {
+            difficulty.start_gap = parse_in(key, value, MIN_GAP..=MAX_GAP)?;
+        }
+        "min_gap"     => {
+            difficulty.min_gap = parse_in(key, value, MIN_GAP..=MAX_GAP)?;
+        }
+        "gap_shrink"  => difficulty.gap_shrink_frames = value.parse()?,
+        "scroll"      => {
+            difficulty.scroll_speed =
+                Fxpt(parse_in(key, value, 1..=MAX_SCROLL_SPEED.0)?);
+        }
+        "max_scroll"  => {
+            difficulty.max_scroll_speed =
+                Fxpt(parse_in(key, value, 1..=MAX_SCROLL_SPEED.0)?);
+        }
+        "scroll_ramp" => difficulty.scroll_ramp_frames = value.parse()?,
+        "gravity"     => {
+            difficulty.gravity = Fxpt(parse_in(key, value, 0..=MAX_FORCE.0)?);
+        }
+        "impulse"     => {
+            difficulty.impulse = Fxpt(parse_in(key, value, 0..=MAX_FORCE.0)?);
+        }
+        "friction"    => {
+            difficulty.friction =
+                Fxpt(parse_in(key, value, 0..=MAX_FRICTION.0)?);
+        }
+        "obstacles"   => difficulty.obstacle_frames = value.parse()?,
+        "curve"       => difficulty.curve = Some(Curve::decode(value)?),
+        "smooth"      => rules.smooth_walls = parse_bool(value)?,
+        "drafts"      => rules.drafts = parse_bool(value)?,
+        "biomes"      => rules.biomes = parse_bool(value)?,
+        "adaptive"    => {
+            rules.adaptive =
+                Some(parse_in(key, value, -ADAPT_MAX..=ADAPT_MAX)?);
+        }
+        "continued"   => rules.continued = Some(value.parse()?),
+        "lookahead"   => {
+            rules.lookahead = value.parse()?;
+            if rules.lookahead > MAX_LOOKAHEAD {
+                return Err(format!("Lookahead of {} columns is past \
+                    the limit of {}", value, MAX_LOOKAHEAD).into());
+            }
+        }
+        "start"       => {
+            rules.start_frame = value.parse()?;
+            if rules.start_frame > MAX_START_FRAME {
+                return Err(format!("Start frame {} is past the limit of {}",
+                    value, MAX_START_FRAME).into());
+            }
+        }
+        "tick"        => {
+            rules.tick_rate = value.parse()?;
+            if !TICK_RATES.contains(&rules.tick_rate) {
+                return Err(format!("Unsupported tick rate of {} Hz",
+                    value).into());
+            }
+        }
+        "seed"        => rules.seed = u64::from_str_radix(value, 16)?,
+        "mutators"    => {
+            for name in value.split(',') {
+                rules.mutators |= Mutator::from_name(name)
+                    .ok_or_else(|| format!("Unknown mutator {:?}", name))?
+                    .bit();
+            }
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Parse the `value` of the header field `key`, which has to be in `range`
+fn parse_in<T: FromStr + PartialOrd + Display>(key: &str, value: &str,
+        range: RangeInclusive<T>) -> Result<T>
+        where crate::Error: From<T::Err> {
+    let parsed = value.parse()?;
+    if !range.contains(&parsed) {
+        return Err(format!("{} of {} is not between {} and {}", key, value,
+            range.start(), range.end()).into());
+    }
+    Ok(parsed)
+}
+
+/// Parse comma separated `name:level` pairs of [`Upgrade`]s
+fn parse_upgrades(value: &str) -> Result<[u8; Upgrade::ALL.len()]> {
+    let mut levels = [0; Upgrade::ALL.len()];
+    for pair in value.split(',') {
+        let (name, level) = pair.split_once(':')
+            .ok_or_else(|| format!("Upgrade {:?} has no level", pair))?;
+        let upgrade = Upgrade::from_name(name)
+            .ok_or_else(|| format!("Unknown upgrade {:?}", name))?;
+        let level: u8 = level.parse()?;
+        if level > upgrade.max_level() {
+            return Err(format!("Upgrade {} only goes up to level {}",
+                name, upgrade.max_level()).into());
+        }
+        levels[upgrade as usize] = level;
+    }
+    Ok(levels)
+}
+
+/// Parse a `0` or `1` flag from a replay header
+fn parse_bool(value: &str) -> Result<bool> {
+    match value {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _   => Err(format!("Invalid replay flag {:?}", value).into()),
+    }
+}
+
+/// Convert inputs from replays which stored only thrust as ASCII `'0'` and
+/// `'1'` into input bitmasks
+fn ascii_inputs(bytes: &[u8]) -> VecDeque<u8> {
+    bytes.iter()
+        .map(|&x| if x == b'1' { INPUT_THRUST } else { 0 })
+        .collect()
+}
@@ -0,0 +1,82 @@
+//! Rumble on deaths, near-misses, and pickups. macroquad can't talk to
+//! gamepads, so the browser does it on WASM, through the vibration actuator of
+//! any connected gamepad or else the phone's vibration motor. Native builds
+//! don't rumble
+
+use crate::GameField;
+
+/// Strength (from 0 to 1) and seconds of the pulse for a lost life
+const DEATH_PULSE: (f32, f32) = (1.0, 0.4);
+
+/// Strength and seconds of the pulse for a near-miss
+const NEAR_MISS_PULSE: (f32, f32) = (0.3, 0.08);
+
+/// Strength and seconds of the pulse for a collected pickup
+const PICKUP_PULSE: (f32, f32) = (0.5, 0.12);
+
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    /// Rumble at `strength` from 0 to 1 for `millis` milliseconds, provided
+    /// by the plugin in `index.html`
+    fn mqtest_rumble(strength: f32, millis: u32);
+}
+
+/// What was seen of a run, to tell when something happens
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Seen {
+    lives:       u8,
+    dead:        bool,
+    near_misses: u64,
+    pickups:     u64,
+}
+
+/// Rumble for a single run
+pub struct Rumble {
+    /// Strength in percent all pulses are scaled by, 0 is off
+    strength: u8,
+
+    /// What had happened in the run as of the last rendered frame
+    seen: Option<Seen>,
+}
+
+impl Rumble {
+    /// Rumble at `strength` percent
+    pub fn new(strength: u8) -> Self {
+        Self { strength, seen: None }
+    }
+
+    /// Rumble for whatever happened in `field` since the last rendered frame,
+    /// the strongest pulse wins if several things happened at once
+    pub fn update(&mut self, field: &GameField) {
+        let now = Seen {
+            lives:       field.lives,
+            dead:        field.dead,
+            near_misses: field.near_misses,
+            pickups:     field.pickups_collected,
+        };
+        let Some(seen) = self.seen.replace(now) else { return };
+        if self.strength == 0 || seen == now {
+            return;
+        }
+
+        let pulse = if now.lives < seen.lives || now.dead && !seen.dead {
+            DEATH_PULSE
+        } else if now.pickups > seen.pickups {
+            PICKUP_PULSE
+        } else if now.near_misses > seen.near_misses {
+            NEAR_MISS_PULSE
+        } else {
+            return;
+        };
+        pulse_for(pulse.0 * self.strength as f32 / 100., pulse.1);
+    }
+}
+
+/// Rumble at `strength` from 0 to 1 for `seconds`
+fn pulse_for(strength: f32, seconds: f32) {
+    log_debug!("Rumble at {:.2} for {:.2} s", strength, seconds);
+    #[cfg(target_arch = "wasm32")]
+    unsafe {
+        mqtest_rumble(strength, (seconds * 1000.) as u32);
+    }
+}
@@ -0,0 +1,270 @@
+//! Sandbox for trying out how the game feels, where sliders over the field
+//! tune the physics and generation of the run while it's being played.
+//! Sandbox runs are debug runs. The tuning gets exported to a file of
+//! `key value` lines like the settings, in the raw fixed point of the
+//! simulation, which `--tuning` loads back on top of the preset:
+//!
+//! ```text
+//! gravity  64
+//! friction 30
+//! gap      250
+//! ```
+
+use macroquad::prelude::*;
+use crate::{error, field_transform, Difficulty, GameField, Result};
+use crate::{FIXED_POINT_DIVISOR, GAME_FIELD_WIDTH, MAX_FORCE, MAX_FRICTION};
+use crate::{MAX_GAP, MAX_SCROLL_SPEED, MIN_GAP};
+
+/// File the tuning gets exported to
+pub const TUNING_FILE: &str = "tuning.txt";
+
+/// Size (in field pixels) of the text of the sliders
+const TEXT_SIZE: f32 = 16.;
+
+/// Width (in field pixels) of the bar of a slider
+const BAR_WIDTH: f32 = 160.;
+
+/// Height (in field pixels) of every slider, its label above its bar
+const ROW_HEIGHT: f32 = 36.;
+
+/// Parameters of the [`Difficulty`] the sliders tune
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Knob {
+    /// Downwards acceleration of the player
+    Gravity,
+
+    /// Fraction of the player's speed kept every physics frame
+    Friction,
+
+    /// Upwards acceleration of thrusting
+    Impulse,
+
+    /// Scroll speed, which stays put rather than ramping up
+    Scroll,
+
+    /// Gap between the walls, which stays put rather than shrinking
+    Gap,
+
+    /// Least physics frames between obstacles
+    Spawn,
+}
+
+impl Knob {
+    /// Every knob, in the order of the sliders
+    const ALL: [Knob; 6] = [
+        Knob::Gravity, Knob::Friction, Knob::Impulse, Knob::Scroll, Knob::Gap,
+        Knob::Spawn,
+    ];
+
+    /// Name of the knob on its slider and in the tuning file
+    fn name(&self) -> &'static str {
+        match self {
+            Knob::Gravity  => "gravity",
+            Knob::Friction => "friction",
+            Knob::Impulse  => "impulse",
+            Knob::Scroll   => "scroll",
+            Knob::Gap      => "gap",
+            Knob::Spawn    => "spawn",
+        }
+    }
+
+    /// Look up a knob by its [`Knob::name`]
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|x| x.name() == name)
+    }
+
+    /// Least and most raw value the slider goes between, within what replay
+    /// headers allow
+    fn range(&self) -> (i64, i64) {
+        let fxpt = FIXED_POINT_DIVISOR as i64;
+        match self {
+            Knob::Gravity  => (fxpt / 4, MAX_FORCE.0 as i64),
+            Knob::Friction => (fxpt / 2, MAX_FRICTION.0 as i64),
+            Knob::Impulse  => (fxpt / 4, MAX_FORCE.0 as i64),
+            Knob::Scroll   => (fxpt, MAX_SCROLL_SPEED.0 as i64),
+            Knob::Gap      => (MIN_GAP as i64, MAX_GAP as i64),
+            Knob::Spawn    => (1, 120),
+        }
+    }
+
+    /// Raw value of the knob in `difficulty`
+    fn get(&self, difficulty: &Difficulty) -> i64 {
+        match self {
+            Knob::Gravity  => difficulty.gravity.0 as i64,
+            Knob::Friction => difficulty.friction.0 as i64,
+            Knob::Impulse  => difficulty.impulse.0 as i64,
+            Knob::Scroll   => difficulty.scroll_speed.0 as i64,
+            Knob::Gap      => difficulty.start_gap as i64,
+            Knob::Spawn    => difficulty.obstacle_frames as i64,
+        }
+    }
+
+    /// Set the knob in `difficulty` to the raw `value`, clamped to its range
+    fn set(&self, difficulty: &mut Difficulty, value: i64) {
+        let (min, max) = self.range();
+        let value = value.clamp(min, max);
+        match self {
+            Knob::Gravity  => difficulty.gravity.0 = value as i16,
+            Knob::Friction => difficulty.friction.0 = value as i16,
+            Knob::Impulse  => difficulty.impulse.0 = value as i16,
+            Knob::Scroll   => {
+                difficulty.scroll_speed.0 = value as i16;
+                difficulty.max_scroll_speed.0 = value as i16;
+            }
+            Knob::Gap      => {
+                difficulty.start_gap = value as i16;
+                difficulty.min_gap = value as i16;
+            }
+            Knob::Spawn    => difficulty.obstacle_frames = value as u64,
+        }
+
+        // The curve would override the scroll speed, gap, and cadence
+        difficulty.curve = None;
+    }
+
+    /// Raw `value` of the knob as shown on its slider, forces and speeds in
+    /// pixels
+    fn show(&self, value: i64) -> String {
+        match self {
+            Knob::Gap | Knob::Spawn => value.to_string(),
+            _ => format!("{:.2}", value as f64 / FIXED_POINT_DIVISOR as f64),
+        }
+    }
+}
+
+/// Values of the knobs loaded from a tuning file, to apply on top of the
+/// difficulty of the preset
+#[derive(Clone, Copy, Default)]
+pub struct Tuning([Option<i64>; Knob::ALL.len()]);
+
+impl Tuning {
+    /// Load the tuning file at `path`
+    pub fn load(path: &str) -> Result<Self> {
+        Self::parse(&error::read_to_string("tuning", path)?)
+            .map_err(|err| err.in_file("tuning", path))
+    }
+
+    /// Parse a tuning file, knobs which aren't in it keep their values
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut tuning = Self::default();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line.split_once(char::is_whitespace)
+                .map(|(key, value)| (key, value.trim()))
+                .ok_or_else(|| format!("{:?} has no value", line))?;
+            let knob = Knob::from_name(key)
+                .ok_or_else(|| format!("Unknown knob {:?}", key))?;
+            tuning.0[knob as usize] = Some(value.parse()?);
+        }
+        Ok(tuning)
+    }
+
+    /// Set the knobs of the tuning in `difficulty`
+    pub fn apply(&self, difficulty: &mut Difficulty) {
+        for (knob, value) in Knob::ALL.iter().zip(self.0) {
+            if let Some(value) = value {
+                knob.set(difficulty, value);
+            }
+        }
+    }
+}
+
+/// Sliders over the field which tune the run being played
+#[derive(Default)]
+pub struct Sandbox {
+    /// Knob of the slider being dragged
+    dragging: Option<Knob>,
+
+    /// Outcome of the last export
+    status: String,
+}
+
+impl Sandbox {
+    /// Screen coords of the left end of the bar of the slider for `knob`,
+    /// vertically centered, and the width of the bar
+    fn bar(knob: Knob) -> (f32, f32, f32) {
+        let (offset_x, offset_y, scale) = field_transform();
+        let row = Knob::ALL.iter().position(|&x| x == knob).unwrap_or(0);
+        let x = offset_x + (f32::from(GAME_FIELD_WIDTH) - BAR_WIDTH - 16.) *
+            scale;
+        let y = offset_y + (64. + (row as f32 + 1.) * ROW_HEIGHT) * scale;
+        (x, y, BAR_WIDTH * scale)
+    }
+
+    /// Drag the sliders with the mouse, tuning the rules of `field` right
+    /// away, and export the tuning with F5. The run becomes a debug run
+    pub fn update(&mut self, field: &mut GameField) {
+        field.debug = true;
+
+        let (mouse_x, mouse_y) = mouse_position();
+        let (_, _, scale) = field_transform();
+        if is_mouse_button_pressed(MouseButton::Left) {
+            self.dragging = Knob::ALL.iter().copied().find(|&knob| {
+                let (x, y, width) = Self::bar(knob);
+                (x..=x + width).contains(&mouse_x) &&
+                    (mouse_y - y).abs() <= ROW_HEIGHT * scale / 4.
+            });
+        }
+        if !is_mouse_button_down(MouseButton::Left) {
+            self.dragging = None;
+        }
+
+        if let Some(knob) = self.dragging {
+            let (x, _, width) = Self::bar(knob);
+            let (min, max) = knob.range();
+            let at = ((mouse_x - x) / width).clamp(0., 1.);
+            let value = min + ((max - min) as f32 * at).round() as i64;
+            knob.set(&mut field.rules.difficulty, value);
+            knob.set(&mut field.base_rules.difficulty, value);
+        }
+
+        if is_key_pressed(KeyCode::F5) {
+            let text = Knob::ALL.iter().map(|knob| {
+                format!("{:<8} {}\n", knob.name(),
+                    knob.get(&field.rules.difficulty))
+            }).collect::<String>();
+            self.status = match error::write("tuning", TUNING_FILE, text) {
+                Ok(())   => format!("Exported to {}", TUNING_FILE),
+                Err(err) => {
+                    log_error!("{}", err);
+                    err.to_string()
+                }
+            };
+        }
+    }
+
+    /// Draw the sliders with the values the rules of `field` have
+    pub fn draw(&self, field: &GameField) {
+        let (_, _, scale) = field_transform();
+        let size = TEXT_SIZE * scale;
+        for &knob in Knob::ALL.iter() {
+            let (x, y, width) = Self::bar(knob);
+            let (min, max) = knob.range();
+            let value = knob.get(&field.rules.difficulty);
+            let at = (value - min) as f32 / (max - min) as f32;
+            let color = if self.dragging == Some(knob) {
+                YELLOW
+            } else {
+                WHITE
+            };
+
+            draw_text(&format!("{} {}", knob.name(), knob.show(value)), x,
+                y - 6. * scale, size, color);
+            draw_rectangle(x, y, width, 3. * scale,
+                Color::from_rgba(0xff, 0xff, 0xff, 0x60));
+            draw_circle(x + width * at.clamp(0., 1.), y + 1.5 * scale,
+                6. * scale, color);
+        }
+
+        let (x, y, _) = Self::bar(Knob::ALL[Knob::ALL.len() - 1]);
+        let status = if self.status.is_empty() {
+            "F5 to export the tuning"
+        } else {
+            &self.status
+        };
+        draw_text(status, x, y + ROW_HEIGHT * scale, size, GRAY);
+    }
+}
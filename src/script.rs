@@ -0,0 +1,571 @@
+//! Scripts with custom rules, so runs can be modded without rebuilding the
+//! game. A script is a text file of rules, one per line, each doing an action
+//! whenever its hook fires and its condition (if it has one) holds:
+//!
+//! ```text
+//! # Obstacles are worth double after frame 1000
+//! on_tick  frame >= 1000 => set pass_bonus 50
+//! on_spawn obstacle_height > 60 => bonus 10
+//! on_death => set deaths deaths + 1
+//! set      gravity 3
+//! ```
+//!
+//! `on_tick` fires at the start of every physics frame, `on_spawn` whenever
+//! an obstacle spawns, and `on_death` whenever a life is lost. Lines starting
+//! with `set` override a parameter when the run starts. Actions either `set`
+//! a parameter or a variable of the script's own, or award a `bonus`.
+//!
+//! Expressions are integer math with `+ - * / %`, comparisons, `and`, `or`,
+//! and parentheses. They can use the parameters, the script's variables, and
+//! `frame`, `score`, `lives`, `coins`, `combo`, `stage`, and `player_y`, plus
+//! `obstacle_y` and `obstacle_height` in `on_spawn`. Positions and sizes are
+//! in pixels, `gravity` and `impulse` are in the fixed point units of the
+//! replay header. Math wraps around rather than overflowing and dividing by
+//! zero gives zero, so scripts are as deterministic as the simulation. Replays
+//! record the hash of the script and only play back with the same one
+
+use crate::{fnv1a, Error, Result, FNV_OFFSET};
+use crate::error;
+use crate::state::{Deserialize, Reader, Serialize, Writer};
+
+/// Parameters of the simulation scripts can override
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    /// Bonus for getting past an obstacle
+    PassBonus,
+
+    /// Bonus for a near-miss, before the combo multiplier
+    NearMissBonus,
+
+    /// Gravity of the player physics
+    Gravity,
+
+    /// Impulse of thrusting
+    Impulse,
+
+    /// Minimum number of physics frames between obstacles
+    ObstacleFrames,
+}
+
+impl Param {
+    /// Every parameter, in the order of their discriminants
+    pub const ALL: [Param; 5] = [
+        Param::PassBonus,
+        Param::NearMissBonus,
+        Param::Gravity,
+        Param::Impulse,
+        Param::ObstacleFrames,
+    ];
+
+    /// Name of the parameter in scripts
+    pub fn name(&self) -> &'static str {
+        match self {
+            Param::PassBonus      => "pass_bonus",
+            Param::NearMissBonus  => "near_miss_bonus",
+            Param::Gravity        => "gravity",
+            Param::Impulse        => "impulse",
+            Param::ObstacleFrames => "obstacle_frames",
+        }
+    }
+}
+
+/// When rules run
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// Once, when the run starts
+    Start,
+
+    /// At the start of every physics frame
+    Tick,
+
+    /// When an obstacle spawns
+    Spawn,
+
+    /// When a life is lost
+    Death,
+}
+
+impl Hook {
+    /// Get the hook named `name` in scripts
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "on_tick"  => Hook::Tick,
+            "on_spawn" => Hook::Spawn,
+            "on_death" => Hook::Death,
+            _ => return None,
+        })
+    }
+}
+
+/// State of the game scripts can read, with positions and sizes in pixels
+#[derive(Default)]
+pub struct Values {
+    /// Current physics frame
+    pub frame: i64,
+
+    /// Current score
+    pub score: i64,
+
+    /// Lives left
+    pub lives: i64,
+
+    /// Coins collected
+    pub coins: i64,
+
+    /// Obstacles passed in a row
+    pub combo: i64,
+
+    /// Current stage, counting from zero
+    pub stage: i64,
+
+    /// Y coord of the top of the player
+    pub player_y: i64,
+
+    /// Y coord of the top of the obstacle which spawned
+    pub obstacle_y: i64,
+
+    /// Height of the obstacle which spawned
+    pub obstacle_height: i64,
+
+    /// Value of each of [`Param::ALL`] unless the script overrides it
+    pub params: [i64; Param::ALL.len()],
+}
+
+/// Something an expression can read
+#[derive(Clone, Copy)]
+enum Var {
+    Frame,
+    Score,
+    Lives,
+    Coins,
+    Combo,
+    Stage,
+    PlayerY,
+    ObstacleY,
+    ObstacleHeight,
+    Param(Param),
+
+    /// Index of one of the script's own variables
+    Variable(usize),
+}
+
+impl Var {
+    /// Get the value named `name`, where `variables` are the names of the
+    /// script's own variables
+    fn from_name(name: &str, variables: &[String]) -> Option<Self> {
+        Some(match name {
+            "frame"           => Var::Frame,
+            "score"           => Var::Score,
+            "lives"           => Var::Lives,
+            "coins"           => Var::Coins,
+            "combo"           => Var::Combo,
+            "stage"           => Var::Stage,
+            "player_y"        => Var::PlayerY,
+            "obstacle_y"      => Var::ObstacleY,
+            "obstacle_height" => Var::ObstacleHeight,
+            _ => {
+                if let Some(param) = Param::ALL.iter()
+                        .find(|x| x.name() == name) {
+                    Var::Param(*param)
+                } else {
+                    Var::Variable(variables.iter()
+                        .position(|x| x == name)?)
+                }
+            }
+        })
+    }
+}
+
+/// Binary operators, see [`binary`]
+#[derive(Clone, Copy)]
+enum Op {
+    Or, And, Eq, Ne, Lt, Le, Gt, Ge, Add, Sub, Mul, Div, Rem,
+}
+
+/// Get the operator of `token` and how tightly it binds, if it is one
+fn binary(token: &str) -> Option<(Op, u8)> {
+    Some(match token {
+        "or"  => (Op::Or,  1),
+        "and" => (Op::And, 2),
+        "=="  => (Op::Eq,  3),
+        "!="  => (Op::Ne,  3),
+        "<"   => (Op::Lt,  3),
+        "<="  => (Op::Le,  3),
+        ">"   => (Op::Gt,  3),
+        ">="  => (Op::Ge,  3),
+        "+"   => (Op::Add, 4),
+        "-"   => (Op::Sub, 4),
+        "*"   => (Op::Mul, 5),
+        "/"   => (Op::Div, 5),
+        "%"   => (Op::Rem, 5),
+        _ => return None,
+    })
+}
+
+#[derive(Clone)]
+enum Expr {
+    Num(i64),
+    Var(Var),
+    Neg(Box<Expr>),
+    Bin(Op, Box<Expr>, Box<Expr>),
+}
+
+/// Split an expression into numbers, names, operators, and parentheses
+fn tokenize(text: &str) -> Result<Vec<&str>> {
+    let mut ret = Vec::new();
+    let mut rest = text.trim_start();
+    while let Some(chr) = rest.chars().next() {
+        let len = if chr.is_ascii_alphanumeric() || chr == '_' {
+            rest.find(|x: char| !x.is_ascii_alphanumeric() && x != '_')
+                .unwrap_or(rest.len())
+        } else if ["==", "!=", "<=", ">="].iter()
+                .any(|x| rest.starts_with(x)) {
+            2
+        } else if "+-*/%<>()".contains(chr) {
+            1
+        } else {
+            return Err(format!("Unexpected {:?}", chr).into());
+        };
+        ret.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    Ok(ret)
+}
+
+/// Precedence climbing parser of expressions
+struct Parser<'a> {
+    /// Tokens of the expression
+    tokens: Vec<&'a str>,
+
+    /// Index of the next token
+    pos: usize,
+
+    /// Names of the script's own variables
+    variables: &'a [String],
+}
+
+impl<'a> Parser<'a> {
+    /// Parse the whole expression `text`
+    fn parse(text: &'a str, variables: &'a [String]) -> Result<Expr> {
+        let mut parser = Parser { tokens: tokenize(text)?, pos: 0, variables };
+        let ret = parser.expr(0)?;
+        match parser.tokens.get(parser.pos) {
+            Some(token) => Err(format!("Unexpected {:?}", token).into()),
+            None => Ok(ret),
+        }
+    }
+
+    /// Get the next token
+    fn next(&mut self) -> Option<&'a str> {
+        let ret = self.tokens.get(self.pos).copied();
+        self.pos += 1;
+        ret
+    }
+
+    /// Parse an expression of operators binding at least as tight as `min`
+    fn expr(&mut self, min: u8) -> Result<Expr> {
+        let mut lhs = self.atom()?;
+        while let Some((op, precedence)) =
+                self.tokens.get(self.pos).and_then(|x| binary(x)) {
+            if precedence < min {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.expr(precedence + 1)?;
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    /// Parse a number, name, negation, or expression in parentheses
+    fn atom(&mut self) -> Result<Expr> {
+        let variables = self.variables;
+        match self.next().ok_or("Expression ends too early")? {
+            "-" => Ok(Expr::Neg(Box::new(self.atom()?))),
+            "(" => {
+                let ret = self.expr(0)?;
+                match self.next() {
+                    Some(")") => Ok(ret),
+                    _ => Err("Missing a closing parenthesis".into()),
+                }
+            }
+            token if token.starts_with(|x: char| x.is_ascii_digit()) => {
+                Ok(Expr::Num(token.parse()?))
+            }
+            token => Var::from_name(token, variables).map(Expr::Var)
+                .ok_or_else(|| format!("Unknown name {:?}", token).into()),
+        }
+    }
+}
+
+/// What a rule does
+#[derive(Clone)]
+enum Action {
+    /// Set a parameter or variable, which can't be any of the game's values
+    Set(Var, Expr),
+
+    /// Award a bonus, if it's positive
+    Bonus(Expr),
+}
+
+/// Action to take on a hook
+#[derive(Clone)]
+struct Rule {
+    hook: Hook,
+
+    /// Condition which has to be non-zero for the action to happen
+    cond: Option<Expr>,
+
+    action: Action,
+}
+
+/// Split a script line into its hook, condition, and action, or `None` if
+/// it's empty
+fn split(line: &str) -> Result<Option<(Hook, Option<&str>, &str)>> {
+    let line = line.split('#').next().unwrap_or("").trim();
+    let Some(first) = line.split_whitespace().next() else {
+        return Ok(None);
+    };
+    if first == "set" {
+        return Ok(Some((Hook::Start, None, line)));
+    }
+    let hook = Hook::from_name(first)
+        .ok_or_else(|| format!("Unknown hook {:?}", first))?;
+    let rest = line[first.len()..].trim();
+    Ok(Some(match rest.split_once("=>") {
+        Some((cond, action)) if cond.trim().is_empty() => {
+            (hook, None, action.trim())
+        }
+        Some((cond, action)) => (hook, Some(cond), action.trim()),
+        None => (hook, None, rest),
+    }))
+}
+
+/// A loaded script, along with the state it keeps during a run
+#[derive(Clone)]
+pub struct Script {
+    /// Path the script was loaded from
+    pub path: String,
+
+    /// [`fnv1a`] hash of the text of the script
+    pub hash: u64,
+
+    /// Rules in the order they're written, which is the order they run in
+    rules: Vec<Rule>,
+
+    /// Values of the script's own variables
+    variables: Vec<i64>,
+
+    /// Values the script set each of [`Param::ALL`] to
+    params: [Option<i64>; Param::ALL.len()],
+}
+
+impl Script {
+    /// Load the script file at `path`
+    pub fn load(path: &str) -> Result<Self> {
+        let mut ret = Self::parse(&error::read_to_string("script", path)?)
+            .map_err(|err| err.in_file("script", path))?;
+        ret.path = path.to_string();
+        Ok(ret)
+    }
+
+    /// Load the script file at `path` a replay was recorded with, making sure
+    /// it still hashes to `hash`
+    pub fn load_recorded(path: &str, hash: u64) -> Result<Self> {
+        let ret = Self::load(path)?;
+        if ret.hash != hash {
+            return Err(format!("The script {} changed since the replay was \
+                recorded", path).into());
+        }
+        Ok(ret)
+    }
+
+    /// The script as it was before the run started, for starting the run over
+    pub fn restarted(&self) -> Self {
+        Self {
+            variables: vec![0; self.variables.len()],
+            params:    Default::default(),
+            ..self.clone()
+        }
+    }
+
+    /// Parse a script file
+    pub fn parse(text: &str) -> Result<Self> {
+        let err = |line_no: usize, err: Error| -> Error {
+            format!("Script line {}: {}", line_no + 1, err).into()
+        };
+
+        // Anything which gets set and isn't a parameter is a variable
+        let mut variables: Vec<String> = Vec::new();
+        for (line_no, line) in text.lines().enumerate() {
+            let Some((_, _, action)) =
+                split(line).map_err(|x| err(line_no, x))? else { continue };
+            let mut words = action.split_whitespace();
+            if let (Some("set"), Some(name)) = (words.next(), words.next()) {
+                if Var::from_name(name, &variables).is_none() {
+                    variables.push(name.to_string());
+                }
+            }
+        }
+
+        let mut rules = Vec::new();
+        for (line_no, line) in text.lines().enumerate() {
+            let Some((hook, cond, action)) =
+                split(line).map_err(|x| err(line_no, x))? else { continue };
+            let parse = |text: &str| {
+                Parser::parse(text, &variables).map_err(|x| err(line_no, x))
+            };
+            let cond = cond.map(parse).transpose()?;
+
+            let (kind, rest) = action.split_once(char::is_whitespace)
+                .unwrap_or((action, ""));
+            let action = match kind {
+                "set" => {
+                    let (name, value) = rest.trim()
+                        .split_once(char::is_whitespace)
+                        .ok_or_else(|| err(line_no, "Set needs a name and a \
+                            value".into()))?;
+                    let var = Var::from_name(name, &variables)
+                        .filter(|x| {
+                            matches!(x, Var::Param(_) | Var::Variable(_))
+                        })
+                        .ok_or_else(|| err(line_no,
+                            format!("Can't set {:?}", name).into()))?;
+                    Action::Set(var, parse(value)?)
+                }
+                "bonus" => Action::Bonus(parse(rest)?),
+                _ => {
+                    return Err(err(line_no,
+                        format!("Unknown action {:?}", kind).into()));
+                }
+            };
+            rules.push(Rule { hook, cond, action });
+        }
+
+        Ok(Self {
+            path:      String::new(),
+            hash:      fnv1a(FNV_OFFSET, text.as_bytes()),
+            rules,
+            variables: vec![0; variables.len()],
+            params:    [None; Param::ALL.len()],
+        })
+    }
+
+    /// Value the script set `param` to, if it did
+    pub fn param(&self, param: Param) -> Option<i64> {
+        self.params[param as usize]
+    }
+
+    /// Run the rules for `hook` with the game in the state of `values`,
+    /// returning the bonuses they award
+    pub fn run(&mut self, hook: Hook, values: &Values) -> Vec<u64> {
+        let mut ret = Vec::new();
+        for idx in 0..self.rules.len() {
+            let rule = &self.rules[idx];
+            if rule.hook != hook ||
+                    rule.cond.as_ref().is_some_and(|x| {
+                        self.eval(x, values) == 0
+                    }) {
+                continue;
+            }
+
+            match rule.action {
+                Action::Set(var, ref expr) => {
+                    let value = self.eval(expr, values);
+                    match var {
+                        Var::Param(param) =>
+                            self.params[param as usize] = Some(value),
+                        Var::Variable(idx) => self.variables[idx] = value,
+                        _ => unreachable!(),
+                    }
+                }
+                Action::Bonus(ref expr) => {
+                    let value = self.eval(expr, values);
+                    if value > 0 {
+                        ret.push(value as u64);
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    /// Evaluate `expr` with the game in the state of `values`
+    fn eval(&self, expr: &Expr, values: &Values) -> i64 {
+        let (op, lhs, rhs) = match expr {
+            Expr::Num(value) => return *value,
+            Expr::Neg(expr) => return self.eval(expr, values).wrapping_neg(),
+            Expr::Var(var) => {
+                return match *var {
+                    Var::Frame          => values.frame,
+                    Var::Score          => values.score,
+                    Var::Lives          => values.lives,
+                    Var::Coins          => values.coins,
+                    Var::Combo          => values.combo,
+                    Var::Stage          => values.stage,
+                    Var::PlayerY        => values.player_y,
+                    Var::ObstacleY      => values.obstacle_y,
+                    Var::ObstacleHeight => values.obstacle_height,
+                    Var::Param(param)   => self.params[param as usize]
+                        .unwrap_or(values.params[param as usize]),
+                    Var::Variable(idx)  => self.variables[idx],
+                };
+            }
+            Expr::Bin(op, lhs, rhs) => {
+                (*op, self.eval(lhs, values), self.eval(rhs, values))
+            }
+        };
+
+        match op {
+            Op::Or  => (lhs != 0 || rhs != 0) as i64,
+            Op::And => (lhs != 0 && rhs != 0) as i64,
+            Op::Eq  => (lhs == rhs) as i64,
+            Op::Ne  => (lhs != rhs) as i64,
+            Op::Lt  => (lhs < rhs) as i64,
+            Op::Le  => (lhs <= rhs) as i64,
+            Op::Gt  => (lhs > rhs) as i64,
+            Op::Ge  => (lhs >= rhs) as i64,
+            Op::Add => lhs.wrapping_add(rhs),
+            Op::Sub => lhs.wrapping_sub(rhs),
+            Op::Mul => lhs.wrapping_mul(rhs),
+            Op::Div => lhs.checked_div(rhs).unwrap_or(0),
+            Op::Rem => lhs.checked_rem(rhs).unwrap_or(0),
+        }
+    }
+
+    /// Continue the [`fnv1a`] `hash` over the state the script keeps
+    pub fn state_hash(&self, hash: u64) -> u64 {
+        let params = self.params.iter().map(|x| x.unwrap_or(i64::MIN));
+        self.variables.iter().copied().chain(params).fold(hash, |hash, x| {
+            fnv1a(hash, &x.to_le_bytes())
+        })
+    }
+}
+
+/// Scripts are saved by their path and hash, followed by the values they
+/// keep, and get loaded from the file again
+impl Serialize for Script {
+    fn serialize(&self, out: &mut Writer) {
+        out.field("path", &self.path)
+            .field("hash", &format!("{:016x}", self.hash))
+            .field("variables", &self.variables)
+            .field("params", &self.params);
+    }
+}
+
+impl Deserialize for Script {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        let path: String = inp.field("path")?;
+        let hash: String = inp.field("hash")?;
+        let mut ret = Self::load_recorded(&path, u64::from_str_radix(&hash,
+            16)?)?;
+        let variables: Vec<i64> = inp.field("variables")?;
+        if variables.len() != ret.variables.len() {
+            return Err(format!("The script {} has {} variables, not {}",
+                path, ret.variables.len(), variables.len()).into());
+        }
+        ret.variables = variables;
+        ret.params = inp.field("params")?;
+        Ok(ret)
+    }
+}
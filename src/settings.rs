@@ -0,0 +1,213 @@
+//! Presentation settings, which never change how the game plays. They're read
+//! from a file of `key value` lines, where `#` starts a comment
+
+use std::time::SystemTime;
+use crate::{error, locale, storage, Result};
+
+/// File the settings are read from
+pub const SETTINGS_FILE: &str = "settings.txt";
+
+/// Seconds between checks of whether a watched file changed
+const WATCH_INTERVAL: f64 = 1.;
+
+/// Settings picked by the player
+#[derive(Clone)]
+pub struct Settings {
+    /// Draw the game field through the CRT filter
+    pub crt: bool,
+
+    /// Fragment shader file to draw the game field through instead of the CRT
+    /// filter, see [`crate::filter`]
+    pub shader: Option<String>,
+
+    /// Have the camera follow the player rather than show the whole field
+    pub dynamic_camera: bool,
+
+    /// Show the upcoming walls and obstacles above the field
+    pub preview: bool,
+
+    /// Strength in percent of the rumble on deaths, near-misses, and
+    /// pickups, 0 turns it off
+    pub rumble: u8,
+
+    /// Discord application ID to publish the run being played under, see
+    /// [`crate::presence`]
+    pub discord: Option<String>,
+
+    /// Color of the screen around the field as RGB, for keying it out, see
+    /// [`crate::streamer`]
+    pub background: [u8; 3],
+
+    /// Show the score in big above the field
+    pub big_score: bool,
+
+    /// Show the recent deaths below the field
+    pub death_ticker: bool,
+
+    /// Light up the inputs of the last physics frame on the field
+    pub input_display: bool,
+
+    /// Click on every physics frame thrust registers on
+    pub click: bool,
+
+    /// Screen pixels to leave free around the edges of the screen
+    pub safe_margin: u16,
+
+    /// Code of the language text is shown in, see [`crate::locale`]
+    pub language: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            crt:            false,
+            shader:         None,
+            dynamic_camera: false,
+            preview:        false,
+            rumble:         100,
+            discord:        None,
+            background:     [0x00, 0x00, 0x00],
+            big_score:      false,
+            death_ticker:   false,
+            input_display:  false,
+            click:          false,
+            safe_margin:    0,
+            language:       "en".to_string(),
+        }
+    }
+}
+
+impl Settings {
+    /// Load the settings file at `path`
+    pub fn load(path: &str) -> Result<Self> {
+        Self::parse(&error::read_to_string("settings", path)?)
+            .map_err(|err| err.in_file("settings", path))
+    }
+
+    /// Parse a settings file, settings which aren't in it keep their defaults
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut settings = Self::default();
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line.split_once(char::is_whitespace)
+                .map(|(key, value)| (key, value.trim()))
+                .ok_or_else(|| format!("Line {}: {:?} has no value",
+                    line_no + 1, line))?;
+
+            match key {
+                "crt"          => settings.crt = parse_bool(value)?,
+                "shader"       => settings.shader = Some(value.to_string()),
+                "discord"      => settings.discord = Some(value.to_string()),
+                "preview"      => settings.preview = parse_bool(value)?,
+                "click"        => settings.click = parse_bool(value)?,
+                "big_score"    => settings.big_score = parse_bool(value)?,
+                "death_ticker" => settings.death_ticker = parse_bool(value)?,
+                "input_display" => settings.input_display = parse_bool(value)?,
+                "safe_margin"  => settings.safe_margin = value.parse()?,
+                "background"   => settings.background = parse_color(value)?,
+                "language"     => {
+                    if !locale::exists(value) {
+                        return Err(format!("Unknown language {:?}",
+                            value).into());
+                    }
+                    settings.language = value.to_string();
+                }
+                "rumble"       => {
+                    settings.rumble = match value {
+                        "off" => 0,
+                        "on"  => 100,
+                        _ => value.trim_end_matches('%').parse().ok()
+                            .filter(|x| *x <= 100)
+                            .ok_or_else(|| format!("Expected on, off, or a \
+                                percentage, not {:?}", value))?,
+                    };
+                }
+                "camera" => {
+                    settings.dynamic_camera = match value {
+                        "fixed"   => false,
+                        "dynamic" => true,
+                        _ => {
+                            return Err(format!("Expected fixed or dynamic, \
+                                not {:?}", value).into());
+                        }
+                    };
+                }
+                _ => {
+                    return Err(format!("Line {}: Unknown setting {:?}",
+                        line_no + 1, key).into());
+                }
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// Watches a file for changes to when it was last modified, so it can be
+/// loaded again while the game is running
+pub struct Watcher {
+    /// File being watched
+    pub path: String,
+
+    /// When the file was last modified as of the last check
+    modified: Option<SystemTime>,
+
+    /// Time (in seconds) of the last check
+    checked: f64,
+}
+
+impl Watcher {
+    /// Watch the file at `path` for changes from how it is now
+    pub fn new(path: &str) -> Self {
+        Self {
+            path:     path.to_string(),
+            modified: storage::modified(path),
+            checked:  0.,
+        }
+    }
+
+    /// Check if the file changed since the last time it did, at the time
+    /// `now` in seconds. It's only checked every [`WATCH_INTERVAL`]
+    pub fn changed(&mut self, now: f64) -> bool {
+        if now - self.checked < WATCH_INTERVAL {
+            return false;
+        }
+        self.checked = now;
+
+        let modified = storage::modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+/// Parse a color setting, either a name or hex `rrggbb`
+pub fn parse_color(value: &str) -> Result<[u8; 3]> {
+    let hex = match value {
+        "black"   => "000000",
+        "green"   => "00ff00",
+        "blue"    => "0000ff",
+        "magenta" => "ff00ff",
+        _         => value.trim_start_matches('#'),
+    };
+    let channel = |ii: usize| hex.get(ii * 2..ii * 2 + 2)
+        .and_then(|x| u8::from_str_radix(x, 16).ok());
+    match (hex.len(), channel(0), channel(1), channel(2)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => Err(format!("Expected black, green, blue, magenta, or a hex \
+            rrggbb color, not {:?}", value).into()),
+    }
+}
+
+/// Parse an `on` or `off` setting
+fn parse_bool(value: &str) -> Result<bool> {
+    match value {
+        "on"  => Ok(true),
+        "off" => Ok(false),
+        _     => Err(format!("Expected on or off, not {:?}", value).into()),
+    }
+}
@@ -0,0 +1,154 @@
+//! `solve` subcommand, which beam searches the headless simulation for inputs
+//! surviving as long as possible on a seed, and writes them out as a replay
+
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use crate::{GameField, Mutator, Preset, Replay, Result, Rules};
+use crate::error;
+use crate::{FIXED_POINT_DIVISOR, GAME_FIELD_HEIGHT, PLAYER_X};
+use crate::{INPUT_FIRE, INPUT_THRUST};
+
+/// Physics frames each search step extends the candidates by
+const CHUNK_FRAMES: usize = 8;
+
+/// Candidates hold thrust for a multiple of this many frames of each chunk
+/// before letting go
+const HOLD_STEP: usize = 2;
+
+/// Default number of candidates kept after each search step
+const DEFAULT_BEAM: usize = 64;
+
+/// Default number of physics frames to stop searching after
+const DEFAULT_FRAMES: u64 = 3600;
+
+/// Default file the solution gets written to
+const DEFAULT_OUT: &str = "solved.bin";
+
+/// Candidates in the same cell of this many pixels of height and this much
+/// speed are considered the same, and only the best of them is kept, so the
+/// beam doesn't collapse onto a single path
+const CELL_HEIGHT: i16 = 8 * FIXED_POINT_DIVISOR;
+const CELL_SPEED:  i16 = 2 * FIXED_POINT_DIVISOR;
+
+/// Number of physics frames between progress reports
+const REPORT_FRAMES: u64 = 600;
+
+/// How far (in fixed point) the player is off the center of the gap in the
+/// wall column it's in, used to break ties between equal scores
+fn miss(field: &GameField) -> i32 {
+    let size = field.player_size().0 as i32;
+    let (mut top, mut bottom) = (0, GAME_FIELD_HEIGHT.0 as i32);
+    for wall in field.walls.iter().filter(|x| {
+        x.x.0 < PLAYER_X.0 + size as i16 && x.x.0 + x.width.0 > PLAYER_X.0
+    }) {
+        if wall.y.0 == 0 {
+            top = top.max((wall.y.0 + wall.height.0) as i32);
+        } else {
+            bottom = bottom.min(wall.y.0 as i32);
+        }
+    }
+    (field.player_y.0 as i32 + size / 2 - (top + bottom) / 2).abs()
+}
+
+/// Run the `solve` subcommand with the arguments following it
+pub fn run(args: &[String]) -> Result<()> {
+    let mut rules = Rules::default();
+    let mut frames = DEFAULT_FRAMES;
+    let mut beam = DEFAULT_BEAM;
+    let mut out = DEFAULT_OUT.to_string();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |what: &str| {
+            args.next().ok_or_else(|| format!("{} requires {}", arg, what))
+        };
+        match arg.as_str() {
+            "--seed"    => {
+                rules.seed = u64::from_str_radix(
+                    value("a seed")?.trim_start_matches("0x"), 16)?;
+            }
+            "--preset"  => {
+                let name = value("a preset name")?;
+                rules.preset = Preset::from_name(name)
+                    .ok_or_else(|| format!("Unknown preset {:?}", name))?;
+                rules.difficulty = rules.preset.difficulty();
+            }
+            "--mutator" => {
+                let name = value("a mutator name")?;
+                rules.mutators |= Mutator::from_name(name)
+                    .ok_or_else(|| format!("Unknown mutator {:?}", name))?
+                    .bit();
+            }
+            "--frames"  => frames = value("a frame count")?.parse()?,
+            "--beam"    => beam = value("a beam width")?.parse()?,
+            "--out"     => out = value("a replay file")?.clone(),
+            "--verbose" => crate::log::verbose(),
+            _ => return Err(format!("Unknown solve argument {:?}", arg).into()),
+        }
+    }
+
+    // Extend every candidate by a chunk of each hold length, keeping the
+    // highest scoring candidates which are still alive. Dead ends are only
+    // kept around in case nothing survives
+    let mut candidates = vec![GameField::new(rules)];
+    let mut best = candidates[0].clone();
+    let mut reported = 0;
+    while !candidates.is_empty() && best.physics_frames < frames {
+        let mut children = Vec::new();
+        for candidate in candidates.iter() {
+            // Shots fire on presses, so only try firing at the start of a
+            // chunk while there's ammo to fire
+            let fire = if candidate.ammo > 0 { 2 } else { 1 };
+            for (hold, shot) in (0..=CHUNK_FRAMES).step_by(HOLD_STEP)
+                    .flat_map(|x| (0..fire).map(move |y| (x, y == 1))) {
+                let mut child = candidate.clone();
+                for frame in 0..CHUNK_FRAMES {
+                    if child.dead || child.finished {
+                        break;
+                    }
+                    let mut input = 0;
+                    if frame < hold {
+                        input |= INPUT_THRUST;
+                    }
+                    if shot && frame == 0 {
+                        input |= INPUT_FIRE;
+                    }
+                    child.step(input);
+                }
+                children.push(child);
+            }
+        }
+
+        children.sort_by_key(|x| (x.dead, Reverse(x.score()), miss(x)));
+        if children[0].score() > best.score() || !children[0].dead {
+            best = children[0].clone();
+        }
+        let mut cells = HashSet::new();
+        children.retain(|x| {
+            !x.dead && !x.finished && cells.insert((
+                x.player_y.0.div_euclid(CELL_HEIGHT),
+                x.player_speed.0.div_euclid(CELL_SPEED),
+            ))
+        });
+        children.truncate(beam);
+        candidates = children;
+
+        if best.physics_frames >= reported + REPORT_FRAMES {
+            reported = best.physics_frames;
+            println!("Frame {:8} | Score {:10}", best.physics_frames,
+                best.score());
+        }
+    }
+
+    error::write("replay", &out, Replay {
+        rules,
+        level:     None,
+        inputs:    best.inputs.clone(),
+        votes:     Vec::new(),
+        script:    None,
+        frames:    (best.dead || best.finished).then_some(best.physics_frames),
+        integrity: (best.dead || best.finished).then_some(best.integrity),
+    }.serialize())?;
+    println!("Solved {} frames with score {} run {}, written to {}",
+        best.physics_frames, best.score(), best.run_id(), out);
+    Ok(())
+}
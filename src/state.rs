@@ -0,0 +1,549 @@
+//! Complete states of the simulation as text, so they can be saved, diffed,
+//! hashed, and pasted into bug reports. Every value is on its own line after
+//! the path to it, like `obstacles.3.x 1234`, always in the same order, so
+//! two states can be compared with a plain `diff`. Fixed point values are
+//! written raw. Only the simulation is saved, not how it's being shown, and
+//! scripts are saved by their path and hash like in replays
+//!
+//! `state <replay> [frame]` prints the state of a replay after `frame` physics
+//! frames, or once the run is over
+
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::fmt::{Display, Write};
+use std::str::FromStr;
+use crate::{Boss, GameField, Mutator, Obstacle, ObstacleKind, Pickup};
+use crate::{Fxpt, PickupKind, Popup, Replay, Result, Rng, Rules, Script};
+use crate::{Biome, Zone, ZoneKind};
+use crate::level::{Block, Item, Level, Region, Wall};
+use crate::replay::{parse_rule, rule_fields};
+
+/// Magic at the start of every state, followed by the version
+const MAGIC: &str = "HELISTATE";
+
+/// Version of the state format
+const VERSION: u32 = 1;
+
+/// A value which can be written into a state
+pub trait Serialize {
+    /// Write the value at the current path of `out`
+    fn serialize(&self, out: &mut Writer);
+}
+
+/// A value which can be read back from a state
+pub trait Deserialize: Sized {
+    /// Read the value at the current path of `inp`
+    fn deserialize(inp: &mut Reader) -> Result<Self>;
+}
+
+/// A state being written
+pub struct Writer {
+    text: String,
+
+    /// Names leading to the value being written
+    path: Vec<String>,
+}
+
+impl Writer {
+    /// Write `value` under `name`
+    pub fn field(&mut self, name: impl Display, value: &impl Serialize)
+            -> &mut Self {
+        self.path.push(name.to_string());
+        value.serialize(self);
+        self.path.pop();
+        self
+    }
+
+    /// Write `value` on a line of its own at the current path
+    pub fn value(&mut self, value: impl Display) {
+        let _ = writeln!(self.text, "{} {}", self.path.join("."), value);
+    }
+}
+
+/// A state being read, line by line
+pub struct Reader<'a> {
+    lines: std::str::Lines<'a>,
+
+    /// Line number of the last line read
+    line: usize,
+
+    /// Names leading to the value being read
+    path: Vec<String>,
+}
+
+impl<'a> Reader<'a> {
+    /// Read the value under `name`
+    pub fn field<T: Deserialize>(&mut self, name: impl Display) -> Result<T> {
+        self.path.push(name.to_string());
+        let ret = T::deserialize(self);
+        self.path.pop();
+        ret
+    }
+
+    /// Read the next line, which has to be at the current path
+    pub fn value(&mut self) -> Result<&'a str> {
+        let path = self.path.join(".");
+        let line = self.lines.next()
+            .ok_or_else(|| format!("State ends before {}", path))?;
+        self.line += 1;
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        if key != path {
+            return Err(format!("State line {}: expected {}, found {:?}",
+                self.line, path, key).into());
+        }
+        Ok(value)
+    }
+
+    /// Read the next line and parse it
+    pub fn parse<T: FromStr>(&mut self) -> Result<T>
+            where T::Err: Display {
+        let value = self.value()?;
+        value.parse().map_err(|err| format!("State line {}: {:?} is \
+            invalid: {}", self.line, value, err).into())
+    }
+}
+
+/// Write `value` as a state
+pub fn save(value: &impl Serialize) -> String {
+    let mut out = Writer {
+        text: format!("{} {}\n", MAGIC, VERSION),
+        path: Vec::new(),
+    };
+    value.serialize(&mut out);
+    out.text
+}
+
+/// Read a value back from the state `text`
+pub fn load<T: Deserialize>(text: &str) -> Result<T> {
+    let mut lines = text.lines();
+    let version = lines.next()
+        .and_then(|x| x.strip_prefix(MAGIC))
+        .ok_or("Not a state, it's missing the magic")?;
+    if version.trim() != VERSION.to_string() {
+        return Err(format!("Unsupported state version {:?}",
+            version.trim()).into());
+    }
+
+    let mut inp = Reader { lines, line: 1, path: Vec::new() };
+    let ret = T::deserialize(&mut inp)?;
+    if let Some(line) = inp.lines.find(|x| !x.trim().is_empty()) {
+        return Err(format!("State line {}: unexpected {:?}", inp.line + 1,
+            line).into());
+    }
+    Ok(ret)
+}
+
+/// Run the `state` subcommand with the arguments following it
+pub fn run(args: &[String]) -> Result<()> {
+    let (path, frame) = match args {
+        [path] => (path, None),
+        [path, frame] => (path, Some(frame.parse::<u64>()?)),
+        _ => return Err("Usage: state <replay> [frame]".into()),
+    };
+    let replay = Replay::load(path, Rules::default())?;
+    let mut field = GameField::new(replay.rules);
+    field.level = replay.level.as_ref()
+        .map(|path| Level::load(path))
+        .transpose()?;
+    field.script = replay.script.as_ref()
+        .map(|(path, hash)| Script::load_recorded(path, *hash))
+        .transpose()?;
+    field.votes = replay.votes;
+
+    for input in replay.inputs.iter().copied().chain(std::iter::repeat(0)) {
+        if field.dead || field.finished ||
+                frame.is_some_and(|x| field.physics_frames >= x) {
+            break;
+        }
+        field.step(input);
+    }
+    print!("{}", save(&field));
+    Ok(())
+}
+
+/// Save and load values as they're displayed
+macro_rules! displayed {
+    ($($ty:ty),*) => {$(
+        impl Serialize for $ty {
+            fn serialize(&self, out: &mut Writer) {
+                out.value(self);
+            }
+        }
+
+        impl Deserialize for $ty {
+            fn deserialize(inp: &mut Reader) -> Result<Self> {
+                inp.parse()
+            }
+        }
+    )*};
+}
+
+displayed!(u8, u16, u32, u64, usize, i8, i16, i32, i64, String);
+
+impl Serialize for bool {
+    fn serialize(&self, out: &mut Writer) {
+        out.value(*self as u8);
+    }
+}
+
+impl Deserialize for bool {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        match inp.value()? {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            value => Err(format!("State line {}: expected 0 or 1, found \
+                {:?}", inp.line, value).into()),
+        }
+    }
+}
+
+impl Serialize for Fxpt {
+    fn serialize(&self, out: &mut Writer) {
+        out.value(self.0);
+    }
+}
+
+impl Deserialize for Fxpt {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        Ok(Fxpt(inp.parse()?))
+    }
+}
+
+impl Serialize for Rng {
+    fn serialize(&self, out: &mut Writer) {
+        out.value(format_args!("{:016x}", self.0));
+    }
+}
+
+impl Deserialize for Rng {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        Ok(Rng(u64::from_str_radix(inp.value()?, 16)?))
+    }
+}
+
+/// Options are whether they're set under `some`, followed by the value
+impl<T: Serialize> Serialize for Option<T> {
+    fn serialize(&self, out: &mut Writer) {
+        out.field("some", &self.is_some());
+        if let Some(value) = self {
+            value.serialize(out);
+        }
+    }
+}
+
+impl<T: Deserialize> Deserialize for Option<T> {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        if inp.field("some")? {
+            Ok(Some(T::deserialize(inp)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Lists are their length under `len`, followed by each item by index
+impl<T: Serialize> Serialize for Vec<T> {
+    fn serialize(&self, out: &mut Writer) {
+        out.field("len", &self.len());
+        for (idx, value) in self.iter().enumerate() {
+            out.field(idx, value);
+        }
+    }
+}
+
+impl<T: Deserialize> Deserialize for Vec<T> {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        let len: usize = inp.field("len")?;
+        (0..len).map(|idx| inp.field(idx)).collect()
+    }
+}
+
+impl<T: Serialize, const N: usize> Serialize for [T; N] {
+    fn serialize(&self, out: &mut Writer) {
+        for (idx, value) in self.iter().enumerate() {
+            out.field(idx, value);
+        }
+    }
+}
+
+impl<T: Deserialize, const N: usize> Deserialize for [T; N] {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        let ret = (0..N).map(|idx| inp.field(idx))
+            .collect::<Result<Vec<T>>>()?;
+        ret.try_into().map_err(|_| unreachable!())
+    }
+}
+
+impl<A: Serialize, B: Serialize> Serialize for (A, B) {
+    fn serialize(&self, out: &mut Writer) {
+        out.field(0, &self.0).field(1, &self.1);
+    }
+}
+
+impl<A: Deserialize, B: Deserialize> Deserialize for (A, B) {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        Ok((inp.field(0)?, inp.field(1)?))
+    }
+}
+
+/// Inputs are a single line of hex, one byte per physics frame, or `-` if
+/// there are none
+impl Serialize for VecDeque<u8> {
+    fn serialize(&self, out: &mut Writer) {
+        if self.is_empty() {
+            out.value("-");
+            return;
+        }
+        out.value(self.iter().map(|x| format!("{:02x}", x))
+            .collect::<String>());
+    }
+}
+
+impl Deserialize for VecDeque<u8> {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        let value = inp.value()?;
+        if value == "-" {
+            return Ok(VecDeque::new());
+        }
+        if value.len() % 2 != 0 || !value.is_ascii() {
+            return Err(format!("State line {}: malformed hex", inp.line)
+                .into());
+        }
+        (0..value.len()).step_by(2)
+            .map(|x| Ok(u8::from_str_radix(&value[x..x + 2], 16)?))
+            .collect()
+    }
+}
+
+/// Look up `name` with `from_name`, erroring with `what` if there's no such
+/// thing
+fn named<T>(inp: &mut Reader, what: &str,
+        from_name: fn(&str) -> Option<T>) -> Result<T> {
+    let name = inp.value()?;
+    from_name(name).ok_or_else(|| format!("State line {}: unknown {} {:?}",
+        inp.line, what, name).into())
+}
+
+impl Serialize for Mutator {
+    fn serialize(&self, out: &mut Writer) {
+        out.value(self.name());
+    }
+}
+
+impl Deserialize for Mutator {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        named(inp, "mutator", Mutator::from_name)
+    }
+}
+
+impl Serialize for Biome {
+    fn serialize(&self, out: &mut Writer) {
+        out.value(self.name());
+    }
+}
+
+impl Deserialize for Biome {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        named(inp, "biome", Biome::from_name)
+    }
+}
+
+impl Serialize for PickupKind {
+    fn serialize(&self, out: &mut Writer) {
+        out.value(self.name());
+    }
+}
+
+impl Deserialize for PickupKind {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        named(inp, "pickup", PickupKind::from_name)
+    }
+}
+
+impl Serialize for ZoneKind {
+    fn serialize(&self, out: &mut Writer) {
+        out.value(self.name());
+    }
+}
+
+impl Deserialize for ZoneKind {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        named(inp, "zone", ZoneKind::from_name)
+    }
+}
+
+/// Rules are a single line of the `key=value` fields of replay headers
+impl Serialize for Rules {
+    fn serialize(&self, out: &mut Writer) {
+        out.value(rule_fields(self).iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>().join(" "));
+    }
+}
+
+impl Deserialize for Rules {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        let mut ret = Rules::default();
+        for field in inp.value()?.split(' ') {
+            let (key, value) = field.split_once('=')
+                .ok_or_else(|| format!("Malformed rule {:?}", field))?;
+            if !parse_rule(&mut ret, key, value)? {
+                return Err(format!("Unknown rule {:?}", key).into());
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Obstacle kinds are their name, followed by their fields
+impl Serialize for ObstacleKind {
+    fn serialize(&self, out: &mut Writer) {
+        out.value(self.name());
+        match *self {
+            ObstacleKind::Static => {}
+            ObstacleKind::Oscillating { center_y, amplitude, phase } => {
+                out.field("center_y", &center_y)
+                    .field("amplitude", &amplitude)
+                    .field("phase", &phase);
+            }
+            ObstacleKind::Blade { angle, spin } => {
+                out.field("angle", &angle).field("spin", &spin);
+            }
+            ObstacleKind::Gate { anchor, extent, ceiling } => {
+                out.field("anchor", &anchor)
+                    .field("extent", &extent)
+                    .field("ceiling", &ceiling);
+            }
+            ObstacleKind::Slope { left, right, ceiling } => {
+                out.field("left", &left)
+                    .field("right", &right)
+                    .field("ceiling", &ceiling);
+            }
+            ObstacleKind::Turret { cooldown, ceiling } => {
+                out.field("cooldown", &cooldown).field("ceiling", &ceiling);
+            }
+            ObstacleKind::Projectile { vx, vy } => {
+                out.field("vx", &vx).field("vy", &vy);
+            }
+        }
+    }
+}
+
+impl Deserialize for ObstacleKind {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        Ok(match inp.value()? {
+            "static"      => ObstacleKind::Static,
+            "oscillating" => ObstacleKind::Oscillating {
+                center_y:  inp.field("center_y")?,
+                amplitude: inp.field("amplitude")?,
+                phase:     inp.field("phase")?,
+            },
+            "blade"       => ObstacleKind::Blade {
+                angle: inp.field("angle")?,
+                spin:  inp.field("spin")?,
+            },
+            "gate"        => ObstacleKind::Gate {
+                anchor:  inp.field("anchor")?,
+                extent:  inp.field("extent")?,
+                ceiling: inp.field("ceiling")?,
+            },
+            "slope"       => ObstacleKind::Slope {
+                left:    inp.field("left")?,
+                right:   inp.field("right")?,
+                ceiling: inp.field("ceiling")?,
+            },
+            "turret"      => ObstacleKind::Turret {
+                cooldown: inp.field("cooldown")?,
+                ceiling:  inp.field("ceiling")?,
+            },
+            "projectile"  => ObstacleKind::Projectile {
+                vx: inp.field("vx")?,
+                vy: inp.field("vy")?,
+            },
+            name => return Err(format!("State line {}: unknown obstacle \
+                {:?}", inp.line, name).into()),
+        })
+    }
+}
+
+/// Implement saving and loading a struct as each of its fields in order. For
+/// types with private fields or which can't be created from their fields
+/// alone, the impls are written out instead
+macro_rules! fields {
+    ($ty:ident { $($field:ident),* $(,)? }) => {
+        impl Serialize for $ty {
+            fn serialize(&self, out: &mut Writer) {
+                $(out.field(stringify!($field), &self.$field);)*
+            }
+        }
+
+        impl Deserialize for $ty {
+            fn deserialize(inp: &mut Reader) -> Result<Self> {
+                Ok(Self { $($field: inp.field(stringify!($field))?,)* })
+            }
+        }
+    };
+}
+
+fields!(Obstacle { x, y, width, height, kind, passed, clearance });
+fields!(Pickup { x, y, kind });
+fields!(Zone { x, width, kind });
+fields!(Popup { x, y, value, age });
+fields!(Boss { x, y, health, age, attacks });
+fields!(Wall { column, top, bottom });
+fields!(Block { column, y, height });
+fields!(Item { column, y, kind });
+fields!(Region { column, columns, kind });
+fields!(Level { length, walls, blocks, items, regions });
+
+/// Fields of the [`GameField`] which are part of its state, in order, handed
+/// to `$then`. The rules it was started with come first as the field gets
+/// created from them
+macro_rules! game_fields {
+    ($then:ident) => {
+        $then!(rules, rng, physics_frames, player_y, player_speed, walls,
+            obstacles, pickups, zones, bullets, ammo, shield, flash_frames,
+            slow_frames, shrink_frames, magnet_frames, coins, bonus, penalty,
+            popups, combo, base_size, votes, applied_votes, pending_vote, lives,
+            invulnerable_frames, on_edge, wall_skew, tunnel, pattern,
+            pattern_cooldown, reachable, gap_history, last_obstacle, dead,
+            level, script, column, finish, finished, stage, stage_start,
+            shop, shop_frames, continue_score, biome, stage_times, splits,
+            flag, boss, ease, window_deaths, window_near_misses, near_misses,
+            pickups_collected, close_calls, inputs, run_hash, integrity)
+    };
+}
+
+impl Serialize for GameField {
+    fn serialize(&self, out: &mut Writer) {
+        out.field("base_rules", &self.base_rules);
+        macro_rules! write_fields {
+            ($($field:ident),*) => {
+                $(out.field(stringify!($field), &self.$field);)*
+            };
+        }
+        game_fields!(write_fields);
+    }
+}
+
+impl Deserialize for GameField {
+    fn deserialize(inp: &mut Reader) -> Result<Self> {
+        let mut ret = GameField::new(inp.field("base_rules")?);
+        macro_rules! read_fields {
+            ($($field:ident),*) => {
+                $(ret.$field = inp.field(stringify!($field))?;)*
+            };
+        }
+        game_fields!(read_fields);
+        Ok(ret)
+    }
+}
+
+/// A run in progress saved when the game gets closed, along with the path of
+/// the level it's played on for writing its replay once it's over
+pub(crate) struct Suspended {
+    pub level: Option<String>,
+    pub field: GameField,
+}
+
+fields!(Suspended { level, field });
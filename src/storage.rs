@@ -0,0 +1,91 @@
+//! Where files the game keeps between runs live. Natively that's the file
+//! system, on WASM it's the browser's `localStorage` through the plugin in
+//! `index.html`, with the path as the key, so settings, stage times, and
+//! replays survive page reloads
+
+use std::io;
+use std::time::SystemTime;
+
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    /// Get the length of the stored file `key`, or -1 if there's none
+    fn mqtest_storage_len(key: *const u8, key_len: usize) -> i32;
+
+    /// Copy the stored file `key` into the `len` bytes at `out`
+    fn mqtest_storage_read(key: *const u8, key_len: usize, out: *mut u8,
+        len: usize);
+
+    /// Store `data` as the file `key`, returning 0 on success
+    fn mqtest_storage_write(key: *const u8, key_len: usize, data: *const u8,
+        data_len: usize) -> i32;
+
+    /// Remove the stored file `key`, if there is one
+    fn mqtest_storage_remove(key: *const u8, key_len: usize);
+}
+
+/// Read the file at `path`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(path: &str) -> io::Result<Vec<u8>> {
+    std::fs::read(path)
+}
+
+/// Write `contents` to the file at `path`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(path: &str, contents: &[u8]) -> io::Result<()> {
+    std::fs::write(path, contents)
+}
+
+/// Remove the file at `path`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn remove(path: &str) -> io::Result<()> {
+    std::fs::remove_file(path)
+}
+
+/// Time the file at `path` was last modified, if it can be told
+#[cfg(not(target_arch = "wasm32"))]
+pub fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|x| x.modified()).ok()
+}
+
+/// Read the file at `path`
+#[cfg(target_arch = "wasm32")]
+pub fn read(path: &str) -> io::Result<Vec<u8>> {
+    let len = unsafe { mqtest_storage_len(path.as_ptr(), path.len()) };
+    if len < 0 {
+        return Err(io::ErrorKind::NotFound.into());
+    }
+    let mut ret = vec![0u8; len as usize];
+    unsafe {
+        mqtest_storage_read(path.as_ptr(), path.len(), ret.as_mut_ptr(),
+            ret.len());
+    }
+    Ok(ret)
+}
+
+/// Write `contents` to the file at `path`
+#[cfg(target_arch = "wasm32")]
+pub fn write(path: &str, contents: &[u8]) -> io::Result<()> {
+    let status = unsafe {
+        mqtest_storage_write(path.as_ptr(), path.len(), contents.as_ptr(),
+            contents.len())
+    };
+    if status != 0 {
+        return Err(io::Error::new(io::ErrorKind::Other,
+            "the browser's storage is full or turned off"));
+    }
+    Ok(())
+}
+
+/// Remove the file at `path`
+#[cfg(target_arch = "wasm32")]
+pub fn remove(path: &str) -> io::Result<()> {
+    unsafe { mqtest_storage_remove(path.as_ptr(), path.len()) };
+    Ok(())
+}
+
+/// Time the file at `path` was last modified, which is never known for the
+/// browser's storage, where files only change through the game anyway
+#[cfg(target_arch = "wasm32")]
+pub fn modified(_path: &str) -> Option<SystemTime> {
+    None
+}
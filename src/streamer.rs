@@ -0,0 +1,198 @@
+//! Streamer mode, for putting the game on a stream: a background color which
+//! can be keyed out around the field, a big score above the field, a ticker
+//! of recent deaths below it, the inputs lighting up on the field, and a
+//! margin keeping the field clear of whatever the stream puts over the edges
+//! of the screen. Each of them is its own setting
+
+use std::collections::VecDeque;
+use macroquad::prelude::*;
+use crate::{Control, GameField, INPUT_FIRE, INPUT_THRUST};
+use crate::{INPUT_THROTTLE_SHIFT, THROTTLE_LEVELS};
+use crate::settings::Settings;
+
+/// Height in screen pixels of the band above the field with the big score
+pub const SCORE_HEIGHT: f32 = 56.;
+
+/// Height in screen pixels of the band below the field with the ticker
+pub const TICKER_HEIGHT: f32 = 28.;
+
+/// Size of the buttons of the input display, in game field pixels
+const BUTTON_SIZE: f32 = 20.;
+
+/// Margin (in screen pixels) `--streamer` leaves around the screen
+const STREAMER_MARGIN: u16 = 20;
+
+/// Number of recent deaths the ticker remembers
+const DEATHS: usize = 10;
+
+/// How the game gets laid out on the screen
+#[derive(Clone, Copy)]
+pub struct Streamer {
+    /// Color of the screen around the field
+    pub background: Color,
+
+    /// Show the score in big above the field
+    pub big_score: bool,
+
+    /// Show the recent deaths below the field
+    pub ticker: bool,
+
+    /// Light up the inputs of the last physics frame on the field
+    pub inputs: bool,
+
+    /// Screen pixels left free around the edges of the screen
+    pub margin: f32,
+}
+
+impl Default for Streamer {
+    fn default() -> Self {
+        Self {
+            background: BLACK,
+            big_score:  false,
+            ticker:     false,
+            inputs:     false,
+            margin:     0.,
+        }
+    }
+}
+
+impl Streamer {
+    /// Lay out the game as picked in `settings`
+    pub fn new(settings: &Settings) -> Self {
+        let [r, g, b] = settings.background;
+        Self {
+            background: Color::from_rgba(r, g, b, 0xff),
+            big_score:  settings.big_score,
+            ticker:     settings.death_ticker,
+            inputs:     settings.input_display,
+            margin:     f32::from(settings.safe_margin),
+        }
+    }
+
+    /// Turn on everything streamer mode does in `settings`, keeping any
+    /// background color which was picked
+    pub fn enable(settings: &mut Settings) {
+        settings.big_score    = true;
+        settings.death_ticker = true;
+        settings.input_display = true;
+        settings.safe_margin  = settings.safe_margin.max(STREAMER_MARGIN);
+    }
+
+    /// Screen pixels needed above the field
+    pub fn top(&self) -> f32 {
+        if self.big_score { SCORE_HEIGHT } else { 0. }
+    }
+
+    /// Screen pixels needed below the field
+    pub fn bottom(&self) -> f32 {
+        if self.ticker { TICKER_HEIGHT } else { 0. }
+    }
+
+    /// Draw the score of `field` in big, centered in the `width` screen
+    /// pixels starting at `x` with the band's top at `y`
+    pub fn draw_score(&self, field: &GameField, x: f32, y: f32, width: f32) {
+        if !self.big_score {
+            return;
+        }
+        let text = format!("{}", field.score());
+        let size = SCORE_HEIGHT - 4.;
+        let dims = measure_text(&text, None, size as u16, 1.);
+        draw_text(&text, x + (width - dims.width) / 2., y + dims.offset_y,
+            size, WHITE);
+    }
+
+    /// Draw the thrust and fire buttons, lit up if they were held in the
+    /// input of the last physics frame of `field`, in the bottom corner of
+    /// the field away from the player. Throttle control shows the throttle
+    /// filling the thrust button instead
+    pub fn draw_inputs(&self, field: &GameField) {
+        if !self.inputs {
+            return;
+        }
+        let input = field.inputs.back().copied().unwrap_or(0);
+        let (x, y, scale) = field.layout();
+        let size = BUTTON_SIZE * scale;
+        let gap = size / 4.;
+        let width = scale * f32::from(crate::GAME_FIELD_WIDTH);
+        let left = if field.mirror {
+            x + gap
+        } else {
+            x + width - gap * 2. - size * 2.
+        };
+        let top = y + scale * f32::from(crate::GAME_FIELD_HEIGHT) - gap -
+            size;
+        let lit = |held: bool| {
+            if held { WHITE } else { Color::from_rgba(0xff, 0xff, 0xff, 0x40) }
+        };
+
+        // Thrust is an arrow pointing up
+        let held = if field.rules.control == Control::Throttle {
+            let level = (input >> INPUT_THROTTLE_SHIFT) as f32 /
+                THROTTLE_LEVELS as f32;
+            draw_rectangle(left, top + size * (1. - level), size,
+                size * level, Color::from_rgba(0xff, 0xff, 0xff, 0x60));
+            level > 0.
+        } else {
+            input & INPUT_THRUST != 0
+        };
+        draw_rectangle_lines(left, top, size, size, 2., lit(held));
+        draw_triangle(vec2(left + size / 2., top + size * 0.2),
+            vec2(left + size * 0.2, top + size * 0.75),
+            vec2(left + size * 0.8, top + size * 0.75), lit(held));
+
+        // Fire is a dot
+        let left = left + size + gap;
+        let held = input & INPUT_FIRE != 0;
+        draw_rectangle_lines(left, top, size, size, 2., lit(held));
+        draw_circle(left + size / 2., top + size / 2., size * 0.25,
+            lit(held));
+    }
+}
+
+/// Recent deaths, across runs
+#[derive(Default)]
+pub struct Ticker {
+    /// Descriptions of the deaths, newest first
+    deaths: VecDeque<String>,
+
+    /// Start time of the last run seen dying, so it's only counted once
+    seen: Option<u64>,
+}
+
+impl Ticker {
+    /// Remember the run in `field` if it just died
+    pub fn update(&mut self, field: &GameField) {
+        let run = field.start_time.to_bits();
+        if !field.dead || field.frames == 0 || self.seen == Some(run) {
+            return;
+        }
+        self.seen = Some(run);
+        if self.deaths.len() == DEATHS {
+            self.deaths.pop_back();
+        }
+        self.deaths.push_front(tr!("death", field.score(),
+            field.rules.preset.title()));
+    }
+
+    /// Draw as many of the recent deaths as fit into the band below the field
+    /// of `field`, if the ticker is on
+    pub fn draw(&self, field: &GameField) {
+        if !field.streamer.ticker {
+            return;
+        }
+        let (x, y, scale) = field.layout();
+        let y = y + scale * f32::from(crate::GAME_FIELD_HEIGHT);
+        let width = scale * f32::from(crate::GAME_FIELD_WIDTH);
+        let size = TICKER_HEIGHT - 8.;
+
+        let mut text = tr!("deaths").to_string();
+        for death in &self.deaths {
+            let next = format!("{}  |  {}", text, death);
+            if measure_text(&next, None, size as u16, 1.).width > width {
+                break;
+            }
+            text = next;
+        }
+        draw_text(&text, x, y + TICKER_HEIGHT - 6., size, LIGHTGRAY);
+    }
+}
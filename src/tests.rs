@@ -0,0 +1,1031 @@
+//! Unit tests for the private guts of the simulation
+
+use crate::*;
+
+/// Number of random cases each property gets checked with
+const CASES: usize = 100_000;
+
+/// Random raw fixed point value
+fn any_fxpt(rng: &mut Rng) -> Fxpt {
+    Fxpt(rng.rand() as i16)
+}
+
+/// Every difficulty the player physics can run with, every preset and craft
+/// with and without the mutators which change the physics, and every stage of
+/// it
+fn difficulties() -> Vec<Difficulty> {
+    let mut ret = Vec::new();
+    for (preset, craft) in Preset::ALL.iter()
+            .flat_map(|&x| Craft::ALL.iter().map(move |&y| (x, y))) {
+        for mutators in 0..4 {
+            let mut rules = Rules {
+                preset,
+                craft,
+                difficulty: preset.difficulty(),
+                ..Default::default()
+            };
+            if mutators & 1 != 0 {
+                rules.mutators |= Mutator::DoubleGravity.bit();
+            }
+            if mutators & 2 != 0 {
+                rules.mutators |= Mutator::Icy.bit();
+            }
+            let difficulty = GameField::new(rules).rules.difficulty;
+            for stage in 0..64 {
+                ret.push(difficulty.for_stage(stage));
+            }
+        }
+    }
+    ret
+}
+
+#[test]
+fn fxpt_from_i16_round_trips() {
+    let limit = i16::MAX / FIXED_POINT_DIVISOR;
+    for val in -limit..=limit {
+        assert_eq!(f32::from(Fxpt::from(val)), val as f32);
+    }
+}
+
+#[test]
+fn fxpt_to_f32_is_exact() {
+    for raw in i16::MIN..=i16::MAX {
+        let val = f32::from(Fxpt(raw));
+        assert_eq!(val * FIXED_POINT_DIVISOR as f32, raw as f32);
+        assert_eq!((val * FIXED_POINT_DIVISOR as f32) as i16, raw);
+    }
+}
+
+#[test]
+fn fxpt_ordering_matches_values() {
+    let mut rng = Rng::new(1);
+    for _ in 0..CASES {
+        let (a, b) = (any_fxpt(&mut rng), any_fxpt(&mut rng));
+        assert_eq!(a.cmp(&b), a.0.cmp(&b.0));
+        assert_eq!(f32::from(a).partial_cmp(&f32::from(b)),
+            Some(a.cmp(&b)));
+        assert_eq!(a == b, f32::from(a) == f32::from(b));
+    }
+}
+
+#[test]
+fn sine_stays_in_range() {
+    for angle in 0..=u8::MAX {
+        assert!(sine(angle).abs() <= 256);
+        assert_eq!(sine(angle), -sine(angle.wrapping_add(128)));
+    }
+}
+
+/// Overflow checks are on in tests, so any wrapping in the physics panics
+#[test]
+fn player_physics_never_wraps() {
+    let mut rng = Rng::new(2);
+    for difficulty in difficulties() {
+        let (up, down) = terminal_speeds(&difficulty);
+        assert!(up.0 > 0 && down.0 > 0);
+        assert!(up < GAME_FIELD_HEIGHT && down < GAME_FIELD_HEIGHT);
+
+        // Random thrusting never leaves the terminal speeds, in either
+        // direction of gravity
+        let (mut speed, mut flipped) = (Fxpt(0), Fxpt(0));
+        for _ in 0..CASES / 100 {
+            let thrust = rng.rand() & 1 != 0;
+            speed = player_physics(speed, thrust, &difficulty);
+            flipped = flipped_physics(flipped, thrust, &difficulty);
+            assert!(-up.0 <= speed.0 && speed.0 <= down.0);
+            assert!(-down.0 <= flipped.0 && flipped.0 <= up.0);
+        }
+    }
+}
+
+#[test]
+fn difficulty_ramps_never_wrap() {
+    let mut rng = Rng::new(4);
+    for difficulty in difficulties() {
+        for _ in 0..CASES / 1000 {
+            let mut difficulty = difficulty;
+            difficulty.ease = (rng.rand() % (ADAPT_MAX as u64 * 2 + 1)) as i16
+                - ADAPT_MAX;
+            let frames = rng.rand() % (1 << 40);
+
+            let gap = difficulty.gap(frames);
+            assert!(gap.0 > 0 && gap <= GAME_FIELD_HEIGHT);
+
+            // Fast zones double the scroll speed
+            let scroll = difficulty.scroll_speed(frames);
+            assert!(scroll.0 > 0 && scroll.0 < i16::MAX / 2);
+        }
+    }
+}
+
+/// Box with the top left at (`x`, `y`) in whole pixels
+fn rect(x: i16, y: i16, width: i16, height: i16) -> Obstacle {
+    Obstacle::new(Fxpt::from(x), Fxpt::from(y), Fxpt::from(width),
+        Fxpt::from(height))
+}
+
+#[test]
+fn overlaps_excludes_touching_edges() {
+    let a = rect(0, 0, 10, 10);
+    for b in [rect(10, 0, 10, 10), rect(-10, 0, 10, 10),
+              rect(0, 10, 10, 10), rect(0, -10, 10, 10),
+              rect(10, 10, 10, 10), rect(-10, -10, 10, 10)] {
+        assert!(!a.overlaps(&b) && !b.overlaps(&a));
+    }
+
+    // A single fixed point unit of overlap is enough
+    let b = Obstacle::new(Fxpt(Fxpt::from(10).0 - 1), Fxpt(0),
+        Fxpt::from(10), Fxpt::from(10));
+    assert!(a.overlaps(&b) && b.overlaps(&a));
+}
+
+#[test]
+fn overlaps_containment_and_negative_coords() {
+    let outer = rect(-50, -50, 100, 100);
+    for inner in [rect(-10, -10, 20, 20), rect(-50, -50, 100, 100),
+                  rect(-50, -50, 1, 1), rect(49, 49, 1, 1)] {
+        assert!(outer.overlaps(&inner) && inner.overlaps(&outer));
+    }
+    assert!(!outer.overlaps(&rect(-60, -60, 10, 10)));
+    assert!(!rect(-20, 5, 10, 10).overlaps(&rect(-5, 5, 10, 10)));
+}
+
+#[test]
+fn overlaps_matches_brute_force() {
+    let mut rng = Rng::new(5);
+    let mut coord = || (rng.rand() % 64) as i16 - 32;
+    for _ in 0..CASES {
+        let a = rect(coord(), coord(), coord().abs(), coord().abs());
+        let b = rect(coord(), coord(), coord().abs(), coord().abs());
+
+        // Check every pixel of `a` for being inside of `b` too
+        let inside = |x, y| {
+            b.x.0 <= x && x < b.x.0 + b.width.0 &&
+                b.y.0 <= y && y < b.y.0 + b.height.0
+        };
+        let brute = (0..a.width.0 / FIXED_POINT_DIVISOR).any(|x| {
+            (0..a.height.0 / FIXED_POINT_DIVISOR).any(|y| inside(
+                a.x.0 + x * FIXED_POINT_DIVISOR,
+                a.y.0 + y * FIXED_POINT_DIVISOR))
+        });
+        assert_eq!(a.overlaps(&b), brute);
+        assert_eq!(b.overlaps(&a), brute);
+        assert_eq!(a.hits(&b), brute);
+    }
+}
+
+/// Play `rules` for a while without ever dying, checking the level
+/// generation invariants every physics frame
+fn check_generation(rules: Rules) {
+    let mut field = GameField::new(rules);
+    let min_gap = Fxpt::from(field.rules.difficulty.min_gap);
+    let mut columns = field.column;
+    let mut filled = false;
+    let mut rng = Rng::new(rules.seed);
+    for _ in 0..20_000 {
+        field.invulnerable_frames = u16::MAX;
+        field.step((rng.rand() & 1) as u8 * INPUT_THRUST);
+
+        let mut ceilings: Vec<&Obstacle> =
+            field.walls.iter().filter(|x| x.y.0 == 0).collect();
+        ceilings.sort_by_key(|x| x.x);
+        let floor = |ceiling: &Obstacle| {
+            field.walls.iter()
+                .find(|x| x.y.0 != 0 && x.x == ceiling.x)
+                .expect("Column without a floor")
+        };
+
+        // Columns follow each other without any holes, all the way across
+        // the field once it has filled up from the right and on past it by
+        // the lookahead, and every floor goes down to the bottom
+        filled |= ceilings[0].x.0 <= 0;
+        assert!(!filled || ceilings[0].x.0 <= 0);
+        assert!(ceilings.last().unwrap().x.0 + OBSTACLE_WIDTH.0 >=
+            GAME_FIELD_WIDTH.0 + OBSTACLE_WIDTH.0 * rules.lookahead as i16);
+        for pair in ceilings.windows(2) {
+            assert_eq!(pair[0].x.0 + OBSTACLE_WIDTH.0, pair[1].x.0);
+        }
+        for ceiling in ceilings.iter() {
+            let floor = floor(ceiling);
+            assert_eq!(floor.y.0 + floor.height.0, GAME_FIELD_HEIGHT.0);
+        }
+
+        // New columns never get narrower than the minimum gap, apart from
+        // in tunnels
+        if field.column != columns {
+            columns = field.column;
+            let ceiling = ceilings.last().unwrap();
+            let gap = Fxpt(floor(ceiling).y.0 - ceiling.height.0);
+            let min = if field.tunnel > 0 { TUNNEL_GAP } else { min_gap };
+            assert!(gap >= min, "Gap {} below {}", gap.0, min.0);
+        }
+
+        // Obstacles stay in the gap of the column they were put in. Shots
+        // fly wherever they want
+        for obstacle in field.obstacles.iter().filter(|x| {
+            !matches!(x.kind, ObstacleKind::Projectile { .. })
+        }) {
+            let Some(ceiling) = ceilings.iter().find(|x| {
+                x.x.0 <= obstacle.x.0 && obstacle.x.0 < x.x.0 + x.width.0
+            }) else { continue };
+            assert!(obstacle.y >= ceiling.height);
+            assert!(obstacle.y.0 + obstacle.height.0 <= floor(ceiling).y.0);
+        }
+    }
+}
+
+#[test]
+fn generation_invariants() {
+    for seed in 1..=4 {
+        for preset in Preset::ALL {
+            check_generation(Rules {
+                seed,
+                preset,
+                difficulty: preset.difficulty(),
+                lookahead:  (seed as u16 - 1) * MAX_LOOKAHEAD / 3,
+                ..Default::default()
+            });
+        }
+    }
+}
+
+#[test]
+fn voted_mutators_replay_the_same() {
+    let rules = Rules { seed: 7, ..Default::default() };
+    let mut field = GameField::new(rules);
+    field.vote(Mutator::Giant);
+    while !field.dead {
+        if field.physics_frames == 300 {
+            field.vote(Mutator::DoubleGravity);
+        }
+
+        // Let the autopilot fly for a while, then drop out of the sky like
+        // replays which ran out of inputs do
+        let input = if field.physics_frames < 600 {
+            Autopilot.decide(&field.state())
+        } else {
+            0
+        };
+        field.step(input);
+    }
+    assert_eq!(field.votes.len(), 2);
+
+    let bytes = Replay {
+        rules,
+        level:     None,
+        inputs:    field.inputs.clone(),
+        votes:     field.votes.clone(),
+        script:    None,
+        frames:    Some(field.physics_frames),
+        integrity: Some(field.integrity),
+    }.serialize();
+    let verdict = env::Env::verify(&bytes).unwrap();
+    assert_eq!(verdict.frames, field.physics_frames);
+    assert_eq!(verdict.state, field.state_hash());
+
+    // Replays which end on another frame than they were recorded on desynced
+    let mut replay = Replay::parse(&bytes, rules).unwrap();
+    replay.frames = Some(field.physics_frames - 1);
+    assert!(env::Env::verify(&replay.serialize()).is_err());
+
+    // So did ones which end on the right frame with another integrity hash
+    let mut replay = Replay::parse(&bytes, rules).unwrap();
+    assert_eq!(replay.integrity, Some(field.integrity));
+    replay.integrity = Some(field.integrity ^ 1);
+    assert!(env::Env::verify(&replay.serialize()).is_err());
+}
+
+#[test]
+fn faster_tick_rates_replay_the_same() {
+    let rules = Rules { seed: 5, tick_rate: 120, ..Default::default() };
+    let mut field = GameField::new(rules);
+    while !field.dead && field.physics_frames < 2400 {
+        field.step(Autopilot.decide(&field.state()));
+    }
+
+    // Scores and ramps count physics frames at the ranked rate
+    assert_eq!(field.elapsed(), field.physics_frames / 2);
+    assert!(field.score() >= field.elapsed());
+
+    let bytes = Replay {
+        rules,
+        level:     None,
+        inputs:    field.inputs.clone(),
+        votes:     Vec::new(),
+        script:    None,
+        frames:    None,
+        integrity: None,
+    }.serialize();
+    assert_eq!(Replay::parse(&bytes, Rules::default()).unwrap()
+        .rules.tick_rate, 120);
+    let verdict = env::Env::verify(&bytes).unwrap();
+    assert_eq!(verdict.frames, field.physics_frames);
+    assert_eq!(verdict.state, field.state_hash());
+}
+
+#[test]
+fn out_of_range_headers_are_rejected() {
+    // Each of these used to panic somewhere in the simulation
+    for fields in ["start_gap=60 min_gap=60", "start_gap=320 min_gap=320",
+            "start_gap=2000", "gravity=30000", "impulse=-30000",
+            "friction=30000", "scroll=-64 max_scroll=-64", "scroll=20000",
+            "adaptive=30000", "curve=0:250:30:20000"] {
+        let bytes = format!("HELIREPLAY 2 seed=1 {}\n", fields);
+        assert!(env::Env::verify(bytes.as_bytes()).is_err(), "{}", fields);
+    }
+}
+
+#[test]
+fn headers_at_their_limits_play_back() {
+    // The most extreme rules headers can have play out without overflowing,
+    // thrusting the whole way or not at all
+    for fields in [
+        format!("start_gap={0} min_gap={0}", MIN_GAP),
+        format!("mode=career upgrades=gap:3 start_gap={0} min_gap={0}",
+            MAX_GAP),
+        format!("scroll={0} max_scroll={0}", MAX_SCROLL_SPEED.0),
+        "scroll=1 max_scroll=1".to_string(),
+        format!("gravity={} impulse=0 friction={} craft=heavy \
+            mutators=double_gravity", MAX_FORCE.0, MAX_FRICTION.0),
+        format!("gravity=0 impulse={} friction={} craft=heavy",
+            MAX_FORCE.0, MAX_FRICTION.0),
+        format!("adaptive={}", -ADAPT_MAX),
+        format!("adaptive={}", ADAPT_MAX),
+    ] {
+        for input in [0, INPUT_THRUST] {
+            let mut bytes = format!("HELIREPLAY 2 seed=1 {}\n", fields)
+                .into_bytes();
+            bytes.extend(std::iter::repeat_n(input, 10_000));
+            assert!(env::Env::verify(&bytes).is_ok(), "{}", fields);
+        }
+    }
+
+    // Bounces never end the run, but replays stop where they say they ended
+    // or at the cap, whichever is first
+    for fields in ["frames=600", "", "frames=18446744073709551615"] {
+        let bytes = format!("HELIREPLAY 2 seed=1 mutators=bounce {}\n",
+            fields);
+        assert!(env::Env::verify(bytes.as_bytes()).is_err(), "{}", fields);
+    }
+}
+
+#[test]
+fn practice_rewinds_to_before_the_death() {
+    let rules = Rules { seed: 9, ..Default::default() };
+    let mut field = GameField::new(rules);
+    let mut snapshots = Vec::new();
+    while !field.dead {
+        snapshots.push(field.state_hash());
+        let input = if field.physics_frames < 900 {
+            Autopilot.decide(&field.state())
+        } else {
+            0
+        };
+        field.step(input);
+    }
+
+    let rewound = field.rewound(3);
+    assert_eq!(rewound.physics_frames, field.physics_frames - 180);
+    assert_eq!(rewound.state_hash(),
+        snapshots[rewound.physics_frames as usize]);
+    assert!(!rewound.dead);
+}
+
+#[test]
+fn practice_runs_fast_forward_to_their_start() {
+    let rules = Rules { seed: 4, start_frame: 3000, ..Default::default() };
+    let mut field = GameField::new(rules);
+    field.fast_forward();
+    assert_eq!(field.physics_frames, 3000);
+    assert!(!field.dead);
+    assert_eq!((field.score(), field.coins), (3000, 0));
+
+    // Replays hold the inputs of the fast-forward too
+    for _ in 0..300 {
+        field.step(Autopilot.decide(&field.state()));
+    }
+    let bytes = Replay {
+        rules,
+        level:     None,
+        inputs:    field.inputs.clone(),
+        votes:     Vec::new(),
+        script:    None,
+        frames:    None,
+        integrity: None,
+    }.serialize();
+    assert_eq!(Replay::parse(&bytes, Rules::default()).unwrap()
+        .rules.start_frame, 3000);
+    let mut replayed = GameField::new(rules);
+    for &input in &field.inputs {
+        replayed.step(input);
+    }
+    assert_eq!(replayed.state_hash(), field.state_hash());
+}
+
+#[test]
+fn console_commands_change_the_run() {
+    let mut field = GameField::new(Rules::default());
+    assert!(console::run(&mut field, "help").is_ok());
+    assert!(console::run(&mut field, "set wind 3").is_err());
+    assert!(console::run(&mut field, "spawn rock 1 2").is_err());
+    assert!(!field.debug);
+
+    console::run(&mut field, "set gravity 2.5").unwrap();
+    assert_eq!(field.script_param(Param::Gravity), Some(80));
+    console::run(&mut field, "tp 100").unwrap();
+    assert!(field.player_y == Fxpt::from(100));
+    console::run(&mut field, "spawn coin 10 20").unwrap();
+    assert!(field.pickups.iter().any(|x| {
+        x.kind == PickupKind::Coin && x.x == Fxpt::from(10)
+    }));
+    assert!(field.debug);
+
+    // God mode survives flying straight into the floor
+    console::run(&mut field, "god").unwrap();
+    for _ in 0..600 {
+        field.step(0);
+    }
+    assert!(!field.dead);
+
+    assert!(matches!(console::run(&mut field, "seed 2a").unwrap().1,
+        Some(console::Action::Restart(0x2a))));
+}
+
+#[test]
+fn languages_match_english() {
+    let english = locale::parse(locale::LANGUAGES[0].1);
+    for (code, file) in locale::LANGUAGES {
+        let texts = locale::parse(file);
+        for (key, text) in &english {
+            let Some(translated) = texts.get(key) else {
+                panic!("{} is missing {}", code, key);
+            };
+            assert_eq!(translated.matches("{}").count(),
+                text.matches("{}").count(), "{} {}", code, key);
+        }
+        assert_eq!(texts.len(), english.len(), "{} has extra keys", code);
+    }
+}
+
+#[test]
+fn url_queries_become_arguments() {
+    assert_eq!(url::from_query(
+        "?seed=00ff&mode=daily&mirror&mutators=icy%2Cnight"), ["--seed", "00ff", "--mode", "daily", "--mirror", "--mutators",
+         "icy,night"]);
+    assert_eq!(url::from_query(""), Vec::<String>::new());
+    assert_eq!(url::from_query("level=my+level%zz"),
+        ["--level", "my level%zz"]);
+}
+
+#[test]
+fn scripts_override_the_rules() {
+    let mut script = Script::parse("
+        # Obstacles are worth double after frame 1000
+        on_tick  frame >= 1000 => set pass_bonus pass_bonus * 2
+        on_spawn obstacle_height > 60 and frame % 2 == 0 => bonus 1 + 2 * 3
+        on_death => set deaths deaths + 1 / (lives - lives) + 1
+        set      gravity -(3 - 5) * 4
+    ").unwrap();
+    let mut values = Values { frame: 999, ..Default::default() };
+    values.params[Param::PassBonus as usize] = 25;
+
+    assert!(script.run(Hook::Start, &values).is_empty());
+    assert_eq!(script.param(Param::Gravity), Some(8));
+    script.run(Hook::Tick, &values);
+    assert_eq!(script.param(Param::PassBonus), None);
+    values.frame = 1000;
+    script.run(Hook::Tick, &values);
+    assert_eq!(script.param(Param::PassBonus), Some(50));
+
+    values.obstacle_height = 61;
+    assert_eq!(script.run(Hook::Spawn, &values), [7]);
+    values.frame = 1001;
+    assert!(script.run(Hook::Spawn, &values).is_empty());
+
+    let hash = script.state_hash(FNV_OFFSET);
+    script.run(Hook::Death, &values);
+    assert_ne!(script.state_hash(FNV_OFFSET), hash);
+
+    for bad in ["on_tick => set frame 1", "on_tick => bonus nope",
+            "on_land => bonus 1", "set gravity (1", "on_tick => bonus 1 $"] {
+        assert!(Script::parse(bad).is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn content_packs_get_discovered() {
+    let dir = std::env::temp_dir()
+        .join(format!("mods-{}", std::process::id()));
+    let pack = dir.join("pack");
+    for kind in ["levels", "palettes", "sounds"] {
+        std::fs::create_dir_all(pack.join(kind)).unwrap();
+    }
+    std::fs::write(pack.join("levels/one.txt"), "length 10").unwrap();
+    std::fs::write(pack.join("levels/notes.md"), "").unwrap();
+    std::fs::write(pack.join("palettes/duo.txt"), "ff0000\n0000ff").unwrap();
+
+    let mut packs = Registry::discover(dir.to_str().unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(!packs.is_empty());
+    assert!(packs.load().unwrap().level.is_none());
+    assert!(Registry::discover(dir.to_str().unwrap()).is_empty());
+
+    let palette = Palette::parse("# Two colors\nff0000\n0000ff").unwrap();
+    assert_eq!(palette.at(0.), (0xff, 0, 0));
+    assert_eq!(palette.at(0.25), (0x7f, 0, 0x7f));
+    assert_eq!(palette.at(1.5), (0, 0, 0xff));
+    assert!(Palette::parse("# Nothing").is_err());
+    assert!(Palette::parse("red").is_err());
+}
+
+#[test]
+fn states_round_trip() {
+    let rules = Rules {
+        mode:     Mode::Casual,
+        mutators: Mutator::Swarm.bit(),
+        seed:     11,
+        ..Default::default()
+    };
+    let mut field = GameField::new(rules);
+    field.vote(Mutator::Tiny);
+    for _ in 0..500 {
+        field.step(Autopilot.decide(&field.state()));
+    }
+
+    let text = state::save(&field);
+    let mut loaded: GameField = state::load(&text).unwrap();
+    assert_eq!(state::save(&loaded), text);
+    assert_eq!(loaded.state_hash(), field.state_hash());
+
+    // Both keep playing out the same from there
+    for _ in 0..500 {
+        let input = Autopilot.decide(&field.state());
+        field.step(input);
+        loaded.step(input);
+    }
+    assert_eq!(loaded.state_hash(), field.state_hash());
+    assert_eq!(state::save(&loaded), state::save(&field));
+
+    for bad in [text.replace("player_y ", "player_x "),
+            text.replace("HELISTATE 1", "HELISTATE 2"),
+            text.replace("\nrun_hash", "\nextra 1\nrun_hash"),
+            text.lines().take(20).collect::<Vec<_>>().join("\n")] {
+        assert!(state::load::<GameField>(&bad).is_err());
+    }
+}
+
+#[test]
+fn frozen_spawns_keep_the_walls() {
+    let rules = Rules { seed: 9, ..Default::default() };
+    let mut normal = GameField::new(rules);
+    let mut frozen = GameField::new(rules);
+    frozen.freeze_spawn = true;
+    frozen.god = true;
+    normal.god = true;
+    for _ in 0..1200 {
+        normal.step(0);
+        frozen.step(0);
+    }
+    assert!(!normal.obstacles.is_empty());
+    assert!(frozen.obstacles.is_empty());
+    assert_eq!(normal.walls.len(), frozen.walls.len());
+    assert!(normal.walls.iter().zip(&frozen.walls)
+        .all(|(a, b)| a.x == b.x && a.y == b.y && a.height == b.height));
+}
+
+#[test]
+fn compare_finds_where_runs_diverge() {
+    let rules = Rules { seed: 6, ..Default::default() };
+    let mut field = GameField::new(rules);
+    for _ in 0..600 {
+        field.step(Autopilot.decide(&field.state()));
+    }
+    let replay = |inputs: VecDeque<u8>| Replay {
+        rules,
+        level:     None,
+        inputs,
+        votes:     Vec::new(),
+        script:    None,
+        frames:    None,
+        integrity: None,
+    };
+    let mut nudged = field.inputs.clone();
+    nudged[300] ^= INPUT_THRUST;
+
+    let a = compare::Run::play("a", replay(field.inputs.clone())).unwrap();
+    let b = compare::Run::play("b", replay(nudged)).unwrap();
+    let report = compare::report(&a, &b, 5);
+    assert!(report.contains("Inputs diverge at 5.00 s (frame 300)"));
+    assert!(report.contains("Trajectories diverge at 5.02 s (frame 301)"));
+    assert!(report.contains("    0-    5 s"));
+
+    let same = compare::report(&a, &a, 5);
+    assert!(same.contains("Inputs never diverge"));
+    assert!(same.contains("B scored +0 against A"));
+
+    // Bouncing runs never end, so they stop where they say they ended
+    let bounce = Rules { mutators: Mutator::Bounce.bit(), ..rules };
+    let run = compare::Run::play("c", Replay {
+        rules:  bounce,
+        frames: Some(600),
+        ..replay(VecDeque::new())
+    });
+    assert!(run.is_err());
+}
+
+#[test]
+fn closest_calls_are_the_tightest_near_misses() {
+    let mut field = GameField::new(Rules { seed: 3, ..Default::default() });
+    while field.near_misses < 4 && !field.dead {
+        field.step(Autopilot.decide(&field.state()));
+    }
+    assert_eq!(field.close_calls.len() as u64, field.near_misses);
+
+    let calls = field.closest_calls(3);
+    assert_eq!(calls.len(), 3);
+    assert!(calls.windows(2).all(|x| x[0].1 <= x[1].1));
+    assert!(calls.iter().all(|&(frame, clearance)| {
+        frame <= field.physics_frames && clearance < NEAR_MISS_DISTANCE
+    }));
+    assert!(field.close_calls.iter().all(|x| x.1 >= calls[2].1 ||
+        calls.contains(x)));
+}
+
+#[test]
+fn overlaps_are_not_near_misses() {
+    // Flying through an obstacle in god mode isn't a close call
+    let mut field = GameField::new(Rules { seed: 3, ..Default::default() });
+    field.obstacles.clear();
+    field.obstacles.push(rect(100, 0, 40, 300));
+    field.god = true;
+    while field.obstacles.iter().any(|x| !x.passed) {
+        field.step(0);
+    }
+    assert!(field.near_misses == 0 && field.close_calls.is_empty());
+}
+
+#[test]
+fn edges_can_cost_score_or_kill() {
+    // Thrusting into the ceiling of an empty field
+    let touch = |edges| {
+        let rules = Rules { seed: 2, edges, ..Default::default() };
+        let mut field = GameField::new(rules);
+        field.walls.clear();
+        field.obstacles.clear();
+        field.invulnerable_frames = 0;
+        field.bonus = 150;
+        field.player_y = Fxpt(0);
+        for _ in 0..2 {
+            field.step(INPUT_THRUST);
+        }
+        field
+    };
+    let safe = touch(Edges::Safe);
+    assert!(!safe.dead && safe.bonus == 150);
+    let penalty = touch(Edges::Penalty);
+    assert!(!penalty.dead && penalty.bonus == 150 - EDGE_PENALTY);
+    assert!(touch(Edges::Lethal).dead);
+
+    let rules = Rules { edges: Edges::Lethal, ..Default::default() };
+    let bytes = Replay {
+        rules,
+        level:     None,
+        inputs:    VecDeque::new(),
+        votes:     Vec::new(),
+        script:    None,
+        frames:    None,
+        integrity: None,
+    }.serialize();
+    assert!(Replay::parse(&bytes, Rules::default()).unwrap().rules.edges ==
+        Edges::Lethal);
+}
+
+#[test]
+fn moon_preset_floats() {
+    // Falling tops out slower than in the normal game, and climbing is no
+    // faster
+    let (up, down) = terminal_speeds(&Preset::Moon.difficulty());
+    let (normal_up, normal_down) = terminal_speeds(&Difficulty::default());
+    assert!(up.0 > 0 && up <= normal_up && down < normal_down);
+
+    let rules = Rules {
+        preset:     Preset::Moon,
+        difficulty: Preset::Moon.difficulty(),
+        ..Default::default()
+    };
+    let bytes = Replay {
+        rules,
+        level:     None,
+        inputs:    VecDeque::new(),
+        votes:     Vec::new(),
+        script:    None,
+        frames:    None,
+        integrity: None,
+    }.serialize();
+    let replay = Replay::parse(&bytes, Rules::default()).unwrap();
+    assert!(replay.rules.preset == Preset::Moon &&
+        replay.rules.difficulty == Preset::Moon.difficulty());
+}
+
+#[test]
+fn crafts_fly_differently() {
+    let speeds = |craft| {
+        let rules = Rules { craft, ..Default::default() };
+        terminal_speeds(&GameField::new(rules).rules.difficulty)
+    };
+    let (up, down) = speeds(Craft::Standard);
+    assert!(speeds(Craft::Heavy).1 > down);
+    assert!(speeds(Craft::Glider).1 < down);
+    assert!(speeds(Craft::Stunt).0 < up && speeds(Craft::Stunt).1 < down);
+
+    // The craft is kept in the replay, the difficulty in it is untouched
+    let rules = Rules { craft: Craft::Glider, ..Default::default() };
+    let bytes = Replay {
+        rules,
+        level:     None,
+        inputs:    VecDeque::new(),
+        votes:     Vec::new(),
+        script:    None,
+        frames:    None,
+        integrity: None,
+    }.serialize();
+    let replay = Replay::parse(&bytes, Rules::default()).unwrap();
+    assert!(replay.rules.craft == Craft::Glider &&
+        replay.rules.difficulty == Difficulty::default());
+}
+
+#[test]
+fn upgrades_only_apply_to_career_runs() {
+    let upgraded = |mode| {
+        let rules = Rules { mode, upgrades: [1, 2, 1], ..Default::default() };
+        GameField::new(rules)
+    };
+    let vanilla = upgraded(Mode::Score);
+    assert!(!vanilla.shield && vanilla.lives == 1);
+    assert!(vanilla.rules.difficulty == Difficulty::default());
+    let career = upgraded(Mode::Career);
+    assert!(career.shield && career.lives == 2);
+    assert!(career.rules.difficulty.start_gap ==
+        Difficulty::default().start_gap + 2 * UPGRADE_GAP);
+
+    // The upgrades are kept in the replay, and can't go past the max
+    let bytes = Replay {
+        rules:     career.base_rules,
+        level:     None,
+        inputs:    VecDeque::new(),
+        votes:     Vec::new(),
+        script:    None,
+        frames:    None,
+        integrity: None,
+    }.serialize();
+    let replay = Replay::parse(&bytes, Rules::default()).unwrap();
+    assert!(replay.rules.upgrades == [1, 2, 1]);
+    let bytes = String::from_utf8_lossy(&bytes)
+        .replace("gap:2", "gap:9");
+    assert!(Replay::parse(bytes.as_bytes(), Rules::default()).is_err());
+}
+
+#[test]
+fn shop_sells_boosts_between_stages() {
+    let rules = Rules { seed: 6, mode: Mode::Stage, ..Default::default() };
+    let mut field = GameField::new(rules);
+    field.shop = Some(0);
+    field.coins = 20;
+    let (score, player_y) = (field.score(), field.player_y);
+
+    // Buy a shield, fail to afford a shrink, and leave
+    for input in [INPUT_FIRE, 0, INPUT_THRUST, 0, INPUT_FIRE, 0,
+            INPUT_THRUST, INPUT_THRUST, 0, INPUT_THRUST, 0, INPUT_FIRE] {
+        field.step(input);
+    }
+    assert!(field.shop.is_none() && field.shield && field.coins == 5);
+    assert!(field.score() == score && field.player_y == player_y);
+    assert!(field.stage_start == field.physics_frames);
+
+    // The world moves again once the shop is left
+    field.step(0);
+    assert!(field.score() == score + 1);
+}
+
+#[test]
+fn rank_goes_by_the_best_recent_runs() {
+    assert!(Rank::of(&[]) == Rank::Bronze);
+
+    // One great run isn't enough on its own, three are
+    assert!(Rank::of(&[30000, 100, 100]) == Rank::Platinum);
+    assert!(Rank::of(&[100, 30000, 500, 25000, 20000]) == Rank::Diamond);
+    assert!(Rank::of(&[2000, 2000, 2000, 1]) == Rank::Silver);
+}
+
+#[test]
+fn drafts_change_gravity() {
+    // Drafts only get generated when they're on
+    let drafts = |drafts| {
+        let rules = Rules { seed: 5, drafts, ..Default::default() };
+        let mut field = GameField::new(rules);
+        field.god = true;
+        let mut kinds = Vec::new();
+        for _ in 0..20000 {
+            field.step(0);
+            kinds.extend(field.zones.iter().map(|x| x.kind)
+                .filter(|x| matches!(x, ZoneKind::Updraft |
+                    ZoneKind::Downdraft)));
+        }
+        kinds
+    };
+    assert!(drafts(false).is_empty());
+    let kinds = drafts(true);
+    assert!(kinds.contains(&ZoneKind::Updraft) &&
+        kinds.contains(&ZoneKind::Downdraft));
+
+    // Falling through an updraft is slower than through a downdraft
+    let fall = |kind: Option<ZoneKind>| {
+        let mut field = GameField::new(Rules::default());
+        field.walls.clear();
+        field.obstacles.clear();
+        field.zones = kind.map(|kind| {
+            Zone { x: Fxpt(0), width: GAME_FIELD_WIDTH, kind }
+        }).into_iter().collect();
+        field.player_y = Fxpt(0);
+        for _ in 0..10 {
+            field.step(0);
+        }
+        field.player_y
+    };
+    assert!(fall(Some(ZoneKind::Updraft)) < fall(None));
+    assert!(fall(None) < fall(Some(ZoneKind::Downdraft)));
+}
+
+#[test]
+fn bounces_reflect_the_player() {
+    // Thrusting into a lethal ceiling bounces back down instead
+    let rules = Rules {
+        seed:     2,
+        edges:    Edges::Lethal,
+        mutators: Mutator::Bounce.bit(),
+        ..Default::default()
+    };
+    let mut field = GameField::new(rules);
+    field.walls.clear();
+    field.obstacles.clear();
+    field.invulnerable_frames = 0;
+    field.physics_frames = 600;
+    field.player_y = Fxpt(0);
+    field.player_speed = Fxpt(-64);
+    let score = field.score();
+    field.step(0);
+    assert!(!field.dead && field.lives == rules.mode.lives());
+    assert!(field.player_speed > Fxpt(0));
+    assert!(field.invulnerable_frames > 0);
+
+    // The bounce costs score even without any bonus to take it from
+    assert!(field.bonus == 0);
+    assert!(field.score() == score + 1 - BOUNCE_PENALTY);
+}
+
+#[test]
+fn shields_absorb_everything_they_hit() {
+    // Two obstacles and a wall overlapping at once all go with the shield
+    let mut field = GameField::new(Rules { seed: 2, ..Default::default() });
+    field.walls.clear();
+    field.obstacles.clear();
+    field.invulnerable_frames = 0;
+    field.shield = true;
+    field.obstacles.push(rect(90, 0, 40, 300));
+    field.obstacles.push(rect(95, 0, 40, 300));
+    field.walls.push(rect(90, 0, 40, 300));
+    field.step(0);
+    assert!(!field.dead && !field.shield);
+    assert!(field.obstacles.is_empty());
+    assert!(!field.collides(&field.player_bounds()));
+}
+
+#[test]
+fn biomes_follow_the_level_rng() {
+    // Every biome lasts `BIOME_FRAMES` and is never followed by itself
+    let biomes = |biomes| {
+        let rules = Rules { seed: 9, biomes, ..Default::default() };
+        let mut field = GameField::new(rules);
+        field.god = true;
+        let mut seen = Vec::new();
+        for _ in 0..BIOME_FRAMES * 6 {
+            field.step(0);
+            seen.push(field.biome);
+        }
+        seen
+    };
+    assert!(biomes(false).iter().all(|x| x.is_none()));
+    let seen = biomes(true);
+    let changes: Vec<_> = seen.windows(2).enumerate()
+        .filter(|(_, x)| x[0] != x[1]).map(|(frame, _)| frame + 1).collect();
+    assert!(seen.iter().all(|x| x.is_some()));
+    assert!(changes.iter().enumerate()
+        .all(|(ii, &frame)| frame as u64 == (ii as u64 + 1) * BIOME_FRAMES));
+    assert!(changes.len() == 5);
+
+    // Replays pass through the same biomes
+    assert!(biomes(true) == seen);
+
+    // Ice caves are slippery
+    let mut difficulty = Difficulty::default();
+    Biome::IceCave.apply(&mut difficulty);
+    assert!(difficulty.friction > Difficulty::default().friction);
+}
+
+#[test]
+fn checkpoints_split_against_the_best_run() {
+    // A split gets taken at every checkpoint, with a flag left behind
+    let mut field = GameField::new(Rules { seed: 3, ..Default::default() });
+    field.god = true;
+    for _ in 0..CHECKPOINT_FRAMES * 2 {
+        field.step(0);
+    }
+    assert!(field.splits.len() == 2 && field.flag == Some(PLAYER_X));
+    assert!(field.splits[0] < field.splits[1]);
+    assert!(field.split_delta(0).is_none());
+
+    // Splits compare against the best run at the same checkpoint
+    field.best_splits = vec![field.splits[0] + 10, field.splits[1] - 20];
+    assert!(field.split_delta(0) == Some(-10));
+    assert!(field.split_delta(1) == Some(20));
+}
+
+#[test]
+fn continued_runs_replay() {
+    // Falling without thrusting dies quickly in a casual run
+    let rules = Rules { mode: Mode::Casual, seed: 6, ..Default::default() };
+    let mut field = GameField::new(rules);
+    while !field.dead {
+        field.step(0);
+    }
+    assert!(field.can_continue());
+    let died = field.score();
+    field.continue_run();
+    assert!(!field.dead && !field.can_continue());
+    assert!(field.obstacles.iter().all(|x| x.x >= GAME_FIELD_WIDTH));
+
+    // Points after continuing count for less
+    for _ in 0..100 {
+        field.step(INPUT_THRUST * (field.physics_frames % 2) as u8);
+    }
+    let raw = field.elapsed() + field.bonus;
+    assert!(raw > died && field.score() == died + (raw - died) / 2);
+
+    // Replaying the inputs with the continue marked plays out the same
+    let mut replayed = GameField::new(field.base_rules);
+    for &input in field.inputs.iter() {
+        replayed.step(input);
+    }
+    assert_eq!(replayed.state_hash(), field.state_hash());
+    assert_eq!(replayed.score(), field.score());
+}
+
+#[test]
+fn tuning_sets_the_difficulty() {
+    let tuning = Tuning::parse("# Floaty\ngravity 16\ngap     300\n").unwrap();
+    let mut difficulty = Difficulty::default();
+    tuning.apply(&mut difficulty);
+    assert!(difficulty.gravity == Fxpt(16));
+    assert!(difficulty.start_gap == 300 && difficulty.min_gap == 300);
+    assert!(difficulty.impulse == Difficulty::default().impulse);
+
+    // Values get clamped to what the sliders go between
+    let tuning = Tuning::parse("spawn 0").unwrap();
+    tuning.apply(&mut difficulty);
+    assert!(difficulty.obstacle_frames == 1);
+
+    // Which stay within what replay headers allow
+    let tuning = Tuning::parse("gap 400\nscroll 30000\nfriction 32\n\
+        impulse 512").unwrap();
+    tuning.apply(&mut difficulty);
+    assert!(difficulty.start_gap == MAX_GAP && difficulty.min_gap == MAX_GAP);
+    assert!(difficulty.scroll_speed == MAX_SCROLL_SPEED);
+    assert!(difficulty.friction == MAX_FRICTION);
+    assert!(difficulty.impulse == MAX_FORCE);
+    assert!(Tuning::parse("wind 3").is_err());
+    assert!(Tuning::parse("gravity").is_err());
+}
+
+#[test]
+fn watchers_see_changed_files() {
+    // Modification times are set explicitly, filesystems can be too coarse
+    // to tell writes apart otherwise
+    let path = std::env::temp_dir()
+        .join(format!("watch-{}.txt", std::process::id()));
+    let write = |text: &str, secs: u64| {
+        std::fs::write(&path, text).unwrap();
+        std::fs::File::options().write(true).open(&path).unwrap()
+            .set_modified(std::time::UNIX_EPOCH +
+                std::time::Duration::from_secs(secs)).unwrap();
+    };
+    write("crt off", 1000);
+    let mut watcher = Watcher::new(path.to_str().unwrap());
+    assert!(!watcher.changed(10.));
+
+    // Changes only get seen once the interval is up, and only once
+    write("crt on", 2000);
+    assert!(!watcher.changed(10.5));
+    assert!(watcher.changed(11.));
+    assert!(!watcher.changed(12.));
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,249 @@
+//! Twitch chat voting. Every so often viewers of the channel get to pick one
+//! of two mutators by typing `1` or `2` (or `!vote 1`) in chat, and the winner
+//! gets voted into the run at the next safe point, replacing the last one.
+//! Chat is read anonymously over IRC on a background thread, which isn't
+//! possible on WASM. Voted in mutators are part of the simulation, so they're
+//! recorded in the replay
+
+use std::collections::HashMap;
+use macroquad::prelude::*;
+use crate::{fnv1a, GameField, Mutator, Rng, FNV_OFFSET};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::TcpStream;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+/// Address of Twitch's IRC server
+#[cfg(not(target_arch = "wasm32"))]
+const SERVER: &str = "irc.chat.twitch.tv:6667";
+
+/// Seconds between the end of one vote and the start of the next
+const ROUND_INTERVAL: f64 = 30.;
+
+/// Seconds viewers have to vote
+const ROUND_TIME: f64 = 20.;
+
+/// A vote between two mutators
+struct Round {
+    /// Mutators being voted on
+    options: [Mutator; 2],
+
+    /// Option each viewer voted for, the last vote of a viewer counts
+    votes: HashMap<String, usize>,
+
+    /// Time (in seconds) the vote closes
+    ends: f64,
+}
+
+impl Round {
+    /// Number of votes for each option
+    fn counts(&self) -> [usize; 2] {
+        let mut ret = [0; 2];
+        for &option in self.votes.values() {
+            ret[option] += 1;
+        }
+        ret
+    }
+
+    /// Count the chat `message` from `nick` if it's a vote
+    #[cfg(not(target_arch = "wasm32"))]
+    fn count(&mut self, nick: String, message: &str) {
+        let message = message.trim();
+        let choice = message.strip_prefix("!vote").unwrap_or(message).trim();
+        let option = match choice {
+            "1" => 0,
+            "2" => 1,
+            _   => return,
+        };
+        self.votes.insert(nick, option);
+    }
+}
+
+/// Chat voting for a channel, if there is one
+pub struct Twitch {
+    /// Chat messages as (nick, message) from the connection
+    #[cfg(not(target_arch = "wasm32"))]
+    messages: Option<Receiver<(String, String)>>,
+
+    /// Vote which is open right now
+    round: Option<Round>,
+
+    /// Time (in seconds) the next vote opens
+    next_round: f64,
+
+    /// Picks the mutators to vote on, which is separate from the simulation
+    rng: Rng,
+}
+
+impl Twitch {
+    /// Join the chat of `channel`, if there is one. Failing to connect just
+    /// leaves voting off
+    pub fn connect(channel: Option<&str>) -> Self {
+        let rng = Rng::new(fnv1a(FNV_OFFSET,
+            &miniquad::date::now().to_bits().to_le_bytes()));
+        let mut ret = Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            messages:   None,
+            round:      None,
+            next_round: get_time() + ROUND_INTERVAL,
+            rng,
+        };
+        let Some(channel) = channel else { return ret };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        match join(channel) {
+            Ok(messages) => {
+                log_info!("Joined the Twitch chat of {}", channel);
+                ret.messages = Some(messages);
+            }
+            Err(err) => log_warn!("Couldn't join the Twitch chat of {}: {}",
+                channel, err),
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        log_warn!("Ignoring the Twitch channel {}, chat voting isn't \
+            supported on WASM", channel);
+
+        ret
+    }
+
+    /// Count the votes which came in, opening and closing votes as it's
+    /// time. Winners get queued up in `field`, votes are only open while the
+    /// run is live
+    pub fn update(&mut self, field: &mut GameField) {
+        if !self.receive() {
+            return;
+        }
+
+        let now = get_time();
+        if field.dead || field.finished || field.paused {
+            self.round = None;
+            self.next_round = now + ROUND_INTERVAL;
+            return;
+        }
+
+        match &self.round {
+            Some(round) if now >= round.ends => {
+                let counts = round.counts();
+                let winner = if counts[1] > counts[0] { 1 } else { 0 };
+                if counts[winner] > 0 {
+                    let mutator = round.options[winner];
+                    log_info!("Chat voted for {} {} to {}", mutator.name(),
+                        counts[winner], counts[1 - winner]);
+                    field.vote(mutator);
+                }
+                self.round = None;
+                self.next_round = now + ROUND_INTERVAL;
+            }
+            None if now >= self.next_round => {
+                // Only offer mutators which the run doesn't have already
+                let mut pool: Vec<Mutator> = Mutator::ALL.iter().copied()
+                    .filter(|x| !field.base_rules.has(*x))
+                    .collect();
+                if pool.len() < 2 {
+                    return;
+                }
+                let first = pool.remove(
+                    (self.rng.rand() % pool.len() as u64) as usize);
+                let second = pool[(self.rng.rand() % pool.len() as u64)
+                    as usize];
+                self.round = Some(Round {
+                    options: [first, second],
+                    votes:   HashMap::new(),
+                    ends:    now + ROUND_TIME,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Count the votes which came in from chat, returning whether chat is
+    /// still connected
+    #[cfg(not(target_arch = "wasm32"))]
+    fn receive(&mut self) -> bool {
+        let Some(messages) = self.messages.as_ref() else { return false };
+        loop {
+            match messages.try_recv() {
+                Ok((nick, message)) => {
+                    if let Some(round) = self.round.as_mut() {
+                        round.count(nick, &message);
+                    }
+                }
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => {
+                    log_warn!("Lost the connection to Twitch chat");
+                    self.messages = None;
+                    self.round = None;
+                    return false;
+                }
+            }
+        }
+    }
+
+    /// There's no chat to receive votes from on WASM
+    #[cfg(target_arch = "wasm32")]
+    fn receive(&mut self) -> bool {
+        false
+    }
+
+    /// Draw the open vote in the bottom left corner, if there is one
+    pub fn draw(&self) {
+        let Some(round) = &self.round else { return };
+        let counts = round.counts();
+        let x = 10.;
+        let y = screen_height() - 70.;
+        draw_rectangle(x, y, 260., 60., Color::from_rgba(0, 0, 0, 0xc0));
+        draw_text(&tr!("chat_vote", (round.ends - get_time()).max(0.).ceil()),
+            x + 6., y + 18., 20., PURPLE);
+        for (ii, (mutator, count)) in round.options.iter()
+                .zip(counts).enumerate() {
+            draw_text(&tr!("chat_option", ii + 1, mutator.title(), count),
+                x + 6., y + 38. + ii as f32 * 18., 20., WHITE);
+        }
+    }
+}
+
+/// Join the chat of `channel` anonymously, with the messages coming in from a
+/// background thread
+#[cfg(not(target_arch = "wasm32"))]
+fn join(channel: &str) -> std::io::Result<Receiver<(String, String)>> {
+    let mut socket = TcpStream::connect(SERVER)?;
+    let nick = format!("justinfan{}", miniquad::date::now() as u64 % 100000);
+    write!(socket, "NICK {}\r\nJOIN #{}\r\n", nick,
+        channel.trim_start_matches('#').to_lowercase())?;
+
+    let (sender, receiver) = mpsc::channel();
+    let mut reader = BufReader::new(socket.try_clone()?);
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let line = line.trim_end();
+
+            // The server drops us unless we answer its pings
+            if let Some(server) = line.strip_prefix("PING ") {
+                if write!(socket, "PONG {}\r\n", server).is_err() {
+                    break;
+                }
+                continue;
+            }
+
+            // Messages look like `:nick!user@host PRIVMSG #channel :text`
+            let Some((prefix, rest)) = line.strip_prefix(':')
+                .and_then(|x| x.split_once(' ')) else { continue };
+            let Some((_, text)) = rest.strip_prefix("PRIVMSG ")
+                .and_then(|x| x.split_once(" :")) else { continue };
+            let nick = prefix.split('!').next().unwrap_or(prefix);
+            if sender.send((nick.to_string(), text.to_string())).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(receiver)
+}
@@ -0,0 +1,74 @@
+//! Command line arguments, which on WASM come from the page's URL instead, so
+//! configurations can be linked to directly. `?seed=1234&mode=daily` is the
+//! same as `--seed 1234 --mode daily`, and a key without a value like
+//! `?mirror` is just the flag
+
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    /// Copy up to `len` bytes of the page's query string (without the `?`)
+    /// to `out`, returning its full length. Provided by the plugin in
+    /// `index.html`
+    fn mqtest_query(out: *mut u8, len: usize) -> usize;
+}
+
+/// Arguments the game was started with
+#[cfg(not(target_arch = "wasm32"))]
+pub fn args() -> Vec<String> {
+    std::env::args().skip(1).collect()
+}
+
+/// Arguments the game was started with, from the page's URL
+#[cfg(target_arch = "wasm32")]
+pub fn args() -> Vec<String> {
+    let mut query = vec![0u8; 256];
+    loop {
+        let len = unsafe { mqtest_query(query.as_mut_ptr(), query.len()) };
+        if len <= query.len() {
+            query.truncate(len);
+            break;
+        }
+        query.resize(len, 0);
+    }
+    from_query(&String::from_utf8_lossy(&query))
+}
+
+/// Turn the `key=value` pairs of a URL query into arguments
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn from_query(query: &str) -> Vec<String> {
+    let mut ret = Vec::new();
+    for pair in query.trim_start_matches('?').split('&')
+            .filter(|x| !x.is_empty()) {
+        let (key, value) = match pair.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (pair, None),
+        };
+        ret.push(format!("--{}", decode(key)));
+        ret.extend(value.map(decode));
+    }
+    ret
+}
+
+/// Decode the `%XX` escapes and `+` for spaces of a URL query
+#[cfg(any(target_arch = "wasm32", test))]
+fn decode(text: &str) -> String {
+    let mut ret = Vec::new();
+    let mut bytes = text.bytes();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'+' => ret.push(b' '),
+            b'%' => {
+                let hex = bytes.clone().take(2).collect::<Vec<_>>();
+                match std::str::from_utf8(&hex).ok()
+                        .and_then(|x| u8::from_str_radix(x, 16).ok()) {
+                    Some(decoded) if hex.len() == 2 => {
+                        ret.push(decoded);
+                        bytes.nth(1);
+                    }
+                    _ => ret.push(b'%'),
+                }
+            }
+            byte => ret.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&ret).into_owned()
+}
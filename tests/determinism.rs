@@ -0,0 +1,70 @@
+//! The simulation only counts frames and never looks at the clock or floats,
+//! so the same actions end in the same state everywhere. These hashes have to
+//! match natively and when the tests run on `wasm32-wasi` under a runner like
+//! wasmtime, which is how the web build is checked against the native one
+
+use mqtest::env::{Env, ACTION_FIRE, ACTION_THRUST};
+
+/// Physics frames to play each preset for
+const FRAMES: usize = 2000;
+
+/// Presets with the seed they're played on and the state hash they end with
+const EXPECTED: &[(&str, u64, u64)] = &[
+    ("easy",      1, 0xff788aa202e4ca9a),
+    ("normal",    2, 0xfe31c1f17ff417b0),
+    ("hard",      3, 0x8191ede6d5f8dec3),
+    ("nightmare", 4, 0xa8812ce5fc085091),
+];
+
+/// Play `preset` starting on `seed` with pseudo-random actions, moving on to
+/// the next seed whenever a game ends, and return the state hash of every
+/// physics frame
+fn play(preset: &str, seed: u64) -> Vec<u64> {
+    let mut env = Env::with_preset(preset).expect("Unknown preset");
+    let mut game = seed;
+    env.reset(game);
+
+    // Xorshift, thrusting in runs so the player moves around the field
+    let mut rng = seed | 1;
+    let mut thrust = false;
+    let mut hashes = Vec::new();
+    for _ in 0..FRAMES {
+        rng ^= rng << 13;
+        rng ^= rng >> 7;
+        rng ^= rng << 17;
+        if rng.is_multiple_of(8) {
+            thrust = !thrust;
+        }
+
+        let mut action = 0;
+        if thrust {
+            action |= ACTION_THRUST;
+        }
+        if rng % 64 == 1 {
+            action |= ACTION_FIRE;
+        }
+        hashes.push(env.state_hash());
+        if env.step(action).2 {
+            game += 1;
+            env.reset(game);
+        }
+    }
+    hashes
+}
+
+#[test]
+fn same_actions_same_state() {
+    for &(preset, seed, _) in EXPECTED {
+        assert_eq!(play(preset, seed), play(preset, seed),
+            "{} diverged between runs", preset);
+    }
+}
+
+#[test]
+fn state_hashes_match_every_platform() {
+    for &(preset, seed, expected) in EXPECTED {
+        let hash = play(preset, seed).iter()
+            .fold(0, |acc: u64, x| acc.rotate_left(5) ^ x);
+        assert_eq!(hash, expected, "{} ended in a different state", preset);
+    }
+}
@@ -0,0 +1,33 @@
+//! Known-good replays which have to keep playing out exactly the same, so any
+//! change to the physics, generation, or RNG gets caught
+
+use mqtest::env::{Env, Verdict};
+
+/// Replays in `tests/replays`, with the score, frames, run, and state hash
+/// they end with
+const GOLDEN: &[(&str, u64, u64, &str, u64)] = &[
+    ("normal.bin",             22178, 1528, "0000000000000001-7ba2a3c6",
+        0x7aed47ce80daaabb),
+    ("hard.bin",                7916,  641, "0000000000000002-1e6b1b4e",
+        0x3642648eee3809c2),
+    ("easy_swarm.bin",         10873,  523, "0000000000000003-1102fabf",
+        0x65adcad53d336634),
+    ("nightmare_icy_tiny.bin", 25736, 1386, "0000000000000004-049d2379",
+        0x63c582b2838f3393),
+];
+
+#[test]
+fn golden_replays() {
+    for &(name, score, frames, run, state) in GOLDEN {
+        let path = format!("{}/tests/replays/{}",
+            env!("CARGO_MANIFEST_DIR"), name);
+        let bytes = std::fs::read(&path).expect("Failed to read replay");
+        let verdict = Env::verify(&bytes).expect("Failed to verify replay");
+        assert_eq!(verdict, Verdict {
+            score,
+            frames,
+            run: run.to_string(),
+            state,
+        }, "{} played out differently", name);
+    }
+}